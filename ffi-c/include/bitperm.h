@@ -0,0 +1,175 @@
+#ifndef BITPERM_H
+#define BITPERM_H
+
+/* Generated by cbindgen from ffi-c/src/lib.rs; regenerate with `cbindgen --config cbindgen.toml --output include/bitperm.h` instead of editing. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+/*
+ The result of a fallible call. 
+ */
+typedef enum BitpermStatus {
+  BITPERM_STATUS_OK = 0,
+  /*
+   A pointer argument was null. 
+   */
+  BITPERM_STATUS_NULL_ARGUMENT = 1,
+  /*
+   A string argument was not valid UTF-8. 
+   */
+  BITPERM_STATUS_INVALID_UTF8 = 2,
+  /*
+   A permission could not be created, granted or revoked. 
+   */
+  BITPERM_STATUS_PERMISSION_ERROR = 3,
+  /*
+   A scope operation failed, e.g. an unknown path or a broken rule. 
+   */
+  BITPERM_STATUS_SCOPE_ERROR = 4,
+  /*
+   The JSON passed to `bitperm_from_json` could not be parsed. 
+   */
+  BITPERM_STATUS_INVALID_JSON = 5,
+} BitpermStatus;
+
+/*
+ An opaque scope tree, created by `bitperm_scope_new` or `bitperm_from_json` and freed by `bitperm_scope_free`. 
+ */
+typedef struct BitpermScope BitpermScope;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+/*
+ Create an empty root scope. Returns null if `name` is null or not UTF-8.
+
+ # Safety
+ `name` must be null or a valid NUL-terminated string.
+ */
+struct BitpermScope *bitperm_scope_new(const char *name);
+
+/*
+ Free a scope created by `bitperm_scope_new` or `bitperm_from_json`. Passing null does nothing.
+
+ # Safety
+ `scope` must be null or a pointer returned by this library that has not been freed yet.
+ */
+void bitperm_scope_free(struct BitpermScope *scope);
+
+/*
+ Add a permission named `name` to the scope at `path` relative to `scope`, e.g. `billing`, or `""` for `scope`.
+
+ # Safety
+ `scope` must be a live pointer returned by this library and `path` and `name` valid NUL-terminated strings.
+ */
+enum BitpermStatus bitperm_add_permission(struct BitpermScope *scope,
+                                          const char *path,
+                                          const char *name);
+
+/*
+ Add a child scope named `name` to the scope at `path` relative to `scope`, or `""` for `scope` itself.
+
+ # Safety
+ `scope` must be a live pointer returned by this library and `path` and `name` valid NUL-terminated strings.
+ */
+enum BitpermStatus bitperm_add_scope(struct BitpermScope *scope,
+                                     const char *path,
+                                     const char *name);
+
+/*
+ Grant the permission at `path`, e.g. `READ` or `billing:REFUND`, subject to the rules of its scope.
+
+ # Safety
+ `scope` must be a live pointer returned by this library and `path` a valid NUL-terminated string.
+ */
+enum BitpermStatus bitperm_grant(struct BitpermScope *scope, const char *path);
+
+/*
+ Revoke the permission at `path`, e.g. `READ` or `billing:REFUND`, subject to the rules of its scope.
+
+ # Safety
+ `scope` must be a live pointer returned by this library and `path` a valid NUL-terminated string.
+ */
+enum BitpermStatus bitperm_revoke(struct BitpermScope *scope,
+                                  const char *path);
+
+/*
+ Check whether the permission at `path` is granted or implied. Unknown paths and invalid arguments are never granted.
+
+ # Safety
+ `scope` must be null or a live pointer returned by this library and `path` null or a valid NUL-terminated string.
+ */
+bool bitperm_check(const struct BitpermScope *scope,
+                   const char *path);
+
+/*
+ Get the value of the root scope, not including its child scopes. Returns 0 for a null scope.
+
+ # Safety
+ `scope` must be null or a live pointer returned by this library.
+ */
+uint64_t bitperm_as_u64(const struct BitpermScope *scope);
+
+/*
+ Get the fingerprint of the schema of the tree, to store next to values and pass back when applying or importing
+ them. Returns 0 for a null scope.
+
+ # Safety
+ `scope` must be null or a live pointer returned by this library.
+ */
+uint64_t bitperm_fingerprint(const struct BitpermScope *scope);
+
+/*
+ Set the grants of the root scope from a value produced by `bitperm_as_u64` under the schema with fingerprint
+ `expected_fingerprint`, failing with `ScopeError` if the schema has changed since.
+
+ # Safety
+ `scope` must be a live pointer returned by this library.
+ */
+enum BitpermStatus bitperm_apply_u64(struct BitpermScope *scope,
+                                     uint64_t value,
+                                     uint64_t expected_fingerprint);
+
+/*
+ Export the tree as JSON text, in the format of `Scope::to_json`. The string must be freed with
+ `bitperm_string_free`. Returns null for a null scope.
+
+ # Safety
+ `scope` must be null or a live pointer returned by this library.
+ */
+char *bitperm_to_json(const struct BitpermScope *scope);
+
+/*
+ Import a tree from JSON text written by `bitperm_to_json`. When `expected_fingerprint` is not null, the import
+ fails unless the schema has that fingerprint. Returns null on failure, with the reason available from
+ `bitperm_last_error`.
+
+ # Safety
+ `json` must be null or a valid NUL-terminated string, and `expected_fingerprint` null or a valid pointer.
+ */
+struct BitpermScope *bitperm_from_json(const char *json,
+                                       const uint64_t *expected_fingerprint);
+
+/*
+ Free a string returned by this library. Passing null does nothing.
+
+ # Safety
+ `value` must be null or a string returned by this library that has not been freed yet.
+ */
+void bitperm_string_free(char *value);
+
+/*
+ Get the message of the last error reported on the calling thread, or null if there was none. The string is
+ owned by the library and stays valid until the next failing call on the same thread.
+ */
+const char *bitperm_last_error(void);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* BITPERM_H */
@@ -0,0 +1,385 @@
+/*!
+    A stable C ABI over bitperm, so services written in Go, C++ or Swift can build the same schema and evaluate the
+    same bitmasks. Scopes are handed out as opaque `BitpermScope` pointers and every fallible call returns a
+    `BitpermStatus`; the message of the last error on the calling thread is available from `bitperm_last_error`.
+
+    The header in `include/bitperm.h` is generated from this file with cbindgen.
+ */
+
+#![allow(clippy::needless_return)]
+#![cfg_attr(test, allow(clippy::bool_assert_comparison))]
+
+use std::cell::RefCell;
+use std::ffi::{c_char, CStr, CString};
+use std::ptr;
+use bitperm::scope::fingerprint::VersionedValue;
+use bitperm::scope::path::{PermissionPath, ScopePath};
+use bitperm::{ErrorKind, Scope};
+
+/** The result of a fallible call. */
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BitpermStatus {
+    Ok = 0,
+    /** A pointer argument was null. */
+    NullArgument = 1,
+    /** A string argument was not valid UTF-8. */
+    InvalidUtf8 = 2,
+    /** A permission could not be created, granted or revoked. */
+    PermissionError = 3,
+    /** A scope operation failed, e.g. an unknown path or a broken rule. */
+    ScopeError = 4,
+    /** The JSON passed to `bitperm_from_json` could not be parsed. */
+    InvalidJson = 5
+}
+
+/** An opaque scope tree, created by `bitperm_scope_new` or `bitperm_from_json` and freed by `bitperm_scope_free`. */
+pub struct BitpermScope(Scope);
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+fn fail(status: BitpermStatus, message: &str) -> BitpermStatus {
+    let message = CString::new(message.replace('\0', "")).unwrap_or_default();
+    LAST_ERROR.with(|last| *last.borrow_mut() = Some(message));
+
+    return status;
+}
+
+fn fail_with(err: ErrorKind) -> BitpermStatus {
+    let status = match err {
+        ErrorKind::PermissionError(_) => BitpermStatus::PermissionError,
+        ErrorKind::ScopeError(_) => BitpermStatus::ScopeError
+    };
+
+    return fail(status, &err.to_string());
+}
+
+/** Read a C string argument, recording the error and returning the status to report if it is null or not UTF-8. */
+unsafe fn read_str<'a>(value: *const c_char) -> Result<&'a str, BitpermStatus> {
+    if value.is_null() {
+        return Err(fail(BitpermStatus::NullArgument, "null argument"));
+    }
+
+    return CStr::from_ptr(value).to_str().map_err(|_| fail(BitpermStatus::InvalidUtf8, "argument is not valid UTF-8"));
+}
+
+/** Find a child scope of `scope` by its path relative to it. An empty path is `scope` itself. */
+fn find_scope_mut<'a>(scope: &'a mut Scope, path: &ScopePath) -> Result<&'a mut Scope, BitpermStatus> {
+    let mut current = scope;
+    for segment in path.segments() {
+        current = match current.scope(segment) {
+            Some(child) => child,
+            None => return Err(fail(BitpermStatus::ScopeError, &format!("unknown scope `{}`", path)))
+        };
+    }
+
+    return Ok(current);
+}
+
+/**
+    Create an empty root scope. Returns null if `name` is null or not UTF-8.
+
+    # Safety
+    `name` must be null or a valid NUL-terminated string.
+ */
+#[no_mangle]
+pub unsafe extern "C" fn bitperm_scope_new(name: *const c_char) -> *mut BitpermScope {
+    return match read_str(name) {
+        Ok(name) => Box::into_raw(Box::new(BitpermScope(Scope::new(name)))),
+        Err(_) => ptr::null_mut()
+    }
+}
+
+/**
+    Free a scope created by `bitperm_scope_new` or `bitperm_from_json`. Passing null does nothing.
+
+    # Safety
+    `scope` must be null or a pointer returned by this library that has not been freed yet.
+ */
+#[no_mangle]
+pub unsafe extern "C" fn bitperm_scope_free(scope: *mut BitpermScope) {
+    if !scope.is_null() {
+        drop(Box::from_raw(scope));
+    }
+}
+
+/**
+    Add a permission named `name` to the scope at `path` relative to `scope`, e.g. `billing`, or `""` for `scope`.
+
+    # Safety
+    `scope` must be a live pointer returned by this library and `path` and `name` valid NUL-terminated strings.
+ */
+#[no_mangle]
+pub unsafe extern "C" fn bitperm_add_permission(scope: *mut BitpermScope, path: *const c_char, name: *const c_char) -> BitpermStatus {
+    let Some(scope) = scope.as_mut() else { return fail(BitpermStatus::NullArgument, "null scope") };
+    let (path, name) = match (read_str(path), read_str(name)) {
+        (Ok(path), Ok(name)) => (path, name),
+        (Err(status), _) | (_, Err(status)) => return status
+    };
+
+    return match find_scope_mut(&mut scope.0, &ScopePath::parse(path)) {
+        Ok(target) => match target.add_permission(name) {
+            Ok(_) => BitpermStatus::Ok,
+            Err(err) => fail_with(err)
+        },
+        Err(status) => status
+    }
+}
+
+/**
+    Add a child scope named `name` to the scope at `path` relative to `scope`, or `""` for `scope` itself.
+
+    # Safety
+    `scope` must be a live pointer returned by this library and `path` and `name` valid NUL-terminated strings.
+ */
+#[no_mangle]
+pub unsafe extern "C" fn bitperm_add_scope(scope: *mut BitpermScope, path: *const c_char, name: *const c_char) -> BitpermStatus {
+    let Some(scope) = scope.as_mut() else { return fail(BitpermStatus::NullArgument, "null scope") };
+    let (path, name) = match (read_str(path), read_str(name)) {
+        (Ok(path), Ok(name)) => (path, name),
+        (Err(status), _) | (_, Err(status)) => return status
+    };
+
+    return match find_scope_mut(&mut scope.0, &ScopePath::parse(path)) {
+        Ok(target) => match target.add_scope(name) {
+            Ok(_) => BitpermStatus::Ok,
+            Err(err) => fail_with(err)
+        },
+        Err(status) => status
+    }
+}
+
+/** Grant or revoke the permission at a path such as `READ` or `billing:REFUND`. */
+unsafe fn set_granted(scope: *mut BitpermScope, path: *const c_char, grant: bool) -> BitpermStatus {
+    let Some(scope) = scope.as_mut() else { return fail(BitpermStatus::NullArgument, "null scope") };
+    let requested = match read_str(path) {
+        Ok(path) => PermissionPath::parse(path),
+        Err(status) => return status
+    };
+
+    let target = match find_scope_mut(&mut scope.0, &requested.scope) {
+        Ok(target) => target,
+        Err(status) => return status
+    };
+    let result = match grant {
+        true => target.grant(&requested.permission).map(|_| ()),
+        false => target.revoke(&requested.permission).map(|_| ())
+    };
+
+    return match result {
+        Ok(_) => BitpermStatus::Ok,
+        Err(err) => fail_with(err)
+    }
+}
+
+/**
+    Grant the permission at `path`, e.g. `READ` or `billing:REFUND`, subject to the rules of its scope.
+
+    # Safety
+    `scope` must be a live pointer returned by this library and `path` a valid NUL-terminated string.
+ */
+#[no_mangle]
+pub unsafe extern "C" fn bitperm_grant(scope: *mut BitpermScope, path: *const c_char) -> BitpermStatus {
+    return set_granted(scope, path, true);
+}
+
+/**
+    Revoke the permission at `path`, e.g. `READ` or `billing:REFUND`, subject to the rules of its scope.
+
+    # Safety
+    `scope` must be a live pointer returned by this library and `path` a valid NUL-terminated string.
+ */
+#[no_mangle]
+pub unsafe extern "C" fn bitperm_revoke(scope: *mut BitpermScope, path: *const c_char) -> BitpermStatus {
+    return set_granted(scope, path, false);
+}
+
+/**
+    Check whether the permission at `path` is granted or implied. Unknown paths and invalid arguments are never granted.
+
+    # Safety
+    `scope` must be null or a live pointer returned by this library and `path` null or a valid NUL-terminated string.
+ */
+#[no_mangle]
+pub unsafe extern "C" fn bitperm_check(scope: *const BitpermScope, path: *const c_char) -> bool {
+    return match (scope.as_ref(), read_str(path)) {
+        (Some(scope), Ok(path)) => scope.0.check(path),
+        _ => false
+    }
+}
+
+/**
+    Get the value of the root scope, not including its child scopes. Returns 0 for a null scope.
+
+    # Safety
+    `scope` must be null or a live pointer returned by this library.
+ */
+#[no_mangle]
+pub unsafe extern "C" fn bitperm_as_u64(scope: *const BitpermScope) -> u64 {
+    return scope.as_ref().map(|scope| scope.0.as_u64()).unwrap_or(0);
+}
+
+/**
+    Get the fingerprint of the schema of the tree, to store next to values and pass back when applying or importing
+    them. Returns 0 for a null scope.
+
+    # Safety
+    `scope` must be null or a live pointer returned by this library.
+ */
+#[no_mangle]
+pub unsafe extern "C" fn bitperm_fingerprint(scope: *const BitpermScope) -> u64 {
+    return scope.as_ref().map(|scope| scope.0.fingerprint()).unwrap_or(0);
+}
+
+/**
+    Set the grants of the root scope from a value produced by `bitperm_as_u64` under the schema with fingerprint
+    `expected_fingerprint`, failing with `ScopeError` if the schema has changed since.
+
+    # Safety
+    `scope` must be a live pointer returned by this library.
+ */
+#[no_mangle]
+pub unsafe extern "C" fn bitperm_apply_u64(scope: *mut BitpermScope, value: u64, expected_fingerprint: u64) -> BitpermStatus {
+    let Some(scope) = scope.as_mut() else { return fail(BitpermStatus::NullArgument, "null scope") };
+
+    return match scope.0.apply_value(&VersionedValue { schema_hash: expected_fingerprint, value }) {
+        Ok(_) => BitpermStatus::Ok,
+        Err(err) => fail_with(err)
+    }
+}
+
+/**
+    Export the tree as JSON text, in the format of `Scope::to_json`. The string must be freed with
+    `bitperm_string_free`. Returns null for a null scope.
+
+    # Safety
+    `scope` must be null or a live pointer returned by this library.
+ */
+#[no_mangle]
+pub unsafe extern "C" fn bitperm_to_json(scope: *const BitpermScope) -> *mut c_char {
+    return match scope.as_ref().map(|scope| CString::new(scope.0.to_json().to_string())) {
+        Some(Ok(json)) => json.into_raw(),
+        _ => ptr::null_mut()
+    }
+}
+
+/**
+    Import a tree from JSON text written by `bitperm_to_json`. When `expected_fingerprint` is not null, the import
+    fails unless the schema has that fingerprint. Returns null on failure, with the reason available from
+    `bitperm_last_error`.
+
+    # Safety
+    `json` must be null or a valid NUL-terminated string, and `expected_fingerprint` null or a valid pointer.
+ */
+#[no_mangle]
+pub unsafe extern "C" fn bitperm_from_json(json: *const c_char, expected_fingerprint: *const u64) -> *mut BitpermScope {
+    let Ok(json) = read_str(json) else { return ptr::null_mut() };
+    let value = match serde_json::from_str(json) {
+        Ok(value) => value,
+        Err(_) => {
+            fail(BitpermStatus::InvalidJson, "malformed JSON");
+            return ptr::null_mut();
+        }
+    };
+
+    return match Scope::from_json_verified(value, expected_fingerprint.as_ref().copied()) {
+        Ok(scope) => Box::into_raw(Box::new(BitpermScope(scope))),
+        Err(err) => {
+            fail_with(err);
+            ptr::null_mut()
+        }
+    }
+}
+
+/**
+    Free a string returned by this library. Passing null does nothing.
+
+    # Safety
+    `value` must be null or a string returned by this library that has not been freed yet.
+ */
+#[no_mangle]
+pub unsafe extern "C" fn bitperm_string_free(value: *mut c_char) {
+    if !value.is_null() {
+        drop(CString::from_raw(value));
+    }
+}
+
+/**
+    Get the message of the last error reported on the calling thread, or null if there was none. The string is
+    owned by the library and stays valid until the next failing call on the same thread.
+ */
+#[no_mangle]
+pub extern "C" fn bitperm_last_error() -> *const c_char {
+    return LAST_ERROR.with(|last| match last.borrow().as_ref() {
+        Some(message) => message.as_ptr(),
+        None => ptr::null()
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn c(value: &str) -> CString {
+        return CString::new(value).unwrap_or_default();
+    }
+
+    #[test]
+    fn test_scope_lifecycle() {
+        unsafe {
+            let scope = bitperm_scope_new(c("APP").as_ptr());
+            assert_eq!(scope.is_null(), false);
+            assert_eq!(bitperm_add_permission(scope, c("").as_ptr(), c("READ").as_ptr()), BitpermStatus::Ok);
+            assert_eq!(bitperm_add_permission(scope, c("").as_ptr(), c("WRITE").as_ptr()), BitpermStatus::Ok);
+            assert_eq!(bitperm_add_scope(scope, c("").as_ptr(), c("billing").as_ptr()), BitpermStatus::Ok);
+            assert_eq!(bitperm_add_permission(scope, c("billing").as_ptr(), c("REFUND").as_ptr()), BitpermStatus::Ok);
+
+            assert_eq!(bitperm_grant(scope, c("WRITE").as_ptr()), BitpermStatus::Ok);
+            assert_eq!(bitperm_grant(scope, c("billing:REFUND").as_ptr()), BitpermStatus::Ok);
+            assert_eq!(bitperm_as_u64(scope), 0b10);
+            assert_eq!(bitperm_check(scope, c("billing:REFUND").as_ptr()), true);
+
+            let fingerprint = bitperm_fingerprint(scope);
+            let json = bitperm_to_json(scope);
+            let restored = bitperm_from_json(json, &fingerprint);
+            let unverified = bitperm_from_json(json, ptr::null());
+            assert_eq!(unverified.is_null(), false);
+            bitperm_scope_free(unverified);
+            bitperm_string_free(json);
+            assert_eq!(bitperm_check(restored, c("billing:REFUND").as_ptr()), true);
+            assert_eq!(bitperm_revoke(restored, c("WRITE").as_ptr()), BitpermStatus::Ok);
+            assert_eq!(bitperm_as_u64(restored), 0);
+            assert_eq!(bitperm_apply_u64(restored, 0b11, fingerprint), BitpermStatus::Ok);
+            assert_eq!(bitperm_check(restored, c("READ").as_ptr()), true);
+
+            bitperm_scope_free(restored);
+            bitperm_scope_free(scope);
+        }
+    }
+
+    #[test]
+    fn test_errors_are_reported() {
+        unsafe {
+            let scope = bitperm_scope_new(c("APP").as_ptr());
+            assert_eq!(bitperm_grant(scope, c("READ").as_ptr()), BitpermStatus::ScopeError);
+            assert_eq!(bitperm_last_error().is_null(), false);
+            assert_eq!(bitperm_add_permission(scope, c("reports").as_ptr(), c("READ").as_ptr()), BitpermStatus::ScopeError);
+            assert_eq!(bitperm_grant(ptr::null_mut(), c("READ").as_ptr()), BitpermStatus::NullArgument);
+            assert_eq!(bitperm_check(scope, ptr::null()), false);
+
+            assert_eq!(bitperm_from_json(c("{").as_ptr(), ptr::null()).is_null(), true);
+            assert_eq!(CStr::from_ptr(bitperm_last_error()).to_str(), Ok("malformed JSON"));
+
+            // values and imports from another schema are refused
+            let json = bitperm_to_json(scope);
+            let drifted = bitperm_fingerprint(scope) ^ 1;
+            assert_eq!(bitperm_from_json(json, &drifted).is_null(), true);
+            bitperm_string_free(json);
+            assert_eq!(bitperm_apply_u64(scope, 0, drifted), BitpermStatus::ScopeError);
+
+            bitperm_scope_free(scope);
+        }
+    }
+}
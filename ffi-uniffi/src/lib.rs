@@ -0,0 +1,236 @@
+/*!
+    UniFFI bindings over bitperm, so Kotlin and Swift apps can load the schema published by the server and check
+    permissions client-side against the same bit layout. Generate the bindings from the built library with the
+    bundled `uniffi-bindgen` binary:
+
+    `cargo run -p bitperm-uniffi --bin uniffi-bindgen generate --library target/debug/libbitperm_uniffi.so --language kotlin --out-dir out`
+ */
+
+#![allow(clippy::needless_return)]
+#![cfg_attr(test, allow(clippy::bool_assert_comparison))]
+
+use std::fmt::{self, Display, Formatter};
+use std::sync::{Arc, Mutex, MutexGuard};
+use bitperm::scope::path::ScopePath;
+use bitperm::ErrorKind;
+
+uniffi::setup_scaffolding!();
+
+/** The error thrown by every fallible call, carrying the message of the underlying bitperm error. */
+#[derive(Debug, PartialEq, Eq, uniffi::Error)]
+pub enum BitpermError {
+    /** A permission could not be created, granted or revoked. */
+    Permission { message: String },
+    /** A scope operation failed, e.g. an unknown path or a broken rule. */
+    Scope { message: String },
+    /** The JSON passed to `Scope.fromJson` could not be parsed. */
+    InvalidJson { message: String }
+}
+
+impl Display for BitpermError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        return match self {
+            BitpermError::Permission { message } | BitpermError::Scope { message } | BitpermError::InvalidJson { message } => {
+                write!(f, "{}", message)
+            }
+        }
+    }
+}
+
+impl std::error::Error for BitpermError {}
+
+impl From<ErrorKind> for BitpermError {
+    fn from(err: ErrorKind) -> BitpermError {
+        let message = err.to_string();
+
+        return match err {
+            ErrorKind::PermissionError(_) => BitpermError::Permission { message },
+            ErrorKind::ScopeError(_) => BitpermError::Scope { message }
+        }
+    }
+}
+
+/** A permission of a scope, as returned by `Scope.permissions`. */
+#[derive(Clone, Debug, PartialEq, Eq, uniffi::Record)]
+pub struct Permission {
+    pub name: String,
+    pub value: u64,
+    pub granted: bool
+}
+
+/**
+    A scope tree. The tree is held behind a mutex, as foreign code shares objects freely between threads, so every
+    call sees and leaves the tree in a consistent state.
+ */
+#[derive(uniffi::Object)]
+pub struct Scope {
+    inner: Mutex<bitperm::Scope>
+}
+
+impl Scope {
+    fn lock(&self) -> MutexGuard<'_, bitperm::Scope> {
+        // a panic while holding the lock cannot leave the tree half-changed, as every change is applied whole
+        return self.inner.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    }
+
+    /** Run `change` on the scope at `path` relative to the root, or on the root for an empty path. */
+    fn with_scope_at(&self, path: &str, change: impl FnOnce(&mut bitperm::Scope) -> Result<(), ErrorKind>) -> Result<(), BitpermError> {
+        let mut root = self.lock();
+
+        return change(scope_at(&mut root, path)?).map_err(BitpermError::from);
+    }
+}
+
+/** Get the scope at `path` relative to `root`, or `root` itself for an empty path. */
+fn scope_at<'a>(root: &'a mut bitperm::Scope, path: &str) -> Result<&'a mut bitperm::Scope, BitpermError> {
+    let mut scope = root;
+    for segment in ScopePath::parse(path).segments() {
+        scope = match scope.scope(segment) {
+            Some(child) => child,
+            None => return Err(BitpermError::Scope { message: format!("unknown scope `{}`", path) })
+        };
+    }
+
+    return Ok(scope);
+}
+
+#[uniffi::export]
+impl Scope {
+    /** Create an empty root scope. */
+    #[uniffi::constructor]
+    pub fn new(name: String) -> Arc<Scope> {
+        return Arc::new(Scope { inner: Mutex::new(bitperm::Scope::new(&name)) });
+    }
+
+    /**
+        Import a tree from JSON written by `toJson` or by `Scope::to_json` on the server. When `expected_fingerprint`
+        is given, the import fails unless the schema has that fingerprint.
+     */
+    #[uniffi::constructor]
+    pub fn from_json(json: String, expected_fingerprint: Option<u64>) -> Result<Arc<Scope>, BitpermError> {
+        let value = serde_json::from_str(&json).map_err(|err| BitpermError::InvalidJson { message: err.to_string() })?;
+        let scope = bitperm::Scope::from_json_verified(value, expected_fingerprint)?;
+
+        return Ok(Arc::new(Scope { inner: Mutex::new(scope) }));
+    }
+
+    pub fn name(&self) -> String {
+        return self.lock().name().to_string();
+    }
+
+    /** Add a permission to the scope at `path`, e.g. `billing`, or an empty string for the root. */
+    pub fn add_permission(&self, path: String, name: String) -> Result<(), BitpermError> {
+        return self.with_scope_at(&path, |scope| scope.add_permission(&name).map(|_| ()));
+    }
+
+    /** Add a child scope to the scope at `path`, e.g. `billing`, or an empty string for the root. */
+    pub fn add_scope(&self, path: String, name: String) -> Result<(), BitpermError> {
+        return self.with_scope_at(&path, |scope| scope.add_scope(&name).map(|_| ()));
+    }
+
+    /** Grant the permission at `path`, e.g. `READ` or `billing:REFUND`. */
+    pub fn grant(&self, path: String) -> Result<(), BitpermError> {
+        self.lock().grant_path(&path)?;
+
+        return Ok(());
+    }
+
+    /** Revoke the permission at `path`, e.g. `READ` or `billing:REFUND`. */
+    pub fn revoke(&self, path: String) -> Result<(), BitpermError> {
+        self.lock().revoke_path(&path)?;
+
+        return Ok(());
+    }
+
+    /** Check whether the permission at `path` is granted or implied. Unknown paths are never granted. */
+    pub fn check(&self, path: String) -> bool {
+        return self.lock().check(&path);
+    }
+
+    /** Get the permissions defined directly on the scope at `path`, in the order they were added. */
+    pub fn permissions(&self, path: String) -> Result<Vec<Permission>, BitpermError> {
+        let mut permissions = Vec::new();
+        self.with_scope_at(&path, |scope| {
+            permissions = scope.permissions()
+                .map(|permission| Permission { name: permission.name.clone(), value: permission.value, granted: permission.has() })
+                .collect();
+            return Ok(());
+        })?;
+
+        return Ok(permissions);
+    }
+
+    /** Get the value of the scope at `path`, not including its child scopes. */
+    pub fn as_u64(&self, path: String) -> Result<u64, BitpermError> {
+        let mut value = 0;
+        self.with_scope_at(&path, |scope| {
+            value = scope.as_u64();
+            return Ok(());
+        })?;
+
+        return Ok(value);
+    }
+
+    /**
+        Set the grants of the scope at `path` from a value produced by `asU64`, e.g. one read from a token, under
+        the schema with fingerprint `expected_fingerprint`. Fails without changing anything if the schema of the
+        tree has changed since.
+     */
+    pub fn apply_u64(&self, path: String, value: u64, expected_fingerprint: u64) -> Result<(), BitpermError> {
+        let mut root = self.lock();
+        root.verify_fingerprint(expected_fingerprint)?;
+        scope_at(&mut root, &path)?.apply_u64(value)?;
+
+        return Ok(());
+    }
+
+    /** The fingerprint of the schema, to compare against the one the server publishes. */
+    pub fn fingerprint(&self) -> u64 {
+        return self.lock().fingerprint();
+    }
+
+    /** Export the tree as JSON, in the format of `Scope::to_json`. */
+    pub fn to_json(&self) -> String {
+        return self.lock().to_json().to_string();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scope_object() {
+        let scope = Scope::new(String::from("APP"));
+        assert_eq!(scope.add_permission(String::new(), String::from("READ")), Ok(()));
+        assert_eq!(scope.add_scope(String::new(), String::from("billing")), Ok(()));
+        assert_eq!(scope.add_permission(String::from("billing"), String::from("REFUND")), Ok(()));
+        assert_eq!(scope.grant(String::from("billing:REFUND")), Ok(()));
+
+        let restored = match Scope::from_json(scope.to_json(), Some(scope.fingerprint())) {
+            Ok(restored) => restored,
+            Err(err) => panic!("round trip failed: {}", err)
+        };
+        assert_eq!(restored.fingerprint(), scope.fingerprint());
+        assert_eq!(restored.check(String::from("billing:REFUND")), true);
+        assert_eq!(restored.as_u64(String::from("billing")), Ok(1));
+        assert_eq!(restored.apply_u64(String::new(), 1, scope.fingerprint()), Ok(()));
+        assert_eq!(restored.permissions(String::new()).map(|permissions| permissions[0].granted), Ok(true));
+    }
+
+    #[test]
+    fn test_errors_are_mapped() {
+        let scope = Scope::new(String::from("APP"));
+
+        assert!(matches!(scope.grant(String::from("READ")), Err(BitpermError::Scope { .. })));
+        assert!(matches!(scope.add_scope(String::from("reports"), String::from("daily")), Err(BitpermError::Scope { .. })));
+        assert!(matches!(Scope::from_json(String::from("{"), None), Err(BitpermError::InvalidJson { .. })));
+
+        // values and imports produced under another schema are refused
+        let fingerprint = scope.fingerprint();
+        assert_eq!(scope.add_permission(String::new(), String::from("READ")), Ok(()));
+        assert!(matches!(scope.apply_u64(String::new(), 1, fingerprint), Err(BitpermError::Scope { .. })));
+        assert_eq!(scope.check(String::from("READ")), false);
+        assert!(matches!(Scope::from_json(scope.to_json(), Some(fingerprint)), Err(BitpermError::Scope { .. })));
+    }
+}
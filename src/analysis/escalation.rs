@@ -0,0 +1,195 @@
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+use serde::Serialize;
+use crate::scope::path::PermissionPath;
+use crate::scope::Scope;
+
+/** Permissions carrying this tag are treated as high risk when newly granted. */
+pub const DANGEROUS_TAG: &str = "dangerous";
+
+/** Risk is the severity assigned to a grant change, ordered from least to most severe. */
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+pub enum Risk {
+    Low,
+    Medium,
+    High
+}
+
+/** ChangeKind describes how the effective state of a permission path changed. */
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+pub enum ChangeKind {
+    /** The permission was not held before and is held (granted or implied) after. */
+    Granted,
+    /** The permission was held before and is not held after. */
+    Revoked,
+    /** Every permission of the scope is held after although they were not all held before. */
+    ScopeFullyGranted
+}
+
+/** GrantChange is a single classified difference between two grant states. */
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct GrantChange {
+    /** The full permission path, or the scope path for scope-wide changes. */
+    pub path: String,
+    pub kind: ChangeKind,
+    pub risk: Risk
+}
+
+/** EscalationReport lists the changes between two grant states, ordered by path. */
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct EscalationReport {
+    pub changes: Vec<GrantChange>
+}
+
+impl EscalationReport {
+    /** Get the most severe risk among the changes, or None if nothing changed. */
+    pub fn highest_risk(&self) -> Option<Risk> {
+        return self.changes.iter().map(|change| change.risk).max();
+    }
+
+    /** Check whether any change reaches at least the given risk, e.g. to fail a deployment pipeline. */
+    pub fn exceeds(&self, risk: Risk) -> bool {
+        return self.highest_risk().is_some_and(|highest| highest >= risk);
+    }
+}
+
+/**
+    Compare the grant states of two scopes sharing a schema and classify every permission whose effective
+    state changed. Newly held permissions are medium risk, or high risk when tagged `dangerous` in `after`;
+    a scope whose every permission became held is reported as a high risk broadening; revocations are low risk.
+    Child scopes are matched by name, and permissions missing from `after` count as revoked.
+ */
+pub fn diff_grants(before: &Scope, after: &Scope) -> EscalationReport {
+    let mut changes: Vec<GrantChange> = vec![];
+    let mut stack: Vec<(Option<&Scope>, &Scope)> = vec![(Some(before), after)];
+
+    while let Some((old, new)) = stack.pop() {
+        let mut all_held_before = true;
+        let mut all_held_after = true;
+
+        for permission in new.permission_values() {
+            let held_before = old.is_some_and(|old| old.is_effective(&permission.name));
+            let held_after = new.is_effective(&permission.name);
+            all_held_before &= held_before;
+            all_held_after &= held_after;
+
+            let path = PermissionPath::new(new.path().clone(), &permission.name).to_string();
+            if !held_before && held_after {
+                let risk = match permission.tags.iter().any(|tag| tag == DANGEROUS_TAG) {
+                    true => Risk::High,
+                    false => Risk::Medium
+                };
+                changes.push(GrantChange { path, kind: ChangeKind::Granted, risk });
+            } else if held_before && !held_after {
+                changes.push(GrantChange { path, kind: ChangeKind::Revoked, risk: Risk::Low });
+            }
+        }
+
+        if let Some(old) = old {
+            for permission in old.permission_values() {
+                if new.permission_values().all(|current| current.name != permission.name) && old.is_effective(&permission.name) {
+                    changes.push(GrantChange {
+                        path: PermissionPath::new(new.path().clone(), &permission.name).to_string(),
+                        kind: ChangeKind::Revoked,
+                        risk: Risk::Low
+                    });
+                }
+            }
+        }
+
+        // a single grant is already reported on its own, so only flag broadening across several permissions
+        let permission_count = new.permission_values().count();
+        if permission_count > 1 && all_held_after && !all_held_before {
+            changes.push(GrantChange {
+                path: new.path().to_string(),
+                kind: ChangeKind::ScopeFullyGranted,
+                risk: Risk::High
+            });
+        }
+
+        for child in new.child_scopes() {
+            stack.push((old.and_then(|old| old.child_scope(child.name())), child));
+        }
+    }
+
+    changes.sort_by(|a, b| a.path.cmp(&b.path));
+
+    return EscalationReport { changes };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get_schema() -> Scope {
+        let mut scope = Scope::new("APP");
+
+        if let Err(_) = scope
+            .add_permission("READ")
+            .and_then(|sc| sc.add_permission("WRITE"))
+            .and_then(|sc| sc.add_permission("PURGE"))
+            .and_then(|sc| sc.tag("PURGE", DANGEROUS_TAG))
+            .and_then(|sc| sc.add_scope("billing")) {
+            assert!(false);
+        }
+        match scope.scope("billing") {
+            Some(billing) => {
+                if let Err(_) = billing.add_permission("VIEW").and_then(|sc| sc.add_permission("PAY")) {
+                    assert!(false);
+                }
+            },
+            None => assert!(false)
+        }
+
+        return scope;
+    }
+
+    #[test]
+    fn test_diff_classifies_changes() {
+        let mut before = get_schema();
+        let mut after = get_schema();
+
+        if let Err(_) = before.grant("READ") {
+            assert!(false);
+        }
+        if let Err(_) = after.grant("WRITE").and_then(|sc| sc.grant("PURGE")) {
+            assert!(false);
+        }
+        if let Some(billing) = after.scope("billing") {
+            if let Err(_) = billing.grant("VIEW").and_then(|sc| sc.grant("PAY")) {
+                assert!(false);
+            }
+        }
+
+        let report = diff_grants(&before, &after);
+
+        assert_eq!(report.changes, vec![
+            GrantChange { path: "APP.billing".to_string(), kind: ChangeKind::ScopeFullyGranted, risk: Risk::High },
+            GrantChange { path: "APP.billing:PAY".to_string(), kind: ChangeKind::Granted, risk: Risk::Medium },
+            GrantChange { path: "APP.billing:VIEW".to_string(), kind: ChangeKind::Granted, risk: Risk::Medium },
+            GrantChange { path: "APP:PURGE".to_string(), kind: ChangeKind::Granted, risk: Risk::High },
+            GrantChange { path: "APP:READ".to_string(), kind: ChangeKind::Revoked, risk: Risk::Low },
+            GrantChange { path: "APP:WRITE".to_string(), kind: ChangeKind::Granted, risk: Risk::Medium },
+        ]);
+        assert_eq!(report.highest_risk(), Some(Risk::High));
+        assert_eq!(report.exceeds(Risk::High), true);
+    }
+
+    #[test]
+    fn test_diff_counts_implied_grants() {
+        let before = get_schema();
+        let mut after = get_schema();
+
+        if let Err(_) = after.add_implication("WRITE", "READ").and_then(|sc| sc.grant("WRITE")) {
+            assert!(false);
+        }
+
+        let report = diff_grants(&before, &after);
+
+        assert_eq!(report.changes.len(), 2usize);
+        assert_eq!(report.changes[0].path, "APP:READ");
+        assert_eq!(report.exceeds(Risk::High), false);
+        assert_eq!(diff_grants(&after, &after).highest_risk(), None);
+    }
+}
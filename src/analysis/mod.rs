@@ -0,0 +1,3 @@
+pub mod escalation;
+pub mod separation;
+pub mod usage;
@@ -0,0 +1,161 @@
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+use serde::Serialize;
+use crate::scope::Scope;
+
+/** ConflictGroup is a named group of permission paths of which no principal may hold more than one. */
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct ConflictGroup {
+    pub name: String,
+    pub paths: Vec<String>
+}
+
+/** SeparationViolation reports a grant set that holds more than one permission of a conflict group. */
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct SeparationViolation {
+    /** The principal whose grants were analyzed, when analyzing several principals at once. */
+    pub principal: Option<String>,
+    pub group: String,
+    /** The conflicting paths that are held, in the order they were declared. */
+    pub held: Vec<String>
+}
+
+/**
+    SeparationOfDuties analyzes grant sets against groups of conflicting permission paths. Paths are resolved
+    relative to the scope being analyzed and may point into different child scopes, e.g. `payments:SUBMIT` and
+    `approvals:APPROVE`.
+ */
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct SeparationOfDuties {
+    groups: Vec<ConflictGroup>
+}
+
+impl SeparationOfDuties {
+    pub fn new() -> SeparationOfDuties {
+        return SeparationOfDuties {
+            groups: vec![]
+        }
+    }
+
+    /** Declare a group of permission paths of which at most one may be held. */
+    pub fn add_group(&mut self, name: &str, paths: &[&str]) -> &mut SeparationOfDuties {
+        self.groups.push(ConflictGroup {
+            name: name.to_string(),
+            paths: paths.iter().map(|path| path.to_string()).collect()
+        });
+
+        return self;
+    }
+
+    pub fn groups(&self) -> &[ConflictGroup] {
+        return &self.groups;
+    }
+
+    /** Report every conflict group for which the grant set of `scope` holds more than one path. */
+    pub fn analyze(&self, scope: &Scope) -> Vec<SeparationViolation> {
+        let mut violations: Vec<SeparationViolation> = vec![];
+
+        for group in &self.groups {
+            let held: Vec<String> = group.paths.iter()
+                .filter(|path| scope.check(path))
+                .cloned()
+                .collect();
+
+            if held.len() > 1 {
+                violations.push(SeparationViolation {
+                    principal: None,
+                    group: group.name.clone(),
+                    held
+                });
+            }
+        }
+
+        return violations;
+    }
+
+    /** Analyze the grant sets of several principals, given as (principal, scope) pairs. */
+    pub fn analyze_principals<'a, I>(&self, principals: I) -> Vec<SeparationViolation>
+    where
+        I: IntoIterator<Item = (&'a str, &'a Scope)>
+    {
+        let mut violations: Vec<SeparationViolation> = vec![];
+
+        for (principal, scope) in principals {
+            for mut violation in self.analyze(scope) {
+                violation.principal = Some(principal.to_string());
+                violations.push(violation);
+            }
+        }
+
+        return violations;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /** Build a tree with payment submission and approval in separate child scopes. */
+    fn get_payments_scope(submit: bool, approve: bool) -> Scope {
+        let mut scope = Scope::new("ROOT");
+
+        if let Err(_) = scope.add_scope("payments").and_then(|sc| sc.add_scope("approvals")) {
+            assert!(false);
+        }
+
+        if let Some(payments) = scope.scope("payments") {
+            let _ = payments.add_permission("SUBMIT");
+            if submit {
+                let _ = payments.grant("SUBMIT");
+            }
+        }
+
+        if let Some(approvals) = scope.scope("approvals") {
+            let _ = approvals.add_permission("APPROVE");
+            if approve {
+                let _ = approvals.grant("APPROVE");
+            }
+        }
+
+        return scope;
+    }
+
+    fn get_analyzer() -> SeparationOfDuties {
+        let mut analyzer = SeparationOfDuties::new();
+        analyzer.add_group("PAYMENTS", &["payments:SUBMIT", "approvals:APPROVE"]);
+
+        return analyzer;
+    }
+
+    #[test]
+    fn test_analyze_no_violation() {
+        let analyzer = get_analyzer();
+
+        assert_eq!(analyzer.analyze(&get_payments_scope(true, false)).is_empty(), true);
+        assert_eq!(analyzer.analyze(&get_payments_scope(false, true)).is_empty(), true);
+    }
+
+    #[test]
+    fn test_analyze_violation_across_scopes() {
+        let analyzer = get_analyzer();
+        let violations = analyzer.analyze(&get_payments_scope(true, true));
+
+        assert_eq!(violations.len(), 1usize);
+        assert_eq!(violations[0].group, "PAYMENTS");
+        assert_eq!(violations[0].held, vec!["payments:SUBMIT".to_string(), "approvals:APPROVE".to_string()]);
+        assert_eq!(violations[0].principal, None);
+    }
+
+    #[test]
+    fn test_analyze_principals() {
+        let analyzer = get_analyzer();
+        let alice = get_payments_scope(true, true);
+        let bob = get_payments_scope(true, false);
+
+        let violations = analyzer.analyze_principals(vec![("alice", &alice), ("bob", &bob)]);
+
+        assert_eq!(violations.len(), 1usize);
+        assert_eq!(violations[0].principal, Some("alice".to_string()));
+    }
+}
@@ -0,0 +1,131 @@
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::ops::Range;
+use hashbrown::HashSet;
+use serde::{Deserialize, Serialize};
+use crate::scope::path::PermissionPath;
+use crate::scope::Scope;
+
+/** UsageEvent records that a principal's grants were checked against a path, relative to their root scope. */
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UsageEvent {
+    pub principal: String,
+    pub path: String,
+    pub timestamp: u64
+}
+
+/** UnusedPermission is a permission a principal holds that no check within the window relied on. */
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct UnusedPermission {
+    pub principal: String,
+    /** The full permission path, including the root scope, e.g. `APP.billing:PAY`. */
+    pub path: String
+}
+
+/**
+    Report the permissions each principal has been granted but that were never needed by a check in `window`,
+    given as (principal, scope) pairs and a usage log of checked paths. A check counts as using the checked
+    permission and every granted permission implying it, and checks through an alias count for the renamed
+    permission. The result is ordered by principal, then path.
+ */
+pub fn unused_permissions<'a, I>(principals: I, usage: &[UsageEvent], window: Range<u64>) -> Vec<UnusedPermission>
+where
+    I: IntoIterator<Item = (&'a str, &'a Scope)>
+{
+    let mut unused: Vec<UnusedPermission> = vec![];
+
+    for (principal, root) in principals {
+        let mut used: HashSet<String> = HashSet::new();
+
+        for event in usage.iter().filter(|event| event.principal == principal && window.contains(&event.timestamp)) {
+            let requested = PermissionPath::parse(&event.path);
+            let scope = match root.find_scope(&requested.scope) {
+                Some(scope) => scope,
+                None => continue
+            };
+            let name = scope.resolve_name(&requested.permission);
+
+            for permission in scope.permission_values() {
+                if permission.name == name || (permission.has() && scope.implies(&permission.name, &name)) {
+                    used.insert(PermissionPath::new(scope.path().clone(), &permission.name).to_string());
+                }
+            }
+        }
+
+        let mut stack: Vec<&Scope> = vec![root];
+        let mut principal_unused: Vec<UnusedPermission> = vec![];
+        while let Some(scope) = stack.pop() {
+            for permission in scope.permission_values().filter(|permission| permission.has()) {
+                let path = PermissionPath::new(scope.path().clone(), &permission.name).to_string();
+                if !used.contains(&path) {
+                    principal_unused.push(UnusedPermission { principal: principal.to_string(), path });
+                }
+            }
+            stack.extend(scope.child_scopes());
+        }
+
+        principal_unused.sort_by(|a, b| a.path.cmp(&b.path));
+        unused.extend(principal_unused);
+    }
+
+    unused.sort_by(|a, b| a.principal.cmp(&b.principal));
+
+    return unused;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get_principal_scope() -> Scope {
+        let mut scope = Scope::new("APP");
+
+        if let Err(_) = scope
+            .add_permission("READ")
+            .and_then(|sc| sc.add_permission("WRITE"))
+            .and_then(|sc| sc.add_permission("ADMIN"))
+            .and_then(|sc| sc.add_implication("WRITE", "READ"))
+            .and_then(|sc| sc.grant("WRITE"))
+            .and_then(|sc| sc.grant("ADMIN"))
+            .and_then(|sc| sc.add_scope("billing")) {
+            assert!(false);
+        }
+        match scope.scope("billing") {
+            Some(billing) => {
+                if let Err(_) = billing.add_permission("PAY").and_then(|sc| sc.grant("PAY")) {
+                    assert!(false);
+                }
+            },
+            None => assert!(false)
+        }
+
+        return scope;
+    }
+
+    fn event(principal: &str, path: &str, timestamp: u64) -> UsageEvent {
+        return UsageEvent { principal: principal.to_string(), path: path.to_string(), timestamp };
+    }
+
+    #[test]
+    fn test_unused_permissions_in_window() {
+        let alice = get_principal_scope();
+        let bob = get_principal_scope();
+
+        let usage = vec![
+            event("alice", "READ", 10),         // satisfied by the WRITE grant
+            event("alice", "billing:PAY", 5),   // outside of the window
+            event("bob", "billing.PAY", 12),
+            event("bob", "ADMIN", 15),
+            event("bob", "payroll:RUN", 16),    // unknown paths are ignored
+        ];
+
+        let report = unused_permissions([("bob", &bob), ("alice", &alice)], &usage, 10..20);
+
+        assert_eq!(report, vec![
+            UnusedPermission { principal: "alice".to_string(), path: "APP.billing:PAY".to_string() },
+            UnusedPermission { principal: "alice".to_string(), path: "APP:ADMIN".to_string() },
+            UnusedPermission { principal: "bob".to_string(), path: "APP:WRITE".to_string() },
+        ]);
+    }
+}
@@ -0,0 +1,173 @@
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use serde::{Deserialize, Serialize};
+use crate::audit::AuditLog;
+use crate::common::error::ErrorKind;
+use crate::scope::error::{ScopeError, ScopeErrorCase};
+use crate::scope::path::PermissionPath;
+use crate::scope::Scope;
+
+/** GrantStatus is the stage a pending grant has reached. */
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GrantStatus {
+    /** Waiting for approvals. */
+    #[default]
+    Pending,
+    /** Approved and granted. */
+    Applied,
+    /** Turned down by an approver; it will never be granted. */
+    Rejected
+}
+
+/** Approval records who approved a pending grant, and when. */
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Approval {
+    pub approver: String,
+    /** Time of the approval as supplied by the caller, like the timestamps of audit records. */
+    pub timestamp: u64
+}
+
+/**
+    PendingGrant is a request by a principal for a permission, which is only granted once enough approvers have
+    signed off on it. The whole request, including its approvals, serializes with serde so it can be stored between
+    steps, and the grant is written to an `AuditLog` with the approver who completed it as the actor.
+ */
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PendingGrant {
+    /** Identifier of whoever the permission is requested for. */
+    pub principal: String,
+    /** Path of the requested permission, relative to the scope the request was made against. */
+    pub path: String,
+    pub requested_at: u64,
+    pub required_approvals: usize,
+    pub approvals: Vec<Approval>,
+    pub status: GrantStatus
+}
+
+impl PendingGrant {
+    /**
+        Request the permission at `path` (relative to `scope`) for `principal`, to be granted after
+        `required_approvals` approvals. Fails with UnknownScope or UnknownPermission if the permission is not defined.
+     */
+    pub fn request(scope: &Scope, principal: &str, path: &str, required_approvals: usize, timestamp: u64) -> Result<PendingGrant, ErrorKind> {
+        let requested = PermissionPath::parse(path);
+        let target = match scope.find_scope(&requested.scope) {
+            Some(target) => target,
+            None => return Err(ErrorKind::ScopeError(ScopeError::new(ScopeErrorCase::UnknownScope, &requested.scope.to_string())))
+        };
+        let permission = target.find_permission_name(&requested.permission)?;
+
+        return Ok(PendingGrant {
+            principal: principal.to_string(),
+            path: PermissionPath::new(requested.scope, &permission).to_string(),
+            requested_at: timestamp,
+            required_approvals,
+            approvals: Vec::new(),
+            status: GrantStatus::Pending
+        });
+    }
+
+    /** Check whether enough distinct approvers have approved the request. */
+    pub fn is_approved(&self) -> bool {
+        return self.approvals.len() >= self.required_approvals;
+    }
+
+    /**
+        Record an approval and, once the required number is reached, grant the permission through `log`. Approvals
+        by the principal themselves, repeated approvals and approvals of requests that are no longer pending are
+        ignored. If the grant fails the approval is kept and the request stays pending, so it can be applied again
+        with `apply` once the cause is fixed.
+     */
+    pub fn approve(&mut self, scope: &mut Scope, log: &mut AuditLog, approver: &str, timestamp: u64) -> Result<GrantStatus, ErrorKind> {
+        let counts = self.status == GrantStatus::Pending
+            && approver != self.principal
+            && !self.approvals.iter().any(|approval| approval.approver == approver);
+
+        if counts {
+            self.approvals.push(Approval { approver: approver.to_string(), timestamp });
+            if self.is_approved() {
+                self.apply(scope, log, timestamp)?;
+            }
+        }
+
+        return Ok(self.status);
+    }
+
+    /** Grant the permission of an approved request that is still pending, returning the resulting status. */
+    pub fn apply(&mut self, scope: &mut Scope, log: &mut AuditLog, timestamp: u64) -> Result<GrantStatus, ErrorKind> {
+        if self.status == GrantStatus::Pending && self.is_approved() {
+            let actor = match self.approvals.last() {
+                Some(approval) => approval.approver.as_str(),
+                None => self.principal.as_str()
+            };
+
+            log.grant(scope, &self.path, actor, timestamp)?;
+            self.status = GrantStatus::Applied;
+        }
+
+        return Ok(self.status);
+    }
+
+    /** Turn the request down for good. Requests that were already applied are left as they are. */
+    pub fn reject(&mut self) -> GrantStatus {
+        if self.status == GrantStatus::Pending {
+            self.status = GrantStatus::Rejected;
+        }
+
+        return self.status;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::fixtures::billing_scope;
+    use crate::{assert_granted, assert_not_granted, assert_ok, assert_scope_err};
+
+    #[test]
+    fn test_grant_applies_after_required_approvals() {
+        let mut scope = billing_scope();
+        let mut log = AuditLog::new();
+        let mut request = assert_ok!(PendingGrant::request(&scope, "alice", "billing.REFUND", 2, 100));
+        assert_eq!(request.path, "billing:REFUND");
+
+        // self-approvals and repeated approvals do not count
+        assert_eq!(assert_ok!(request.approve(&mut scope, &mut log, "alice", 110)), GrantStatus::Pending);
+        assert_eq!(assert_ok!(request.approve(&mut scope, &mut log, "bob", 120)), GrantStatus::Pending);
+        assert_eq!(assert_ok!(request.approve(&mut scope, &mut log, "bob", 130)), GrantStatus::Pending);
+        assert_not_granted!(scope, "billing:REFUND");
+        assert_eq!(log.records().len(), 0);
+
+        assert_eq!(assert_ok!(request.approve(&mut scope, &mut log, "carol", 140)), GrantStatus::Applied);
+        assert_granted!(scope, "billing:REFUND");
+        assert_eq!(log.records().len(), 1);
+        assert_eq!((log.records()[0].actor.as_str(), log.records()[0].path.as_str()), ("carol", "APP.billing:REFUND"));
+        assert_eq!(request.reject(), GrantStatus::Applied);
+    }
+
+    #[test]
+    fn test_pending_grant_round_trips_through_json() {
+        let scope = billing_scope();
+        let mut request = assert_ok!(PendingGrant::request(&scope, "alice", "billing:REFUND", 1, 100));
+        request.approvals.push(Approval { approver: "bob".to_string(), timestamp: 120 });
+
+        let json = serde_json::to_value(&request).unwrap_or_else(|err| panic!("not serialized: {}", err));
+        assert_eq!(json["status"], "pending");
+        assert_eq!(serde_json::from_value::<PendingGrant>(json).ok(), Some(request));
+    }
+
+    #[test]
+    fn test_rejected_and_unknown_requests() {
+        let mut scope = billing_scope();
+        let mut log = AuditLog::new();
+
+        assert_scope_err!(PendingGrant::request(&scope, "alice", "billing:VOID", 1, 0), ScopeErrorCase::UnknownPermission);
+        assert_scope_err!(PendingGrant::request(&scope, "alice", "payroll:RUN", 1, 0), ScopeErrorCase::UnknownScope);
+
+        let mut request = assert_ok!(PendingGrant::request(&scope, "alice", "billing:REFUND", 1, 0));
+        assert_eq!(request.reject(), GrantStatus::Rejected);
+        assert_eq!(assert_ok!(request.approve(&mut scope, &mut log, "bob", 10)), GrantStatus::Rejected);
+        assert_not_granted!(scope, "billing:REFUND");
+    }
+}
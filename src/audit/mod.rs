@@ -0,0 +1,185 @@
+pub mod approval;
+
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use serde::{Deserialize, Serialize};
+use crate::common::error::ErrorKind;
+use crate::scope::path::PermissionPath;
+use crate::scope::Scope;
+
+/** AuditOp is the kind of change an audit record describes. */
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditOp {
+    Grant,
+    Revoke
+}
+
+/**
+    AuditRecord describes a single change to the grants of a scope. The field names are part of the
+    JSON Lines export format and must not change.
+ */
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AuditRecord {
+    /** Time of the change as supplied by the caller, conventionally milliseconds since the Unix epoch. */
+    pub timestamp: u64,
+    /** Identifier of whoever made the change. */
+    pub actor: String,
+    pub op: AuditOp,
+    /** Full path of the permission that changed, including the root scope, e.g. `APP.billing:READ`. */
+    pub path: String,
+    /** Value of the permission's scope before the change. */
+    pub before: u64,
+    /** Value of the permission's scope after the change. */
+    pub after: u64
+}
+
+/** AuditLog records grant changes made through it, in the order they were applied. */
+#[derive(Clone, Debug, Default)]
+pub struct AuditLog {
+    records: Vec<AuditRecord>
+}
+
+impl AuditLog {
+    pub fn new() -> AuditLog {
+        return AuditLog { records: Vec::new() };
+    }
+
+    /** Append a record for a change applied elsewhere. */
+    pub fn record(&mut self, record: AuditRecord) -> &mut AuditLog {
+        self.records.push(record);
+
+        return self;
+    }
+
+    pub fn records(&self) -> &[AuditRecord] {
+        return &self.records;
+    }
+
+    /** Grant the permission at `path` (relative to `scope`) and record the change. Failed grants are not recorded. */
+    pub fn grant(&mut self, scope: &mut Scope, path: &str, actor: &str, timestamp: u64) -> Result<&mut AuditLog, ErrorKind> {
+        return self.apply(scope, path, actor, timestamp, AuditOp::Grant);
+    }
+
+    /** Revoke the permission at `path` (relative to `scope`) and record the change. Failed revocations are not recorded. */
+    pub fn revoke(&mut self, scope: &mut Scope, path: &str, actor: &str, timestamp: u64) -> Result<&mut AuditLog, ErrorKind> {
+        return self.apply(scope, path, actor, timestamp, AuditOp::Revoke);
+    }
+
+    fn apply(&mut self, scope: &mut Scope, path: &str, actor: &str, timestamp: u64, op: AuditOp) -> Result<&mut AuditLog, ErrorKind> {
+        let requested = PermissionPath::parse(path);
+        let target = scope.resolve_scope_mut(&requested.scope)?;
+        let permission = target.find_permission_name(&requested.permission)?;
+        let before = target.as_u64();
+
+        match op {
+            AuditOp::Grant => target.grant(&permission)?,
+            AuditOp::Revoke => target.revoke(&permission)?
+        };
+
+        let record = AuditRecord {
+            timestamp,
+            actor: actor.to_string(),
+            op,
+            path: PermissionPath::new(target.path().clone(), &permission).to_string(),
+            before,
+            after: target.as_u64()
+        };
+
+        return Ok(self.record(record));
+    }
+
+    /** Iterate over the log as JSON Lines, one serialized record per item without the trailing newline. */
+    pub fn json_lines(&self) -> impl Iterator<Item = String> + '_ {
+        return self.records.iter().map(|record| {
+            serde_json::to_string(record).expect("audit records always serialize")
+        });
+    }
+
+    /** Export the whole log as a JSON Lines document. */
+    pub fn to_json_lines(&self) -> String {
+        let mut lines = String::new();
+
+        for line in self.json_lines() {
+            lines.push_str(&line);
+            lines.push('\n');
+        }
+
+        return lines;
+    }
+
+    /** Stream the log as JSON Lines into a writer, such as a file or socket feeding a SIEM. */
+    #[cfg(feature = "std")]
+    pub fn write_json_lines<W: std::io::Write>(&self, writer: &mut W) -> std::io::Result<()> {
+        for line in self.json_lines() {
+            writer.write_all(line.as_bytes())?;
+            writer.write_all(b"\n")?;
+        }
+
+        return Ok(());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scope::error::ScopeErrorCase;
+
+    fn get_audited_scope() -> Scope {
+        let mut scope = Scope::new("APP");
+
+        if let Err(_) = scope
+            .add_permission("READ")
+            .and_then(|sc| sc.add_scope("billing")) {
+            assert!(false);
+        }
+        match scope.scope("billing") {
+            Some(billing) => {
+                if let Err(_) = billing.add_permission("VIEW").and_then(|sc| sc.add_permission("PAY")) {
+                    assert!(false);
+                }
+            },
+            None => assert!(false)
+        }
+
+        return scope;
+    }
+
+    #[test]
+    fn test_audit_log_json_lines() {
+        let mut scope = get_audited_scope();
+        let mut log = AuditLog::new();
+
+        if let Err(_) = log
+            .grant(&mut scope, "billing:PAY", "alice", 1_700_000_000_000)
+            .and_then(|log| log.grant(&mut scope, "READ", "bob", 1_700_000_000_500))
+            .and_then(|log| log.revoke(&mut scope, "billing.PAY", "alice", 1_700_000_001_000)) {
+            assert!(false);
+        }
+
+        let expected = "\
+            {\"timestamp\":1700000000000,\"actor\":\"alice\",\"op\":\"grant\",\"path\":\"APP.billing:PAY\",\"before\":0,\"after\":2}\n\
+            {\"timestamp\":1700000000500,\"actor\":\"bob\",\"op\":\"grant\",\"path\":\"APP:READ\",\"before\":0,\"after\":1}\n\
+            {\"timestamp\":1700000001000,\"actor\":\"alice\",\"op\":\"revoke\",\"path\":\"APP.billing:PAY\",\"before\":2,\"after\":0}\n";
+
+        assert_eq!(log.to_json_lines(), expected);
+
+        let mut written: Vec<u8> = Vec::new();
+        assert!(log.write_json_lines(&mut written).is_ok());
+        assert_eq!(written, expected.as_bytes());
+    }
+
+    #[test]
+    fn test_failed_changes_are_not_recorded() {
+        let mut scope = get_audited_scope();
+        let mut log = AuditLog::new();
+
+        match log.grant(&mut scope, "payroll:RUN", "alice", 0) {
+            Ok(_) => assert!(false),
+            Err(ErrorKind::ScopeError(err)) => assert_eq!(err.case(), ScopeErrorCase::UnknownScope),
+            Err(ErrorKind::PermissionError(_)) => assert!(false)
+        }
+        assert_eq!(log.revoke(&mut scope, "READ", "alice", 0).is_err(), true);
+        assert_eq!(log.records().len(), 0usize);
+    }
+}
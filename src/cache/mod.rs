@@ -0,0 +1,176 @@
+/*!
+    Caching of check results for services that repeat the same checks many times within a burst of requests.
+    `CachedChecks` keys every result by the principal, the path and the tree revision of the principal's scope, so a
+    change to the scope, including one made through `permission` or `permissions_mut`, invalidates its results
+    without any bookkeeping. The storage is pluggable through
+    `CheckCache`, and `MemoryCheckCache` keeps results in memory.
+ */
+
+use alloc::collections::BTreeMap;
+use alloc::string::{String, ToString};
+use crate::scope::Scope;
+
+/**
+    CheckCache stores check results keyed by principal and path, each tagged with the tree revision it was
+    computed at. A result is only served for the revision it was stored with.
+ */
+pub trait CheckCache {
+    /** Get the result stored for `principal` and `path` at `revision`, if any. */
+    fn get(&mut self, principal: &str, path: &str, revision: u64) -> Option<bool>;
+
+    /** Store a result, replacing any result for the same principal and path. */
+    fn insert(&mut self, principal: &str, path: &str, revision: u64, allowed: bool);
+
+    /** Drop every result stored for a principal, e.g. when their scope is replaced or deleted. */
+    fn invalidate(&mut self, principal: &str);
+
+    /** Drop every stored result, e.g. when the schema changes. */
+    fn clear(&mut self);
+}
+
+/** MemoryCheckCache keeps the latest result for every principal and path in memory, counting hits and misses. */
+#[derive(Clone, Debug, Default)]
+pub struct MemoryCheckCache {
+    entries: BTreeMap<(String, String), (u64, bool)>,
+    hits: u64,
+    misses: u64
+}
+
+impl MemoryCheckCache {
+    pub fn new() -> MemoryCheckCache {
+        return MemoryCheckCache::default();
+    }
+
+    /** Get the number of stored results. */
+    pub fn len(&self) -> usize {
+        return self.entries.len();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.entries.is_empty();
+    }
+
+    /** Get the number of lookups answered from the cache and the number that were not, since it was created. */
+    pub fn stats(&self) -> (u64, u64) {
+        return (self.hits, self.misses);
+    }
+}
+
+impl CheckCache for MemoryCheckCache {
+    fn get(&mut self, principal: &str, path: &str, revision: u64) -> Option<bool> {
+        let found = match self.entries.get(&(principal.to_string(), path.to_string())) {
+            Some((stored, allowed)) if *stored == revision => Some(*allowed),
+            _ => None
+        };
+        match found {
+            Some(_) => self.hits += 1,
+            None => self.misses += 1
+        }
+
+        return found;
+    }
+
+    fn insert(&mut self, principal: &str, path: &str, revision: u64, allowed: bool) {
+        self.entries.insert((principal.to_string(), path.to_string()), (revision, allowed));
+    }
+
+    fn invalidate(&mut self, principal: &str) {
+        self.entries.retain(|(stored, _), _| stored != principal);
+    }
+
+    fn clear(&mut self) {
+        self.entries.clear();
+    }
+}
+
+/** CachedChecks answers `check` through a cache. */
+#[derive(Clone, Debug, Default)]
+pub struct CachedChecks<C: CheckCache = MemoryCheckCache> {
+    cache: C
+}
+
+impl<C: CheckCache> CachedChecks<C> {
+    pub fn new(cache: C) -> CachedChecks<C> {
+        return CachedChecks { cache };
+    }
+
+    pub fn cache(&self) -> &C {
+        return &self.cache;
+    }
+
+    pub fn cache_mut(&mut self) -> &mut C {
+        return &mut self.cache;
+    }
+
+    /**
+        Check the permission at `path` in the scope of `principal` like `Scope::check`, reusing the stored result
+        if the scope has not changed since it was computed. Only call it with the scope belonging to `principal`.
+     */
+    pub fn check(&mut self, principal: &str, scope: &Scope, path: &str) -> bool {
+        let revision = scope.tree_revision();
+        if let Some(allowed) = self.cache.get(principal, path, revision) {
+            return allowed;
+        }
+
+        let allowed = scope.check(path);
+        self.cache.insert(principal, path, revision, allowed);
+
+        return allowed;
+    }
+
+    /** Drop every result stored for a principal. Call it when their scope is replaced, e.g. after loading it again. */
+    pub fn invalidate(&mut self, principal: &str) {
+        self.cache.invalidate(principal);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::fixtures::billing_scope;
+    use crate::assert_ok;
+
+    #[test]
+    fn test_results_are_reused_until_the_scope_changes() {
+        let mut checks: CachedChecks = CachedChecks::new(MemoryCheckCache::new());
+        let mut alice = billing_scope();
+
+        assert_eq!(checks.check("alice", &alice, "billing:PAY"), false);
+        assert_eq!(checks.check("alice", &alice, "billing:PAY"), false);
+        assert_eq!(checks.cache().stats(), (1, 1));
+
+        // a change made directly on a child scope invalidates the result too
+        if let Some(billing) = alice.scope("billing") {
+            assert_ok!(billing.grant("PAY"));
+        }
+        assert_eq!(checks.check("alice", &alice, "billing:PAY"), true);
+        assert_eq!(checks.cache().stats(), (1, 2));
+    }
+
+    #[test]
+    fn test_grants_through_permission_references_invalidate() {
+        let mut checks: CachedChecks = CachedChecks::default();
+        let mut alice = billing_scope();
+        assert_eq!(checks.check("alice", &alice, "READ"), false);
+
+        if let Some(read) = alice.permission("READ") {
+            assert_ok!(read.grant());
+        }
+        assert_eq!(checks.check("alice", &alice, "READ"), true);
+        assert_eq!(alice.check("READ"), true);
+    }
+
+    #[test]
+    fn test_invalidate_principal() {
+        let mut checks: CachedChecks = CachedChecks::default();
+        let scope = billing_scope();
+
+        checks.check("alice", &scope, "READ");
+        checks.check("bob", &scope, "READ");
+        checks.invalidate("alice");
+        assert_eq!(checks.cache().len(), 1);
+
+        checks.cache_mut().clear();
+        assert_eq!(checks.cache().is_empty(), true);
+    }
+}
@@ -1,7 +1,76 @@
+use core::fmt;
+use core::fmt::{Debug, Display, Formatter};
 use crate::permission::error::PermissionError;
 use crate::scope::error::ScopeError;
 
+/**
+    ErrorKind is the error returned by fallible operations of the crate. It implements `Error`, so it can be
+    propagated with `?` into `Box<dyn Error>` or other error types of an application.
+ */
 pub enum ErrorKind {
     PermissionError(PermissionError),
     ScopeError(ScopeError)
-}
\ No newline at end of file
+}
+
+impl Debug for ErrorKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        return match self {
+            ErrorKind::PermissionError(err) => f.debug_tuple("PermissionError").field(err).finish(),
+            ErrorKind::ScopeError(err) => f.debug_tuple("ScopeError").field(err).finish()
+        }
+    }
+}
+
+impl Display for ErrorKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        return match self {
+            ErrorKind::PermissionError(err) => Display::fmt(err, f),
+            ErrorKind::ScopeError(err) => Display::fmt(err, f)
+        }
+    }
+}
+
+impl core::error::Error for ErrorKind {}
+
+impl From<PermissionError> for ErrorKind {
+    fn from(err: PermissionError) -> ErrorKind {
+        return ErrorKind::PermissionError(err);
+    }
+}
+
+impl From<ScopeError> for ErrorKind {
+    fn from(err: ScopeError) -> ErrorKind {
+        return ErrorKind::ScopeError(err);
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+    use alloc::boxed::Box;
+    use alloc::format;
+    use alloc::string::ToString;
+    use crate::scope::error::ScopeErrorCase;
+    use crate::scope::Scope;
+
+    fn grant_missing() -> Result<(), Box<dyn std::error::Error>> {
+        let mut scope = Scope::new("APP");
+        scope.grant("READ")?;
+
+        return Ok(());
+    }
+
+    #[test]
+    fn test_error_kind_composes() {
+        let err = match grant_missing() {
+            Err(err) => err,
+            Ok(_) => panic!("granting an unknown permission succeeded")
+        };
+        let expected = ScopeError::new(ScopeErrorCase::UnknownPermission, "READ");
+        assert_eq!(err.to_string(), expected.to_string());
+
+        let kind = ErrorKind::from(expected);
+        assert_eq!(format!("{:?}", kind).starts_with("ScopeError("), true);
+        assert_eq!(kind.to_string(), err.to_string());
+    }
+}
@@ -1 +1,3 @@
-pub mod error;
\ No newline at end of file
+pub mod error;
+pub mod telemetry;
+pub(crate) mod trace;
@@ -0,0 +1,155 @@
+/*!
+    Metrics recorded through the `metrics` facade when the `metrics` feature is enabled. Install any
+    `metrics`-compatible recorder (e.g. a Prometheus exporter) to collect them; without the feature the
+    recording functions compile to nothing.
+ */
+#[cfg(feature = "metrics")]
+use std::time::Instant;
+
+/** Counter of `check` calls, labelled with `allowed` = `true` or `false`. */
+pub const CHECKS_TOTAL: &str = "bitperm_checks_total";
+/** Counter of `check` calls that were denied. */
+pub const DENIALS_TOTAL: &str = "bitperm_denials_total";
+/** Counter of `grant` calls, labelled with `result` = `ok` or `error`. */
+pub const GRANTS_TOTAL: &str = "bitperm_grants_total";
+/** Counter of `revoke` calls, labelled with `result` = `ok` or `error`. */
+pub const REVOCATIONS_TOTAL: &str = "bitperm_revocations_total";
+/** Histogram of the time taken by `from_tuple` in seconds, labelled with `result` = `ok` or `error`. */
+pub const IMPORT_DURATION_SECONDS: &str = "bitperm_import_duration_seconds";
+
+/** Measures the duration of an operation when metrics are enabled. */
+pub(crate) struct Timer {
+    #[cfg(feature = "metrics")]
+    started: Instant
+}
+
+impl Timer {
+    pub(crate) fn start() -> Timer {
+        return Timer {
+            #[cfg(feature = "metrics")]
+            started: Instant::now()
+        };
+    }
+}
+
+#[cfg_attr(not(feature = "metrics"), allow(unused_variables))]
+pub(crate) fn record_check(allowed: bool) {
+    #[cfg(feature = "metrics")]
+    {
+        metrics::counter!(CHECKS_TOTAL, "allowed" => if allowed { "true" } else { "false" }).increment(1);
+        if !allowed {
+            metrics::counter!(DENIALS_TOTAL).increment(1);
+        }
+    }
+}
+
+/** Record a batch of checks with one update per counter, for bulk evaluation. */
+#[cfg_attr(not(feature = "metrics"), allow(unused_variables))]
+pub(crate) fn record_checks(allowed: u64, denied: u64) {
+    #[cfg(feature = "metrics")]
+    {
+        metrics::counter!(CHECKS_TOTAL, "allowed" => "true").increment(allowed);
+        metrics::counter!(CHECKS_TOTAL, "allowed" => "false").increment(denied);
+        metrics::counter!(DENIALS_TOTAL).increment(denied);
+    }
+}
+
+#[cfg_attr(not(feature = "metrics"), allow(unused_variables))]
+pub(crate) fn record_grant(ok: bool) {
+    #[cfg(feature = "metrics")]
+    metrics::counter!(GRANTS_TOTAL, "result" => result_label(ok)).increment(1);
+}
+
+#[cfg_attr(not(feature = "metrics"), allow(unused_variables))]
+pub(crate) fn record_revocation(ok: bool) {
+    #[cfg(feature = "metrics")]
+    metrics::counter!(REVOCATIONS_TOTAL, "result" => result_label(ok)).increment(1);
+}
+
+#[cfg_attr(not(feature = "metrics"), allow(unused_variables))]
+pub(crate) fn record_import(timer: Timer, ok: bool) {
+    #[cfg(feature = "metrics")]
+    metrics::histogram!(IMPORT_DURATION_SECONDS, "result" => result_label(ok))
+        .record(timer.started.elapsed().as_secs_f64());
+}
+
+#[cfg(feature = "metrics")]
+fn result_label(ok: bool) -> &'static str {
+    return match ok {
+        true => "ok",
+        false => "error"
+    }
+}
+
+#[cfg(all(test, feature = "metrics"))]
+mod tests {
+    use std::string::{String, ToString};
+    use std::sync::{Arc, Mutex};
+    use std::vec::Vec;
+    use metrics::{Counter, Gauge, Histogram, Key, KeyName, Metadata, Recorder, SharedString, Unit};
+    use crate::scope::Scope;
+
+    /** Recorder that remembers the name and labels of every metric recorded through it. */
+    #[derive(Default)]
+    struct ListingRecorder {
+        recorded: Arc<Mutex<Vec<String>>>
+    }
+
+    impl ListingRecorder {
+        fn push(&self, key: &Key) {
+            let mut entry = key.name().to_string();
+            for label in key.labels() {
+                entry.push_str(&std::format!(" {}={}", label.key(), label.value()));
+            }
+            self.recorded.lock().unwrap().push(entry);
+        }
+    }
+
+    impl Recorder for ListingRecorder {
+        fn describe_counter(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+
+        fn describe_gauge(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+
+        fn describe_histogram(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+
+        fn register_counter(&self, key: &Key, _: &Metadata<'_>) -> Counter {
+            self.push(key);
+            return Counter::noop();
+        }
+
+        fn register_gauge(&self, key: &Key, _: &Metadata<'_>) -> Gauge {
+            self.push(key);
+            return Gauge::noop();
+        }
+
+        fn register_histogram(&self, key: &Key, _: &Metadata<'_>) -> Histogram {
+            self.push(key);
+            return Histogram::noop();
+        }
+    }
+
+    #[test]
+    fn test_operations_record_metrics() {
+        let recorder = ListingRecorder::default();
+
+        metrics::with_local_recorder(&recorder, || {
+            let mut scope = Scope::new("TEST_SCOPE");
+
+            if let Err(_) = scope.add_permission("READ").and_then(|sc| sc.grant("READ")) {
+                assert!(false);
+            }
+            assert_eq!(scope.grant("READ").is_err(), true);
+            assert_eq!(scope.check("WRITE"), false);
+            let _ = Scope::from_tuple(scope.as_tuple(), 8);
+        });
+
+        let recorded = recorder.recorded.lock().unwrap();
+        assert_eq!(*recorded, [
+            "bitperm_grants_total result=ok",
+            "bitperm_grants_total result=error",
+            "bitperm_checks_total allowed=false",
+            "bitperm_denials_total",
+            "bitperm_import_duration_seconds result=ok"
+        ]);
+    }
+}
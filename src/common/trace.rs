@@ -0,0 +1,102 @@
+/*!
+    Instrumentation helpers used throughout the crate. With the `tracing` feature disabled the macros expand to
+    nothing, so call sites do not need their own feature gates and the arguments are never evaluated.
+ */
+#[cfg(feature = "tracing")]
+use core::fmt::Display;
+#[cfg(feature = "tracing")]
+use crate::common::error::ErrorKind;
+
+/** Emit a `tracing` event at the given level, e.g. `trace_event!(debug, path = %path, "message")`. */
+macro_rules! trace_event {
+    ($level:ident, $($arg:tt)+) => {
+        #[cfg(feature = "tracing")]
+        {
+            tracing::$level!($($arg)+);
+        }
+    };
+}
+
+/** Enter a debug-level `tracing` span for the rest of the enclosing block, returning its guard. */
+macro_rules! trace_span {
+    ($($arg:tt)+) => {{
+        #[cfg(feature = "tracing")]
+        let guard = tracing::debug_span!($($arg)+).entered();
+        #[cfg(not(feature = "tracing"))]
+        let guard = $crate::common::trace::NoSpan;
+        guard
+    }};
+}
+
+pub(crate) use trace_event;
+pub(crate) use trace_span;
+
+/** Stand-in for a span guard when the `tracing` feature is disabled. */
+#[cfg(not(feature = "tracing"))]
+pub(crate) struct NoSpan;
+
+/** Get the displayable error wrapped by an ErrorKind, for use as an event field. */
+#[cfg(feature = "tracing")]
+pub(crate) fn error_message(kind: &ErrorKind) -> &dyn Display {
+    return match kind {
+        ErrorKind::PermissionError(err) => err,
+        ErrorKind::ScopeError(err) => err
+    }
+}
+
+#[cfg(all(test, feature = "tracing", feature = "std"))]
+mod tests {
+    use alloc::sync::Arc;
+    use core::sync::atomic::{AtomicUsize, Ordering};
+    use tracing::{span, Event, Metadata, Subscriber};
+    use crate::scope::Scope;
+
+    /** Subscriber that only counts the spans and events it sees. */
+    struct CountingSubscriber {
+        spans: Arc<AtomicUsize>,
+        events: Arc<AtomicUsize>
+    }
+
+    impl Subscriber for CountingSubscriber {
+        fn enabled(&self, _: &Metadata<'_>) -> bool {
+            return true;
+        }
+
+        fn new_span(&self, _: &span::Attributes<'_>) -> span::Id {
+            return span::Id::from_u64(self.spans.fetch_add(1, Ordering::SeqCst) as u64 + 1);
+        }
+
+        fn record(&self, _: &span::Id, _: &span::Record<'_>) {}
+
+        fn record_follows_from(&self, _: &span::Id, _: &span::Id) {}
+
+        fn event(&self, _: &Event<'_>) {
+            self.events.fetch_add(1, Ordering::SeqCst);
+        }
+
+        fn enter(&self, _: &span::Id) {}
+
+        fn exit(&self, _: &span::Id) {}
+    }
+
+    #[test]
+    fn test_operations_are_instrumented() {
+        let spans = Arc::new(AtomicUsize::new(0));
+        let events = Arc::new(AtomicUsize::new(0));
+        let subscriber = CountingSubscriber { spans: spans.clone(), events: events.clone() };
+
+        tracing::subscriber::with_default(subscriber, || {
+            let mut scope = Scope::new("TEST_SCOPE");
+
+            if let Err(_) = scope.add_permission("READ").and_then(|sc| sc.grant("READ")) {
+                assert!(false);
+            }
+            assert_eq!(scope.grant("READ").is_err(), true);
+            assert_eq!(scope.check("READ"), true);
+        });
+
+        // two grant spans; permission added, granted, grant failed and checked events
+        assert_eq!(spans.load(Ordering::SeqCst), 2usize);
+        assert_eq!(events.load(Ordering::SeqCst), 4usize);
+    }
+}
@@ -0,0 +1,112 @@
+use alloc::vec::Vec;
+use bitvec::field::BitField;
+use bitvec::order::Lsb0;
+use bitvec::slice::BitSlice;
+use bitvec::vec::BitVec;
+use crate::common::error::ErrorKind;
+use crate::scope::error::{ScopeError, ScopeErrorCase};
+use crate::scope::Scope;
+
+/** GrantBits holds the grant state of a whole tree as one bit vector, the layout of `Scope::grant_bits`. */
+pub type GrantBits = BitVec<u64, Lsb0>;
+
+/** Names the input in the error returned when a bit vector does not match the tree. */
+const GRANT_BITS_SOURCE: &str = "grant bits";
+
+impl Scope {
+    /**
+        Get the grant state of this scope and its child scopes as a single bit vector, which is not limited to 64
+        permissions. Scopes follow each other in the order of `encode_values` (depth-first, child scopes in name
+        order), and each contributes one bit per shift handed out to its permissions, lowest first.
+     */
+    pub fn grant_bits(&self) -> GrantBits {
+        let mut bits = GrantBits::new();
+
+        for scope in self.scopes_in_encoding_order() {
+            let value = scope.as_u64();
+            for shift in 0..scope.assigned_width() {
+                bits.push(value >> shift & 1 == 1);
+            }
+        }
+
+        return bits;
+    }
+
+    /**
+        Restore the grant state of this scope and its child scopes from a bit vector produced by `grant_bits`.
+        Fails without changing anything with InvalidEncoding if the length does not match the tree, or with
+        UndefinedBits if a bit of a removed permission is set.
+     */
+    pub fn apply_grant_bits(&mut self, bits: &BitSlice<u64, Lsb0>) -> Result<&mut Scope, ErrorKind> {
+        let widths: Vec<usize> = self.scopes_in_encoding_order().iter().map(|scope| scope.assigned_width()).collect();
+        if widths.iter().sum::<usize>() != bits.len() {
+            return Err(ErrorKind::ScopeError(ScopeError::new(ScopeErrorCase::InvalidEncoding, GRANT_BITS_SOURCE)));
+        }
+
+        let mut values: Vec<u64> = Vec::with_capacity(widths.len());
+        let mut start = 0;
+        for width in widths {
+            values.push(match width {
+                0 => 0,
+                _ => bits[start..start + width].load_le::<u64>()
+            });
+            start += width;
+        }
+        self.apply_values_in_encoding_order(&values, GRANT_BITS_SOURCE)?;
+
+        return Ok(self);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bitvec::bitvec;
+    use crate::{assert_granted, assert_not_granted, assert_ok, assert_scope_err};
+
+    fn get_scope() -> Scope {
+        let mut scope = Scope::new("APP");
+        for i in 0..40 {
+            assert_ok!(scope.add_permission(&alloc::format!("FLAG_{}", i)));
+        }
+        assert_ok!(scope.add_scope("empty").and_then(|sc| sc.add_scope("reports")));
+
+        if let Some(reports) = scope.scope("reports") {
+            for i in 0..40 {
+                assert_ok!(reports.add_permission(&alloc::format!("REPORT_{}", i)));
+            }
+        }
+
+        return scope;
+    }
+
+    #[test]
+    fn test_grant_bits_span_scopes() {
+        let mut scope = get_scope();
+        assert_ok!(scope.grant("FLAG_39"));
+        if let Some(reports) = scope.scope("reports") {
+            assert_ok!(reports.grant("REPORT_0").and_then(|sc| sc.grant("REPORT_39")));
+        }
+
+        let bits = scope.grant_bits();
+        // APP, then the empty scope, then reports
+        assert_eq!(bits.len(), 80);
+        assert_eq!(bits.iter_ones().collect::<Vec<usize>>(), [39, 40, 79]);
+
+        let mut restored = get_scope();
+        assert_ok!(restored.apply_grant_bits(&bits));
+        assert_granted!(restored, "FLAG_39");
+        assert_granted!(restored, "reports:REPORT_0");
+        assert_granted!(restored, "reports:REPORT_39");
+        assert_not_granted!(restored, "FLAG_0");
+        assert_eq!(restored.grant_bits(), bits);
+    }
+
+    #[test]
+    fn test_grant_bits_must_match_tree() {
+        let mut scope = get_scope();
+
+        assert_scope_err!(scope.apply_grant_bits(&bitvec![u64, Lsb0; 1; 79]), ScopeErrorCase::InvalidEncoding);
+        assert_eq!(scope.grant_bits().any(), false);
+    }
+}
@@ -0,0 +1,115 @@
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt::Debug;
+use enumflags2::{BitFlag, BitFlags};
+use crate::common::error::ErrorKind;
+use crate::scope::error::{ScopeError, ScopeErrorCase};
+use crate::scope::grant_map::GrantEntry;
+use crate::scope::Scope;
+
+/** Get the permission name of a flag variant, which is its `Debug` name, e.g. `Read`. */
+fn variant_name<T: BitFlag + Debug>(variant: T) -> String {
+    return format!("{:?}", variant);
+}
+
+impl Scope {
+    /**
+        Build a scope named `name` with one permission per variant of the `#[bitflags]` enum `T`, in bit order. As
+        enum variants carry no names at runtime, each permission is named after the variant's `Debug` output.
+     */
+    pub fn from_flag_enum<T>(name: &str) -> Result<Scope, ErrorKind> where T: BitFlag + Debug {
+        let mut scope = Scope::new(name);
+        for variant in BitFlags::<T>::all().iter() {
+            scope.add_permission(&variant_name(variant))?;
+        }
+
+        return Ok(scope);
+    }
+
+    /** Build the scope of `from_flag_enum` with every flag set in `flags` granted. */
+    pub fn from_bit_flags<T>(name: &str, flags: BitFlags<T>) -> Result<Scope, ErrorKind> where T: BitFlag + Debug {
+        let mut scope = Scope::from_flag_enum::<T>(name)?;
+        for variant in flags.iter() {
+            scope.grant(&variant_name(variant))?;
+        }
+
+        return Ok(scope);
+    }
+
+    /**
+        Read the grants of this scope (but not its child scopes) as `BitFlags<T>`. Permissions that are not granted
+        need no variant, but a granted permission without a variant of the same name fails with UnknownPermission.
+     */
+    pub fn to_bit_flags<T>(&self) -> Result<BitFlags<T>, ErrorKind> where T: BitFlag + Debug {
+        let names = match self.grant_map().get(&self.path().to_string()) {
+            Some(GrantEntry::Names(names)) => names.clone(),
+            _ => Vec::new()
+        };
+
+        let mut flags = BitFlags::<T>::empty();
+        for name in names {
+            match BitFlags::<T>::all().iter().find(|variant| variant_name(*variant) == name) {
+                Some(variant) => flags.insert(variant),
+                None => return Err(ErrorKind::ScopeError(ScopeError::new(ScopeErrorCase::UnknownPermission, &name)))
+            }
+        }
+
+        return Ok(flags);
+    }
+}
+
+impl<T> TryFrom<&Scope> for BitFlags<T> where T: BitFlag + Debug {
+    type Error = ErrorKind;
+
+    fn try_from(scope: &Scope) -> Result<BitFlags<T>, ErrorKind> {
+        return scope.to_bit_flags();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use enumflags2::{bitflags, make_bitflags};
+    use crate::{assert_granted, assert_not_granted, assert_ok, assert_scope_err};
+
+    #[bitflags]
+    #[repr(u16)]
+    #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+    enum Access {
+        Read = 1 << 0,
+        Write = 1 << 3,
+        Admin = 1 << 9
+    }
+
+    #[test]
+    fn test_schema_from_flag_enum() {
+        let scope = assert_ok!(Scope::from_flag_enum::<Access>("APP"));
+
+        assert_eq!(scope.as_schema_json(), serde_json::json!(["APP", 0, [["Read", 0], ["Write", 1], ["Admin", 2]], []]));
+    }
+
+    #[test]
+    fn test_bit_flags_round_trip() {
+        let flags = make_bitflags!(Access::{Read | Admin});
+        let scope = assert_ok!(Scope::from_bit_flags("APP", flags));
+
+        assert_granted!(scope, "Read");
+        assert_granted!(scope, "Admin");
+        assert_not_granted!(scope, "Write");
+        // permissions are numbered in bit order, whatever bits the enum uses
+        assert_eq!(scope.as_u64(), 0b101);
+        assert_eq!(BitFlags::<Access>::try_from(&scope).ok(), Some(flags));
+    }
+
+    #[test]
+    fn test_bit_flags_from_foreign_scope() {
+        let mut scope = Scope::new("APP");
+        assert_ok!(scope.add_permission("Write").and_then(|sc| sc.add_permission("Delete")).and_then(|sc| sc.grant("Write")));
+
+        assert_eq!(scope.to_bit_flags::<Access>().ok(), Some(BitFlags::from_flag(Access::Write)));
+
+        assert_ok!(scope.grant("Delete"));
+        assert_scope_err!(scope.to_bit_flags::<Access>(), ScopeErrorCase::UnknownPermission);
+    }
+}
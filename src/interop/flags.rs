@@ -0,0 +1,138 @@
+use alloc::string::ToString;
+use alloc::vec::Vec;
+use bitflags::Flags;
+use crate::common::error::ErrorKind;
+use crate::scope::error::{ScopeError, ScopeErrorCase};
+use crate::scope::grant_map::GrantEntry;
+use crate::scope::Scope;
+
+/**
+    ScopeFlags converts between a `bitflags!` type and a single-level scope. It is implemented for every flags type
+    whose bits fit in a u64, and stands in for `From` and `TryFrom`, which cannot be implemented for every flags type
+    at once. Permissions are matched to flags by name, so a scope keeps working when flags are reordered.
+ */
+pub trait ScopeFlags: Sized {
+    /**
+        Build a scope named `name` with one permission per named flag of a single bit, in bit order. Composite
+        flags such as `READ_WRITE = READ | WRITE` have no permission of their own.
+     */
+    fn schema(name: &str) -> Result<Scope, ErrorKind>;
+
+    /** Build the scope of `schema` with every flag set in `self` granted. */
+    fn to_scope(&self, name: &str) -> Result<Scope, ErrorKind>;
+
+    /**
+        Read the flags granted in `scope`. Permissions that are not granted need no flag, but a granted permission
+        without a flag of the same name fails with UnknownPermission. Child scopes are ignored.
+     */
+    fn from_scope(scope: &Scope) -> Result<Self, ErrorKind>;
+}
+
+impl<F> ScopeFlags for F where F: Flags, F::Bits: Into<u64> {
+    fn schema(name: &str) -> Result<Scope, ErrorKind> {
+        let mut flags: Vec<(u64, &str)> = F::FLAGS.iter()
+            .filter(|flag| flag.is_named())
+            .map(|flag| (flag.value().bits().into(), flag.name()))
+            .filter(|(bits, _)| bits.count_ones() == 1)
+            .collect();
+        flags.sort();
+
+        let mut scope = Scope::new(name);
+        for (_, name) in flags {
+            scope.add_permission(name)?;
+        }
+
+        return Ok(scope);
+    }
+
+    fn to_scope(&self, name: &str) -> Result<Scope, ErrorKind> {
+        let mut scope = F::schema(name)?;
+        let bits: u64 = self.bits().into();
+
+        for flag in F::FLAGS {
+            let flag_bits: u64 = flag.value().bits().into();
+            if flag.is_named() && flag_bits.count_ones() == 1 && bits & flag_bits != 0 {
+                scope.grant(flag.name())?;
+            }
+        }
+
+        return Ok(scope);
+    }
+
+    fn from_scope(scope: &Scope) -> Result<Self, ErrorKind> {
+        let mut flags = F::empty();
+        let names = match scope.grant_map().get(&scope.path().to_string()) {
+            Some(GrantEntry::Names(names)) => names.clone(),
+            _ => Vec::new()
+        };
+
+        for name in names {
+            match F::from_name(&name) {
+                Some(flag) => flags.insert(flag),
+                None => return Err(ErrorKind::ScopeError(ScopeError::new(ScopeErrorCase::UnknownPermission, &name)))
+            }
+        }
+
+        return Ok(flags);
+    }
+}
+
+impl Scope {
+    /** Build a single-level scope from a `bitflags!` value, with a permission per flag and the set flags granted. */
+    pub fn from_flags<F: ScopeFlags>(name: &str, flags: &F) -> Result<Scope, ErrorKind> {
+        return flags.to_scope(name);
+    }
+
+    /** Read the grants of this scope (but not its child scopes) as a `bitflags!` value. */
+    pub fn to_flags<F: ScopeFlags>(&self) -> Result<F, ErrorKind> {
+        return F::from_scope(self);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{assert_granted, assert_not_granted, assert_ok, assert_scope_err};
+
+    bitflags::bitflags! {
+        #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+        struct Access: u8 {
+            const WRITE = 1 << 1;
+            const READ = 1;
+            const ADMIN = 1 << 4;
+            const READ_WRITE = Self::READ.bits() | Self::WRITE.bits();
+        }
+    }
+
+    #[test]
+    fn test_flags_schema() {
+        let scope = assert_ok!(Access::schema("APP"));
+
+        assert_eq!(scope.as_schema_json(), serde_json::json!(["APP", 0, [["READ", 0], ["WRITE", 1], ["ADMIN", 2]], []]));
+    }
+
+    #[test]
+    fn test_flags_round_trip() {
+        let scope = assert_ok!(Scope::from_flags("APP", &(Access::READ | Access::ADMIN)));
+
+        assert_granted!(scope, "READ");
+        assert_granted!(scope, "ADMIN");
+        assert_not_granted!(scope, "WRITE");
+        assert_eq!(scope.to_flags::<Access>().ok(), Some(Access::READ | Access::ADMIN));
+
+        let scope = assert_ok!(Scope::from_flags("APP", &Access::READ_WRITE));
+        assert_eq!(scope.to_flags::<Access>().ok(), Some(Access::READ_WRITE));
+    }
+
+    #[test]
+    fn test_flags_from_foreign_scope() {
+        let mut scope = Scope::new("APP");
+        assert_ok!(scope.add_permission("WRITE").and_then(|sc| sc.add_permission("DELETE")).and_then(|sc| sc.grant("WRITE")));
+
+        // permissions without a flag are fine as long as they are not granted
+        assert_eq!(scope.to_flags::<Access>().ok(), Some(Access::WRITE));
+
+        assert_ok!(scope.grant("DELETE"));
+        assert_scope_err!(scope.to_flags::<Access>(), ScopeErrorCase::UnknownPermission);
+    }
+}
@@ -0,0 +1,12 @@
+/*!
+    Conversions between scopes and the flag types of other crates, so code that already models permissions with
+    them can adopt bitperm's naming and serialization without rewriting its call sites. Each integration is behind
+    a feature named after the crate it targets.
+ */
+
+#[cfg(feature = "bitvec")]
+pub mod bits;
+#[cfg(feature = "bitflags")]
+pub mod flags;
+#[cfg(feature = "enumflags2")]
+pub mod enumflags;
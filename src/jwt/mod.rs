@@ -0,0 +1,125 @@
+/*!
+    Helpers for carrying grant state inside the claims of a JWT issued and verified by another library. The grant
+    state of a tree is written as a single claim, `perm` by default, holding the schema fingerprint and the value of
+    every scope. Reading it back checks the fingerprint against the canonical schema, so a token minted under an
+    older schema is rejected instead of granting whatever its bits now mean.
+ */
+
+use alloc::format;
+use alloc::string::{String, ToString};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use crate::common::error::ErrorKind;
+use crate::scope::encoding::Radix;
+use crate::scope::error::{ScopeError, ScopeErrorCase};
+use crate::scope::Scope;
+
+/** The name of the claim written by `Scope::insert_claim` and read by `Scope::from_claims`. */
+pub const CLAIM_NAME: &str = "perm";
+
+/**
+    PermissionClaim is the claim carrying the grant state of a tree. The fingerprint is written in hex and the
+    values as base62 text, one value per scope in encoding order, because JSON numbers above 2^53 lose precision in
+    JS and most JWT consumers read claims in JS.
+ */
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PermissionClaim {
+    /** The schema fingerprint, as 16 lowercase hex digits. */
+    pub schema: String,
+    /** The values of the tree as written by `Scope::encode_values` in base62. */
+    pub values: String
+}
+
+fn invalid_claim(reason: &str) -> ErrorKind {
+    return ErrorKind::ScopeError(ScopeError::new(ScopeErrorCase::InvalidToken, reason));
+}
+
+impl Scope {
+    /** Get the claim describing the grant state of this scope and every child scope, as a JSON value. */
+    pub fn to_claim(&self) -> Value {
+        let claim = PermissionClaim {
+            schema: format!("{:016x}", self.fingerprint()),
+            values: self.encode_values(Radix::Base62)
+        };
+
+        return serde_json::to_value(claim).expect("permission claims always serialize");
+    }
+
+    /** Add the claim describing the grant state of this tree to a JWT claims map, under `CLAIM_NAME`. */
+    pub fn insert_claim(&self, claims: &mut Map<String, Value>) {
+        claims.insert(CLAIM_NAME.to_string(), self.to_claim());
+    }
+
+    /**
+        Set the grant state of this tree from a claim written by `to_claim`. Fails with InvalidToken if the claim is
+        malformed and SchemaMismatch if it was written under a different schema. Nothing is changed unless the claim
+        holds one valid value per scope.
+     */
+    pub fn apply_claim(&mut self, claim: &Value) -> Result<&mut Scope, ErrorKind> {
+        let claim = PermissionClaim::deserialize(claim).map_err(|_| invalid_claim("malformed permission claim"))?;
+        let fingerprint = u64::from_str_radix(&claim.schema, 16).map_err(|_| invalid_claim("malformed permission claim"))?;
+
+        self.verify_fingerprint(fingerprint)?;
+        return self.apply_encoded_values(&claim.values, Radix::Base62);
+    }
+
+    /**
+        Rebuild the grant state carried by the claims of a verified JWT on a copy of the canonical `schema`. Fails
+        with InvalidToken if the claims hold no permission claim, and as `apply_claim` otherwise.
+     */
+    pub fn from_claims(schema: &Scope, claims: &Map<String, Value>) -> Result<Scope, ErrorKind> {
+        let claim = claims.get(CLAIM_NAME).ok_or_else(|| invalid_claim("missing permission claim"))?;
+        let mut scope = schema.clone();
+        scope.apply_claim(claim)?;
+
+        return Ok(scope);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use crate::{assert_granted, assert_not_granted, assert_ok, assert_scope_err};
+
+    fn schema() -> Scope {
+        let mut scope = Scope::new("APP");
+        assert_ok!(scope.add_permission("READ").and_then(|sc| sc.add_permission("WRITE")).and_then(|sc| sc.add_scope("billing")));
+        if let Some(billing) = scope.scope("billing") {
+            assert_ok!(billing.add_permission("REFUND"));
+        }
+
+        return scope;
+    }
+
+    #[test]
+    fn test_claims_round_trip() {
+        let mut scope = schema();
+        assert_ok!(scope.grant("WRITE"));
+        if let Some(billing) = scope.scope("billing") {
+            assert_ok!(billing.grant("REFUND"));
+        }
+
+        let mut claims = Map::new();
+        claims.insert(String::from("sub"), json!("alice"));
+        scope.insert_claim(&mut claims);
+        assert_eq!(claims[CLAIM_NAME]["values"], json!("2-1"));
+
+        let restored = assert_ok!(Scope::from_claims(&schema(), &claims));
+        assert_granted!(restored, "WRITE");
+        assert_granted!(restored, "billing:REFUND");
+        assert_not_granted!(restored, "READ");
+    }
+
+    #[test]
+    fn test_claims_are_validated() {
+        let claim = schema().to_claim();
+        let mut changed = schema();
+        assert_ok!(changed.add_permission("DELETE"));
+        assert_scope_err!(changed.apply_claim(&claim), ScopeErrorCase::SchemaMismatch);
+
+        assert_scope_err!(Scope::from_claims(&schema(), &Map::new()), ScopeErrorCase::InvalidToken);
+        assert_scope_err!(schema().apply_claim(&json!({ "schema": "zz", "values": "0-0" })), ScopeErrorCase::InvalidToken);
+        assert_scope_err!(schema().apply_claim(&json!(42)), ScopeErrorCase::InvalidToken);
+    }
+}
@@ -1,3 +1,39 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+#![allow(clippy::needless_return)]
+#![cfg_attr(test, allow(
+    clippy::assertions_on_constants,
+    clippy::bool_assert_comparison,
+    clippy::bind_instead_of_map,
+    clippy::explicit_counter_loop,
+    clippy::manual_ok_err,
+    clippy::redundant_pattern_matching,
+    clippy::useless_vec
+))]
+
+extern crate alloc;
+
 pub mod permission;
 pub mod scope;
-pub mod common;
\ No newline at end of file
+pub mod common;
+pub mod analysis;
+pub mod audit;
+pub mod cache;
+pub mod role;
+pub mod schema;
+pub mod store;
+#[cfg(feature = "crypto")]
+pub mod token;
+#[cfg(feature = "jwt")]
+pub mod jwt;
+#[cfg(any(feature = "bitflags", feature = "enumflags2", feature = "bitvec"))]
+pub mod interop;
+#[cfg(feature = "async")]
+pub mod watch;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+#[cfg(any(test, feature = "test-util"))]
+pub mod testing;
+
+pub use common::error::ErrorKind;
+pub use permission::Permission;
+pub use scope::{Scope, ScopeTuple};
@@ -1,5 +1,7 @@
-use std::fmt;
-use std::fmt::{Debug, Display, Formatter};
+use alloc::format;
+use alloc::string::{String, ToString};
+use core::fmt;
+use core::fmt::{Debug, Display, Formatter};
 use crate::permission::MAX_VALUE;
 
 pub struct PermissionError {
@@ -8,55 +10,116 @@ pub struct PermissionError {
     metadata: PermissionErrorMetadata
 }
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum PermissionErrorCase {
     MaxValue,
     InvalidValue,
     MaxShift,
     GrantError,
-    RevocationError
+    RevocationError,
+    DeniedError
 }
 
 pub struct PermissionErrorMetadata {
-    pub(crate) shift: Option<u8>
+    pub(crate) shift: Option<u8>,
+    pub(crate) ceiling: Option<u64>,
+    pub(crate) scope_path: Option<String>,
+    pub(crate) capacity: Option<usize>
 }
 
 impl PermissionErrorMetadata {
     pub fn new() -> PermissionErrorMetadata {
         return PermissionErrorMetadata {
             shift: None,
+            ceiling: None,
+            scope_path: None,
+            capacity: None,
         }
     }
+
+    /** The shift that was requested for the permission, if relevant to the error. */
+    pub fn shift(&self) -> Option<u8> {
+        return self.shift;
+    }
+
+    /** The value ceiling that was enforced, if relevant to the error. */
+    pub fn ceiling(&self) -> Option<u64> {
+        return self.ceiling;
+    }
+
+    /** The full path of the scope the permission belongs to, if known. */
+    pub fn scope_path(&self) -> Option<&str> {
+        return self.scope_path.as_deref();
+    }
+
+    /** The total number of permissions the scope can hold, if known. */
+    pub fn capacity(&self) -> Option<usize> {
+        return self.capacity;
+    }
+}
+
+impl Default for PermissionErrorMetadata {
+    fn default() -> Self {
+        PermissionErrorMetadata::new()
+    }
 }
 
 impl PermissionError {
-    pub fn new(case: PermissionErrorCase, permission_name: &String, error_metadata: PermissionErrorMetadata) -> PermissionError {
+    pub fn new(case: PermissionErrorCase, permission_name: &str, error_metadata: PermissionErrorMetadata) -> PermissionError {
         return PermissionError {
-            name: (*permission_name).clone(),
+            name: permission_name.to_string(),
             case,
             metadata: error_metadata
         }
     }
+
+    /** Attach the location and capacity of the scope the permission was being added to. */
+    pub(crate) fn in_scope(mut self, scope_path: String, capacity: usize) -> PermissionError {
+        self.metadata.scope_path = Some(scope_path);
+        self.metadata.capacity = Some(capacity);
+
+        return self;
+    }
+
+    pub fn name(&self) -> &str {
+        return &self.name;
+    }
+
+    pub fn case(&self) -> PermissionErrorCase {
+        return self.case;
+    }
+
+    pub fn metadata(&self) -> &PermissionErrorMetadata {
+        return &self.metadata;
+    }
 }
 
 const ERROR_NAME: &str = "PermissionError";
 
-fn format_error_message(f: &mut Formatter<'_>, case: &PermissionErrorCase, name: &String, metadata: &PermissionErrorMetadata) -> fmt::Result {
+fn format_error_message(f: &mut Formatter<'_>, case: &PermissionErrorCase, name: &str, metadata: &PermissionErrorMetadata) -> fmt::Result {
     let err: String = match *case {
         PermissionErrorCase::MaxValue | PermissionErrorCase::MaxShift => {
-            if let Some(shift_value) = (*metadata).shift {
-                format!("{}: parameter 'shift' ({}) for permission '{}' exceeded maximum safe value ({}).",
+            if let Some(shift_value) = metadata.shift {
+                let message = format!("{}: parameter 'shift' ({}) for permission '{}' exceeded maximum safe value ({}).",
                         ERROR_NAME,
                         shift_value,
-                        *name,
-                        MAX_VALUE)
+                        name,
+                        metadata.ceiling.unwrap_or(MAX_VALUE));
+
+                match (&metadata.scope_path, metadata.capacity) {
+                    (Some(path), Some(capacity)) => format!("{} Scope '{}' is at capacity ({} permissions).", message, path, capacity),
+                    (Some(path), None) => format!("{} Scope '{}' is at capacity.", message, path),
+                    _ => message
+                }
             } else {
                 // need to panic here because we're missing the necessary properties to even parse an error
                 panic!("{} - PANIC: Unable to format error message due to missing metadata property 'shift'", ERROR_NAME);
             }
         },
-        PermissionErrorCase::InvalidValue => format!("{}: permission '{}' evaluated to an illegal value that is not 1 or a power of 2.", ERROR_NAME, *name),
-        PermissionErrorCase::GrantError => format!("{}: permission '{}' cannot be granted because it already has a value of <true>.", ERROR_NAME, *name),
-        PermissionErrorCase::RevocationError => format!("{}: permission '{}' cannot be revoked because it already has a value of <false>", ERROR_NAME, *name),
+        PermissionErrorCase::InvalidValue => format!("{}: permission '{}' evaluated to an illegal value that is not 1 or a power of 2.", ERROR_NAME, name),
+        PermissionErrorCase::GrantError => format!("{}: permission '{}' cannot be granted because it already has a value of <true>.", ERROR_NAME, name),
+        PermissionErrorCase::RevocationError => format!("{}: permission '{}' cannot be revoked because it already has a value of <false>", ERROR_NAME, name),
+        PermissionErrorCase::DeniedError => format!("{}: permission '{}' cannot be granted because it is explicitly denied.", ERROR_NAME, name),
     };
 
     write!(f, "{}", err)
@@ -74,5 +137,5 @@ impl Display for PermissionError {
     }
 }
 
-impl std::error::Error for PermissionError {}
+impl core::error::Error for PermissionError {}
 
@@ -1,34 +1,87 @@
 pub mod error;
 
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use serde::{Deserialize, Serialize};
 use crate::common::error::ErrorKind;
 use crate::permission::error::{PermissionErrorCase, PermissionErrorMetadata};
 use crate::permission::error::PermissionError;
+#[cfg(feature = "uuid")]
+use uuid::Uuid;
 
+#[derive(Clone, Debug)]
 pub struct Permission {
     pub name: String,
     pub value: u64,
-    pub has_permission: bool
+    pub has_permission: bool,
+    /** Set when the permission is explicitly denied, which overrides any grant or implication of it. */
+    pub denied: bool,
+    /** Human readable explanation of what the permission allows, used for generated documentation. */
+    pub description: Option<String>,
+    /** Free-form labels used to group permissions in generated documentation. */
+    pub tags: Vec<String>,
+    /** Time at which the grant lapses, for time-limited grants, in the caller's clock. Cleared by grant and revoke. */
+    pub expires_at: Option<u64>,
+    /** Identifier that survives renames, for external systems that reference the permission. */
+    #[cfg(feature = "uuid")]
+    pub id: Option<Uuid>
 }
 
 pub const MAX_VALUE: u64 = 9007199254740991; // = JsNumber.MAX_SAFE_INTEGER
+pub const MAX_SHIFT: u8 = 52; // highest shift whose value does not exceed MAX_VALUE
+
+/** The highest value a permission may evaluate to, which bounds the number of permissions in a scope. */
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ValueCeiling {
+    /** Values stay within JS `Number.MAX_SAFE_INTEGER`, allowing 53 permissions per scope. */
+    #[default]
+    JsSafe,
+    /** Values may use every bit of an unsigned 64-bit integer, allowing 64 permissions per scope. */
+    Full
+}
+
+impl ValueCeiling {
+    /** Get the highest value a permission or scope may evaluate to under this ceiling. */
+    pub fn max_value(&self) -> u64 {
+        return match self {
+            ValueCeiling::JsSafe => MAX_VALUE,
+            ValueCeiling::Full => u64::MAX
+        }
+    }
+
+    /** Get the highest shift that can be used to create a permission under this ceiling. */
+    pub fn max_shift(&self) -> u8 {
+        return match self {
+            ValueCeiling::JsSafe => MAX_SHIFT,
+            ValueCeiling::Full => (u64::BITS - 1) as u8
+        }
+    }
+}
 
 impl Permission {
-    /** Creates a new permission. */
+    /** Creates a new permission whose value is safe to use in JS. */
     pub fn new(name: &str, shift: u8) -> Result<Permission, ErrorKind> {
+        return Permission::with_ceiling(name, shift, ValueCeiling::JsSafe);
+    }
+
+    /** Creates a new permission whose value may not exceed the given ceiling. */
+    pub fn with_ceiling(name: &str, shift: u8, ceiling: ValueCeiling) -> Result<Permission, ErrorKind> {
         // verify that the shift is within constraints and create a permission object
-        let validated_shift = match validate_shift(&name.to_string(), &shift) {
-            Ok(result) => result,
-            Err(err) => {
-                return Err(err)
-            }
-        };
+        let validated_shift = validate_shift(name, &shift, &ceiling)?;
 
         // Verify that the value we created with the shift is legal for bitwise operations
-        return match validate_value(&name.to_string(), &(1 << validated_shift)) {
+        return match validate_value(name, &(1 << validated_shift)) {
             Ok(_) => Ok(Permission {
                 name: name.to_string(),
                 value: 1 << validated_shift,
                 has_permission: false,
+                denied: false,
+                description: None,
+                tags: Vec::new(),
+                expires_at: None,
+                #[cfg(feature = "uuid")]
+                id: None
             }),
             Err(err) => Err(err),
         };
@@ -36,6 +89,16 @@ impl Permission {
 
     /** Grants the permission to the holder of this reference. */
     pub fn grant(&mut self) -> Result<&mut Permission, ErrorKind> {
+        if self.denied {
+            return Err(
+                ErrorKind::PermissionError(
+                    PermissionError::new(
+                        PermissionErrorCase::DeniedError, &self.name, PermissionErrorMetadata::new()
+                    )
+                )
+            );
+        }
+
         // check if the user has already been granted this permission
         if self.has_permission {
             return Err(
@@ -48,6 +111,7 @@ impl Permission {
         }
 
         self.has_permission = true; // grant
+        self.expires_at = None;
 
         return Ok(self);
     }
@@ -66,41 +130,80 @@ impl Permission {
         }
 
         self.has_permission = false; // revoke
+        self.expires_at = None;
 
         return Ok(self);
     }
 
+    /** Whether the permission is granted. A denied permission is never held, even if its grant bit is set. */
     pub fn has(&self) -> bool {
-        return self.has_permission;
+        return self.has_permission && !self.denied;
+    }
+
+    /** Explicitly deny the permission, revoking any grant of it. Denying a denied permission changes nothing. */
+    pub fn deny(&mut self) -> &mut Permission {
+        self.denied = true;
+        self.has_permission = false;
+        self.expires_at = None;
+
+        return self;
+    }
+
+    /** Lift an explicit denial, leaving the permission unset. */
+    pub fn clear_denial(&mut self) -> &mut Permission {
+        self.denied = false;
+
+        return self;
+    }
+
+    /** Get whether the permission is granted, denied or neither. */
+    pub fn state(&self) -> PermissionState {
+        return match (self.denied, self.has_permission) {
+            (true, _) => PermissionState::Denied,
+            (false, true) => PermissionState::Granted,
+            (false, false) => PermissionState::Unset
+        }
     }
 }
 
-/** Validate that a bitwise shift is safe to perform both in Rust and JS **/
-fn validate_shift(name: &String, shift: &u8) -> Result<u8, ErrorKind> {
-    if *shift > 53 {
+/** The state of a permission: granted, explicitly denied, or neither. Denial overrides grants and implications. */
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PermissionState {
+    Unset,
+    Granted,
+    Denied
+}
+
+/** Validate that a bitwise shift is safe to perform in Rust and stays within the configured ceiling **/
+fn validate_shift(name: &str, shift: &u8, ceiling: &ValueCeiling) -> Result<u8, ErrorKind> {
+    if *shift as u32 >= u64::BITS {
         return Err(ErrorKind::PermissionError(PermissionError::new(
             PermissionErrorCase::MaxShift,
             name,
             PermissionErrorMetadata {
-                shift: Some(*shift)
+                shift: Some(*shift),
+                ceiling: Some(ceiling.max_value()),
+                ..PermissionErrorMetadata::new()
             }
         )))
     }
-    // check that we have not exceeded the safe left-shift that can be performed in the JSVM
-    return match (1 << *shift) <= MAX_VALUE {
+    // check that we have not exceeded the left-shift allowed by the ceiling (e.g. the safe limit of the JSVM)
+    return match (1u64 << *shift) <= ceiling.max_value() {
         true  => Ok(*shift),
         false => Err(ErrorKind::PermissionError(PermissionError::new(
             PermissionErrorCase::MaxValue,
             name,
             PermissionErrorMetadata {
-                shift: Some(*shift)
+                shift: Some(*shift),
+                ceiling: Some(ceiling.max_value()),
+                ..PermissionErrorMetadata::new()
             }
         )))
     }
 }
 
 /** Validate that the calculated value of a permission can be evaluated using binary. **/
-fn validate_value(name: &String, value: &u64) -> Result<(), ErrorKind> {
+fn validate_value(name: &str, value: &u64) -> Result<(), ErrorKind> {
     // check that the value is 0, 1, or a power of 2 thereafter
     return match *value == 1 || (*value).is_power_of_two() {
         true => Ok(()),
@@ -188,7 +291,29 @@ mod tests {
                 }
             }
 
-            i = i + 1;
+            i += 1;
+        }
+    }
+
+    #[test]
+    fn test_full_ceiling_allows_64_bits() {
+        match Permission::with_ceiling("TEST_PERMISSION", 63, ValueCeiling::Full) {
+            Ok(permission) => assert_eq!(permission.value, 1 << 63),
+            Err(_) => assert!(false)
+        }
+
+        // the JS-safe ceiling still rejects the same shift
+        assert!(Permission::with_ceiling("TEST_PERMISSION", 63, ValueCeiling::JsSafe).is_err());
+    }
+
+    #[test]
+    fn test_full_ceiling_err_exceeded_max_shift() {
+        match Permission::with_ceiling("TEST_PERMISSION", 64, ValueCeiling::Full) {
+            Ok(_) => assert!(false), // a shift of 64 would overflow a u64
+            Err(kind) => match kind {
+                ErrorKind::PermissionError(_) => assert!(true),
+                ErrorKind::ScopeError(_) => assert!(false),
+            }
         }
     }
 
@@ -196,7 +321,7 @@ mod tests {
     fn test_err_invalid_value_not_power_of_two() {
         // value that is not 1 or a power of 2
         let invalid_value: u64 = (1 << 26) + 17; // 67108881 is not 1 or a power of 2
-        let ret = validate_value(&"RANDOM_NAME".to_string(), &invalid_value);
+        let ret = validate_value("RANDOM_NAME", &invalid_value);
 
         assert!(ret.is_err())
     }
@@ -205,7 +330,7 @@ mod tests {
     fn test_err_invalid_value_zero() {
         // value of zero should fail
         let invalid_value: u64 = 0;
-        let ret = validate_value(&"RANDOM_NAME".to_string(), &invalid_value);
+        let ret = validate_value("RANDOM_NAME", &invalid_value);
 
         assert!(ret.is_err())
     }
@@ -214,7 +339,7 @@ mod tests {
     fn test_err_valid_value_one() {
         // value that is not 0, 1, or a power of 2
         let valid_value: u64 = 1;
-        let ret = validate_value(&"RANDOM_NAME".to_string(), &valid_value);
+        let ret = validate_value("RANDOM_NAME", &valid_value);
 
         assert!(ret.is_ok())
     }
@@ -0,0 +1,227 @@
+use alloc::collections::BTreeMap;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use serde::{Deserialize, Serialize};
+use crate::common::error::ErrorKind;
+use crate::role::set_paths;
+use crate::scope::error::{ScopeError, ScopeErrorCase};
+use crate::scope::Scope;
+
+/** Bundle is one published version of a named set of permission paths, such as a feature-flag cohort. */
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Bundle {
+    pub name: String,
+    /** Version of the bundle, counting up from 1 for its first publication. */
+    pub version: u32,
+    pub paths: Vec<String>
+}
+
+/** PrincipalMigration describes how one principal's grants changed when a bundle was upgraded. */
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PrincipalMigration {
+    pub principal: String,
+    pub from_version: u32,
+    /** Paths of the new version that were granted, excluding paths the principal already held. */
+    pub granted: Vec<String>,
+    /** Paths dropped from the bundle that were revoked. */
+    pub revoked: Vec<String>
+}
+
+/** MigrationReport lists what an upgrade of a bundle changed, principal by principal. */
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MigrationReport {
+    pub bundle: String,
+    pub to_version: u32,
+    /** Principals that were upgraded, in name order. */
+    pub migrated: Vec<PrincipalMigration>,
+    /** Holders of an older version whose scope was not supplied, and who stay on it. */
+    pub skipped: Vec<String>
+}
+
+/**
+    BundleRegistry publishes versions of bundles and remembers which version each principal holds, so a new version
+    can be rolled out to every holder at once. It serializes with serde, to be stored alongside the principals.
+ */
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BundleRegistry {
+    /** Every published version of every bundle, oldest first. */
+    bundles: BTreeMap<String, Vec<Bundle>>,
+    /** The version of each bundle held by each principal. */
+    holders: BTreeMap<String, BTreeMap<String, u32>>
+}
+
+fn unknown_bundle(name: &str) -> ErrorKind {
+    return ErrorKind::ScopeError(ScopeError::new(ScopeErrorCase::UnknownBundle, name));
+}
+
+impl BundleRegistry {
+    pub fn new() -> BundleRegistry {
+        return BundleRegistry::default();
+    }
+
+    /** Publish a new version of a bundle, returning its version number. Holders keep their version until upgraded. */
+    pub fn publish(&mut self, name: &str, paths: &[&str]) -> u32 {
+        let versions = self.bundles.entry(name.to_string()).or_default();
+        let version = versions.len() as u32 + 1;
+        versions.push(Bundle {
+            name: name.to_string(),
+            version,
+            paths: paths.iter().map(|path| path.to_string()).collect()
+        });
+
+        return version;
+    }
+
+    /** Get a published version of a bundle. */
+    pub fn bundle(&self, name: &str, version: u32) -> Option<&Bundle> {
+        return self.bundles.get(name).and_then(|versions| versions.iter().find(|bundle| bundle.version == version));
+    }
+
+    /** Get the latest version of a bundle. */
+    pub fn latest(&self, name: &str) -> Option<&Bundle> {
+        return self.bundles.get(name).and_then(|versions| versions.last());
+    }
+
+    /** List the principals holding a bundle with the version each holds, in principal order. */
+    pub fn holders(&self, name: &str) -> Vec<(&str, u32)> {
+        return self.holders.iter()
+            .filter_map(|(principal, held)| held.get(name).map(|version| (principal.as_str(), *version)))
+            .collect();
+    }
+
+    /**
+        Grant the latest version of a bundle to a principal, returning the version assigned. Paths already granted
+        are left as they are. Nothing is changed if any of the grants fails.
+     */
+    pub fn assign(&mut self, principal: &str, scope: &mut Scope, name: &str) -> Result<u32, ErrorKind> {
+        let bundle = self.latest(name).ok_or_else(|| unknown_bundle(name))?;
+        let version = bundle.version;
+
+        let mut working = scope.clone();
+        set_paths(&mut working, &bundle.paths, true)?;
+        working.touch();
+        *scope = working;
+        self.holders.entry(principal.to_string()).or_default().insert(name.to_string(), version);
+
+        return Ok(version);
+    }
+
+    /**
+        Move every holder of an older version of a bundle to its latest version: paths added to the bundle are
+        granted and paths dropped from it are revoked, even if the principal was also granted them some other way.
+        `principals` maps principal names to their scopes. The upgrade is atomic: if the grants of any principal
+        cannot be changed, no principal is changed and the error is returned. Holders missing from `principals`
+        are skipped and reported.
+     */
+    pub fn upgrade(&mut self, name: &str, principals: &mut BTreeMap<String, Scope>) -> Result<MigrationReport, ErrorKind> {
+        let latest = self.latest(name).ok_or_else(|| unknown_bundle(name))?;
+        let mut report = MigrationReport {
+            bundle: name.to_string(),
+            to_version: latest.version,
+            migrated: Vec::new(),
+            skipped: Vec::new()
+        };
+
+        let mut upgraded: Vec<(String, Scope)> = Vec::new();
+        for (principal, from_version) in self.holders(name) {
+            if from_version == latest.version {
+                continue;
+            }
+            let scope = match principals.get(principal) {
+                Some(scope) => scope,
+                None => {
+                    report.skipped.push(principal.to_string());
+                    continue;
+                }
+            };
+
+            let previous = self.bundle(name, from_version).map(|bundle| bundle.paths.clone()).unwrap_or_default();
+            let dropped: Vec<String> = previous.into_iter().filter(|path| !latest.paths.contains(path)).collect();
+
+            let mut working = scope.clone();
+            let revoked = set_paths(&mut working, &dropped, false)?;
+            let granted = set_paths(&mut working, &latest.paths, true)?;
+            working.touch();
+
+            report.migrated.push(PrincipalMigration { principal: principal.to_string(), from_version, granted, revoked });
+            upgraded.push((principal.to_string(), working));
+        }
+
+        for (principal, scope) in upgraded {
+            principals.insert(principal.clone(), scope);
+            self.holders.entry(principal).or_default().insert(name.to_string(), report.to_version);
+        }
+
+        return Ok(report);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{assert_granted, assert_not_granted, assert_ok, assert_scope_err};
+
+    fn get_scope() -> Scope {
+        let mut scope = Scope::new("APP");
+        assert_ok!(scope.add_scope("beta"));
+        if let Some(beta) = scope.scope("beta") {
+            assert_ok!(beta.add_permission("NEW_EDITOR").and_then(|sc| sc.add_permission("AI_SUMMARY")).and_then(|sc| sc.add_permission("DARK_MODE")));
+        }
+
+        return scope;
+    }
+
+    fn get_principals(registry: &mut BundleRegistry) -> BTreeMap<String, Scope> {
+        let mut principals: BTreeMap<String, Scope> = BTreeMap::new();
+        for principal in ["alice", "bob"] {
+            let mut scope = get_scope();
+            assert_ok!(registry.assign(principal, &mut scope, "early-access"));
+            principals.insert(principal.to_string(), scope);
+        }
+
+        return principals;
+    }
+
+    #[test]
+    fn test_upgrade_moves_every_holder() {
+        let mut registry = BundleRegistry::new();
+        assert_eq!(registry.publish("early-access", &["beta:NEW_EDITOR", "beta:DARK_MODE"]), 1);
+        let mut principals = get_principals(&mut registry);
+
+        assert_eq!(registry.publish("early-access", &["beta:NEW_EDITOR", "beta:AI_SUMMARY"]), 2);
+        let report = assert_ok!(registry.upgrade("early-access", &mut principals));
+        assert_eq!(report.to_version, 2);
+        assert_eq!(report.migrated.len(), 2);
+        assert_eq!(report.migrated[0].principal, "alice");
+        assert_eq!(report.migrated[0].granted, ["beta:AI_SUMMARY"]);
+        assert_eq!(report.migrated[0].revoked, ["beta:DARK_MODE"]);
+
+        for scope in principals.values() {
+            assert_granted!(scope, "beta:AI_SUMMARY");
+            assert_granted!(scope, "beta:NEW_EDITOR");
+            assert_not_granted!(scope, "beta:DARK_MODE");
+        }
+        assert_eq!(registry.holders("early-access"), [("alice", 2), ("bob", 2)]);
+
+        // nobody is left on an older version
+        assert_eq!(assert_ok!(registry.upgrade("early-access", &mut principals)).migrated.len(), 0);
+    }
+
+    #[test]
+    fn test_upgrade_is_atomic() {
+        let mut registry = BundleRegistry::new();
+        registry.publish("early-access", &["beta:NEW_EDITOR"]);
+        let mut principals = get_principals(&mut registry);
+        principals.remove("alice");
+
+        registry.publish("early-access", &["beta:NEW_EDITOR", "beta:VOICE"]);
+        assert_scope_err!(registry.upgrade("early-access", &mut principals), ScopeErrorCase::UnknownPermission);
+        assert_eq!(registry.holders("early-access"), [("alice", 1), ("bob", 1)]);
+
+        registry.publish("early-access", &["beta:DARK_MODE"]);
+        let report = assert_ok!(registry.upgrade("early-access", &mut principals));
+        assert_eq!(report.skipped, ["alice"]);
+        assert_eq!(registry.holders("early-access"), [("alice", 1), ("bob", 3)]);
+        assert_scope_err!(registry.upgrade("late-access", &mut principals), ScopeErrorCase::UnknownBundle);
+    }
+}
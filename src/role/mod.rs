@@ -0,0 +1,317 @@
+/*!
+    Roles name bundles of permission paths, such as "editor" for `posts:READ` and `posts:WRITE`, so grants can be
+    assigned by role instead of path by path. A role can extend other roles, receiving every path they grant.
+ */
+
+pub mod bundle;
+
+use alloc::collections::BTreeMap;
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+use serde::{Deserialize, Serialize};
+use crate::common::error::ErrorKind;
+use crate::scope::error::{ScopeError, ScopeErrorCase, ScopeErrorMetadata};
+use crate::scope::grant_map::GrantMap;
+use crate::scope::path::{PermissionPath, ScopePath};
+use crate::scope::Scope;
+
+/** Role is a named set of permission paths, relative to the scope it is assigned in, plus the roles it extends. */
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Role {
+    pub name: String,
+    pub paths: Vec<String>,
+    /** Names of the roles whose paths this role grants as well. */
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub extends: Vec<String>
+}
+
+impl Role {
+    pub fn new(name: &str) -> Role {
+        return Role {
+            name: name.to_string(),
+            ..Role::default()
+        };
+    }
+
+    /** Add a permission path, e.g. `posts:WRITE`, to the role. */
+    pub fn with_path(mut self, path: &str) -> Role {
+        self.paths.push(path.to_string());
+        return self;
+    }
+
+    /** Make the role grant every path of another role. */
+    pub fn extending(mut self, role: &str) -> Role {
+        self.extends.push(role.to_string());
+        return self;
+    }
+}
+
+fn unknown_role(name: &str) -> ErrorKind {
+    return ErrorKind::ScopeError(ScopeError::new(ScopeErrorCase::UnknownRole, name));
+}
+
+/** RoleSet holds the roles of an application, keyed by name. It serializes as an object of roles. */
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct RoleSet {
+    roles: BTreeMap<String, Role>
+}
+
+impl RoleSet {
+    pub fn new() -> RoleSet {
+        return RoleSet::default();
+    }
+
+    /**
+        Define a role, replacing any role of the same name. The roles it extends must already be defined, failing
+        with UnknownRole otherwise, and redefining a role may not make it extend itself, failing with RoleCycle.
+        Nothing is changed if the role is rejected.
+     */
+    pub fn define(&mut self, role: Role) -> Result<&mut RoleSet, ErrorKind> {
+        if let Some(extended) = role.extends.iter().find(|extended| **extended != role.name && !self.roles.contains_key(*extended)) {
+            return Err(unknown_role(extended));
+        }
+
+        let name = role.name.clone();
+        let previous = self.roles.insert(name.clone(), role);
+        if let Err(err) = self.lineage(&name) {
+            match previous {
+                Some(previous) => self.roles.insert(name, previous),
+                None => self.roles.remove(&name)
+            };
+            return Err(err);
+        }
+
+        return Ok(self);
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Role> {
+        return self.roles.get(name);
+    }
+
+    /** Iterate over the roles in name order. */
+    pub fn roles(&self) -> impl Iterator<Item = &Role> {
+        return self.roles.values();
+    }
+
+    /**
+        List a role and every role it extends, directly or indirectly, with each role after the roles it extends
+        and each listed once. Fails with UnknownRole or RoleCycle, which deserialized sets may contain.
+     */
+    fn lineage(&self, name: &str) -> Result<Vec<&Role>, ErrorKind> {
+        let root = self.roles.get(name).ok_or_else(|| unknown_role(name))?;
+        let mut ordered: Vec<&Role> = vec![];
+        let mut stack: Vec<(&Role, usize)> = vec![(root, 0)];
+
+        while let Some((role, next)) = stack.last_mut() {
+            let role: &Role = role;
+            let extended = match role.extends.get(*next) {
+                Some(extended) => extended,
+                None => {
+                    ordered.push(role);
+                    stack.pop();
+                    continue;
+                }
+            };
+            *next += 1;
+
+            if ordered.iter().any(|done| done.name == *extended) {
+                continue;
+            }
+            if stack.iter().any(|(open, _)| open.name == *extended) {
+                return Err(ErrorKind::ScopeError(ScopeError::with_metadata(
+                    ScopeErrorCase::RoleCycle,
+                    &role.name,
+                    ScopeErrorMetadata {
+                        related: Some(extended.clone()),
+                        ..ScopeErrorMetadata::new()
+                    }
+                )));
+            }
+            stack.push((self.roles.get(extended).ok_or_else(|| unknown_role(extended))?, 0));
+        }
+
+        return Ok(ordered);
+    }
+
+    /**
+        Resolve a role to the union of its own paths and those of every role it extends, without duplicates. Paths
+        of extended roles come first, so "admin extends editor extends viewer" lists the viewer's paths first.
+     */
+    pub fn resolve(&self, name: &str) -> Result<Vec<String>, ErrorKind> {
+        let mut paths: Vec<String> = vec![];
+        for role in self.lineage(name)? {
+            for path in &role.paths {
+                if !paths.contains(path) {
+                    paths.push(path.clone());
+                }
+            }
+        }
+
+        return Ok(paths);
+    }
+
+    /**
+        Grant every path of a role, including the paths of the roles it extends, as resolved at the time of the
+        call. Paths that are already granted are left as they are. Nothing is changed if any of the grants fails.
+     */
+    pub fn assign(&self, scope: &mut Scope, name: &str) -> Result<(), ErrorKind> {
+        let paths = self.resolve(name)?;
+
+        let mut working = scope.clone();
+        set_paths(&mut working, &paths, true)?;
+        working.touch();
+        *scope = working;
+
+        return Ok(());
+    }
+
+    /**
+        Resolve roles into the value of every scope of `schema` they grant anything in, keyed by full scope path, as
+        if they were assigned on a copy of the schema without grants. Scopes the roles grant nothing in are left
+        out. Implied permissions add no bits, as they are evaluated from these values when checked.
+     */
+    pub fn effective_masks(&self, schema: &Scope, roles: &[&str]) -> Result<BTreeMap<String, u64>, ErrorKind> {
+        let granted = self.assign_fresh(schema, roles)?;
+
+        return Ok(granted.walk_scopes()
+            .filter(|scope| scope.as_u64() != 0)
+            .map(|scope| (scope.path().to_string(), scope.as_u64()))
+            .collect());
+    }
+
+    /**
+        Resolve roles into the grants they make on `schema`, by permission name, as if they were assigned on a copy
+        of the schema without grants. The map can be stored per principal and applied with `apply_grant_map`.
+     */
+    pub fn grant_map(&self, schema: &Scope, roles: &[&str]) -> Result<GrantMap, ErrorKind> {
+        return Ok(self.assign_fresh(schema, roles)?.grant_map());
+    }
+
+    /** Assign roles in order on a copy of `schema` with every grant cleared. */
+    fn assign_fresh(&self, schema: &Scope, roles: &[&str]) -> Result<Scope, ErrorKind> {
+        let mut granted = schema.clone();
+        let paths: Vec<ScopePath> = schema.walk_scopes().map(|scope| scope.path().relative_to(schema.path())).collect();
+        for path in paths {
+            granted.resolve_scope_mut(&path)?.apply_u64_unguarded(0)?;
+        }
+
+        for role in roles {
+            self.assign(&mut granted, role)?;
+        }
+
+        return Ok(granted);
+    }
+}
+
+/**
+    Grant or revoke each of `paths` in place, skipping paths that are already in the requested state, and return the
+    paths that changed. Stops at the first failure, so callers work on a copy of the tree.
+ */
+pub(crate) fn set_paths(scope: &mut Scope, paths: &[String], grant: bool) -> Result<Vec<String>, ErrorKind> {
+    let mut changed: Vec<String> = vec![];
+    for path in paths {
+        let requested = PermissionPath::parse(path);
+        let granted = match scope.find_scope(&requested.scope) {
+            Some(target) => target.is_granted(&target.find_permission_name(&requested.permission)?),
+            None => false
+        };
+
+        if granted != grant {
+            scope.apply_linked(path, grant)?;
+            changed.push(path.clone());
+        }
+    }
+
+    return Ok(changed);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{assert_granted, assert_not_granted, assert_ok, assert_scope_err};
+
+    fn get_roles() -> RoleSet {
+        let mut roles = RoleSet::new();
+        assert_ok!(roles.define(Role::new("viewer").with_path("posts:READ")));
+        assert_ok!(roles.define(Role::new("editor").extending("viewer").with_path("posts:WRITE")));
+        assert_ok!(roles.define(Role::new("admin").extending("editor").with_path("posts:READ").with_path("users:MANAGE")));
+
+        return roles;
+    }
+
+    fn get_scope() -> Scope {
+        let mut scope = Scope::new("APP");
+        assert_ok!(scope.add_scope("posts").and_then(|sc| sc.add_scope("users")));
+        if let Some(posts) = scope.scope("posts") {
+            assert_ok!(posts.add_permission("READ").and_then(|sc| sc.add_permission("WRITE")).and_then(|sc| sc.add_requirement("WRITE", "READ")));
+        }
+        if let Some(users) = scope.scope("users") {
+            assert_ok!(users.add_permission("MANAGE"));
+        }
+
+        return scope;
+    }
+
+    #[test]
+    fn test_resolve_inherited_paths() {
+        let roles = get_roles();
+
+        assert_eq!(assert_ok!(roles.resolve("admin")), ["posts:READ", "posts:WRITE", "users:MANAGE"]);
+        assert_eq!(assert_ok!(roles.resolve("viewer")), ["posts:READ"]);
+        assert_scope_err!(roles.resolve("owner"), ScopeErrorCase::UnknownRole);
+    }
+
+    #[test]
+    fn test_assign_grants_union() {
+        let roles = get_roles();
+        let mut scope = get_scope();
+
+        // viewer's READ is granted before editor's WRITE, which requires it
+        assert_ok!(roles.assign(&mut scope, "editor"));
+        assert_granted!(scope, "posts:WRITE");
+        assert_not_granted!(scope, "users:MANAGE");
+
+        assert_ok!(roles.assign(&mut scope, "admin"));
+        assert_granted!(scope, "users:MANAGE");
+    }
+
+    #[test]
+    fn test_resolve_effective_masks() {
+        let roles = get_roles();
+        let mut schema = get_scope();
+        assert_ok!(schema.grant_path("users:MANAGE"));
+
+        let masks = assert_ok!(roles.effective_masks(&schema, &["editor"]));
+        assert_eq!(masks.into_iter().collect::<Vec<(String, u64)>>(), [(String::from("APP.posts"), 0b11)]);
+
+        let masks = assert_ok!(roles.effective_masks(&schema, &["viewer", "admin"]));
+        assert_eq!(masks.get("APP.users"), Some(&1));
+        assert_scope_err!(roles.effective_masks(&schema, &["owner"]), ScopeErrorCase::UnknownRole);
+
+        let map = assert_ok!(roles.grant_map(&schema, &["viewer"]));
+        let mut user = get_scope();
+        assert_ok!(user.apply_grant_map(&map));
+        assert_granted!(user, "posts:READ");
+        assert_not_granted!(user, "posts:WRITE");
+    }
+
+    #[test]
+    fn test_cycles_are_rejected() {
+        let mut roles = get_roles();
+
+        assert_scope_err!(roles.define(Role::new("viewer").extending("admin")), ScopeErrorCase::RoleCycle);
+        assert_scope_err!(roles.define(Role::new("viewer").extending("viewer")), ScopeErrorCase::RoleCycle);
+        assert_scope_err!(roles.define(Role::new("guest").extending("nobody")), ScopeErrorCase::UnknownRole);
+        assert_eq!(assert_ok!(roles.resolve("admin")).len(), 3);
+
+        // cycles in deserialized sets are caught when resolving
+        let json = serde_json::json!({
+            "a": { "name": "a", "paths": [], "extends": ["b"] },
+            "b": { "name": "b", "paths": [], "extends": ["a"] }
+        });
+        let roles: RoleSet = serde_json::from_value(json).unwrap_or_else(|err| panic!("not deserialized: {}", err));
+        assert_scope_err!(roles.resolve("a"), ScopeErrorCase::RoleCycle);
+    }
+}
@@ -0,0 +1,236 @@
+use alloc::collections::BTreeMap;
+use alloc::string::{String, ToString};
+use serde::{Deserialize, Serialize};
+use crate::common::error::ErrorKind;
+use crate::scope::error::{ScopeError, ScopeErrorCase};
+use crate::scope::path::{PermissionPath, ScopePath};
+use crate::scope::Scope;
+
+/**
+    Migration rewrites values stored under one version of a schema into the bits of the next, following renamed
+    and moved permissions and scopes. Permissions that keep their name and scope are followed without being listed,
+    as are renames recorded as aliases by `rename_permission` and permissions with the same stable identifier.
+    Keep the migration next to the schema version it leads to, so values stored under any older version can be
+    brought forward.
+ */
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Migration {
+    permissions: BTreeMap<String, String>,
+    scopes: BTreeMap<String, String>
+}
+
+impl Migration {
+    pub fn new() -> Migration {
+        return Migration::default();
+    }
+
+    /**
+        Record that the permission at `from` in the old schema is the permission at `to` in the new one. Paths look
+        like `posts:EDIT` and are relative to the root, so a permission can move to another scope.
+     */
+    pub fn rename(mut self, from: &str, to: &str) -> Migration {
+        self.permissions.insert(from.to_string(), to.to_string());
+
+        return self;
+    }
+
+    /**
+        Record that the scope at `from` in the old schema is the scope at `to` in the new one, together with every
+        scope below it. Its permissions are followed by name.
+     */
+    pub fn rename_scope(mut self, from: &str, to: &str) -> Migration {
+        self.scopes.insert(from.to_string(), to.to_string());
+
+        return self;
+    }
+
+    /** Get the path a scope was renamed to, if this migration renames it. */
+    pub(crate) fn scope_target(&self, from: &str) -> Option<&str> {
+        return self.scopes.get(from).map(|to| to.as_str());
+    }
+
+    /**
+        Rewrite values stored under `old`, keyed by scope path relative to the root (empty for the root), into
+        values for `new` keyed the same way. Bits of permissions that no longer exist are dropped; check
+        `schema::diff` first if that is not acceptable. Fails with UnknownScope or UnknownPermission if a rename
+        names something missing from its schema, and with UndefinedBits if a value sets bits that have no
+        permission in `old`.
+     */
+    pub fn apply(&self, old: &Scope, new: &Scope, values: &BTreeMap<String, u64>) -> Result<BTreeMap<String, u64>, ErrorKind> {
+        let renames = self.resolve_renames(old, new)?;
+        let mut migrated: BTreeMap<String, u64> = BTreeMap::new();
+
+        for (path, value) in values {
+            let scope = match old.find_scope(&ScopePath::parse(path)) {
+                Some(scope) => scope,
+                None => return Err(unknown_scope(path))
+            };
+            if value & !scope.defined_bits() != 0 {
+                return Err(ErrorKind::ScopeError(ScopeError::new(ScopeErrorCase::UndefinedBits, path)));
+            }
+
+            let target_path = self.translate(path);
+            if new.find_scope(&ScopePath::parse(&target_path)).is_some() {
+                migrated.entry(target_path.clone()).or_insert(0);
+            }
+
+            for permission in scope.permission_values().filter(|permission| value & permission.value != 0) {
+                let source = PermissionPath::new(ScopePath::parse(path), &permission.name).to_string();
+                let target = match renames.get(&source) {
+                    Some(target) => Some(target.clone()),
+                    None => self.follow(scope, &permission.name, &target_path, new)
+                };
+
+                if let Some((scope_path, bit)) = target {
+                    *migrated.entry(scope_path).or_insert(0) |= bit;
+                }
+            }
+        }
+
+        return Ok(migrated);
+    }
+
+    /** Resolve the explicit permission renames to the old permission path and the new scope path and bit. */
+    fn resolve_renames(&self, old: &Scope, new: &Scope) -> Result<BTreeMap<String, (String, u64)>, ErrorKind> {
+        for (from, to) in self.scopes.iter() {
+            if old.find_scope(&ScopePath::parse(from)).is_none() {
+                return Err(unknown_scope(from));
+            }
+            if new.find_scope(&ScopePath::parse(to)).is_none() {
+                return Err(unknown_scope(to));
+            }
+        }
+
+        let mut renames: BTreeMap<String, (String, u64)> = BTreeMap::new();
+        for (from, to) in self.permissions.iter() {
+            let from = PermissionPath::parse(from);
+            let name = match old.find_scope(&from.scope) {
+                Some(scope) => scope.find_permission_name(&from.permission)?,
+                None => return Err(unknown_scope(&from.scope.to_string()))
+            };
+
+            let to = PermissionPath::parse(to);
+            let bit = match new.find_scope(&to.scope) {
+                Some(scope) => match scope.permission_ref(&to.permission) {
+                    Some(permission) => permission.value,
+                    None => return Err(ErrorKind::ScopeError(ScopeError::new(ScopeErrorCase::UnknownPermission, &to.to_string())))
+                },
+                None => return Err(unknown_scope(&to.scope.to_string()))
+            };
+
+            renames.insert(PermissionPath::new(from.scope, &name).to_string(), (to.scope.to_string(), bit));
+        }
+
+        return Ok(renames);
+    }
+
+    /**
+        Find the scope path and bit in `new` of a permission that was not renamed explicitly: by name in the scope
+        its scope became, through an alias, or by its stable identifier anywhere in the tree.
+     */
+    fn follow(&self, scope: &Scope, name: &str, target_path: &str, new: &Scope) -> Option<(String, u64)> {
+        let found = new.find_scope(&ScopePath::parse(target_path))
+            .and_then(|target| target.permission_ref(name))
+            .map(|permission| (target_path.to_string(), permission.value));
+
+        #[cfg(feature = "uuid")]
+        if found.is_none() {
+            let path = PermissionPath::parse(&new.find_by_id(scope.permission_id(name)?)?);
+            let value = new.find_scope(&path.scope)?.permission_ref(&path.permission)?.value;
+            return Some((path.scope.to_string(), value));
+        }
+        #[cfg(not(feature = "uuid"))]
+        let _ = scope;
+
+        return found;
+    }
+
+    /** Rewrite an old scope path through the scope renames, the most specific one first. */
+    fn translate(&self, path: &str) -> String {
+        let renamed = self.scopes.iter()
+            .filter(|(from, _)| path == from.as_str() || path.strip_prefix(from.as_str()).is_some_and(|rest| rest.starts_with('.')))
+            .max_by_key(|(from, _)| from.len());
+
+        return match renamed {
+            Some((from, to)) => [to.as_str(), &path[from.len()..]].concat(),
+            None => path.to_string()
+        }
+    }
+}
+
+fn unknown_scope(path: &str) -> ErrorKind {
+    return ErrorKind::ScopeError(ScopeError::new(ScopeErrorCase::UnknownScope, path));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{assert_ok, assert_scope_err};
+
+    fn get_schema() -> Scope {
+        let mut scope = Scope::new("APP");
+        assert_ok!(scope.add_permission("ADMIN").and_then(|sc| sc.add_scope("posts")).and_then(|sc| sc.add_scope("billing")));
+        if let Some(posts) = scope.scope("posts") {
+            assert_ok!(posts.add_permission("READ").and_then(|sc| sc.add_permission("EDIT")).and_then(|sc| sc.add_permission("PUBLISH")));
+        }
+        if let Some(billing) = scope.scope("billing") {
+            assert_ok!(billing.add_permission("PAY"));
+        }
+
+        return scope;
+    }
+
+    fn values(entries: &[(&str, u64)]) -> BTreeMap<String, u64> {
+        return entries.iter().map(|(path, value)| (path.to_string(), *value)).collect();
+    }
+
+    #[test]
+    fn test_renamed_permissions_keep_their_grants() {
+        let old = get_schema();
+
+        // EDIT becomes WRITE on a new bit, PUBLISH moves to its own scope and billing becomes payments
+        let mut new = Scope::new("APP");
+        assert_ok!(new.add_permission("ADMIN").and_then(|sc| sc.add_scope("posts")).and_then(|sc| sc.add_scope("payments")).and_then(|sc| sc.add_scope("publishing")));
+        if let Some(posts) = new.scope("posts") {
+            assert_ok!(posts.add_permission("WRITE").and_then(|sc| sc.add_permission("READ")));
+        }
+        if let Some(payments) = new.scope("payments") {
+            assert_ok!(payments.add_permission("PAY"));
+        }
+        if let Some(publishing) = new.scope("publishing") {
+            assert_ok!(publishing.add_permission("PUBLISH"));
+        }
+
+        let migration = Migration::new()
+            .rename("posts.EDIT", "posts.WRITE")
+            .rename("posts:PUBLISH", "publishing:PUBLISH")
+            .rename_scope("billing", "payments");
+
+        let stored = values(&[("", 0b1), ("posts", 0b111), ("billing", 0b1)]);
+        let migrated = assert_ok!(migration.apply(&old, &new, &stored));
+        assert_eq!(migrated, values(&[("", 0b1), ("posts", 0b11), ("payments", 0b1), ("publishing", 0b1)]));
+    }
+
+    #[test]
+    fn test_aliases_and_removals() {
+        let old = get_schema();
+        let mut new = get_schema();
+        if let Some(posts) = new.scope("posts") {
+            assert_ok!(posts.rename_permission("EDIT", "WRITE", true).and_then(|sc| sc.remove_permission("PUBLISH")));
+        }
+
+        let migrated = assert_ok!(Migration::new().apply(&old, &new, &values(&[("posts", 0b110)])));
+        assert_eq!(migrated, values(&[("posts", 0b010)]));
+    }
+
+    #[test]
+    fn test_invalid_migrations() {
+        let old = get_schema();
+        let new = get_schema();
+
+        assert_scope_err!(Migration::new().rename("posts:DRAFT", "posts:EDIT").apply(&old, &new, &BTreeMap::new()), ScopeErrorCase::UnknownPermission);
+        assert_scope_err!(Migration::new().rename_scope("billing", "payments").apply(&old, &new, &BTreeMap::new()), ScopeErrorCase::UnknownScope);
+        assert_scope_err!(Migration::new().apply(&old, &new, &values(&[("reports", 1)])), ScopeErrorCase::UnknownScope);
+        assert_scope_err!(Migration::new().apply(&old, &new, &values(&[("posts", 0b1000)])), ScopeErrorCase::UndefinedBits);
+    }
+}
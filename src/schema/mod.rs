@@ -0,0 +1,339 @@
+/*!
+    Tools for evolving a schema once values produced under it have been stored. `diff` compares two versions of a
+    schema and tells whether values stored under the old one keep their meaning under the new one, so deployments
+    that would silently remap bits can be stopped before they ship. `migration::Migration` rewrites stored values
+    across renames that would otherwise strand them.
+ */
+
+use alloc::collections::{BTreeMap, BTreeSet};
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+use serde::Serialize;
+use crate::scope::path::{PermissionPath, ScopePath};
+use crate::scope::Scope;
+
+pub mod migration;
+
+use migration::Migration;
+
+/**
+    SchemaChange is a single difference between two versions of a schema. Scope paths are relative to the root
+    (`billing.invoices`) and permission paths look like `billing:READ`.
+ */
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(tag = "change", rename_all = "snake_case")]
+pub enum SchemaChange {
+    ScopeAdded { path: String },
+    /** The scope and every scope below it are gone, so values stored for them can no longer be applied. */
+    ScopeRemoved { path: String },
+    /**
+        A scope was renamed or moved, as recorded by `Migration::rename_scope`. Values keyed by its path and
+        positional encodings of the tree no longer line up, so stored values have to go through the migration.
+     */
+    ScopeRenamed { from: String, to: String },
+    PermissionAdded { path: String, shift: u8 },
+    /** The permission is gone and its bit is left undefined. */
+    PermissionRemoved { path: String, shift: u8 },
+    /** A permission was renamed, recognised by an alias left by `rename_permission` or by its stable identifier. */
+    PermissionRenamed { from: String, to: String },
+    /** A permission kept its name but now has a different bit. */
+    PermissionMoved { path: String, from: u8, to: u8 },
+    /** The bit of a permission now belongs to an unrelated permission, e.g. after `compact`. */
+    BitReassigned { scope: String, shift: u8, from: String, to: String }
+}
+
+impl SchemaChange {
+    /**
+        Check whether values stored before this change keep their meaning after it. Additions and permission
+        renames are compatible; scope renames and anything that frees, moves or reuses a bit are not.
+     */
+    pub fn is_wire_compatible(&self) -> bool {
+        return matches!(self,
+            SchemaChange::ScopeAdded { .. }
+            | SchemaChange::PermissionAdded { .. }
+            | SchemaChange::PermissionRenamed { .. });
+    }
+}
+
+/** SchemaDiff lists the changes between two versions of a schema, scope by scope in path order. */
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct SchemaDiff {
+    pub changes: Vec<SchemaChange>
+}
+
+impl SchemaDiff {
+    pub fn is_empty(&self) -> bool {
+        return self.changes.is_empty();
+    }
+
+    /** Check whether every value stored under the old schema means the same under the new one. */
+    pub fn is_wire_compatible(&self) -> bool {
+        return self.changes.iter().all(|change| change.is_wire_compatible());
+    }
+
+    /** Get the changes that break values stored under the old schema. */
+    pub fn incompatible_changes(&self) -> Vec<&SchemaChange> {
+        return self.changes.iter().filter(|change| !change.is_wire_compatible()).collect();
+    }
+}
+
+/**
+    Compare two versions of a schema. Grant state is ignored. Scopes are matched by their path relative to the
+    root, so a renamed scope shows up as removed and added; use `diff_with` to report it as a rename. Permissions
+    are matched by name, then by stable identifier, then through the aliases of `new`.
+ */
+pub fn diff(old: &Scope, new: &Scope) -> SchemaDiff {
+    return diff_with(old, new, &Migration::new());
+}
+
+/**
+    Compare two versions of a schema like `diff`, following the scope renames recorded in `migration`. Scopes are
+    only reported as renamed when the migration says so, never because their permissions happen to match.
+ */
+pub fn diff_with(old: &Scope, new: &Scope, migration: &Migration) -> SchemaDiff {
+    let old_scopes = scopes_by_path(old);
+    let new_scopes = scopes_by_path(new);
+    let mut changes: Vec<SchemaChange> = vec![];
+
+    // old scope paths paired with the new paths they became
+    let mut matched: BTreeMap<&str, &str> = BTreeMap::new();
+    let mut renamed: Vec<(&str, &str)> = vec![];
+    for path in old_scopes.keys() {
+        if new_scopes.contains_key(path) {
+            matched.insert(path, path);
+            continue;
+        }
+
+        // descendants of a renamed scope move with it
+        let translated = translate(path, &renamed);
+        if let Some((target, _)) = new_scopes.get_key_value(&translated) {
+            matched.insert(path, target);
+            continue;
+        }
+
+        let candidate = migration.scope_target(path)
+            .and_then(|target| new_scopes.get_key_value(target))
+            .filter(|(target, _)| !old_scopes.contains_key(*target) && !matched.values().any(|matched| matched == target));
+        match candidate {
+            Some((target, _)) => {
+                matched.insert(path, target);
+                renamed.push((path, target));
+                changes.push(SchemaChange::ScopeRenamed { from: path.clone(), to: target.clone() });
+            },
+            None => {
+                // removing a scope removes its descendants, so only report the topmost one
+                if !old_scopes.keys().any(|ancestor| is_below(path, ancestor) && !matched.contains_key(ancestor.as_str())) {
+                    changes.push(SchemaChange::ScopeRemoved { path: path.clone() });
+                }
+            }
+        }
+    }
+
+    let targets: BTreeSet<&str> = matched.values().copied().collect();
+    for path in new_scopes.keys().filter(|path| !targets.contains(path.as_str())) {
+        if !new_scopes.keys().any(|ancestor| is_below(path, ancestor) && !targets.contains(ancestor.as_str())) {
+            changes.push(SchemaChange::ScopeAdded { path: path.clone() });
+        }
+    }
+
+    for (old_path, new_path) in matched {
+        diff_permissions(old_scopes[old_path], old_path, new_scopes[new_path], new_path, &mut changes);
+    }
+
+    return SchemaDiff { changes };
+}
+
+/** Compare the permissions of a scope in the old schema with the same scope in the new one. */
+fn diff_permissions(old: &Scope, old_path: &str, new: &Scope, new_path: &str, changes: &mut Vec<SchemaChange>) {
+    let permission_path = |scope: &str, name: &str| PermissionPath::new(ScopePath::parse(scope), name).to_string();
+    let old_layout = layout(old);
+    let new_layout = layout(new);
+
+    let mut claimed: BTreeSet<&str> = BTreeSet::new();
+    let mut unmatched: Vec<(u8, &str)> = vec![];
+    for (shift, name) in old_layout.iter() {
+        let target = match new_layout.iter().find(|(_, candidate)| candidate == name) {
+            Some((new_shift, candidate)) => Some((*new_shift, candidate.as_str())),
+            None => renamed_to(old, name, new, &new_layout)
+        };
+
+        let Some((new_shift, target)) = target else {
+            unmatched.push((*shift, name.as_str()));
+            continue;
+        };
+        claimed.insert(target);
+        if target != name {
+            changes.push(SchemaChange::PermissionRenamed { from: permission_path(old_path, name), to: permission_path(new_path, target) });
+        }
+        if new_shift != *shift {
+            changes.push(SchemaChange::PermissionMoved { path: permission_path(new_path, target), from: *shift, to: new_shift });
+        }
+    }
+
+    let mut reassigned: BTreeSet<u8> = BTreeSet::new();
+    for (shift, name) in new_layout.iter().filter(|(_, name)| !claimed.contains(name.as_str())) {
+        match old_layout.iter().find(|(old_shift, _)| old_shift == shift) {
+            Some((_, previous)) => {
+                reassigned.insert(*shift);
+                changes.push(SchemaChange::BitReassigned {
+                    scope: new_path.to_string(),
+                    shift: *shift,
+                    from: previous.clone(),
+                    to: name.clone()
+                });
+            },
+            None => changes.push(SchemaChange::PermissionAdded { path: permission_path(new_path, name), shift: *shift })
+        }
+    }
+
+    for (shift, name) in unmatched.into_iter().filter(|(shift, _)| !reassigned.contains(shift)) {
+        changes.push(SchemaChange::PermissionRemoved { path: permission_path(old_path, name), shift });
+    }
+}
+
+/** Find the permission of `new` an old permission was renamed to, with its shift. */
+fn renamed_to<'a>(old: &Scope, name: &str, new: &Scope, new_layout: &'a [(u8, String)]) -> Option<(u8, &'a str)> {
+    #[cfg(feature = "uuid")]
+    if let Some(id) = old.permission_id(name) {
+        if let Some((shift, target)) = new_layout.iter().find(|(_, candidate)| new.permission_id(candidate) == Some(id)) {
+            return Some((*shift, target.as_str()));
+        }
+    }
+    #[cfg(not(feature = "uuid"))]
+    let _ = old;
+
+    let alias = new.alias_target(name)?;
+    return new_layout.iter()
+        .find(|(_, candidate)| candidate == alias)
+        .map(|(shift, target)| (*shift, target.as_str()));
+}
+
+/** Get every scope of the tree keyed by its path relative to the root. */
+fn scopes_by_path(root: &Scope) -> BTreeMap<String, &Scope> {
+    return root.walk_scopes().map(|scope| (scope.path().relative_to(root.path()).to_string(), scope)).collect();
+}
+
+/** Get the shift and name of every permission defined directly on a scope, ordered by shift. */
+fn layout(scope: &Scope) -> Vec<(u8, String)> {
+    let mut layout: Vec<(u8, String)> = scope.permission_values()
+        .map(|permission| (permission.value.trailing_zeros() as u8, permission.name.clone()))
+        .collect();
+    layout.sort();
+
+    return layout;
+}
+
+/** Check whether `path` is strictly below `ancestor`. */
+fn is_below(path: &str, ancestor: &str) -> bool {
+    return !ancestor.is_empty() && path.len() > ancestor.len() && path.starts_with(ancestor) && path[ancestor.len()..].starts_with('.');
+}
+
+/** Rewrite a scope path through the renames recorded so far. */
+fn translate(path: &str, renamed: &[(&str, &str)]) -> String {
+    return match renamed.iter().find(|(from, _)| is_below(path, from)) {
+        Some((from, to)) => format!("{}{}", to, &path[from.len()..]),
+        None => path.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assert_ok;
+
+    fn get_schema() -> Scope {
+        let mut scope = Scope::new("APP");
+        assert_ok!(scope.add_permission("READ").and_then(|sc| sc.add_permission("WRITE")).and_then(|sc| sc.add_scope("billing")));
+        if let Some(billing) = scope.scope("billing") {
+            assert_ok!(billing.add_permission("PAY").and_then(|sc| sc.add_scope("invoices")));
+        }
+
+        return scope;
+    }
+
+    #[test]
+    fn test_compatible_changes() {
+        let old = get_schema();
+        assert_eq!(diff(&old, &old).is_empty(), true);
+
+        let mut new = get_schema();
+        assert_ok!(new.add_permission("DELETE").and_then(|sc| sc.rename_permission("WRITE", "EDIT", true)).and_then(|sc| sc.add_scope("reports")));
+        if let Some(reports) = new.scope("reports") {
+            assert_ok!(reports.add_scope("daily"));
+        }
+
+        let changes = diff(&old, &new);
+        assert_eq!(changes.changes, vec![
+            SchemaChange::ScopeAdded { path: String::from("reports") },
+            SchemaChange::PermissionRenamed { from: String::from("WRITE"), to: String::from("EDIT") },
+            SchemaChange::PermissionAdded { path: String::from("DELETE"), shift: 2 }
+        ]);
+        assert_eq!(changes.is_wire_compatible(), true);
+    }
+
+    #[test]
+    fn test_renamed_scope() {
+        let old = get_schema();
+
+        let mut new = Scope::new("APP");
+        assert_ok!(new.add_permission("READ").and_then(|sc| sc.add_permission("WRITE")).and_then(|sc| sc.add_scope("payments")));
+        if let Some(payments) = new.scope("payments") {
+            assert_ok!(payments.add_permission("PAY").and_then(|sc| sc.add_scope("invoices")));
+        }
+
+        // matching permissions alone are no evidence of a rename
+        let changes = diff(&old, &new);
+        assert_eq!(changes.changes, vec![
+            SchemaChange::ScopeRemoved { path: String::from("billing") },
+            SchemaChange::ScopeAdded { path: String::from("payments") }
+        ]);
+        assert_eq!(changes.is_wire_compatible(), false);
+
+        let changes = diff_with(&old, &new, &Migration::new().rename_scope("billing", "payments"));
+        assert_eq!(changes.changes, vec![SchemaChange::ScopeRenamed { from: String::from("billing"), to: String::from("payments") }]);
+        assert_eq!(changes.is_wire_compatible(), false);
+    }
+
+    #[test]
+    fn test_unrelated_scopes_are_not_renames() {
+        let mut old = Scope::new("APP");
+        assert_ok!(old.add_scope("billing"));
+        if let Some(billing) = old.scope("billing") {
+            assert_ok!(billing.add_permission("READ"));
+        }
+
+        let mut new = Scope::new("APP");
+        assert_ok!(new.add_scope("reports"));
+        if let Some(reports) = new.scope("reports") {
+            assert_ok!(reports.add_permission("READ"));
+        }
+
+        let changes = diff(&old, &new);
+        assert_eq!(changes.incompatible_changes(), vec![&SchemaChange::ScopeRemoved { path: String::from("billing") }]);
+    }
+
+    #[test]
+    fn test_remapped_bits_are_incompatible() {
+        let old = get_schema();
+
+        // READ is removed and the compaction hands its bit to WRITE, and billing loses its child scope
+        let mut new = get_schema();
+        assert_ok!(new.remove_permission("READ"));
+        new.compact();
+        assert_ok!(new.add_permission("AUDIT"));
+        if let Some(billing) = new.scope("billing") {
+            assert_eq!(billing.remove_scope("invoices").is_some(), true);
+        }
+
+        let changes = diff(&old, &new);
+        assert_eq!(changes.is_wire_compatible(), false);
+        assert_eq!(changes.incompatible_changes(), vec![
+            &SchemaChange::ScopeRemoved { path: String::from("billing.invoices") },
+            &SchemaChange::PermissionMoved { path: String::from("WRITE"), from: 1, to: 0 },
+            &SchemaChange::BitReassigned { scope: String::new(), shift: 1, from: String::from("WRITE"), to: String::from("AUDIT") },
+            &SchemaChange::PermissionRemoved { path: String::from("READ"), shift: 0 }
+        ]);
+    }
+}
@@ -0,0 +1,136 @@
+use alloc::vec::Vec;
+use core::ops::{BitAnd, BitOr, Sub};
+use crate::common::error::ErrorKind;
+use crate::scope::path::ScopePath;
+use crate::scope::Scope;
+
+impl Scope {
+    /**
+        Combine the grants of two trees with the same schema, such as the scopes granted by two roles: a permission
+        is granted in the result if it is granted in either. Denials of either tree carry over, as denial overrides
+        grants. Fails with SchemaMismatch if the fingerprints differ. The rules and metadata of `self` are kept.
+     */
+    pub fn union(&self, other: &Scope) -> Result<Scope, ErrorKind> {
+        return self.combine(other, |ours, theirs| ours | theirs, |ours, theirs| ours | theirs);
+    }
+
+    /**
+        Keep the grants two trees with the same schema have in common: a permission is granted in the result if
+        it is granted in both. Denials of either tree carry over. Fails with SchemaMismatch if the fingerprints differ.
+     */
+    pub fn intersection(&self, other: &Scope) -> Result<Scope, ErrorKind> {
+        return self.combine(other, |ours, theirs| ours & theirs, |ours, theirs| ours | theirs);
+    }
+
+    /**
+        Remove the grants of `other` from this tree: a permission is granted in the result if it is granted here
+        but not in `other`. Only the denials of this tree carry over. Fails with SchemaMismatch if the fingerprints
+        differ.
+     */
+    pub fn difference(&self, other: &Scope) -> Result<Scope, ErrorKind> {
+        return self.combine(other, |ours, theirs| ours & !theirs, |ours, _| ours);
+    }
+
+    /** Copy this tree with the value and deny mask of every scope combined with those of the same scope in `other`. */
+    fn combine(&self, other: &Scope, values: fn(u64, u64) -> u64, denials: fn(u64, u64) -> u64) -> Result<Scope, ErrorKind> {
+        self.verify_fingerprint(other.fingerprint())?;
+
+        let mut changes: Vec<(ScopePath, u64, u64)> = Vec::new();
+        for scope in self.walk_scopes() {
+            let path = scope.path.relative_to(&self.path);
+            let (value, denied) = match other.find_scope(&path) {
+                Some(theirs) => (values(scope.as_u64(), theirs.as_u64()), denials(scope.as_deny_u64(), theirs.as_deny_u64())),
+                None => (scope.as_u64(), scope.as_deny_u64())
+            };
+            changes.push((path, value, denied));
+        }
+
+        let mut combined = self.clone();
+        for (path, value, denied) in changes {
+            let scope = combined.resolve_scope_mut(&path)?;
+            // the combined tree is derived from both sides rather than changed on behalf of a caller
+            scope.apply_u64_unguarded(value & !denied)?;
+            scope.apply_deny_u64(denied)?;
+        }
+
+        return Ok(combined);
+    }
+}
+
+/** `&a | &b` is `a.union(&b)`. */
+impl BitOr for &Scope {
+    type Output = Result<Scope, ErrorKind>;
+
+    fn bitor(self, other: &Scope) -> Result<Scope, ErrorKind> {
+        return self.union(other);
+    }
+}
+
+/** `&a & &b` is `a.intersection(&b)`. */
+impl BitAnd for &Scope {
+    type Output = Result<Scope, ErrorKind>;
+
+    fn bitand(self, other: &Scope) -> Result<Scope, ErrorKind> {
+        return self.intersection(other);
+    }
+}
+
+/** `&a - &b` is `a.difference(&b)`. */
+impl Sub for &Scope {
+    type Output = Result<Scope, ErrorKind>;
+
+    fn sub(self, other: &Scope) -> Result<Scope, ErrorKind> {
+        return self.difference(other);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::fixtures::billing_scope;
+    use crate::{assert_granted, assert_not_granted, assert_ok, assert_scope_err};
+    use crate::scope::error::ScopeErrorCase;
+
+    fn granted(paths: &[&str]) -> Scope {
+        let mut scope = billing_scope();
+        for path in paths {
+            assert_ok!(scope.grant_path(path));
+        }
+
+        return scope;
+    }
+
+    #[test]
+    fn test_set_operations() {
+        let viewer = granted(&["READ", "billing:PAY"]);
+        let editor = granted(&["READ", "WRITE"]);
+
+        let both = assert_ok!(&viewer | &editor);
+        assert_eq!(both.as_u64(), 0b11);
+        assert_granted!(both, "billing:PAY");
+
+        let common = assert_ok!(&viewer & &editor);
+        assert_eq!(common.as_u64(), 0b01);
+        assert_not_granted!(common, "billing:PAY");
+
+        let only_viewer = assert_ok!(&viewer - &editor);
+        assert_eq!(only_viewer.as_u64(), 0);
+        assert_granted!(only_viewer, "billing:PAY");
+        assert_eq!(assert_ok!(viewer.union(&editor)).as_u64(), both.as_u64());
+    }
+
+    #[test]
+    fn test_denials_and_schemas() {
+        let mut restricted = granted(&["READ"]);
+        assert_ok!(restricted.deny("WRITE"));
+        let editor = granted(&["READ", "WRITE"]);
+
+        let both = assert_ok!(restricted.union(&editor));
+        assert_not_granted!(both, "WRITE");
+        assert_eq!(assert_ok!(editor.difference(&restricted)).is_denied("WRITE"), false);
+
+        let mut other = billing_scope();
+        assert_ok!(other.add_permission("DELETE"));
+        assert_scope_err!(restricted.union(&other), ScopeErrorCase::SchemaMismatch);
+    }
+}
@@ -0,0 +1,244 @@
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use hashbrown::HashMap;
+use crate::common::error::ErrorKind;
+use crate::scope::deprecation::DeprecationWarning;
+use crate::scope::error::{ScopeError, ScopeErrorCase};
+use crate::scope::path::{PermissionPath, ScopePath};
+use crate::scope::Scope;
+
+impl Scope {
+    /**
+        Rename a permission, keeping its bit and every rule declared on it. When `keep_alias` is set, the old
+        name keeps resolving to the renamed permission for lookups, checks, grants and imports, so values and
+        paths recorded before the rename remain usable. Granting or revoking through an alias records a
+        deprecation warning.
+
+        `old` may be a path into a child scope, such as `billing:VIEW`, and references declared on this scope or
+        on any scope down to the permission follow the rename. Fails with Referenced if a scope above this one
+        declares a reference to the permission; rename it through a path from that scope instead.
+     */
+    pub fn rename_permission(&mut self, old: &str, new: &str, keep_alias: bool) -> Result<&mut Scope, ErrorKind> {
+        let requested = PermissionPath::parse(old);
+        let scope = match self.find_scope(&requested.scope) {
+            Some(scope) => scope,
+            None => return Err(ErrorKind::ScopeError(ScopeError::new(ScopeErrorCase::UnknownScope, &requested.scope.to_string())))
+        };
+        let old = scope.find_permission_name(&requested.permission)?;
+        let new = scope.policy.normalize(new).into_owned();
+        scope.validate_name(&new)?;
+
+        // references declared above this scope cannot be rewritten from here
+        let depth = requested.scope.len();
+        if scope.referenced.iter().any(|(distance, name)| *name == old && *distance > depth) {
+            return Err(ErrorKind::ScopeError(ScopeError::new(ScopeErrorCase::Referenced, &old)));
+        }
+
+        let segments: Vec<&str> = requested.scope.segments().iter().map(|segment| segment.as_str()).collect();
+        for level in 0..depth {
+            let declaring = self.resolve_scope_mut(&ScopePath::from_segments(&segments[..level]))?;
+            declaring.rename_in_references(&ScopePath::from_segments(&segments[level..]), &old, &new);
+            declaring.touch();
+        }
+
+        let scope = self.resolve_scope_mut(&requested.scope)?;
+        scope.rename_defined(&old, &new, keep_alias);
+
+        return Ok(self);
+    }
+
+    /** Rename a permission defined on this scope, whose old and new names have been checked. */
+    fn rename_defined(&mut self, old: &str, new: &str, keep_alias: bool) {
+        let (old, new) = (old.to_string(), new.to_string());
+        self.rename_in_references(&ScopePath::new(), &old, &new);
+        for (_, name) in self.referenced.iter_mut().filter(|(_, name)| *name == old) {
+            *name = new.clone();
+        }
+
+        if let Some(mut permission) = self.permissions.remove(&old) {
+            permission.name = new.clone();
+            self.permissions.insert(new.clone(), permission);
+        }
+        for name in self.permission_order.iter_mut().filter(|name| **name == old) {
+            *name = new.clone();
+        }
+
+        rename_in_rules(&mut self.requirements, &old, &new);
+        rename_in_rules(&mut self.implications, &old, &new);
+        rename_in_rules(&mut self.exclusions, &old, &new);
+        for (_, permissions) in self.required_sets.iter_mut() {
+            rename_in_list(permissions, &old, &new);
+        }
+        for members in self.composites.values_mut() {
+            rename_in_list(members, &old, &new);
+        }
+        if let Some(replacement) = self.deprecations.remove(&old) {
+            self.deprecations.insert(new.clone(), replacement);
+        }
+        if let Some(conditions) = self.conditions.remove(&old) {
+            self.conditions.insert(new.clone(), conditions);
+        }
+
+        // aliases from earlier renames follow the permission to its new name
+        for target in self.aliases.values_mut() {
+            if *target == old {
+                *target = new.clone();
+            }
+        }
+        if keep_alias {
+            self.aliases.insert(old, new);
+        }
+
+        self.touch();
+    }
+
+    /** Get the current name of the permission an alias refers to. */
+    pub fn alias_target(&self, alias: &str) -> Option<&str> {
+        return self.aliases.get(self.policy.normalize(alias).as_ref()).map(|target| target.as_str());
+    }
+
+    /** Get every alias of this scope as (old name, current name) pairs, ordered by old name. */
+    pub fn aliases(&self) -> Vec<(&str, &str)> {
+        let mut aliases: Vec<(&str, &str)> = self.aliases.iter().map(|(alias, target)| (alias.as_str(), target.as_str())).collect();
+        aliases.sort();
+
+        return aliases;
+    }
+
+    /** Normalize a permission name and follow it through an alias if it is not the name of a permission. */
+    pub(crate) fn resolve_name(&self, name: &str) -> String {
+        let name = self.policy.normalize(name);
+
+        if !self.permissions.contains_key(name.as_ref()) {
+            if let Some(target) = self.aliases.get(name.as_ref()) {
+                return target.clone();
+            }
+        }
+
+        return name.into_owned();
+    }
+
+    /** Record a deprecation warning when a permission was addressed by one of its aliases. */
+    pub(crate) fn note_alias_use(&mut self, requested: &str) {
+        let requested = self.policy.normalize(requested).into_owned();
+
+        if let Some(target) = self.aliases.get(&requested) {
+            let warning = DeprecationWarning {
+                path: self.path.clone(),
+                permission: requested.clone(),
+                replacement: Some(target.clone())
+            };
+            self.warnings.push(warning);
+        }
+    }
+
+    /** Fail if a name is already in use as an alias, which would make lookups ambiguous. */
+    pub(crate) fn validate_not_alias(&self, name: &str) -> Result<(), ErrorKind> {
+        return match self.aliases.contains_key(name) {
+            true => Err(ErrorKind::ScopeError(ScopeError::new(ScopeErrorCase::PermissionExists, name))),
+            false => Ok(())
+        }
+    }
+}
+
+/** Rename a permission wherever it appears in a map of rules, as a key or as a value. */
+fn rename_in_rules(rules: &mut HashMap<String, Vec<String>>, old: &str, new: &str) {
+    if let Some(values) = rules.remove(old) {
+        rules.insert(new.to_string(), values);
+    }
+
+    for values in rules.values_mut() {
+        rename_in_list(values, old, new);
+    }
+}
+
+fn rename_in_list(names: &mut [String], old: &str, new: &str) {
+    for name in names.iter_mut() {
+        if name == old {
+            *name = new.to_string();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get_renamed_scope(keep_alias: bool) -> Scope {
+        let mut scope = Scope::new("TEST_SCOPE");
+
+        if let Err(_) = scope
+            .add_permission("READ")
+            .and_then(|sc| sc.add_permission("EDIT"))
+            .and_then(|sc| sc.add_requirement("EDIT", "READ"))
+            .and_then(|sc| sc.grant("READ"))
+            .and_then(|sc| sc.grant("EDIT"))
+            .and_then(|sc| sc.rename_permission("EDIT", "WRITE", keep_alias)) {
+            assert!(false);
+        }
+
+        return scope;
+    }
+
+    #[test]
+    fn test_rename_keeps_bit_and_rules() {
+        let mut scope = get_renamed_scope(false);
+
+        assert_eq!(scope.check("WRITE"), true);
+        assert_eq!(scope.check("EDIT"), false);
+        assert_eq!(scope.requirements("WRITE"), ["READ".to_string()]);
+        assert_eq!(scope.as_u64(), 0b11);
+
+        match scope.permission("WRITE") {
+            Some(permission) => {
+                assert_eq!(permission.name, "WRITE");
+                assert_eq!(permission.value, 1 << 1);
+            },
+            None => assert!(false)
+        }
+    }
+
+    #[test]
+    fn test_rename_with_alias_resolves_old_name() {
+        let mut scope = get_renamed_scope(true);
+
+        assert_eq!(scope.alias_target("EDIT"), Some("WRITE"));
+        assert_eq!(scope.check("EDIT"), true);
+        assert_eq!(scope.permission("EDIT").is_some(), true);
+
+        // using the alias works but signals that the name is deprecated
+        assert!(scope.revoke("EDIT").is_ok());
+        assert_eq!(scope.check("WRITE"), false);
+
+        let warnings = scope.take_warnings();
+        assert_eq!(warnings.len(), 1usize);
+        assert_eq!(warnings[0].permission, "EDIT");
+        assert_eq!(warnings[0].replacement, Some("WRITE".to_string()));
+    }
+
+    #[test]
+    fn test_alias_name_cannot_be_reused() {
+        let mut scope = get_renamed_scope(true);
+
+        match scope.add_permission("EDIT") {
+            Ok(_) => assert!(false),
+            Err(kind) => match kind {
+                ErrorKind::ScopeError(err) => assert_eq!(err.case(), ScopeErrorCase::PermissionExists),
+                ErrorKind::PermissionError(_) => assert!(false),
+            }
+        }
+    }
+
+    #[test]
+    fn test_chained_renames_follow_alias() {
+        let mut scope = get_renamed_scope(true);
+
+        if let Ok(_) = scope.rename_permission("WRITE", "MODIFY", true) {
+            assert_eq!(scope.alias_target("EDIT"), Some("MODIFY"));
+            assert_eq!(scope.alias_target("WRITE"), Some("MODIFY"));
+            assert_eq!(scope.check("EDIT"), true);
+        } else {
+            assert!(false);
+        }
+    }
+}
@@ -0,0 +1,195 @@
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+use serde::{Deserialize, Serialize};
+use crate::common::error::ErrorKind;
+use crate::scope::conversion::{PermissionEntry, ScopeMetadata, ScopeTuple};
+use crate::scope::error::{ScopeError, ScopeErrorCase};
+use crate::scope::{Scope, DEFAULT_MAX_DEPTH};
+
+/** The version written as the first byte of every binary export. Bump it whenever the layout changes. */
+pub const BINARY_FORMAT_VERSION: u8 = 1;
+
+/**
+    A scope as written by `to_bytes`. The tree is flattened into a list in depth-first order, each scope pointing at
+    its parent by index, so decoding never recurses however deep the tree is. Rules are rare and their set keeps
+    growing, so the metadata is embedded as JSON, and left empty when the scope has none.
+ */
+#[derive(Serialize, Deserialize)]
+struct BinaryScope {
+    parent: u32,
+    name: String,
+    value: u64,
+    permissions: Vec<(String, Option<u8>)>,
+    metadata: Vec<u8>
+}
+
+impl Scope {
+    /**
+        Export this scope and its child scopes in a compact binary form, for stores such as Redis or a session cookie
+        where JSON is too bulky. The first byte is the format version, `BINARY_FORMAT_VERSION`, and the rest is the
+        tree in postcard encoding. Holds the same information as `as_json`.
+     */
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut scopes: Vec<BinaryScope> = vec![];
+        let mut stack: Vec<(ScopeTuple, u32)> = vec![(self.as_tuple(), 0)];
+
+        while let Some((ScopeTuple(name, value, permissions, children, metadata), parent)) = stack.pop() {
+            let index = scopes.len() as u32;
+            scopes.push(BinaryScope {
+                parent,
+                name,
+                value,
+                permissions: permissions.into_iter().map(|entry| (entry.name().to_string(), entry.shift())).collect(),
+                metadata: match metadata.is_empty() {
+                    true => vec![],
+                    false => serde_json::to_vec(&metadata).unwrap_or_default()
+                }
+            });
+            stack.extend(children.into_iter().rev().map(|child| (child, index)));
+        }
+
+        let mut bytes = vec![BINARY_FORMAT_VERSION];
+        // encoding into a Vec only fails if the allocation does
+        bytes.extend(postcard::to_allocvec(&scopes).unwrap_or_default());
+
+        return bytes;
+    }
+
+    /**
+        Import a tree exported by `to_bytes`. Fails with InvalidEncoding if the bytes are malformed or were written
+        by an unsupported format version, and with the usual import errors if the tree itself is invalid.
+     */
+    pub fn from_bytes(bytes: &[u8]) -> Result<Scope, ErrorKind> {
+        let (version, body) = match bytes.split_first() {
+            Some(split) => split,
+            None => return Err(invalid_bytes("empty input"))
+        };
+        if *version != BINARY_FORMAT_VERSION {
+            return Err(invalid_bytes(&format!("unsupported binary format version {}", version)));
+        }
+
+        let scopes: Vec<BinaryScope> = postcard::from_bytes(body).map_err(|err| invalid_bytes(&err.to_string()))?;
+        if scopes.is_empty() {
+            return Err(invalid_bytes("no root scope"));
+        }
+
+        let mut tuples: Vec<(u32, ScopeTuple)> = Vec::with_capacity(scopes.len());
+        for (index, scope) in scopes.into_iter().enumerate() {
+            if index > 0 && scope.parent as usize >= index {
+                return Err(invalid_bytes("scope listed before its parent"));
+            }
+
+            let metadata = match scope.metadata.is_empty() {
+                true => ScopeMetadata::new(),
+                false => serde_json::from_slice(&scope.metadata).map_err(|err| invalid_bytes(&err.to_string()))?
+            };
+            let permissions = scope.permissions.into_iter()
+                .map(|(name, shift)| match shift {
+                    Some(shift) => PermissionEntry::Placed(name, shift),
+                    None => PermissionEntry::Named(name)
+                })
+                .collect();
+            tuples.push((scope.parent, ScopeTuple(scope.name, scope.value, permissions, vec![], metadata)));
+        }
+
+        // every scope comes after its parent, so walking backwards completes each subtree before it is attached
+        while tuples.len() > 1 {
+            if let Some((parent, mut tuple)) = tuples.pop() {
+                tuple.3.reverse();
+                tuples[parent as usize].1.3.push(tuple);
+            }
+        }
+
+        return match tuples.pop() {
+            Some((_, mut root)) => {
+                root.3.reverse();
+                Scope::from_tuple(root, DEFAULT_MAX_DEPTH)
+            },
+            None => Err(invalid_bytes("no root scope"))
+        }
+    }
+
+    /**
+        Import a tree exported by `to_bytes` like `from_bytes`, and when `expected_fingerprint` is given, fail with
+        SchemaMismatch unless the imported schema has that fingerprint.
+     */
+    pub fn from_bytes_verified(bytes: &[u8], expected_fingerprint: Option<u64>) -> Result<Scope, ErrorKind> {
+        let scope = Scope::from_bytes(bytes)?;
+
+        if let Some(expected) = expected_fingerprint {
+            scope.verify_fingerprint(expected)?;
+        }
+
+        return Ok(scope);
+    }
+}
+
+fn invalid_bytes(reason: &str) -> ErrorKind {
+    return ErrorKind::ScopeError(ScopeError::new(ScopeErrorCase::InvalidEncoding, reason));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{assert_granted, assert_ok, assert_scope_err};
+
+    fn get_scope() -> Scope {
+        let mut scope = Scope::new("APP");
+        assert_ok!(scope.add_permission("READ").and_then(|sc| sc.add_permission("WRITE")).and_then(|sc| sc.add_scope("billing")).and_then(|sc| sc.add_scope("reports")));
+        assert_ok!(scope.add_requirement("WRITE", "READ"));
+        if let Some(billing) = scope.scope("billing") {
+            assert_ok!(billing.add_permission("PAY").and_then(|sc| sc.add_scope("invoices")).and_then(|sc| sc.grant("PAY")));
+            if let Some(invoices) = billing.scope("invoices") {
+                assert_ok!(invoices.add_permission("VOID"));
+            }
+        }
+
+        return scope;
+    }
+
+    #[test]
+    fn test_binary_round_trip() {
+        let scope = get_scope();
+        let bytes = scope.to_bytes();
+        assert_eq!(bytes[0], BINARY_FORMAT_VERSION);
+        assert_eq!(bytes.len() < scope.as_json().to_string().len(), true);
+
+        let imported = assert_ok!(Scope::from_bytes(&bytes));
+        assert_eq!(imported.as_json(), scope.as_json());
+        assert_eq!(imported.fingerprint(), scope.fingerprint());
+        assert_granted!(imported, "billing:PAY");
+    }
+
+    #[test]
+    fn test_binary_import_verifies_fingerprint() {
+        let scope = get_scope();
+        let bytes = scope.to_bytes();
+
+        let imported = assert_ok!(Scope::from_bytes_verified(&bytes, Some(scope.fingerprint())));
+        assert_granted!(imported, "billing:PAY");
+        assert_ok!(Scope::from_bytes_verified(&bytes, None));
+        assert_scope_err!(Scope::from_bytes_verified(&bytes, Some(scope.fingerprint() ^ 1)), ScopeErrorCase::SchemaMismatch);
+    }
+
+    #[test]
+    fn test_binary_rejects_bad_input() {
+        let mut bytes = get_scope().to_bytes();
+
+        assert_scope_err!(Scope::from_bytes(&[]), ScopeErrorCase::InvalidEncoding);
+        assert_scope_err!(Scope::from_bytes(&bytes[..bytes.len() - 1]), ScopeErrorCase::InvalidEncoding);
+
+        bytes[0] = BINARY_FORMAT_VERSION + 1;
+        match Scope::from_bytes(&bytes) {
+            Err(err) => assert_eq!(err.to_string().contains("unsupported binary format version 2"), true),
+            Ok(_) => panic!("an unknown format version was accepted")
+        }
+
+        // names are validated as they are for JSON imports
+        let scopes = vec![BinaryScope { parent: 0, name: String::from("APP"), value: 0, permissions: vec![(String::from("RE AD:x"), Some(0))], metadata: vec![] }];
+        let mut bytes = vec![BINARY_FORMAT_VERSION];
+        bytes.extend(postcard::to_allocvec(&scopes).unwrap_or_default());
+        assert_scope_err!(Scope::from_bytes(&bytes), ScopeErrorCase::InvalidName);
+    }
+}
@@ -0,0 +1,178 @@
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use crate::common::error::ErrorKind;
+use crate::scope::guard::MutationContext;
+use crate::scope::path::ScopePath;
+use crate::scope::Scope;
+
+impl Scope {
+    /**
+        Grant several permissions of this scope as a single change: either every grant succeeds, or nothing is
+        changed and the error is returned. Permissions already granted are skipped, and names may be listed in any
+        order, as a permission is retried once the permissions it requires have been granted.
+     */
+    pub fn grant_many(&mut self, names: &[&str]) -> Result<&mut Scope, ErrorKind> {
+        let mut working = self.clone();
+        working.set_each(names.iter().map(|name| name.to_string()).collect(), true, &MutationContext::new())?;
+        *self = working;
+
+        return Ok(self);
+    }
+
+    /**
+        Revoke several permissions of this scope as a single change: either every revocation succeeds, or nothing
+        is changed and the error is returned. Permissions that are not granted are skipped.
+     */
+    pub fn revoke_many(&mut self, names: &[&str]) -> Result<&mut Scope, ErrorKind> {
+        let mut working = self.clone();
+        working.set_each(names.iter().map(|name| name.to_string()).collect(), false, &MutationContext::new())?;
+        *self = working;
+
+        return Ok(self);
+    }
+
+    /**
+        Grant every permission of this scope, including those spilled into its continuations, and with `recursive`
+        every permission of its child scopes as well. It is a single change like `grant_many`, so it fails without
+        changing anything if, for instance, the scope has mutually exclusive permissions.
+     */
+    pub fn grant_all(&mut self, recursive: bool) -> Result<&mut Scope, ErrorKind> {
+        return self.set_all(recursive, true);
+    }
+
+    /** Revoke every permission of this scope, and with `recursive` of its child scopes as well, as a single change. */
+    pub fn revoke_all(&mut self, recursive: bool) -> Result<&mut Scope, ErrorKind> {
+        return self.set_all(recursive, false);
+    }
+
+    fn set_all(&mut self, recursive: bool, grant: bool) -> Result<&mut Scope, ErrorKind> {
+        let mut working = self.clone();
+
+        match recursive {
+            true => {
+                let paths: Vec<ScopePath> = self.walk_scopes().map(|scope| scope.path.relative_to(&self.path)).collect();
+                for path in paths {
+                    let scope = working.resolve_scope_mut(&path)?;
+                    let names: Vec<String> = scope.permissions().map(|permission| permission.name.clone()).collect();
+                    scope.set_each(names, grant, &MutationContext::new())?;
+                }
+            },
+            false => {
+                let mut names: Vec<String> = self.permissions().map(|permission| permission.name.clone()).collect();
+                for continuation in self.continuations() {
+                    names.extend(continuation.permissions().map(|permission| permission.name.clone()));
+                }
+                working.set_each(names, grant, &MutationContext::new())?;
+            }
+        }
+        *self = working;
+
+        return Ok(self);
+    }
+
+    /**
+        Grant or revoke each of `names` in place, skipping permissions already in the requested state. Failed
+        grants are retried while others succeed, so that requirements listed later are granted first.
+     */
+    pub(crate) fn set_each(&mut self, names: Vec<String>, grant: bool, context: &MutationContext) -> Result<(), ErrorKind> {
+        let mut pending: Vec<String> = Vec::new();
+        for name in names {
+            if self.is_granted_directly(&name)? != grant {
+                pending.push(name);
+            }
+        }
+
+        loop {
+            let attempted = pending.len();
+            let mut failed: Vec<String> = Vec::new();
+            let mut error: Option<ErrorKind> = None;
+
+            for name in pending {
+                let result = match grant {
+                    true => self.grant_in(&name, context).map(|_| ()),
+                    false => self.revoke_in(&name, context).map(|_| ())
+                };
+                if let Err(err) = result {
+                    failed.push(name);
+                    error.get_or_insert(err);
+                }
+            }
+
+            match error {
+                None => return Ok(()),
+                Some(err) if failed.len() == attempted => return Err(err),
+                Some(_) => pending = failed
+            }
+        }
+    }
+
+    /** Check whether a permission of this scope or one of its continuations is granted directly. */
+    fn is_granted_directly(&self, name: &str) -> Result<bool, ErrorKind> {
+        let resolved = self.resolve_name(name);
+        if let Some(continuation) = self.continuation_holding(&resolved) {
+            return Ok(continuation.is_granted(&resolved));
+        }
+
+        return Ok(self.is_granted(&self.find_permission_name(name)?));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{assert_granted, assert_not_granted, assert_ok, assert_scope_err};
+    use crate::scope::error::ScopeErrorCase;
+
+    fn get_scope() -> Scope {
+        let mut scope = Scope::new("APP");
+        assert_ok!(scope.add_permission("READ").and_then(|sc| sc.add_permission("WRITE")).and_then(|sc| sc.add_permission("DELETE")));
+        assert_ok!(scope.add_requirement("WRITE", "READ").and_then(|sc| sc.add_scope("billing")));
+        if let Some(billing) = scope.scope("billing") {
+            assert_ok!(billing.add_permission("PAY").and_then(|sc| sc.add_permission("REFUND")));
+        }
+
+        return scope;
+    }
+
+    #[test]
+    fn test_grant_and_revoke_many() {
+        let mut scope = get_scope();
+
+        // READ is required by WRITE but listed after it
+        assert_ok!(scope.grant_many(&["WRITE", "READ"]));
+        assert_granted!(scope, "WRITE");
+        assert_ok!(scope.grant_many(&["READ", "DELETE"]));
+        assert_eq!(scope.as_u64(), 0b111);
+
+        assert_ok!(scope.revoke_many(&["WRITE", "DELETE"]));
+        assert_eq!(scope.as_u64(), 0b1);
+    }
+
+    #[test]
+    fn test_bulk_changes_are_atomic() {
+        let mut scope = get_scope();
+
+        assert_scope_err!(scope.grant_many(&["READ", "UNKNOWN"]), ScopeErrorCase::UnknownPermission);
+        assert_not_granted!(scope, "READ");
+
+        assert_ok!(scope.add_exclusion("WRITE", "DELETE"));
+        assert_scope_err!(scope.grant_all(false), ScopeErrorCase::MutuallyExclusive);
+        assert_eq!(scope.as_u64(), 0);
+    }
+
+    #[test]
+    fn test_grant_and_revoke_all() {
+        let mut scope = get_scope();
+
+        assert_ok!(scope.grant_all(false));
+        assert_eq!(scope.as_u64(), 0b111);
+        assert_not_granted!(scope, "billing:PAY");
+
+        assert_ok!(scope.grant_all(true));
+        assert_granted!(scope, "billing:REFUND");
+
+        assert_ok!(scope.revoke_all(true));
+        assert_not_granted!(scope, "billing:PAY");
+        assert_eq!(scope.as_u64(), 0);
+    }
+}
@@ -0,0 +1,244 @@
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+use crate::common::error::ErrorKind;
+use crate::common::telemetry;
+use crate::common::trace::trace_event;
+use crate::scope::error::{ScopeError, ScopeErrorCase};
+use crate::scope::path::{PermissionPath, ScopePath, WILDCARD};
+use crate::scope::Scope;
+
+impl Scope {
+    /**
+        Check whether the permission at `path` (relative to this scope, e.g. `READ` or `billing.invoices:READ`)
+        is granted or implied by another granted permission in the same scope. Unknown paths are never granted.
+     */
+    pub fn check(&self, path: &str) -> bool {
+        let requested = PermissionPath::parse(path);
+
+        let allowed = match self.find_scope(&requested.scope) {
+            Some(scope) => {
+                let name = scope.resolve_name(&requested.permission);
+                match scope.continuation_holding(&name) {
+                    Some(continuation) => continuation.is_effective(&name),
+                    None => scope.is_effective(&name)
+                }
+            },
+            None => false
+        };
+        trace_event!(trace, scope = %self.path, path, allowed, "permission checked");
+        telemetry::record_check(allowed);
+
+        return allowed;
+    }
+
+    /**
+        Check a path that may contain wildcards: `*` as a scope name matches any direct child scope at that level,
+        and `*` as the permission name matches any permission of the matched scopes. True if any permission matched
+        is granted or implied, so `admin.*:DELETE` reads "can delete in some scope under admin". Paths without
+        wildcards behave exactly like `check`.
+     */
+    pub fn has(&self, path: &str) -> bool {
+        let allowed = self.matching_permissions(path).iter().any(|(scope, name)| scope.is_effective(name));
+        trace_event!(trace, scope = %self.path, path, allowed, "permission pattern checked");
+        telemetry::record_check(allowed);
+
+        return allowed;
+    }
+
+    /**
+        Check a path that may contain wildcards, as for `has`, but require every matched permission to be granted
+        or implied, so `admin.*:DELETE` reads "can delete in every scope under admin". A pattern matching nothing
+        is never granted.
+     */
+    pub fn has_all(&self, path: &str) -> bool {
+        let matched = self.matching_permissions(path);
+        let allowed = !matched.is_empty() && matched.iter().all(|(scope, name)| scope.is_effective(name));
+        trace_event!(trace, scope = %self.path, path, allowed, "permission pattern checked");
+        telemetry::record_check(allowed);
+
+        return allowed;
+    }
+
+    /** List the scope and permission name of every defined permission matched by a wildcard path. */
+    fn matching_permissions(&self, path: &str) -> Vec<(&Scope, String)> {
+        let requested = PermissionPath::parse(path);
+        let mut scopes: Vec<&Scope> = vec![self];
+
+        for segment in requested.scope.segments() {
+            scopes = match segment.as_str() {
+                // continuations belong to their scope, so a wildcard does not match them as child scopes
+                WILDCARD => scopes.into_iter()
+                    .flat_map(|scope| scope.scopes.values().filter(|child| !scope.is_continuation(&child.name)))
+                    .collect(),
+                name => scopes.into_iter()
+                    .filter_map(|scope| scope.scopes.get(scope.policy.normalize(name).as_ref()))
+                    .collect()
+            };
+        }
+
+        let mut matched: Vec<(&Scope, String)> = vec![];
+        for scope in scopes {
+            match requested.permission.as_str() {
+                WILDCARD => {
+                    matched.extend(scope.permissions.keys().map(|name| (scope, name.clone())));
+                    for continuation in scope.continuations() {
+                        matched.extend(continuation.permissions.keys().map(|name| (continuation, name.clone())));
+                    }
+                },
+                requested => {
+                    let name = scope.resolve_name(requested);
+                    if scope.permissions.contains_key(&name) || scope.virtuals.contains_key(&name) || scope.composites.contains_key(&name) {
+                        matched.push((scope, name));
+                    } else if let Some(continuation) = scope.continuation_holding(&name) {
+                        matched.push((continuation, name));
+                    }
+                }
+            }
+        }
+
+        return matched;
+    }
+
+    /** Find a child scope by its path relative to this scope. An empty path is this scope. */
+    pub(crate) fn find_scope(&self, path: &ScopePath) -> Option<&Scope> {
+        let mut scope = self;
+
+        for segment in path.segments() {
+            let name = scope.policy.normalize(segment);
+            scope = scope.scopes.get(name.as_ref())?;
+        }
+
+        return Some(scope);
+    }
+
+    /** Find a child scope by its path relative to this scope for modification, failing if it does not exist. */
+    pub(crate) fn resolve_scope_mut(&mut self, path: &ScopePath) -> Result<&mut Scope, ErrorKind> {
+        let mut scope = self;
+
+        for segment in path.segments() {
+            let name = scope.policy.normalize(segment).into_owned();
+            scope = match scope.scopes.get_mut(&name) {
+                Some(child) => child,
+                None => return Err(ErrorKind::ScopeError(ScopeError::new(ScopeErrorCase::UnknownScope, &path.to_string())))
+            };
+        }
+
+        return Ok(scope);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{assert_granted, assert_not_granted, assert_ok, assert_scope_err};
+
+    #[test]
+    fn test_check_local_permission() {
+        let mut scope = Scope::new("TEST_SCOPE");
+
+        assert_ok!(scope
+            .add_permission("READ")
+            .and_then(|sc| sc.add_permission("WRITE"))
+            .and_then(|sc| sc.grant("READ")));
+
+        assert_granted!(scope, "READ");
+        assert_not_granted!(scope, "WRITE");
+        assert_not_granted!(scope, "EXECUTE");
+    }
+
+    #[test]
+    fn test_check_child_scope_path() {
+        let mut scope = Scope::new("TEST_SCOPE");
+        let _ = scope.add_scope("billing");
+
+        if let Some(billing) = scope.scope("billing") {
+            let _ = billing.add_scope("invoices");
+
+            if let Some(invoices) = billing.scope("invoices") {
+                assert_ok!(invoices
+                    .add_permission("READ")
+                    .and_then(|sc| sc.add_permission("REFUND"))
+                    .and_then(|sc| sc.grant("READ")));
+            }
+        }
+
+        assert_granted!(scope, "billing.invoices:READ");
+        assert_granted!(scope, "billing.invoices.READ");
+        assert_not_granted!(scope, "billing.invoices:REFUND");
+        assert_not_granted!(scope, "billing.missing:READ");
+        assert_not_granted!(scope, "billing:READ");
+    }
+
+    fn get_tree() -> Scope {
+        let mut scope = Scope::new("APP");
+        assert_ok!(scope.add_scope("admin").and_then(|sc| sc.add_scope("reports")));
+
+        if let Some(admin) = scope.scope("admin") {
+            assert_ok!(admin.add_scope("users").and_then(|sc| sc.add_scope("billing")));
+            for child in ["users", "billing"] {
+                if let Some(child) = admin.scope(child) {
+                    assert_ok!(child.add_permission("VIEW").and_then(|sc| sc.add_permission("DELETE")));
+                }
+            }
+        }
+
+        if let Some(reports) = scope.scope("reports") {
+            assert_ok!(reports.add_scope("q3"));
+            if let Some(q3) = reports.scope("q3") {
+                assert_ok!(q3.add_permission("READ").and_then(|sc| sc.add_permission("EXPORT")));
+            }
+        }
+
+        return scope;
+    }
+
+    #[test]
+    fn test_has_wildcard_scope() {
+        let mut scope = get_tree();
+        assert_eq!(scope.has("admin.*:DELETE"), false);
+        assert_eq!(scope.has_all("admin.*:DELETE"), false);
+
+        if let Some(users) = scope.scope("admin").and_then(|admin| admin.scope("users")) {
+            assert_ok!(users.grant("DELETE"));
+        }
+        assert_eq!(scope.has("admin.*:DELETE"), true);
+        assert_eq!(scope.has_all("admin.*:DELETE"), false);
+        assert_eq!(scope.has("*.*:DELETE"), true);
+
+        if let Some(billing) = scope.scope("admin").and_then(|admin| admin.scope("billing")) {
+            assert_ok!(billing.grant("DELETE"));
+        }
+        assert_eq!(scope.has_all("admin.*:DELETE"), true);
+        assert_eq!(scope.has("admin.*:VIEW"), false);
+    }
+
+    #[test]
+    fn test_has_wildcard_permission() {
+        let mut scope = get_tree();
+        assert_eq!(scope.has("reports.q3:*"), false);
+
+        if let Some(q3) = scope.scope("reports").and_then(|reports| reports.scope("q3")) {
+            assert_ok!(q3.grant("EXPORT"));
+        }
+        assert_eq!(scope.has("reports.q3:*"), true);
+        assert_eq!(scope.has_all("reports.q3:*"), false);
+        assert_eq!(scope.has("reports.q3:EXPORT"), scope.check("reports.q3:EXPORT"));
+
+        // patterns that match nothing are never granted
+        assert_eq!(scope.has("missing.*:*"), false);
+        assert_eq!(scope.has_all("missing.*:*"), false);
+    }
+
+    #[test]
+    fn test_wildcard_is_not_a_name() {
+        let mut scope = get_tree();
+        assert_scope_err!(scope.add_scope(WILDCARD), ScopeErrorCase::InvalidName);
+        assert_scope_err!(scope.add_permission(WILDCARD), ScopeErrorCase::InvalidName);
+
+        let imported = serde_json::json!(["APP", 0, [], [["*", 1, ["READ"], []]]]);
+        assert_scope_err!(Scope::try_from_json(imported), ScopeErrorCase::InvalidName);
+        let imported = serde_json::json!(["APP", 1, ["*"], []]);
+        assert_scope_err!(Scope::try_from_json(imported), ScopeErrorCase::InvalidName);
+    }
+}
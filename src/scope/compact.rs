@@ -0,0 +1,232 @@
+use alloc::collections::BTreeMap;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use serde::{Deserialize, Serialize};
+use crate::common::error::ErrorKind;
+use crate::scope::order::SortOrder;
+use crate::scope::path::ScopePath;
+use crate::scope::Scope;
+
+/** ShiftMove records the bit of a permission before and after a scope was compacted. */
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ShiftMove {
+    pub permission: String,
+    pub from: u8,
+    pub to: u8
+}
+
+/**
+    CompactionRecord lists the bits of every permission of the scopes renumbered by `compact`, keyed by the scope
+    path relative to the compacted scope (empty for the scope itself). Keep it to migrate values stored before the
+    compaction; scopes that had no gaps are left out, as their values do not change.
+ */
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CompactionRecord {
+    pub scopes: BTreeMap<String, Vec<ShiftMove>>
+}
+
+impl CompactionRecord {
+    pub fn is_empty(&self) -> bool {
+        return self.scopes.is_empty();
+    }
+
+    /** Get the moves of the scope at `path`, relative to the compacted scope. */
+    pub fn moves(&self, path: &str) -> &[ShiftMove] {
+        return match self.scopes.get(path) {
+            Some(moves) => moves,
+            None => &[]
+        }
+    }
+
+    /**
+        Rewrite a value stored for the scope at `path` before the compaction to the bits used after it. Bits of
+        permissions removed before the compaction are dropped. Values of scopes without moves are returned as is.
+     */
+    pub fn migrate(&self, path: &str, value: u64) -> u64 {
+        let moves = match self.scopes.get(path) {
+            Some(moves) => moves,
+            None => return value
+        };
+
+        return moves.iter()
+            .filter(|shift| value & (1u64 << shift.from) != 0)
+            .fold(0u64, |migrated, shift| migrated | (1u64 << shift.to));
+    }
+}
+
+impl Scope {
+    /**
+        Remove a permission from this scope together with every rule, alias, condition and reference declared on
+        this scope that mentions it. Its bit is not handed out again until the scope is compacted, so values stored
+        before the removal keep their meaning; such values no longer apply, as the bit is now undefined. References
+        declared on parent scopes are not updated.
+     */
+    pub fn remove_permission(&mut self, name: &str) -> Result<&mut Scope, ErrorKind> {
+        let resolved = self.resolve_name(name);
+        if let Some(continuation) = self.continuation_holding_mut(&resolved) {
+            continuation.remove_permission(&resolved)?;
+            self.touch();
+            return Ok(self);
+        }
+
+        let name = self.find_permission_name(name)?;
+        self.permissions.remove(&name);
+        self.permission_order.retain(|existing| *existing != name);
+
+        for rules in [&mut self.requirements, &mut self.implications, &mut self.exclusions] {
+            rules.remove(&name);
+            for values in rules.values_mut() {
+                values.retain(|value| *value != name);
+            }
+            rules.retain(|_, values| !values.is_empty());
+        }
+        for (_, permissions) in self.required_sets.iter_mut() {
+            permissions.retain(|permission| *permission != name);
+        }
+        self.required_sets.retain(|(_, permissions)| !permissions.is_empty());
+        for members in self.composites.values_mut() {
+            members.retain(|member| *member != name);
+        }
+        self.composites.retain(|_, members| !members.is_empty());
+        self.deprecations.remove(&name);
+        self.conditions.remove(&name);
+        self.aliases.retain(|_, target| *target != name);
+        self.references.retain(|(first, second)| *first != name && *second != name);
+
+        self.touch();
+        return Ok(self);
+    }
+
+    /**
+        Remove every child scope, at any depth, that has no permissions and no child scopes left, returning the
+        paths of the removed scopes. Scopes that only become empty once their own empty children are removed are
+        removed as well.
+     */
+    pub fn prune_empty(&mut self) -> Vec<ScopePath> {
+        let mut pruned: Vec<ScopePath> = Vec::new();
+
+        loop {
+            let empty: Vec<ScopePath> = self.scopes_in_encoding_order().into_iter()
+                .filter(|scope| scope.path != self.path)
+                .filter(|scope| scope.permissions.is_empty() && scope.virtuals.is_empty() && scope.scopes.is_empty())
+                .map(|scope| scope.path.relative_to(&self.path))
+                .collect();
+            if empty.is_empty() {
+                return pruned;
+            }
+
+            for path in empty {
+                if let Some((parent, name)) = path.split_last() {
+                    if let Ok(parent) = self.resolve_scope_mut(&parent) {
+                        parent.remove_scope(name);
+                        parent.touch();
+                    }
+                }
+                pruned.push(path);
+            }
+        }
+    }
+
+    /**
+        Renumber the permissions of this scope and its child scopes to close the gaps left by removed permissions,
+        keeping their relative order. Grants are kept, but values stored before the compaction must be migrated
+        with the returned record, which lists every scope whose bits changed.
+     */
+    pub fn compact(&mut self) -> CompactionRecord {
+        let mut record = CompactionRecord::default();
+        let paths: Vec<ScopePath> = self.scopes_in_encoding_order().into_iter()
+            .filter(|scope| scope.assigned_width() != scope.permissions.len())
+            .map(|scope| scope.path.relative_to(&self.path))
+            .collect();
+
+        for path in paths {
+            if let Ok(scope) = self.resolve_scope_mut(&path) {
+                record.scopes.insert(path.to_string(), scope.compact_flat());
+            }
+        }
+
+        return record;
+    }
+
+    /** Renumber the permissions of this scope only, returning the bit of every permission before and after. */
+    fn compact_flat(&mut self) -> Vec<ShiftMove> {
+        let ordered: Vec<String> = self.permissions_in_order(SortOrder::Shift).into_iter()
+            .map(|permission| permission.name.clone())
+            .collect();
+
+        let mut moves: Vec<ShiftMove> = Vec::new();
+        for (shift, name) in ordered.iter().enumerate() {
+            if let Some(permission) = self.permissions.get_mut(name) {
+                moves.push(ShiftMove {
+                    permission: name.to_string(),
+                    from: permission.value.trailing_zeros() as u8,
+                    to: shift as u8
+                });
+                permission.value = 1u64 << shift;
+            }
+        }
+        self.next_permission_shift = ordered.len() as u8;
+        self.touch();
+
+        return moves;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{assert_granted, assert_not_granted, assert_ok, assert_scope_err};
+    use crate::scope::error::ScopeErrorCase;
+
+    fn get_scope() -> Scope {
+        let mut scope = Scope::new("APP");
+        assert_ok!(scope.add_permission("READ").and_then(|sc| sc.add_permission("WRITE")).and_then(|sc| sc.add_permission("DELETE")));
+        assert_ok!(scope.add_implication("DELETE", "WRITE").and_then(|sc| sc.grant("READ")).and_then(|sc| sc.grant("DELETE")));
+        assert_ok!(scope.add_scope("billing").and_then(|sc| sc.add_scope("archive")));
+        if let Some(billing) = scope.scope("billing") {
+            assert_ok!(billing.add_scope("invoices"));
+        }
+
+        return scope;
+    }
+
+    #[test]
+    fn test_prune_empty_scopes() {
+        let mut scope = get_scope();
+        if let Some(archive) = scope.scope("archive") {
+            assert_ok!(archive.add_permission("VIEW"));
+        }
+
+        let pruned: Vec<String> = scope.prune_empty().iter().map(|path| path.to_string()).collect();
+        assert_eq!(pruned, ["billing.invoices", "billing"]);
+        assert_eq!(scope.scope_ref("billing").is_none(), true);
+        assert_eq!(scope.scope_ref("archive").is_some(), true);
+        assert_eq!(scope.prune_empty().is_empty(), true);
+    }
+
+    #[test]
+    fn test_remove_and_compact() {
+        let mut scope = get_scope();
+        let stored = scope.as_u64();
+
+        assert_ok!(scope.remove_permission("WRITE"));
+        assert_scope_err!(scope.grant("WRITE"), ScopeErrorCase::UnknownPermission);
+        assert_eq!(scope.implications("DELETE").is_empty(), true);
+        assert_eq!(scope.remaining_capacity(), 50);
+        assert_eq!(scope.as_u64(), 0b101);
+
+        let record = scope.compact();
+        assert_eq!(record.moves(""), [
+            ShiftMove { permission: String::from("READ"), from: 0, to: 0 },
+            ShiftMove { permission: String::from("DELETE"), from: 2, to: 1 }
+        ]);
+        assert_eq!(scope.remaining_capacity(), 51);
+        assert_eq!(scope.as_u64(), 0b11);
+        assert_eq!(record.migrate("", stored), 0b11);
+        assert_eq!(record.migrate("billing", 0b1), 0b1);
+        assert_granted!(scope, "DELETE");
+        assert_not_granted!(scope, "WRITE");
+
+        assert_eq!(scope.compact().is_empty(), true);
+    }
+}
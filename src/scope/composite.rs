@@ -0,0 +1,142 @@
+use alloc::string::String;
+use alloc::vec::Vec;
+use crate::common::error::ErrorKind;
+use crate::scope::guard::MutationContext;
+use crate::scope::error::{ScopeError, ScopeErrorCase};
+use crate::scope::Scope;
+
+impl Scope {
+    /**
+        Declare a composite permission such as `ALL = READ | WRITE | DELETE`, standing for several permissions of
+        this scope at once. A composite has no bit of its own: its value is the union of the bits it combines, it
+        is held when every one of them is granted or implied, and granting or revoking it grants or revokes them as
+        a group. Fails with UnknownPermission if a member is not a permission of this scope, and with
+        EmptyComposite if there are no members.
+     */
+    pub fn add_composite(&mut self, name: &str, members: &[&str]) -> Result<&mut Scope, ErrorKind> {
+        let name = self.policy.normalize(name).into_owned();
+        self.validate_name(&name)?;
+        if members.is_empty() {
+            return Err(ErrorKind::ScopeError(ScopeError::new(ScopeErrorCase::EmptyComposite, &name)));
+        }
+
+        let mut resolved: Vec<String> = Vec::new();
+        for member in members {
+            let member = self.find_permission_name(member)?;
+            if !resolved.contains(&member) {
+                resolved.push(member);
+            }
+        }
+
+        self.composites.insert(name, resolved);
+        self.touch();
+
+        return Ok(self);
+    }
+
+    /** Remove a composite permission, returning whether it existed. The permissions it combines are kept. */
+    pub fn remove_composite(&mut self, name: &str) -> bool {
+        let removed = self.composites.remove(self.policy.normalize(name).as_ref()).is_some();
+        if removed {
+            self.touch();
+        }
+
+        return removed;
+    }
+
+    /** Get the permissions combined by a composite permission, or an empty slice if `name` is not composite. */
+    pub fn composite_members(&self, name: &str) -> &[String] {
+        return match self.composites.get(self.policy.normalize(name).as_ref()) {
+            Some(members) => members.as_slice(),
+            None => &[]
+        }
+    }
+
+    /** Get the union of the bits combined by a composite permission, or None if `name` is not composite. */
+    pub fn composite_value(&self, name: &str) -> Option<u64> {
+        let members = self.composites.get(self.policy.normalize(name).as_ref())?;
+
+        return Some(members.iter()
+            .filter_map(|member| self.permissions.get(member))
+            .fold(0u64, |value, permission| value | permission.value));
+    }
+
+    /** Evaluate a composite permission of this scope, or None if `name` is not composite. */
+    pub(crate) fn evaluate_composite(&self, name: &str) -> Option<bool> {
+        return self.composites.get(name).map(|members| members.iter().all(|member| self.is_effective(member)));
+    }
+
+    /**
+        Grant or revoke every member of a composite permission as a single change, skipping members already in the
+        requested state. Returns None if `name` is not composite.
+     */
+    pub(crate) fn set_composite(&mut self, name: &str, grant: bool, context: &MutationContext) -> Option<Result<(), ErrorKind>> {
+        let members = self.composites.get(name)?.clone();
+
+        let mut working = self.clone();
+        return Some(working.set_each(members, grant, context).map(|_| *self = working));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{assert_granted, assert_not_granted, assert_ok, assert_scope_err};
+    use crate::scope::explain::Decision;
+
+    fn get_scope() -> Scope {
+        let mut scope = Scope::new("APP");
+        assert_ok!(scope.add_permission("READ").and_then(|sc| sc.add_permission("WRITE")).and_then(|sc| sc.add_permission("DELETE")));
+        assert_ok!(scope.add_requirement("WRITE", "READ").and_then(|sc| sc.add_composite("ALL", &["WRITE", "DELETE", "READ"])));
+
+        return scope;
+    }
+
+    #[test]
+    fn test_composite_is_held_with_every_member() {
+        let mut scope = get_scope();
+        assert_eq!(scope.composite_value("ALL"), Some(0b111));
+        assert_eq!(scope.composite_members("ALL"), ["WRITE", "DELETE", "READ"]);
+        assert_not_granted!(scope, "ALL");
+
+        assert_ok!(scope.grant("READ").and_then(|sc| sc.grant("WRITE")));
+        assert_not_granted!(scope, "ALL");
+        assert_eq!(scope.explain("ALL").decision, Decision::Composite);
+
+        assert_ok!(scope.grant("DELETE"));
+        assert_granted!(scope, "ALL");
+    }
+
+    #[test]
+    fn test_composite_is_granted_as_a_group() {
+        let mut scope = get_scope();
+
+        // WRITE is listed before the READ it requires
+        assert_ok!(scope.grant("ALL"));
+        assert_eq!(scope.as_u64(), 0b111);
+
+        assert_ok!(scope.revoke("DELETE").and_then(|sc| sc.revoke("ALL")));
+        assert_eq!(scope.as_u64(), 0);
+
+        assert_ok!(scope.add_exclusion("DELETE", "WRITE"));
+        assert_scope_err!(scope.grant("ALL"), ScopeErrorCase::MutuallyExclusive);
+        assert_eq!(scope.as_u64(), 0);
+    }
+
+    #[test]
+    fn test_composite_is_declared_and_exported() {
+        let mut scope = get_scope();
+        assert_scope_err!(scope.add_composite("READ", &["WRITE"]), ScopeErrorCase::PermissionExists);
+        assert_scope_err!(scope.add_composite("EDIT", &["WRITE", "PUBLISH"]), ScopeErrorCase::UnknownPermission);
+        assert_scope_err!(scope.add_composite("NONE", &[]), ScopeErrorCase::EmptyComposite);
+        assert_scope_err!(scope.add_permission("ALL"), ScopeErrorCase::PermissionExists);
+
+        let imported = assert_ok!(Scope::try_from_json(scope.as_json()));
+        assert_eq!(imported.composite_value("ALL"), Some(0b111));
+
+        assert_ok!(scope.remove_permission("DELETE").and_then(|sc| sc.rename_permission("WRITE", "EDIT", false)));
+        assert_eq!(scope.composite_members("ALL"), ["EDIT", "READ"]);
+        assert_eq!(scope.remove_composite("ALL"), true);
+        assert_scope_err!(scope.grant("ALL"), ScopeErrorCase::UnknownPermission);
+    }
+}
@@ -0,0 +1,659 @@
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+use crate::audit::AuditOp;
+use crate::common::error::ErrorKind;
+use crate::common::telemetry;
+use crate::common::trace::{trace_event, trace_span};
+use crate::permission::error::{PermissionError, PermissionErrorCase, PermissionErrorMetadata};
+use crate::scope::error::{ScopeError, ScopeErrorCase, ScopeErrorMetadata};
+use crate::scope::guard::MutationContext;
+use crate::scope::path::ScopePath;
+use crate::scope::Scope;
+
+/** RequirementMode decides what happens when a permission is granted before the permissions it requires. */
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum RequirementMode {
+    /** Refuse to grant the permission until every permission it requires has been granted. */
+    #[default]
+    Reject,
+    /** Grant every permission that is required (directly or indirectly) along with the permission itself. */
+    AutoGrant
+}
+
+/** RequiredSetViolation reports a required set for which none of the permissions are held. */
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RequiredSetViolation {
+    pub path: ScopePath,
+    pub set: String,
+    pub permissions: Vec<String>
+}
+
+impl Scope {
+    /** Get the mode used to enforce requirements when granting permissions through this scope. */
+    pub fn requirement_mode(&self) -> RequirementMode {
+        return self.requirement_mode;
+    }
+
+    /** Set the mode used to enforce requirements for this scope and all of its child scopes. */
+    pub fn set_requirement_mode(&mut self, mode: RequirementMode) -> &mut Scope {
+        for scope in self.scopes.values_mut() {
+            scope.set_requirement_mode(mode);
+        }
+        self.requirement_mode = mode;
+
+        return self;
+    }
+
+    /** Declare that `permission` may only be granted once `required` has been granted. */
+    pub fn add_requirement(&mut self, permission: &str, required: &str) -> Result<&mut Scope, ErrorKind> {
+        let permission = self.find_permission_name(permission)?;
+        let required = self.find_permission_name(required)?;
+
+        let requirements = self.requirements.entry(permission).or_default();
+        if !requirements.contains(&required) {
+            requirements.push(required);
+        }
+
+        self.touch();
+        return Ok(self);
+    }
+
+    /** Get the permissions that must be granted before the given permission. */
+    pub fn requirements(&self, permission: &str) -> &[String] {
+        let permission = self.policy.normalize(permission);
+
+        return match self.requirements.get(permission.as_ref()) {
+            Some(requirements) => requirements.as_slice(),
+            None => &[]
+        }
+    }
+
+    /** Declare that `first` and `second` may never be held at the same time, e.g. for separation of duties. */
+    pub fn add_exclusion(&mut self, first: &str, second: &str) -> Result<&mut Scope, ErrorKind> {
+        let first = self.find_permission_name(first)?;
+        let second = self.find_permission_name(second)?;
+
+        for (permission, excluded) in [(&first, &second), (&second, &first)] {
+            let exclusions = self.exclusions.entry(permission.clone()).or_default();
+            if !exclusions.contains(excluded) {
+                exclusions.push(excluded.clone());
+            }
+        }
+
+        self.touch();
+        return Ok(self);
+    }
+
+    /** Get the permissions that may not be held at the same time as the given permission. */
+    pub fn exclusions(&self, permission: &str) -> &[String] {
+        let permission = self.policy.normalize(permission);
+
+        return match self.exclusions.get(permission.as_ref()) {
+            Some(exclusions) => exclusions.as_slice(),
+            None => &[]
+        }
+    }
+
+    /**
+        Declare a named set of permissions of which at least one must always be held (e.g. every principal must
+        have at least `VIEW`). Declaring a set with an existing name replaces it. Use `validate_grants` to report
+        violations.
+     */
+    pub fn add_required_set(&mut self, set: &str, permissions: &[&str]) -> Result<&mut Scope, ErrorKind> {
+        let mut names: Vec<String> = vec![];
+        for permission in permissions {
+            names.push(self.find_permission_name(permission)?);
+        }
+
+        match self.required_sets.iter_mut().find(|(name, _)| name == set) {
+            Some((_, existing)) => *existing = names,
+            None => self.required_sets.push((set.to_string(), names))
+        }
+
+        self.touch();
+        return Ok(self);
+    }
+
+    /** Get the required sets declared on this scope as (name, permissions) pairs. */
+    pub fn required_sets(&self) -> &[(String, Vec<String>)] {
+        return &self.required_sets;
+    }
+
+    /** Report every required set in this scope and its child scopes for which no permission is held, parents first. */
+    pub fn validate_grants(&self) -> Vec<RequiredSetViolation> {
+        let mut violations: Vec<RequiredSetViolation> = vec![];
+
+        for scope in self.walk_scopes() {
+            for (set, permissions) in &scope.required_sets {
+                if !permissions.iter().any(|permission| scope.is_effective(permission)) {
+                    violations.push(RequiredSetViolation {
+                        path: scope.path.clone(),
+                        set: set.clone(),
+                        permissions: permissions.clone()
+                    });
+                }
+            }
+        }
+
+        return violations;
+    }
+
+    /**
+        Declare that holding `permission` also implies holding `implied` at check time. Implied permissions
+        are not granted, so they never contribute to the scope's numeric value.
+     */
+    pub fn add_implication(&mut self, permission: &str, implied: &str) -> Result<&mut Scope, ErrorKind> {
+        let permission = self.find_permission_name(permission)?;
+        let implied = self.find_permission_name(implied)?;
+
+        let implications = self.implications.entry(permission).or_default();
+        if !implications.contains(&implied) {
+            implications.push(implied);
+        }
+
+        self.touch();
+        return Ok(self);
+    }
+
+    /** Get the permissions directly implied by the given permission. */
+    pub fn implications(&self, permission: &str) -> &[String] {
+        let permission = self.policy.normalize(permission);
+
+        return match self.implications.get(permission.as_ref()) {
+            Some(implications) => implications.as_slice(),
+            None => &[]
+        }
+    }
+
+    /** Check whether a permission defined on this scope is granted or implied by another granted permission. */
+    pub(crate) fn is_effective(&self, name: &str) -> bool {
+        // conditions need a context, so without one a conditioned permission is never held
+        if self.is_denied_name(name) || self.is_conditioned(name) {
+            return false;
+        }
+        if let Some(held) = self.evaluate_virtual(name) {
+            return held;
+        }
+        if let Some(held) = self.evaluate_composite(name) {
+            return held;
+        }
+        if self.is_granted(name) {
+            return true;
+        }
+
+        return self.permissions.values()
+            .filter(|permission| permission.has() && !self.is_conditioned(&permission.name))
+            .any(|permission| self.implies_through(&permission.name, name, |implied| !self.is_conditioned(implied)));
+    }
+
+    /** Check whether `from` implies `target`, directly or through a chain of implications. */
+    pub(crate) fn implies(&self, from: &str, target: &str) -> bool {
+        return self.implies_through(from, target, |_| true);
+    }
+
+    /**
+        Check whether `from` implies `target` through a chain of implications whose intermediate permissions all
+        pass `passes`, e.g. whose conditions hold.
+     */
+    pub(crate) fn implies_through<F>(&self, from: &str, target: &str, passes: F) -> bool where F: Fn(&str) -> bool {
+        let mut visited: Vec<&str> = vec![from];
+        let mut stack: Vec<&str> = self.implications(from).iter().map(|implied| implied.as_str()).collect();
+
+        while let Some(implied) = stack.pop() {
+            if implied == target {
+                return true;
+            }
+            if visited.contains(&implied) || !passes(implied) {
+                continue;
+            }
+            visited.push(implied);
+            stack.extend(self.implications(implied).iter().map(|next| next.as_str()));
+        }
+
+        return false;
+    }
+
+    /**
+        Grant a permission within this scope, enforcing its requirements according to the scope's
+        requirement mode. Granting through `permission(name)` directly bypasses these checks.
+     */
+    pub fn grant(&mut self, name: &str) -> Result<&mut Scope, ErrorKind> {
+        return self.grant_in(name, &MutationContext::new());
+    }
+
+    /** Grant a permission like `grant`, passing `context` to the mutation guards of this scope. */
+    pub fn grant_in(&mut self, name: &str, context: &MutationContext) -> Result<&mut Scope, ErrorKind> {
+        let _span = trace_span!("bitperm.grant", path = %self.path, permission = name);
+
+        let resolved = self.resolve_name(name);
+        let result = match self.set_composite(&resolved, true, context) {
+            Some(result) => result,
+            None => match self.continuation_holding_mut(&resolved) {
+                // permissions spilled into a continuation are changed there, and count as a change to this scope
+                Some(continuation) => continuation.grant_in(name, context).map(|_| ()),
+                None => self.try_grant(name, context)
+            }
+        };
+        telemetry::record_grant(result.is_ok());
+
+        return match result {
+            Ok(_) => {
+                self.touch();
+                trace_event!(debug, "permission granted");
+                Ok(self)
+            },
+            Err(err) => {
+                trace_event!(debug, error = %crate::common::trace::error_message(&err), "grant failed");
+                Err(err)
+            }
+        }
+    }
+
+    pub(crate) fn try_grant(&mut self, name: &str, context: &MutationContext) -> Result<(), ErrorKind> {
+        let requested = name;
+        self.reject_virtual(requested)?;
+        let name = self.find_permission_name(requested)?;
+
+        let mut pending: Vec<String> = match self.requirement_mode {
+            RequirementMode::Reject => {
+                if let Some(required) = self.requirements(&name).iter().find(|required| !self.is_granted(required)) {
+                    return Err(ErrorKind::ScopeError(ScopeError::with_metadata(
+                        ScopeErrorCase::RequirementNotMet,
+                        &name,
+                        ScopeErrorMetadata {
+                            related: Some(required.clone()),
+                            ..ScopeErrorMetadata::new()
+                        }
+                    )));
+                }
+
+                vec![]
+            },
+            // only pull in requirements when the permission itself can be granted
+            RequirementMode::AutoGrant if !self.is_granted(&name) => self.missing_requirements(&name),
+            RequirementMode::AutoGrant => vec![]
+        };
+        pending.push(name);
+
+        if let Some(denied) = pending.iter().find(|candidate| self.is_denied_name(candidate)) {
+            return Err(ErrorKind::PermissionError(PermissionError::new(PermissionErrorCase::DeniedError, denied, PermissionErrorMetadata::new())));
+        }
+
+        // refuse any grant that would leave two mutually exclusive permissions held at once
+        for candidate in &pending {
+            let conflict = self.exclusions(candidate).iter()
+                .find(|excluded| self.is_effective(excluded) || pending.contains(excluded));
+
+            if let Some(excluded) = conflict {
+                return Err(ErrorKind::ScopeError(ScopeError::with_metadata(
+                    ScopeErrorCase::MutuallyExclusive,
+                    candidate,
+                    ScopeErrorMetadata {
+                        related: Some(excluded.clone()),
+                        ..ScopeErrorMetadata::new()
+                    }
+                )));
+            }
+        }
+
+        let warnings = self.check_deprecations(&pending)?;
+        for candidate in &pending {
+            self.check_guards(AuditOp::Grant, candidate, context)?;
+        }
+
+        for candidate in &pending {
+            if let Some(permission) = self.permissions.get_mut(candidate) {
+                permission.grant()?;
+            }
+        }
+        self.note_alias_use(requested);
+        self.warnings.extend(warnings);
+
+        return Ok(());
+    }
+
+    /** Revoke a permission within this scope. */
+    pub fn revoke(&mut self, name: &str) -> Result<&mut Scope, ErrorKind> {
+        return self.revoke_in(name, &MutationContext::new());
+    }
+
+    /** Revoke a permission like `revoke`, passing `context` to the mutation guards of this scope. */
+    pub fn revoke_in(&mut self, name: &str, context: &MutationContext) -> Result<&mut Scope, ErrorKind> {
+        let _span = trace_span!("bitperm.revoke", path = %self.path, permission = name);
+
+        let resolved = self.resolve_name(name);
+        let result = match self.set_composite(&resolved, false, context) {
+            Some(result) => result,
+            None => match self.continuation_holding_mut(&resolved) {
+                // permissions spilled into a continuation are changed there, and count as a change to this scope
+                Some(continuation) => continuation.revoke_in(name, context).map(|_| ()),
+                None => self.try_revoke(name, context)
+            }
+        };
+        telemetry::record_revocation(result.is_ok());
+
+        return match result {
+            Ok(_) => {
+                self.touch();
+                trace_event!(debug, "permission revoked");
+                Ok(self)
+            },
+            Err(err) => {
+                trace_event!(debug, error = %crate::common::trace::error_message(&err), "revocation failed");
+                Err(err)
+            }
+        }
+    }
+
+    pub(crate) fn try_revoke(&mut self, name: &str, context: &MutationContext) -> Result<(), ErrorKind> {
+        let requested = name;
+        self.reject_virtual(requested)?;
+        let name = self.find_permission_name(requested)?;
+        self.check_guards(AuditOp::Revoke, &name, context)?;
+
+        if let Some(permission) = self.permissions.get_mut(&name) {
+            permission.revoke()?;
+        }
+        self.note_alias_use(requested);
+
+        return Ok(());
+    }
+
+    /** Resolve a name to the key of an existing permission, or fail if it is not defined in this scope. */
+    pub(crate) fn find_permission_name(&self, name: &str) -> Result<String, ErrorKind> {
+        let name = self.resolve_name(name);
+
+        return match self.permissions.contains_key(&name) {
+            true => Ok(name),
+            false => Err(ErrorKind::ScopeError(ScopeError::new(ScopeErrorCase::UnknownPermission, &name)))
+        }
+    }
+
+    /** Check whether the permission with the given key has been granted. */
+    pub(crate) fn is_granted(&self, name: &str) -> bool {
+        return match self.permissions.get(name) {
+            Some(permission) => permission.has(),
+            None => false
+        }
+    }
+
+    /** Collect every requirement of a permission that has not been granted, following requirements of requirements. */
+    fn missing_requirements(&self, name: &str) -> Vec<String> {
+        let mut missing: Vec<String> = vec![];
+        let mut visited: Vec<&str> = vec![name];
+        let mut stack: Vec<&str> = self.requirements(name).iter().map(|required| required.as_str()).collect();
+
+        while let Some(required) = stack.pop() {
+            if visited.contains(&required) {
+                continue;
+            }
+            visited.push(required);
+
+            if !self.is_granted(required) {
+                missing.push(required.to_string());
+            }
+            stack.extend(self.requirements(required).iter().map(|next| next.as_str()));
+        }
+
+        return missing;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get_crud_scope() -> Scope {
+        let mut scope = Scope::new("TEST_SCOPE");
+
+        if let Err(_) = scope
+            .add_permission("CREATE")
+            .and_then(|sc| sc.add_permission("READ"))
+            .and_then(|sc| sc.add_permission("UPDATE"))
+            .and_then(|sc| sc.add_permission("DELETE"))
+            .and_then(|sc| sc.add_requirement("DELETE", "READ"))
+            .and_then(|sc| sc.add_requirement("UPDATE", "READ"))
+            .and_then(|sc| sc.add_requirement("DELETE", "UPDATE")) {
+            assert!(false);
+        }
+
+        return scope;
+    }
+
+    #[test]
+    fn test_add_requirement_unknown_permission() {
+        let mut scope = get_crud_scope();
+
+        match scope.add_requirement("DELETE", "EXECUTE") {
+            Ok(_) => assert!(false),
+            Err(kind) => match kind {
+                ErrorKind::ScopeError(err) => {
+                    assert_eq!(err.case(), ScopeErrorCase::UnknownPermission);
+                    assert_eq!(err.name(), "EXECUTE");
+                },
+                ErrorKind::PermissionError(_) => assert!(false),
+            }
+        }
+    }
+
+    #[test]
+    fn test_grant_reject_missing_requirement() {
+        let mut scope = get_crud_scope();
+
+        match scope.grant("DELETE") {
+            Ok(_) => assert!(false), // should not succeed until READ and UPDATE are granted
+            Err(kind) => match kind {
+                ErrorKind::ScopeError(err) => {
+                    assert_eq!(err.case(), ScopeErrorCase::RequirementNotMet);
+                    assert_eq!(err.metadata().related(), Some("READ"));
+                },
+                ErrorKind::PermissionError(_) => assert!(false),
+            }
+        }
+
+        assert_eq!(scope.is_granted("DELETE"), false);
+    }
+
+    #[test]
+    fn test_grant_reject_requirements_met() {
+        let mut scope = get_crud_scope();
+
+        if let Ok(_) = scope
+            .grant("READ")
+            .and_then(|sc| sc.grant("UPDATE"))
+            .and_then(|sc| sc.grant("DELETE")) {
+            assert_eq!(scope.is_granted("DELETE"), true);
+        } else {
+            assert!(false);
+        }
+    }
+
+    #[test]
+    fn test_grant_auto_grants_requirements() {
+        let mut scope = get_crud_scope();
+        scope.set_requirement_mode(RequirementMode::AutoGrant);
+
+        if let Ok(_) = scope.grant("DELETE") {
+            assert_eq!(scope.is_granted("DELETE"), true);
+            assert_eq!(scope.is_granted("UPDATE"), true);
+            assert_eq!(scope.is_granted("READ"), true); // required by both DELETE and UPDATE
+            assert_eq!(scope.is_granted("CREATE"), false);
+        } else {
+            assert!(false);
+        }
+    }
+
+    #[test]
+    fn test_grant_auto_grant_circular_requirements() {
+        let mut scope = Scope::new("TEST_SCOPE");
+        scope.set_requirement_mode(RequirementMode::AutoGrant);
+
+        if let Ok(_) = scope
+            .add_permission("A")
+            .and_then(|sc| sc.add_permission("B"))
+            .and_then(|sc| sc.add_requirement("A", "B"))
+            .and_then(|sc| sc.add_requirement("B", "A"))
+            .and_then(|sc| sc.grant("A")) {
+            assert_eq!(scope.is_granted("A"), true);
+            assert_eq!(scope.is_granted("B"), true);
+        } else {
+            assert!(false);
+        }
+    }
+
+    #[test]
+    fn test_implied_permissions_checked_without_granting() {
+        let mut scope = get_crud_scope();
+
+        if let Ok(_) = scope
+            .add_implication("UPDATE", "READ")
+            .and_then(|sc| sc.add_implication("DELETE", "UPDATE"))
+            .and_then(|sc| sc.grant("READ"))
+            .and_then(|sc| sc.grant("UPDATE"))
+            .and_then(|sc| sc.grant("DELETE"))
+            .and_then(|sc| sc.revoke("READ"))
+            .and_then(|sc| sc.revoke("UPDATE")) {
+            // READ and UPDATE are still effective through DELETE, without their bits being set
+            assert_eq!(scope.check("DELETE"), true);
+            assert_eq!(scope.check("UPDATE"), true);
+            assert_eq!(scope.check("READ"), true);
+            assert_eq!(scope.check("CREATE"), false);
+            assert_eq!(scope.as_u64(), 1 << 3);
+        } else {
+            assert!(false);
+        }
+    }
+
+    fn get_approval_scope() -> Scope {
+        let mut scope = Scope::new("TEST_SCOPE");
+
+        if let Err(_) = scope
+            .add_permission("VIEW")
+            .and_then(|sc| sc.add_permission("SUBMITTER"))
+            .and_then(|sc| sc.add_permission("APPROVER"))
+            .and_then(|sc| sc.add_exclusion("SUBMITTER", "APPROVER")) {
+            assert!(false);
+        }
+
+        return scope;
+    }
+
+    #[test]
+    fn test_grant_mutually_exclusive() {
+        let mut scope = get_approval_scope();
+
+        assert!(scope.grant("SUBMITTER").is_ok());
+        match scope.grant("APPROVER") {
+            Ok(_) => assert!(false), // cannot approve while able to submit
+            Err(kind) => match kind {
+                ErrorKind::ScopeError(err) => {
+                    assert_eq!(err.case(), ScopeErrorCase::MutuallyExclusive);
+                    assert_eq!(err.name(), "APPROVER");
+                    assert_eq!(err.metadata().related(), Some("SUBMITTER"));
+                },
+                ErrorKind::PermissionError(_) => assert!(false),
+            }
+        }
+
+        // once the conflicting permission is revoked the grant is allowed
+        if let Ok(_) = scope.revoke("SUBMITTER").and_then(|sc| sc.grant("APPROVER")) {
+            assert_eq!(scope.is_granted("APPROVER"), true);
+        } else {
+            assert!(false);
+        }
+    }
+
+    #[test]
+    fn test_grant_auto_grant_mutually_exclusive_requirement() {
+        let mut scope = get_approval_scope();
+        scope.set_requirement_mode(RequirementMode::AutoGrant);
+
+        if let Err(_) = scope
+            .add_requirement("APPROVER", "VIEW")
+            .and_then(|sc| sc.add_exclusion("VIEW", "SUBMITTER"))
+            .and_then(|sc| sc.grant("SUBMITTER")) {
+            assert!(false);
+        }
+
+        // APPROVER would pull in VIEW, which conflicts with SUBMITTER, so nothing may be granted
+        assert!(scope.grant("APPROVER").is_err());
+        assert_eq!(scope.is_granted("VIEW"), false);
+        assert_eq!(scope.is_granted("APPROVER"), false);
+    }
+
+    #[test]
+    fn test_validate_grants_required_sets() {
+        let mut scope = get_crud_scope();
+
+        if let Err(_) = scope
+            .add_required_set("BASELINE", &["READ"])
+            .and_then(|sc| sc.add_required_set("WRITER", &["CREATE", "UPDATE"])) {
+            assert!(false);
+        }
+
+        let violations = scope.validate_grants();
+        assert_eq!(violations.len(), 2usize);
+
+        let _ = scope.grant("READ");
+        let violations = scope.validate_grants();
+        assert_eq!(violations.len(), 1usize);
+        assert_eq!(violations[0].set, "WRITER");
+        assert_eq!(violations[0].path.to_string(), "TEST_SCOPE");
+
+        // holding any one of the set's permissions satisfies it
+        let _ = scope.grant("UPDATE");
+        assert_eq!(scope.validate_grants().is_empty(), true);
+    }
+
+    #[test]
+    fn test_validate_grants_required_set_satisfied_by_implication() {
+        let mut scope = get_crud_scope();
+
+        if let Ok(_) = scope
+            .add_required_set("BASELINE", &["READ"])
+            .and_then(|sc| sc.add_implication("CREATE", "READ"))
+            .and_then(|sc| sc.grant("CREATE")) {
+            assert_eq!(scope.validate_grants().is_empty(), true);
+        } else {
+            assert!(false);
+        }
+    }
+
+    #[test]
+    fn test_add_required_set_replaces_existing() {
+        let mut scope = get_crud_scope();
+
+        if let Ok(_) = scope
+            .add_required_set("BASELINE", &["READ"])
+            .and_then(|sc| sc.add_required_set("BASELINE", &["CREATE"])) {
+            assert_eq!(scope.required_sets().len(), 1usize);
+            assert_eq!(scope.required_sets()[0].1, vec!["CREATE".to_string()]);
+        } else {
+            assert!(false);
+        }
+
+        assert!(scope.add_required_set("BASELINE", &["EXECUTE"]).is_err());
+    }
+
+    #[test]
+    fn test_grant_unknown_permission() {
+        let mut scope = get_crud_scope();
+
+        assert!(scope.grant("EXECUTE").is_err());
+        assert!(scope.revoke("EXECUTE").is_err());
+    }
+
+    #[test]
+    fn test_revoke_already_revoked() {
+        let mut scope = get_crud_scope();
+
+        match scope.revoke("READ") {
+            Ok(_) => assert!(false),
+            Err(kind) => match kind {
+                ErrorKind::PermissionError(_) => assert!(true),
+                ErrorKind::ScopeError(_) => assert!(false),
+            }
+        }
+    }
+}
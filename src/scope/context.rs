@@ -0,0 +1,243 @@
+use alloc::collections::BTreeMap;
+use alloc::string::{String, ToString};
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use crate::common::error::ErrorKind;
+use crate::common::telemetry;
+use crate::common::trace::trace_event;
+use crate::scope::path::PermissionPath;
+use crate::scope::Scope;
+
+/**
+    EvalContext describes the request a check is made for: the tenant and environment it runs in, attributes of the
+    resource it targets, and the current time. Passing it to `check_in` lets one schema serve every tenant and
+    environment, with conditions deciding what each of them allows.
+ */
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct EvalContext {
+    pub tenant: Option<String>,
+    pub environment: Option<String>,
+    /** Free-form attributes of the resource, e.g. its `owner` or `classification`. */
+    pub attributes: BTreeMap<String, String>,
+    /** The current time in the clock used for time-limited grants, conventionally seconds since the Unix epoch. */
+    pub now: Option<u64>
+}
+
+impl EvalContext {
+    pub fn new() -> EvalContext {
+        return EvalContext::default();
+    }
+
+    pub fn with_tenant(mut self, tenant: &str) -> EvalContext {
+        self.tenant = Some(tenant.to_string());
+        return self;
+    }
+
+    pub fn with_environment(mut self, environment: &str) -> EvalContext {
+        self.environment = Some(environment.to_string());
+        return self;
+    }
+
+    pub fn with_attribute(mut self, key: &str, value: &str) -> EvalContext {
+        self.attributes.insert(key.to_string(), value.to_string());
+        return self;
+    }
+
+    pub fn at(mut self, now: u64) -> EvalContext {
+        self.now = Some(now);
+        return self;
+    }
+
+    pub fn attribute(&self, key: &str) -> Option<&str> {
+        return self.attributes.get(key).map(|value| value.as_str());
+    }
+}
+
+/**
+    EvalCondition decides whether a permission applies in a given context, for attribute-based rules such as "only
+    in production" or "only for resources of the caller's tenant". Conditions are shared with clones of the scope.
+ */
+pub type EvalCondition = Arc<dyn Fn(&EvalContext) -> bool + Send + Sync>;
+
+impl Scope {
+    /**
+        Attach a named condition to a permission of this scope. `check_in` only allows the permission when every one
+        of its conditions holds for the context, however it is held. Checks without a context, such as `check`,
+        `has` and `check_value`, fail closed and never allow a conditioned permission. Conditions are not exported.
+        Adding a condition under a name already in use replaces it.
+     */
+    pub fn add_condition<F>(&mut self, permission: &str, name: &str, condition: F) -> Result<&mut Scope, ErrorKind>
+        where F: Fn(&EvalContext) -> bool + Send + Sync + 'static {
+        let permission = self.find_permission_name(permission)?;
+        let condition: EvalCondition = Arc::new(condition);
+
+        let conditions = self.conditions.entry(permission).or_default();
+        match conditions.iter_mut().find(|(existing, _)| existing == name) {
+            Some(entry) => entry.1 = condition,
+            None => conditions.push((name.to_string(), condition))
+        }
+
+        self.touch();
+        return Ok(self);
+    }
+
+    /** Remove a condition from a permission of this scope, returning whether it had one of that name. */
+    pub fn remove_condition(&mut self, permission: &str, name: &str) -> bool {
+        let permission = self.resolve_name(permission);
+        let removed = match self.conditions.get_mut(&permission) {
+            Some(conditions) => {
+                let count = conditions.len();
+                conditions.retain(|(existing, _)| existing != name);
+                conditions.len() != count
+            },
+            None => false
+        };
+
+        if removed {
+            self.touch();
+        }
+        return removed;
+    }
+
+    /** List the names of the conditions attached to a permission of this scope. */
+    pub fn condition_names(&self, permission: &str) -> Vec<&str> {
+        return match self.conditions.get(&self.resolve_name(permission)) {
+            Some(conditions) => conditions.iter().map(|(name, _)| name.as_str()).collect(),
+            None => Vec::new()
+        }
+    }
+
+    /**
+        Check the permission at `path` like `check`, in the given context. Every condition of the permission must
+        hold, and when the context carries the current time, grants that have expired by then no longer count,
+        even before `sweep_expired` has revoked them.
+     */
+    pub fn check_in(&self, path: &str, context: &EvalContext) -> bool {
+        let requested = PermissionPath::parse(path);
+
+        let allowed = match self.find_scope(&requested.scope) {
+            Some(scope) => {
+                let name = scope.resolve_name(&requested.permission);
+                match scope.continuation_holding(&name) {
+                    Some(continuation) => continuation.is_effective_in(&name, context),
+                    None => scope.is_effective_in(&name, context)
+                }
+            },
+            None => false
+        };
+        trace_event!(trace, scope = %self.path, path, allowed, "permission checked in context");
+        telemetry::record_check(allowed);
+
+        return allowed;
+    }
+
+    /** Check whether a permission defined on this scope is effective in the given context. */
+    pub(crate) fn is_effective_in(&self, name: &str, context: &EvalContext) -> bool {
+        if !self.conditions_hold(name, context) || self.is_denied_name(name) {
+            return false;
+        }
+        if let Some(held) = self.evaluate_virtual(name) {
+            return held;
+        }
+        if let Some(members) = self.composites.get(name) {
+            return members.iter().all(|member| self.is_effective_in(member, context));
+        }
+
+        // an implying grant only counts where its own conditions hold
+        return self.permissions.values()
+            .filter(|permission| permission.has() && self.conditions_hold(&permission.name, context))
+            .filter(|permission| match (context.now, permission.expires_at) {
+                (Some(now), Some(expires_at)) => expires_at > now,
+                _ => true
+            })
+            .any(|permission| {
+                return permission.name == name
+                    || self.implies_through(&permission.name, name, |implied| self.conditions_hold(implied, context));
+            });
+    }
+
+    /** Check whether every condition of a permission of this scope holds in the given context. */
+    fn conditions_hold(&self, name: &str, context: &EvalContext) -> bool {
+        return match self.conditions.get(name) {
+            Some(conditions) => conditions.iter().all(|(_, condition)| condition(context)),
+            None => true
+        }
+    }
+
+    /** Check whether any condition is attached to a permission of this scope. */
+    pub(crate) fn is_conditioned(&self, name: &str) -> bool {
+        return self.conditions.get(name).is_some_and(|conditions| !conditions.is_empty());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assert_ok;
+
+    fn get_scope() -> Scope {
+        let mut scope = Scope::new("APP");
+        assert_ok!(scope.add_permission("READ").and_then(|sc| sc.add_permission("DEPLOY")).and_then(|sc| sc.add_scope("docs")));
+        assert_ok!(scope.grant("READ").and_then(|sc| sc.grant("DEPLOY")));
+        if let Some(docs) = scope.scope("docs") {
+            assert_ok!(docs.add_permission("EDIT").and_then(|sc| sc.grant_until("EDIT", 100)));
+        }
+
+        return scope;
+    }
+
+    #[test]
+    fn test_conditions_use_context() {
+        let mut scope = get_scope();
+        assert_ok!(scope.add_condition("DEPLOY", "staging-only", |context| context.environment.as_deref() == Some("staging")));
+        assert_ok!(scope.add_condition("READ", "same-tenant", |context| context.tenant.as_deref() == context.attribute("tenant")));
+
+        let staging = EvalContext::new().with_environment("staging").with_tenant("acme").with_attribute("tenant", "acme");
+        let production = EvalContext::new().with_environment("production").with_tenant("acme").with_attribute("tenant", "globex");
+
+        assert_eq!(scope.check_in("DEPLOY", &staging), true);
+        assert_eq!(scope.check_in("READ", &staging), true);
+        assert_eq!(scope.check_in("DEPLOY", &production), false);
+        assert_eq!(scope.check_in("READ", &production), false);
+
+        // a conditioned grant does not leak through what it implies
+        assert_ok!(scope.add_permission("ADMIN").and_then(|sc| sc.add_permission("AUDIT")).and_then(|sc| sc.add_permission("EDITOR")));
+        assert_ok!(scope.add_implication("ADMIN", "EDITOR").and_then(|sc| sc.add_implication("EDITOR", "AUDIT")).and_then(|sc| sc.add_implication("AUDIT", "ADMIN")));
+        assert_ok!(scope.grant("ADMIN").and_then(|sc| sc.add_condition("ADMIN", "staging-only", |context| context.environment.as_deref() == Some("staging"))));
+        assert_eq!(scope.check_in("ADMIN", &production), false);
+        assert_eq!(scope.check_in("AUDIT", &production), false);
+        assert_eq!(scope.check_in("AUDIT", &staging), true);
+
+        // nor through a conditioned permission in the middle of a chain
+        assert_ok!(scope.add_condition("EDITOR", "staging-only", |context| context.environment.as_deref() == Some("staging")));
+        assert_eq!(scope.remove_condition("ADMIN", "staging-only"), true);
+        assert_eq!(scope.check_in("ADMIN", &production), true);
+        assert_eq!(scope.check_in("AUDIT", &production), false);
+
+        // checks without a context fail closed
+        assert_eq!(scope.check("DEPLOY"), false);
+        assert_eq!(scope.has("DEPLOY"), false);
+        assert_eq!(scope.check("ADMIN"), true);
+        assert_eq!(scope.check("AUDIT"), false);
+        assert_eq!(crate::scope::evaluate::check_value(&scope, "DEPLOY", scope.as_u64()).ok(), Some(false));
+        assert_eq!(scope.check_many("AUDIT", &[scope.as_u64()]), [false]);
+        assert_eq!(scope.explain("DEPLOY").decision, crate::scope::explain::Decision::Conditioned);
+        assert_eq!(scope.explain("AUDIT").allowed, false);
+
+        assert_eq!(scope.condition_names("DEPLOY"), ["staging-only"]);
+        assert_eq!(scope.remove_condition("DEPLOY", "staging-only"), true);
+        assert_eq!(scope.check_in("DEPLOY", &production), true);
+        assert_eq!(scope.check("DEPLOY"), true);
+    }
+
+    #[test]
+    fn test_expired_grants_do_not_count_in_context() {
+        let scope = get_scope();
+
+        assert_eq!(scope.check_in("docs:EDIT", &EvalContext::new().at(99)), true);
+        assert_eq!(scope.check_in("docs:EDIT", &EvalContext::new().at(100)), false);
+        // without a time, the grant counts until it is swept
+        assert_eq!(scope.check_in("docs:EDIT", &EvalContext::new()), true);
+        assert_eq!(scope.check_in("docs:MISSING", &EvalContext::new()), false);
+    }
+}
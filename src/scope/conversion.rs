@@ -1,20 +1,167 @@
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
 use serde::{Deserialize, Serialize};
 use serde_json::{from_value, to_value, Value};
+use crate::common::error::ErrorKind;
+use crate::permission::ValueCeiling;
+use crate::scope::error::{ScopeError, ScopeErrorCase};
 
 /** ScopeTuple is a packed version of Scope that is used for import/export operations. */
-#[derive(Serialize, Deserialize)]
-pub struct ScopeTuple (pub String, pub u64, pub Vec<String>, pub Vec<ScopeTuple>);
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ScopeTuple (
+    pub String,
+    pub u64,
+    pub Vec<PermissionEntry>,
+    pub Vec<ScopeTuple>,
+    #[serde(default, skip_serializing_if = "ScopeMetadata::is_empty")]
+    pub ScopeMetadata
+);
+
+/**
+    PermissionEntry lists a permission in a ScopeTuple. Exports pair every name with its shift, so imports put each
+    permission on the same bit whatever order the names are listed in, and bits left unused by removed permissions
+    stay unused. Tuples written before shifts were recorded list bare names, which take the bits in list order.
+ */
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum PermissionEntry {
+    Placed(String, u8),
+    Named(String)
+}
+
+impl PermissionEntry {
+    pub fn name(&self) -> &str {
+        return match self {
+            PermissionEntry::Placed(name, _) => name,
+            PermissionEntry::Named(name) => name
+        }
+    }
+
+    /** Get the recorded shift of the permission, if the entry has one. */
+    pub fn shift(&self) -> Option<u8> {
+        return match self {
+            PermissionEntry::Placed(_, shift) => Some(*shift),
+            PermissionEntry::Named(_) => None
+        }
+    }
+}
+
+impl From<&str> for PermissionEntry {
+    fn from(name: &str) -> PermissionEntry {
+        return PermissionEntry::Named(name.to_string());
+    }
+}
+
+/** ScopeMetadata carries the schema rules of a scope alongside its packed ScopeTuple. It is omitted when empty. */
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScopeMetadata {
+    /** Pairs of (permission, required permission). */
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub requirements: Vec<(String, String)>,
+    /** Pairs of (permission, implied permission). */
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub implications: Vec<(String, String)>,
+    /** Pairs of mutually exclusive permissions. */
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub exclusions: Vec<(String, String)>,
+    /** Named sets of permissions of which at least one must be held. */
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub required_sets: Vec<(String, Vec<String>)>,
+    /** Pairs of (composite permission, the permissions it combines). */
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub composites: Vec<(String, Vec<String>)>,
+    /** Pairs of (deprecated permission, replacement hint). */
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub deprecations: Vec<(String, Option<String>)>,
+    /** Pairs of (old name, current name) kept from renamed permissions. */
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub aliases: Vec<(String, String)>,
+    /** Pairs of permission paths, relative to the scope, that are granted and revoked together. */
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub references: Vec<(String, String)>,
+    /** Pairs of (permission, description). */
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub descriptions: Vec<(String, String)>,
+    /** Pairs of (permission, tags). */
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<(String, Vec<String>)>,
+    /** Pairs of (granted permission, time its grant lapses) for time-limited grants. */
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub expirations: Vec<(String, u64)>,
+    /** Pairs of (permission, stable identifier). */
+    #[cfg(feature = "uuid")]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub ids: Vec<(String, uuid::Uuid)>,
+    /** The deny mask of the scope, as produced by `as_deny_u64`. */
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub denied: u64,
+    /** The value ceiling of the scope, omitted for the default JS-safe ceiling. */
+    #[serde(default, skip_serializing_if = "is_js_safe")]
+    pub ceiling: ValueCeiling,
+    /** The revision of the scope when it was exported. */
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub revision: u64,
+}
+
+fn is_zero(value: &u64) -> bool {
+    return *value == 0;
+}
+
+fn is_js_safe(ceiling: &ValueCeiling) -> bool {
+    return *ceiling == ValueCeiling::JsSafe;
+}
+
+impl ScopeMetadata {
+    pub fn new() -> ScopeMetadata {
+        return ScopeMetadata::default();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.requirements.is_empty()
+            && self.implications.is_empty()
+            && self.exclusions.is_empty()
+            && self.required_sets.is_empty()
+            && self.composites.is_empty()
+            && self.deprecations.is_empty()
+            && self.aliases.is_empty()
+            && self.references.is_empty()
+            && self.descriptions.is_empty()
+            && self.tags.is_empty()
+            && self.expirations.is_empty()
+            && self.ids_are_empty()
+            && self.denied == 0
+            && is_js_safe(&self.ceiling)
+            && self.revision == 0;
+    }
+
+    #[cfg(feature = "uuid")]
+    fn ids_are_empty(&self) -> bool {
+        return self.ids.is_empty();
+    }
+
+    #[cfg(not(feature = "uuid"))]
+    fn ids_are_empty(&self) -> bool {
+        return true;
+    }
+}
 
 impl ScopeTuple {
     /** Convert this value from a ScopeTuple into its equivalent JSON representation. */
-    pub fn to_json(self) -> Value {
-        Value::from(self)
+    pub fn to_json(&self) -> Value {
+        Value::from(self.clone())
     }
 
     /** Convert a value from JSON representation into a ScopeTuple. */
     pub fn from_json(value: Value) -> ScopeTuple {
         ScopeTuple::from(value)
     }
+
+    /** Convert a value from JSON representation into a ScopeTuple, failing instead of panicking on malformed input. */
+    pub fn try_from_json(value: Value) -> Result<ScopeTuple, ErrorKind> {
+        return from_value(value).map_err(|err| {
+            ErrorKind::ScopeError(ScopeError::new(ScopeErrorCase::InvalidEncoding, &err.to_string()))
+        });
+    }
 }
 
 // JSON Value Conversion
@@ -43,8 +190,14 @@ impl From<ScopeTuple> for Value {
 
 
 
+#[cfg(test)]
 mod tests {
+    use alloc::string::ToString;
+    use alloc::vec;
+    use serde_json::json;
+    use crate::scope::order::ExportOptions;
     use crate::scope::Scope;
+    use super::*;
 
     fn validate_scope(left: &Scope, right: &Scope) -> bool {
         if !left.name.eq(right.name.as_str()) {
@@ -59,6 +212,10 @@ mod tests {
             eprintln!("permissions length encoded to tuple ({}) does not equal expected value ({})", left.permissions.len(), right.permissions.len());
             return false;
         }
+        if left.path() != right.path() {
+            eprintln!("scope path decoded from tuple ('{}') does not equal expected value ('{}')", left.path(), right.path());
+            return false;
+        }
         if left.scopes.len() != right.scopes.len() {
             eprintln!("scopes length encoded to tuple ({}) does not equal expected value ({})", left.scopes.len(), right.scopes.len());
             return false;
@@ -76,7 +233,7 @@ mod tests {
                 return false;
             }
 
-            i = i + 1;
+            i += 1;
         }
 
         // for child_scope in left.scopes.values() {
@@ -261,4 +418,174 @@ mod tests {
         assert!(json.is_array());
         assert!(validate_scope(&Scope::from_json(json), &scope));
     }
+
+    #[test]
+    fn test_json_metadata_omitted_when_empty() {
+        let mut scope = Scope::new("USER");
+        let _ = scope.add_permission("READ");
+
+        match scope.as_tuple().to_json() {
+            Value::Array(values) => assert_eq!(values.len(), 4usize),
+            _ => assert!(false)
+        }
+    }
+
+    #[test]
+    fn test_json_revision_round_trip() {
+        let mut scope = Scope::new("USER");
+        if let Err(_) = scope.add_permission("READ").and_then(|sc| sc.grant("READ")) {
+            assert!(false);
+        }
+        assert_eq!(scope.revision(), 2u64);
+
+        // revisions are only exported on request
+        assert_eq!(Scope::from_json(scope.as_json()).revision(), 0u64);
+        let options = ExportOptions { revisions: true, ..ExportOptions::default() };
+        let imported = Scope::from_json(scope.as_json_with(&options));
+        assert_eq!(imported.revision(), 2u64);
+    }
+
+    #[test]
+    fn test_json_export_is_stable() {
+        let build = || {
+            let mut scope = Scope::new("USER");
+            for name in ["WRITE", "READ", "DELETE", "ADMIN", "EXPORT", "AUDIT"] {
+                if let Err(_) = scope.add_permission(name) {
+                    assert!(false);
+                }
+            }
+            for name in ["reports", "billing", "admin"] {
+                if let Err(_) = scope.add_scope(name) {
+                    assert!(false);
+                }
+            }
+            if let Err(_) = scope.grant("READ").and_then(|sc| sc.grant("AUDIT")) {
+                assert!(false);
+            }
+            scope
+        };
+
+        // hash maps may iterate differently in every build, so the two only agree if exports ignore hash order
+        let exported = build().as_json();
+        assert_eq!(exported, build().as_json());
+        assert_eq!(exported[2], json!([["WRITE", 0], ["READ", 1], ["DELETE", 2], ["ADMIN", 3], ["EXPORT", 4], ["AUDIT", 5]]));
+        assert_eq!(exported[1], json!(0b100010));
+        assert_eq!(exported[3].as_array().map(|scopes| scopes.iter().map(|scope| scope[0].clone()).collect::<Vec<Value>>()),
+            Some(vec![json!("reports"), json!("billing"), json!("admin")]));
+
+        let imported = Scope::from_json(exported.clone());
+        assert_eq!(imported.as_json(), exported);
+    }
+
+    #[test]
+    fn test_json_shifts_round_trip() {
+        // names listed out of bit order still land on their recorded bits
+        let mut scope = Scope::from_json(json!(["USER", 0b101, [["DELETE", 2], ["READ", 0], ["WRITE", 1]], []]));
+        assert_eq!(scope.check("READ"), true);
+        assert_eq!(scope.check("DELETE"), true);
+        assert_eq!(scope.check("WRITE"), false);
+
+        // bits freed by removed permissions stay free after a round trip
+        if let Err(_) = scope.remove_permission("WRITE") {
+            assert!(false);
+        }
+        let mut imported = Scope::from_json(scope.as_json());
+        assert_eq!(imported.as_json()[2], json!([["READ", 0], ["DELETE", 2]]));
+        assert_eq!(imported.as_u64(), 0b101);
+        if let Err(_) = imported.add_permission("EXPORT") {
+            assert!(false);
+        }
+        assert_eq!(imported.permission("EXPORT").map(|permission| permission.value), Some(0b1000));
+
+        match Scope::try_from_json(json!(["USER", 0, [["READ", 0], ["WRITE", 0]], []])) {
+            Err(ErrorKind::ScopeError(err)) => assert_eq!(err.case(), ScopeErrorCase::DuplicateShift),
+            _ => assert!(false)
+        }
+    }
+
+    #[test]
+    fn test_import_validates_names() {
+        // names that could not be added one by one are rejected on import as well
+        let cases = [
+            json!(["APP", 0, [["RE AD:x", 0]], [["docs", 0, [], []]], {}]),
+            json!(["APP", 0, [["READ", 0]], [["do.cs", 0, [], []]], {}]),
+            json!(["", 0, [], []])
+        ];
+        for json in cases {
+            match Scope::try_from_json(json) {
+                Err(ErrorKind::ScopeError(err)) => assert_eq!(err.case(), ScopeErrorCase::InvalidName),
+                _ => assert!(false)
+            }
+        }
+
+        let tuple = ScopeTuple(String::from("APP"), 0, vec![PermissionEntry::from("DEL\tETE")], vec![], ScopeMetadata::default());
+        match Scope::from_tuple(tuple, 4) {
+            Err(ErrorKind::ScopeError(err)) => assert_eq!(err.case(), ScopeErrorCase::InvalidName),
+            _ => assert!(false)
+        }
+    }
+
+    #[test]
+    fn test_json_import_without_metadata() {
+        let json = json!(["USER", 1, ["READ"], []]);
+        let mut scope = Scope::from_json(json);
+
+        assert_eq!(scope.permission("READ").is_some(), true);
+        assert_eq!(scope.check("READ"), true);
+    }
+
+    #[test]
+    fn test_try_from_json() {
+        let json = json!(["USER", 1, ["READ"], []]);
+        match Scope::try_from_json(json) {
+            Ok(scope) => assert_eq!(scope.to_json(), scope.as_json()),
+            Err(_) => assert!(false)
+        }
+
+        for malformed in [json!({ "name": "USER" }), json!(["USER", "1", ["READ"], []]), json!(["USER", 1, "READ"])] {
+            assert_eq!(Scope::try_from_json(malformed).is_err(), true);
+        }
+    }
+
+    #[test]
+    fn test_json_rules_round_trip() {
+        let mut scope = Scope::new("USER");
+
+        if let Err(_) = scope
+            .add_permission("READ")
+            .and_then(|sc| sc.add_permission("WRITE"))
+            .and_then(|sc| sc.add_permission("DELETE"))
+            .and_then(|sc| sc.add_requirement("DELETE", "READ"))
+            .and_then(|sc| sc.add_implication("WRITE", "READ"))
+            .and_then(|sc| sc.add_exclusion("WRITE", "DELETE"))
+            .and_then(|sc| sc.add_required_set("BASELINE", &["READ", "WRITE"]))
+            .and_then(|sc| sc.deprecate("DELETE", None))
+            .and_then(|sc| sc.rename_permission("WRITE", "EDIT", true))
+            .and_then(|sc| sc.describe("READ", "View records"))
+            .and_then(|sc| sc.tag("READ", "core")) {
+            assert!(false);
+        }
+
+        let json = scope.as_json();
+        match &json {
+            Value::Array(values) => assert_eq!(values.len(), 5usize),
+            _ => assert!(false)
+        }
+
+        let mut imported = Scope::from_json(json);
+        assert_eq!(imported.requirements("DELETE"), ["READ".to_string()]);
+        assert_eq!(imported.implications("EDIT"), ["READ".to_string()]);
+        assert_eq!(imported.exclusions("DELETE"), ["EDIT".to_string()]);
+        assert_eq!(imported.alias_target("WRITE"), Some("EDIT"));
+
+        match imported.permission("READ") {
+            Some(permission) => {
+                assert_eq!(permission.description, Some("View records".to_string()));
+                assert_eq!(permission.tags, vec!["core".to_string()]);
+            },
+            None => assert!(false)
+        }
+        assert_eq!(imported.is_deprecated("DELETE"), true);
+        assert_eq!(imported.required_sets(), [("BASELINE".to_string(), vec!["READ".to_string(), "EDIT".to_string()])]);
+    }
 }
\ No newline at end of file
@@ -0,0 +1,146 @@
+use alloc::string::ToString;
+use crate::common::error::ErrorKind;
+use crate::scope::error::{ScopeError, ScopeErrorCase};
+use crate::scope::Scope;
+
+impl Scope {
+    /**
+        Explicitly deny a permission of this scope, revoking any grant of it. A denied permission is never held:
+        denial overrides grants, implications and composites, so "every editor permission except PUBLISH" is an
+        implying grant plus one denial. Granting it fails until the denial is cleared with `clear_denial`.
+     */
+    pub fn deny(&mut self, name: &str) -> Result<&mut Scope, ErrorKind> {
+        return self.set_denied(name, true);
+    }
+
+    /** Lift the denial of a permission of this scope, leaving it neither granted nor denied. */
+    pub fn clear_denial(&mut self, name: &str) -> Result<&mut Scope, ErrorKind> {
+        return self.set_denied(name, false);
+    }
+
+    /** Check whether a permission of this scope is explicitly denied. Unknown permissions are not denied. */
+    pub fn is_denied(&self, name: &str) -> bool {
+        let resolved = self.resolve_name(name);
+        if let Some(continuation) = self.continuation_holding(&resolved) {
+            return continuation.is_denied(&resolved);
+        }
+
+        return self.is_denied_name(&resolved);
+    }
+
+    /**
+        Get the deny mask of this scope (but not its child scopes): the bits of every explicitly denied permission.
+        It is stored beside the value from `as_u64`, which never has a denied bit set.
+     */
+    pub fn as_deny_u64(&self) -> u64 {
+        return self.permissions.values()
+            .filter(|permission| permission.denied)
+            .fold(0u64, |mask, permission| mask | permission.value);
+    }
+
+    /**
+        Set the denials of every permission in this scope (but not its child scopes) from a mask produced by
+        `as_deny_u64`, revoking the grants of newly denied permissions. Fails with UndefinedBits, without changing
+        anything, if the mask sets bits that no permission is assigned to.
+     */
+    pub fn apply_deny_u64(&mut self, mask: u64) -> Result<&mut Scope, ErrorKind> {
+        if mask & !self.defined_bits() != 0 {
+            return Err(ErrorKind::ScopeError(ScopeError::new(ScopeErrorCase::UndefinedBits, &self.path.to_string())));
+        }
+
+        for permission in self.permissions.values_mut() {
+            match mask & permission.value != 0 {
+                true => permission.deny(),
+                false => permission.clear_denial()
+            };
+        }
+        self.touch();
+
+        return Ok(self);
+    }
+
+    /** Check whether the permission with the given key is explicitly denied. */
+    pub(crate) fn is_denied_name(&self, name: &str) -> bool {
+        return self.permissions.get(name).is_some_and(|permission| permission.denied);
+    }
+
+    fn set_denied(&mut self, name: &str, denied: bool) -> Result<&mut Scope, ErrorKind> {
+        let resolved = self.resolve_name(name);
+        if let Some(continuation) = self.continuation_holding_mut(&resolved) {
+            continuation.set_denied(&resolved, denied)?;
+            self.touch();
+            return Ok(self);
+        }
+
+        self.reject_virtual(name)?;
+        let name = self.find_permission_name(name)?;
+        if let Some(permission) = self.permissions.get_mut(&name) {
+            match denied {
+                true => permission.deny(),
+                false => permission.clear_denial()
+            };
+        }
+        self.touch();
+
+        return Ok(self);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{assert_granted, assert_not_granted, assert_ok};
+    use crate::permission::PermissionState;
+    use crate::scope::explain::Decision;
+
+    fn get_scope() -> Scope {
+        let mut scope = Scope::new("APP");
+        assert_ok!(scope.add_permission("READ").and_then(|sc| sc.add_permission("PUBLISH")).and_then(|sc| sc.add_permission("EDITOR")));
+        assert_ok!(scope.add_implication("EDITOR", "READ").and_then(|sc| sc.add_implication("EDITOR", "PUBLISH")));
+
+        return scope;
+    }
+
+    #[test]
+    fn test_denial_overrides_grants() {
+        let mut scope = get_scope();
+        assert_ok!(scope.grant("EDITOR").and_then(|sc| sc.grant("PUBLISH")));
+
+        // the editor template minus one permission
+        assert_ok!(scope.deny("PUBLISH"));
+        assert_granted!(scope, "READ");
+        assert_not_granted!(scope, "PUBLISH");
+        assert_eq!(scope.explain("PUBLISH").decision, Decision::Denied);
+        assert_eq!(scope.as_u64(), 0b100);
+        assert_eq!(scope.as_deny_u64(), 0b010);
+
+        match scope.grant("PUBLISH") {
+            Err(err) => assert_eq!(err.to_string().contains("explicitly denied"), true),
+            Ok(_) => panic!("a denied permission was granted")
+        }
+
+        assert_ok!(scope.clear_denial("PUBLISH"));
+        assert_eq!(scope.is_denied("PUBLISH"), false);
+        assert_granted!(scope, "PUBLISH"); // implied by EDITOR again
+        assert_eq!(scope.permission_ref("PUBLISH").map(|permission| permission.state()), Some(PermissionState::Unset));
+    }
+
+    #[test]
+    fn test_denials_are_exported() {
+        let mut scope = get_scope();
+        assert_ok!(scope.grant("EDITOR").and_then(|sc| sc.deny("READ")));
+
+        let imported = assert_ok!(Scope::try_from_json(scope.as_json()));
+        assert_eq!(imported.is_denied("READ"), true);
+        assert_not_granted!(imported, "READ");
+        assert_granted!(imported, "PUBLISH");
+
+        let schema = assert_ok!(Scope::try_from_json(scope.as_schema_json()));
+        assert_eq!(schema.as_deny_u64(), 0);
+
+        let mut restored = get_scope();
+        assert_ok!(restored.apply_deny_u64(scope.as_deny_u64()));
+        assert_eq!(restored.is_denied("READ"), true);
+        assert_eq!(restored.apply_deny_u64(0b1000).is_err(), true);
+    }
+}
@@ -0,0 +1,180 @@
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::mem;
+use crate::common::error::ErrorKind;
+use crate::scope::error::{ScopeError, ScopeErrorCase, ScopeErrorMetadata};
+use crate::scope::path::ScopePath;
+use crate::scope::Scope;
+
+/** DeprecationMode decides what happens when a deprecated permission is granted through a scope. */
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DeprecationMode {
+    /** Grant the permission without complaint. */
+    Allow,
+    /** Grant the permission and record a warning that can be collected with `take_warnings`. */
+    #[default]
+    Warn,
+    /** Refuse to grant the permission. */
+    Reject
+}
+
+/** DeprecationWarning records that a deprecated permission was granted. */
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DeprecationWarning {
+    pub path: ScopePath,
+    pub permission: String,
+    pub replacement: Option<String>
+}
+
+impl Scope {
+    /** Get the mode used when deprecated permissions are granted through this scope. */
+    pub fn deprecation_mode(&self) -> DeprecationMode {
+        return self.deprecation_mode;
+    }
+
+    /** Set the mode used when deprecated permissions are granted, for this scope and all of its child scopes. */
+    pub fn set_deprecation_mode(&mut self, mode: DeprecationMode) -> &mut Scope {
+        for scope in self.scopes.values_mut() {
+            scope.set_deprecation_mode(mode);
+        }
+        self.deprecation_mode = mode;
+
+        return self;
+    }
+
+    /**
+        Mark a permission as deprecated, optionally naming the permission (or path) that replaces it.
+        The permission keeps its bit, so values stored before the deprecation are still interpreted correctly.
+     */
+    pub fn deprecate(&mut self, permission: &str, replacement: Option<&str>) -> Result<&mut Scope, ErrorKind> {
+        let permission = self.find_permission_name(permission)?;
+        self.deprecations.insert(permission, replacement.map(|replacement| replacement.to_string()));
+
+        self.touch();
+        return Ok(self);
+    }
+
+    pub fn is_deprecated(&self, permission: &str) -> bool {
+        return self.deprecations.contains_key(self.policy.normalize(permission).as_ref());
+    }
+
+    /** Get the replacement hint for a deprecated permission, if one was given. */
+    pub fn replacement(&self, permission: &str) -> Option<&str> {
+        return match self.deprecations.get(self.policy.normalize(permission).as_ref()) {
+            Some(replacement) => replacement.as_deref(),
+            None => None
+        }
+    }
+
+    /** Get the warnings recorded since they were last taken. */
+    pub fn warnings(&self) -> &[DeprecationWarning] {
+        return &self.warnings;
+    }
+
+    /** Take the warnings recorded since they were last taken, leaving none behind. */
+    pub fn take_warnings(&mut self) -> Vec<DeprecationWarning> {
+        return mem::take(&mut self.warnings);
+    }
+
+    /**
+        Apply the deprecation mode to a set of permissions that are about to be granted, returning the
+        warnings to record once the grant succeeds.
+     */
+    pub(crate) fn check_deprecations(&self, pending: &[String]) -> Result<Vec<DeprecationWarning>, ErrorKind> {
+        let mut warnings: Vec<DeprecationWarning> = vec![];
+
+        for candidate in pending {
+            if let Some(replacement) = self.deprecations.get(candidate) {
+                match self.deprecation_mode {
+                    DeprecationMode::Allow => {},
+                    DeprecationMode::Warn => warnings.push(DeprecationWarning {
+                        path: self.path.clone(),
+                        permission: candidate.clone(),
+                        replacement: replacement.clone()
+                    }),
+                    DeprecationMode::Reject => return Err(ErrorKind::ScopeError(ScopeError::with_metadata(
+                        ScopeErrorCase::Deprecated,
+                        candidate,
+                        ScopeErrorMetadata {
+                            related: replacement.clone(),
+                            ..ScopeErrorMetadata::new()
+                        }
+                    )))
+                }
+            }
+        }
+
+        return Ok(warnings);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get_deprecated_scope() -> Scope {
+        let mut scope = Scope::new("TEST_SCOPE");
+
+        if let Err(_) = scope
+            .add_permission("EDIT")
+            .and_then(|sc| sc.add_permission("WRITE"))
+            .and_then(|sc| sc.deprecate("EDIT", Some("WRITE"))) {
+            assert!(false);
+        }
+
+        return scope;
+    }
+
+    #[test]
+    fn test_deprecate_permission() {
+        let scope = get_deprecated_scope();
+
+        assert_eq!(scope.is_deprecated("EDIT"), true);
+        assert_eq!(scope.is_deprecated("WRITE"), false);
+        assert_eq!(scope.replacement("EDIT"), Some("WRITE"));
+    }
+
+    #[test]
+    fn test_grant_deprecated_warns_by_default() {
+        let mut scope = get_deprecated_scope();
+
+        assert!(scope.grant("EDIT").is_ok());
+        assert_eq!(scope.check("EDIT"), true);
+
+        let warnings = scope.take_warnings();
+        assert_eq!(warnings.len(), 1usize);
+        assert_eq!(warnings[0].permission, "EDIT");
+        assert_eq!(warnings[0].replacement, Some("WRITE".to_string()));
+        assert_eq!(scope.warnings().is_empty(), true);
+    }
+
+    #[test]
+    fn test_grant_deprecated_allowed() {
+        let mut scope = get_deprecated_scope();
+        scope.set_deprecation_mode(DeprecationMode::Allow);
+
+        assert!(scope.grant("EDIT").is_ok());
+        assert_eq!(scope.warnings().is_empty(), true);
+    }
+
+    #[test]
+    fn test_grant_deprecated_rejected() {
+        let mut scope = get_deprecated_scope();
+        scope.set_deprecation_mode(DeprecationMode::Reject);
+
+        match scope.grant("EDIT") {
+            Ok(_) => assert!(false),
+            Err(kind) => match kind {
+                ErrorKind::ScopeError(err) => {
+                    assert_eq!(err.case(), ScopeErrorCase::Deprecated);
+                    assert_eq!(err.metadata().related(), Some("WRITE"));
+                },
+                ErrorKind::PermissionError(_) => assert!(false),
+            }
+        }
+
+        assert_eq!(scope.check("EDIT"), false);
+        assert!(scope.grant("WRITE").is_ok());
+    }
+}
@@ -0,0 +1,191 @@
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt::Write;
+use crate::permission::Permission;
+use crate::scope::Scope;
+
+/** A step of the depth-first walk used to write nested diagram blocks without recursion. */
+enum Visit<'a> {
+    Enter(&'a Scope, usize),
+    Leave(usize)
+}
+
+impl Scope {
+    /**
+        Export this scope and all of its child scopes as a Graphviz DOT graph. Each scope becomes a cluster
+        nested inside its parent's cluster, and each permission becomes a node within it, filled when granted.
+     */
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::new();
+        let mut cluster = 0usize;
+        let mut stack: Vec<Visit> = vec![Visit::Enter(self, 1)];
+
+        let _ = writeln!(dot, "digraph \"{}\" {{", escape_dot(&self.name));
+        dot.push_str("  node [shape=box];\n");
+
+        while let Some(visit) = stack.pop() {
+            match visit {
+                Visit::Enter(scope, depth) => {
+                    let indent = "  ".repeat(depth);
+
+                    let _ = writeln!(dot, "{}subgraph cluster_{} {{", indent, cluster);
+                    let _ = writeln!(dot, "{}  label=\"{}\";", indent, escape_dot(&scope.name));
+                    for (index, permission) in sorted_permissions(scope).into_iter().enumerate() {
+                        let style = match permission.has() {
+                            true => ", style=filled, fillcolor=palegreen",
+                            false => ""
+                        };
+                        let _ = writeln!(
+                            dot,
+                            "{}  p{}_{} [label=\"{}\"{}];",
+                            indent, cluster, index, escape_dot(&permission.name), style
+                        );
+                    }
+                    cluster += 1;
+
+                    stack.push(Visit::Leave(depth));
+                    stack.extend(sorted_children(scope).into_iter().rev().map(|child| Visit::Enter(child, depth + 1)));
+                },
+                Visit::Leave(depth) => {
+                    let _ = writeln!(dot, "{}}}", "  ".repeat(depth));
+                }
+            }
+        }
+        dot.push_str("}\n");
+
+        return dot;
+    }
+
+    /**
+        Export this scope and all of its child scopes as a Mermaid flowchart, which renders directly in
+        Markdown on most documentation sites. Scopes link to their child scopes and permissions, and granted
+        permissions use the `granted` class.
+     */
+    pub fn to_mermaid(&self) -> String {
+        let mut mermaid = String::from("flowchart TD\n");
+        let mut next_id = 0usize;
+        let mut stack: Vec<(&Scope, Option<usize>)> = vec![(self, None)];
+
+        while let Some((scope, parent)) = stack.pop() {
+            let id = next_id;
+            next_id += 1;
+
+            let _ = writeln!(mermaid, "  s{}[\"{}\"]", id, escape_mermaid(&scope.name));
+            if let Some(parent) = parent {
+                let _ = writeln!(mermaid, "  s{} --> s{}", parent, id);
+            }
+            for (index, permission) in sorted_permissions(scope).into_iter().enumerate() {
+                let class = match permission.has() {
+                    true => ":::granted",
+                    false => ""
+                };
+                let _ = writeln!(
+                    mermaid,
+                    "  s{} --> s{}_p{}(\"{}\"){}",
+                    id, id, index, escape_mermaid(&permission.name), class
+                );
+            }
+
+            stack.extend(sorted_children(scope).into_iter().rev().map(|child| (child, Some(id))));
+        }
+        mermaid.push_str("  classDef granted fill:#9f9,stroke:#393\n");
+
+        return mermaid;
+    }
+}
+
+/** Get the permissions defined directly on a scope, ordered by bit. */
+fn sorted_permissions(scope: &Scope) -> Vec<&Permission> {
+    let mut permissions: Vec<&Permission> = scope.permissions.values().collect();
+    permissions.sort_by_key(|permission| permission.value);
+
+    return permissions;
+}
+
+/** Get the direct child scopes of a scope, ordered by name. */
+fn sorted_children(scope: &Scope) -> Vec<&Scope> {
+    let mut children: Vec<&Scope> = scope.scopes.values().collect();
+    children.sort_by(|a, b| a.name.cmp(&b.name));
+
+    return children;
+}
+
+/** Escape a string for use inside a double-quoted DOT identifier. */
+fn escape_dot(text: &str) -> String {
+    return text.replace('\\', "\\\\").replace('"', "\\\"");
+}
+
+/** Escape a string for use inside a double-quoted Mermaid label. */
+fn escape_mermaid(text: &str) -> String {
+    return text.replace('"', "#quot;");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get_diagram_scope() -> Scope {
+        let mut scope = Scope::new("APP");
+
+        if let Err(_) = scope
+            .add_permission("READ")
+            .and_then(|sc| sc.add_permission("WRITE"))
+            .and_then(|sc| sc.grant("WRITE"))
+            .and_then(|sc| sc.add_scope("billing")) {
+            assert!(false);
+        }
+        match scope.scope("billing") {
+            Some(billing) => {
+                if let Err(_) = billing.add_permission("PAY") {
+                    assert!(false);
+                }
+            },
+            None => assert!(false)
+        }
+
+        return scope;
+    }
+
+    #[test]
+    fn test_to_dot() {
+        let scope = get_diagram_scope();
+
+        let expected = "digraph \"APP\" {\n  \
+            node [shape=box];\n  \
+            subgraph cluster_0 {\n    \
+              label=\"APP\";\n    \
+              p0_0 [label=\"READ\"];\n    \
+              p0_1 [label=\"WRITE\", style=filled, fillcolor=palegreen];\n    \
+              subgraph cluster_1 {\n      \
+                label=\"billing\";\n      \
+                p1_0 [label=\"PAY\"];\n    \
+              }\n  \
+            }\n\
+            }\n";
+
+        assert_eq!(scope.to_dot(), expected);
+    }
+
+    #[test]
+    fn test_to_mermaid() {
+        let scope = get_diagram_scope();
+
+        let expected = "flowchart TD\n  \
+            s0[\"APP\"]\n  \
+            s0 --> s0_p0(\"READ\")\n  \
+            s0 --> s0_p1(\"WRITE\"):::granted\n  \
+            s1[\"billing\"]\n  \
+            s0 --> s1\n  \
+            s1 --> s1_p0(\"PAY\")\n  \
+            classDef granted fill:#9f9,stroke:#393\n";
+
+        assert_eq!(scope.to_mermaid(), expected);
+        assert_eq!(escape_mermaid("say \"hi\""), "say #quot;hi#quot;");
+    }
+
+    #[test]
+    fn test_escape_dot() {
+        assert_eq!(escape_dot("say \"hi\" \\o/"), "say \\\"hi\\\" \\\\o/");
+    }
+}
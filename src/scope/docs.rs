@@ -0,0 +1,131 @@
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt::Write;
+use crate::common::error::ErrorKind;
+use crate::permission::Permission;
+use crate::scope::Scope;
+
+impl Scope {
+    /** Attach a human readable description to a permission, replacing any previous one. */
+    pub fn describe(&mut self, permission: &str, description: &str) -> Result<&mut Scope, ErrorKind> {
+        let permission = self.find_permission_name(permission)?;
+
+        if let Some(permission) = self.permissions.get_mut(&permission) {
+            permission.description = Some(description.to_string());
+        }
+
+        self.touch();
+        return Ok(self);
+    }
+
+    /** Attach a tag to a permission. Adding a tag the permission already carries has no effect. */
+    pub fn tag(&mut self, permission: &str, tag: &str) -> Result<&mut Scope, ErrorKind> {
+        let permission = self.find_permission_name(permission)?;
+
+        if let Some(permission) = self.permissions.get_mut(&permission) {
+            if !permission.tags.iter().any(|existing| existing == tag) {
+                permission.tags.push(tag.to_string());
+            }
+        }
+
+        self.touch();
+        return Ok(self);
+    }
+
+    /**
+        Generate Markdown documentation for this scope and all of its child scopes, with one table per scope
+        listing each permission's bit, value, description and tags. Scopes are written depth-first with
+        child scopes and permissions in a stable order, so the output can be committed and diffed.
+     */
+    pub fn to_markdown(&self) -> String {
+        let mut markdown = String::new();
+        let mut stack: Vec<&Scope> = vec![self];
+
+        while let Some(scope) = stack.pop() {
+            if !markdown.is_empty() {
+                markdown.push('\n');
+            }
+            scope.write_markdown_table(&mut markdown);
+
+            // push in reverse so that children are written in name order
+            let mut children: Vec<&Scope> = scope.scopes.values().collect();
+            children.sort_by(|a, b| b.name.cmp(&a.name));
+            stack.extend(children);
+        }
+
+        return markdown;
+    }
+
+    /** Write the heading and permission table of only this scope. */
+    fn write_markdown_table(&self, markdown: &mut String) {
+        let _ = writeln!(markdown, "## {}\n", self.path);
+
+        if self.permissions.is_empty() {
+            markdown.push_str("No permissions are defined in this scope.\n");
+            return;
+        }
+
+        let mut permissions: Vec<&Permission> = self.permissions.values().collect();
+        permissions.sort_by_key(|permission| permission.value);
+
+        markdown.push_str("| Permission | Bit | Value | Description | Tags |\n");
+        markdown.push_str("| --- | --- | --- | --- | --- |\n");
+        for permission in permissions {
+            let _ = writeln!(
+                markdown,
+                "| {} | {} | {} | {} | {} |",
+                escape_cell(&permission.name),
+                permission.value.trailing_zeros(),
+                permission.value,
+                escape_cell(permission.description.as_deref().unwrap_or("")),
+                escape_cell(&permission.tags.join(", "))
+            );
+        }
+    }
+}
+
+/** Escape characters that would break out of a Markdown table cell. */
+fn escape_cell(text: &str) -> String {
+    return text.replace('|', "\\|").replace('\n', " ");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_to_markdown() {
+        let mut scope = Scope::new("APP");
+
+        if let Err(_) = scope
+            .add_permission("READ")
+            .and_then(|sc| sc.add_permission("WRITE"))
+            .and_then(|sc| sc.describe("READ", "View records | list them"))
+            .and_then(|sc| sc.tag("READ", "core"))
+            .and_then(|sc| sc.tag("READ", "api"))
+            .and_then(|sc| sc.tag("READ", "core"))
+            .and_then(|sc| sc.add_scope("billing"))
+            .and_then(|sc| sc.add_scope("audit")) {
+            assert!(false);
+        }
+
+        let expected = "## APP\n\n\
+            | Permission | Bit | Value | Description | Tags |\n\
+            | --- | --- | --- | --- | --- |\n\
+            | READ | 0 | 1 | View records \\| list them | core, api |\n\
+            | WRITE | 1 | 2 |  |  |\n\
+            \n## APP.audit\n\nNo permissions are defined in this scope.\n\
+            \n## APP.billing\n\nNo permissions are defined in this scope.\n";
+
+        assert_eq!(scope.to_markdown(), expected);
+    }
+
+    #[test]
+    fn test_describe_unknown_permission() {
+        let mut scope = Scope::new("APP");
+
+        assert_eq!(scope.describe("READ", "View records").is_err(), true);
+        assert_eq!(scope.tag("READ", "core").is_err(), true);
+    }
+}
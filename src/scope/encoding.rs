@@ -0,0 +1,232 @@
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+use crate::common::error::ErrorKind;
+use crate::scope::error::{ScopeError, ScopeErrorCase};
+use crate::scope::guard::MutationContext;
+use crate::scope::path::ScopePath;
+use crate::scope::Scope;
+
+/** Separator placed between the encoded values of each scope when a whole tree is encoded. */
+pub const VALUE_SEPARATOR: char = '-';
+
+const BASE62_ALPHABET: &[u8; 62] = b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz";
+const BASE36_ALPHABET: &[u8; 36] = b"0123456789abcdefghijklmnopqrstuvwxyz";
+
+/** Radix selects the alphabet used to encode values compactly. */
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Radix {
+    /** Digits and lowercase letters, for case-insensitive contexts such as support tickets. */
+    Base36,
+    /** Digits, uppercase and lowercase letters, for the shortest URL-safe strings. */
+    Base62
+}
+
+impl Radix {
+    fn alphabet(&self) -> &'static [u8] {
+        return match self {
+            Radix::Base36 => BASE36_ALPHABET,
+            Radix::Base62 => BASE62_ALPHABET
+        }
+    }
+}
+
+/** Encode a value in the given radix. Zero encodes as `0`. */
+pub fn encode_u64(value: u64, radix: Radix) -> String {
+    let alphabet = radix.alphabet();
+    let base = alphabet.len() as u64;
+    let mut digits: Vec<u8> = vec![];
+    let mut remaining = value;
+
+    loop {
+        digits.push(alphabet[(remaining % base) as usize]);
+        remaining /= base;
+        if remaining == 0 {
+            break;
+        }
+    }
+    digits.reverse();
+
+    return digits.into_iter().map(char::from).collect();
+}
+
+/**
+    Decode a value encoded with `encode_u64`. Decoding is strict so that every value has exactly one encoding:
+    empty strings, characters outside the alphabet, leading zeros and values overflowing a u64 are rejected.
+ */
+pub fn decode_u64(text: &str, radix: Radix) -> Result<u64, ErrorKind> {
+    let invalid = || ErrorKind::ScopeError(ScopeError::new(ScopeErrorCase::InvalidEncoding, text));
+    let alphabet = radix.alphabet();
+
+    if text.is_empty() || (text.len() > 1 && text.starts_with('0')) {
+        return Err(invalid());
+    }
+
+    let mut value: u64 = 0;
+    for byte in text.bytes() {
+        let digit = alphabet.iter().position(|candidate| *candidate == byte).ok_or_else(invalid)?;
+        value = value.checked_mul(alphabet.len() as u64)
+            .and_then(|value| value.checked_add(digit as u64))
+            .ok_or_else(invalid)?;
+    }
+
+    return Ok(value);
+}
+
+impl Scope {
+    /** Encode the value of this scope alone in the given radix. */
+    pub fn encode_value(&self, radix: Radix) -> String {
+        return encode_u64(self.as_u64(), radix);
+    }
+
+    /**
+        Encode the grant state of the whole tree as the values of every scope, depth-first with child scopes in
+        name order, joined by `-`. The string can only be decoded against a tree with the same structure.
+     */
+    pub fn encode_values(&self, radix: Radix) -> String {
+        return self.scopes_in_encoding_order().into_iter()
+            .map(|scope| scope.encode_value(radix))
+            .collect::<Vec<String>>()
+            .join(&VALUE_SEPARATOR.to_string());
+    }
+
+    /**
+        Restore the grant state of the whole tree from a string produced by `encode_values`. Nothing is changed
+        unless the string holds one valid value per scope and no value sets bits without a permission.
+     */
+    pub fn apply_encoded_values(&mut self, text: &str, radix: Radix) -> Result<&mut Scope, ErrorKind> {
+        let values = text.split(VALUE_SEPARATOR)
+            .map(|value| decode_u64(value, radix))
+            .collect::<Result<Vec<u64>, ErrorKind>>()?;
+
+        self.apply_values_in_encoding_order(&values, text)?;
+
+        return Ok(self);
+    }
+
+    /**
+        Apply one value per scope, in the order of `scopes_in_encoding_order`, validating all of them and checking
+        them against the mutation guards first. `source` names the input in the error returned when the number of
+        values is wrong.
+     */
+    pub(crate) fn apply_values_in_encoding_order(&mut self, values: &[u64], source: &str) -> Result<(), ErrorKind> {
+        let scopes = self.scopes_in_encoding_order();
+        if values.len() != scopes.len() {
+            return Err(ErrorKind::ScopeError(ScopeError::new(ScopeErrorCase::InvalidEncoding, source)));
+        }
+
+        // validate every value before applying any of them
+        for (scope, value) in scopes.iter().zip(values) {
+            if value & !scope.defined_bits() != 0 {
+                return Err(ErrorKind::ScopeError(ScopeError::new(ScopeErrorCase::UndefinedBits, &scope.path.to_string())));
+            }
+            scope.check_guards_for_value(*value, &MutationContext::new())?;
+        }
+
+        let paths: Vec<ScopePath> = scopes.iter().map(|scope| scope.path.relative_to(&self.path)).collect();
+        for (path, value) in paths.iter().zip(values) {
+            self.resolve_scope_mut(path)?.apply_u64_unguarded(*value)?;
+        }
+
+        return Ok(());
+    }
+
+    /** Get the number of bits handed out to permissions of this scope so far, including those of removed permissions. */
+    pub(crate) fn assigned_width(&self) -> usize {
+        return self.next_permission_shift as usize;
+    }
+
+    /** List this scope and every child scope depth-first, with child scopes in name order. */
+    pub(crate) fn scopes_in_encoding_order(&self) -> Vec<&Scope> {
+        let mut ordered: Vec<&Scope> = vec![];
+        let mut stack: Vec<&Scope> = vec![self];
+
+        while let Some(scope) = stack.pop() {
+            ordered.push(scope);
+
+            let mut children: Vec<&Scope> = scope.scopes.values().collect();
+            children.sort_by(|a, b| b.name.cmp(&a.name));
+            stack.extend(children);
+        }
+
+        return ordered;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode_round_trip() {
+        for value in [0u64, 1, 35, 36, 61, 62, 9007199254740991, u64::MAX] {
+            for radix in [Radix::Base36, Radix::Base62] {
+                assert_eq!(decode_u64(&encode_u64(value, radix), radix).ok(), Some(value));
+            }
+        }
+
+        assert_eq!(encode_u64(61, Radix::Base62), "z");
+        assert_eq!(encode_u64(62, Radix::Base62), "10");
+        assert_eq!(encode_u64(35, Radix::Base36), "z");
+        assert_eq!(encode_u64(u64::MAX, Radix::Base62), "LygHa16AHYF");
+    }
+
+    #[test]
+    fn test_decode_is_strict() {
+        for text in ["", "00", "01", "A", "z!", "3w5e11264sgsg"] {
+            assert_eq!(decode_u64(text, Radix::Base36).is_err(), true);
+        }
+        // one past u64::MAX
+        assert_eq!(decode_u64("LygHa16AHYG", Radix::Base62).is_err(), true);
+    }
+
+    fn get_encoded_scope() -> Scope {
+        let mut scope = Scope::new("APP");
+
+        if let Err(_) = scope
+            .add_permission("READ")
+            .and_then(|sc| sc.add_permission("WRITE"))
+            .and_then(|sc| sc.grant("WRITE"))
+            .and_then(|sc| sc.add_scope("billing"))
+            .and_then(|sc| sc.add_scope("audit")) {
+            assert!(false);
+        }
+        match scope.scope("billing") {
+            Some(billing) => {
+                if let Err(_) = billing.add_permission("VIEW").and_then(|sc| sc.grant("VIEW")) {
+                    assert!(false);
+                }
+            },
+            None => assert!(false)
+        }
+
+        return scope;
+    }
+
+    #[test]
+    fn test_tree_values_round_trip() {
+        let scope = get_encoded_scope();
+        let encoded = scope.encode_values(Radix::Base62);
+        assert_eq!(encoded, "2-0-1");
+
+        let mut target = get_encoded_scope();
+        if let Err(_) = target.revoke("WRITE") {
+            assert!(false);
+        }
+        if let Err(_) = target.apply_encoded_values(&encoded, Radix::Base62) {
+            assert!(false);
+        }
+        assert_eq!(target.encode_values(Radix::Base62), encoded);
+        assert_eq!(target.check("WRITE"), true);
+    }
+
+    #[test]
+    fn test_apply_rejects_mismatched_values() {
+        let mut scope = get_encoded_scope();
+
+        for text in ["2-0", "2-0-1-0", "2-1-1", "2-0-01"] {
+            assert_eq!(scope.apply_encoded_values(text, Radix::Base62).is_err(), true);
+        }
+        assert_eq!(scope.encode_values(Radix::Base62), "2-0-1");
+    }
+}
@@ -1,15 +1,103 @@
-use std::fmt;
-use std::fmt::{Debug, Display, Formatter};
+use alloc::boxed::Box;
+use alloc::format;
+use alloc::string::{String, ToString};
+use core::fmt;
+use core::fmt::{Debug, Display, Formatter};
 
 pub struct ScopeError {
     name: String,
     case: ScopeErrorCase,
+    // boxed so that results carrying a ScopeError stay small however much metadata is added
+    metadata: Box<ScopeErrorMetadata>
 }
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum ScopeErrorCase {
     PermissionExists,
     ScopeExists,
-    BothExist
+    BothExist,
+    InvalidName,
+    MaxDepth,
+    UnknownPermission,
+    UnknownScope,
+    RequirementNotMet,
+    MutuallyExclusive,
+    Deprecated,
+    StaleRevision,
+    UndefinedBits,
+    InvalidEncoding,
+    SchemaMismatch,
+    InvalidToken,
+    UnknownKey,
+    InvalidPattern,
+    DuplicateId,
+    VirtualPermission,
+    GuardRejected,
+    NotHeld,
+    UnknownRole,
+    RoleCycle,
+    UnknownBundle,
+    DuplicateShift,
+    EmptyComposite,
+    Referenced
+}
+
+pub struct ScopeErrorMetadata {
+    pub(crate) related: Option<String>,
+    pub(crate) expected_revision: Option<u64>,
+    pub(crate) revision: Option<u64>,
+    pub(crate) expected_fingerprint: Option<u64>,
+    pub(crate) fingerprint: Option<u64>,
+    pub(crate) reason: Option<String>
+}
+
+impl ScopeErrorMetadata {
+    pub fn new() -> ScopeErrorMetadata {
+        return ScopeErrorMetadata {
+            related: None,
+            expected_revision: None,
+            revision: None,
+            expected_fingerprint: None,
+            fingerprint: None,
+            reason: None
+        }
+    }
+
+    /** The name of another permission or scope involved in the error, if any. */
+    pub fn related(&self) -> Option<&str> {
+        return self.related.as_deref();
+    }
+
+    /** The revision a change was prepared against, for stale revision errors. */
+    pub fn expected_revision(&self) -> Option<u64> {
+        return self.expected_revision;
+    }
+
+    /** The revision the scope was actually at, for stale revision errors. */
+    pub fn revision(&self) -> Option<u64> {
+        return self.revision;
+    }
+
+    /** The schema fingerprint a value was produced under, for schema mismatch errors. */
+    pub fn expected_fingerprint(&self) -> Option<u64> {
+        return self.expected_fingerprint;
+    }
+
+    /** The fingerprint of the schema the value was applied to, for schema mismatch errors. */
+    pub fn fingerprint(&self) -> Option<u64> {
+        return self.fingerprint;
+    }
+
+    /** The reason given by a mutation guard for rejecting a change. */
+    pub fn reason(&self) -> Option<&str> {
+        return self.reason.as_deref();
+    }
+}
+
+impl Default for ScopeErrorMetadata {
+    fn default() -> Self {
+        ScopeErrorMetadata::new()
+    }
 }
 
 const ERROR_NAME: &str = "ScopeError";
@@ -17,21 +105,105 @@ const ERROR_NAME: &str = "ScopeError";
 const UNIQUE_NAME_ERROR_PERMISSION_EXISTS: &str = "is already defined within permissions";
 const UNIQUE_NAME_ERROR_SCOPE_EXISTS: &str = "is already defined within scope";
 const UNIQUE_NAME_ERROR_BOTH_EXIST: &str = "is already defined within permissions and scope";
+const MAX_DEPTH_ERROR: &str = "is nested deeper than the maximum depth allowed for child scopes";
+const UNKNOWN_PERMISSION_ERROR: &str = "is not defined within permissions";
+const UNKNOWN_SCOPE_ERROR: &str = "is not defined within scopes";
+const UNDEFINED_BITS_ERROR: &str = "sets bits that are not assigned to any permission";
+const INVALID_ENCODING_ERROR: &str = "is not a valid encoded value";
+const INVALID_TOKEN_ERROR: &str = "token was rejected";
+const UNKNOWN_KEY_ERROR: &str = "is not in the keyring";
+const INVALID_PATTERN_ERROR: &str = "is not a valid pattern";
+const VIRTUAL_PERMISSION_ERROR: &str = "is computed from other grants and cannot be granted or revoked";
+const NOT_HELD_ERROR: &str = "cannot be delegated because it is not held by the delegator";
+const UNKNOWN_ROLE_ERROR: &str = "is not defined within roles";
+const UNKNOWN_BUNDLE_ERROR: &str = "is not defined within bundles";
+const DUPLICATE_SHIFT_ERROR: &str = "is placed on a bit already assigned to another permission";
+const EMPTY_COMPOSITE_ERROR: &str = "is declared as a composite of no permissions";
+const REFERENCED_ERROR: &str = "is referenced from an enclosing scope and can only be renamed through a path from that scope";
+const INVALID_NAME_ERROR: &str = "is empty or contains characters rejected by the validation policy (path separators, whitespace or control characters)";
 
 impl ScopeError {
-    pub fn new(case: ScopeErrorCase, name: &String) -> ScopeError {
+    pub fn new(case: ScopeErrorCase, name: &str) -> ScopeError {
+        return ScopeError::with_metadata(case, name, ScopeErrorMetadata::new());
+    }
+
+    pub fn with_metadata(case: ScopeErrorCase, name: &str, metadata: ScopeErrorMetadata) -> ScopeError {
         return ScopeError {
-            name: name.clone(),
-            case
+            name: name.to_string(),
+            case,
+            metadata: Box::new(metadata)
         };
     }
+
+    pub fn name(&self) -> &str {
+        return &self.name;
+    }
+
+    pub fn case(&self) -> ScopeErrorCase {
+        return self.case;
+    }
+
+    pub fn metadata(&self) -> &ScopeErrorMetadata {
+        return &self.metadata;
+    }
 }
 
-fn format_error_message(f: &mut Formatter<'_>, case: &ScopeErrorCase, name: &String) -> fmt::Result {
+fn format_error_message(f: &mut Formatter<'_>, case: &ScopeErrorCase, name: &str, metadata: &ScopeErrorMetadata) -> fmt::Result {
     let err: String = match *case {
         ScopeErrorCase::PermissionExists => format!("{}: name '{}' {}", ERROR_NAME, name, UNIQUE_NAME_ERROR_PERMISSION_EXISTS),
         ScopeErrorCase::ScopeExists => format!("{}: name '{}' {}", ERROR_NAME, name, UNIQUE_NAME_ERROR_SCOPE_EXISTS),
         ScopeErrorCase::BothExist => format!("{}: name '{}' {}", ERROR_NAME, name, UNIQUE_NAME_ERROR_BOTH_EXIST),
+        ScopeErrorCase::InvalidName => format!("{}: name '{}' {}", ERROR_NAME, name, INVALID_NAME_ERROR),
+        ScopeErrorCase::MaxDepth => format!("{}: scope '{}' {}", ERROR_NAME, name, MAX_DEPTH_ERROR),
+        ScopeErrorCase::UnknownPermission => format!("{}: name '{}' {}", ERROR_NAME, name, UNKNOWN_PERMISSION_ERROR),
+        ScopeErrorCase::UnknownScope => format!("{}: path '{}' {}", ERROR_NAME, name, UNKNOWN_SCOPE_ERROR),
+        ScopeErrorCase::RequirementNotMet => match &metadata.related {
+            Some(required) => format!("{}: permission '{}' cannot be granted because it requires '{}', which has not been granted.", ERROR_NAME, name, required),
+            None => format!("{}: permission '{}' cannot be granted because one of its requirements has not been granted.", ERROR_NAME, name),
+        },
+        ScopeErrorCase::MutuallyExclusive => match &metadata.related {
+            Some(excluded) => format!("{}: permission '{}' cannot be granted because it is mutually exclusive with '{}'.", ERROR_NAME, name, excluded),
+            None => format!("{}: permission '{}' cannot be granted because it is mutually exclusive with a held permission.", ERROR_NAME, name),
+        },
+        ScopeErrorCase::Deprecated => match &metadata.related {
+            Some(replacement) => format!("{}: permission '{}' cannot be granted because it is deprecated; use '{}' instead.", ERROR_NAME, name, replacement),
+            None => format!("{}: permission '{}' cannot be granted because it is deprecated.", ERROR_NAME, name),
+        },
+        ScopeErrorCase::UndefinedBits => format!("{}: value for scope '{}' {}", ERROR_NAME, name, UNDEFINED_BITS_ERROR),
+        ScopeErrorCase::InvalidEncoding => format!("{}: '{}' {}", ERROR_NAME, name, INVALID_ENCODING_ERROR),
+        ScopeErrorCase::InvalidToken => format!("{}: {}: {}.", ERROR_NAME, INVALID_TOKEN_ERROR, name),
+        ScopeErrorCase::DuplicateId => match &metadata.related {
+            Some(holder) => format!("{}: id '{}' is already assigned to permission '{}'.", ERROR_NAME, name, holder),
+            None => format!("{}: id '{}' is already assigned to another permission.", ERROR_NAME, name),
+        },
+        ScopeErrorCase::InvalidPattern => format!("{}: '{}' {}", ERROR_NAME, name, INVALID_PATTERN_ERROR),
+        ScopeErrorCase::VirtualPermission => format!("{}: permission '{}' {}", ERROR_NAME, name, VIRTUAL_PERMISSION_ERROR),
+        ScopeErrorCase::GuardRejected => match (&metadata.related, &metadata.reason) {
+            (Some(guard), Some(reason)) => format!("{}: change to '{}' was rejected by guard '{}': {}", ERROR_NAME, name, guard, reason),
+            _ => format!("{}: change to '{}' was rejected by a mutation guard.", ERROR_NAME, name),
+        },
+        ScopeErrorCase::NotHeld => format!("{}: permission '{}' {}", ERROR_NAME, name, NOT_HELD_ERROR),
+        ScopeErrorCase::UnknownRole => format!("{}: role '{}' {}", ERROR_NAME, name, UNKNOWN_ROLE_ERROR),
+        ScopeErrorCase::RoleCycle => match &metadata.related {
+            Some(extended) => format!("{}: role '{}' cannot extend '{}' because it would extend itself through it.", ERROR_NAME, name, extended),
+            None => format!("{}: role '{}' cannot extend a role that extends it.", ERROR_NAME, name),
+        },
+        ScopeErrorCase::UnknownBundle => format!("{}: bundle '{}' {}", ERROR_NAME, name, UNKNOWN_BUNDLE_ERROR),
+        ScopeErrorCase::DuplicateShift => format!("{}: permission '{}' {}", ERROR_NAME, name, DUPLICATE_SHIFT_ERROR),
+        ScopeErrorCase::EmptyComposite => format!("{}: permission '{}' {}", ERROR_NAME, name, EMPTY_COMPOSITE_ERROR),
+        ScopeErrorCase::Referenced => format!("{}: permission '{}' {}", ERROR_NAME, name, REFERENCED_ERROR),
+        ScopeErrorCase::UnknownKey => match name {
+            "" => format!("{}: the keyring has no active key.", ERROR_NAME),
+            _ => format!("{}: key '{}' {}", ERROR_NAME, name, UNKNOWN_KEY_ERROR),
+        },
+        ScopeErrorCase::SchemaMismatch => match (metadata.expected_fingerprint, metadata.fingerprint) {
+            (Some(expected), Some(fingerprint)) => format!("{}: scope '{}' has schema fingerprint {:016x}, but the value was produced under schema {:016x}.", ERROR_NAME, name, fingerprint, expected),
+            _ => format!("{}: scope '{}' does not match the schema the value was produced under.", ERROR_NAME, name),
+        },
+        ScopeErrorCase::StaleRevision => match (metadata.expected_revision, metadata.revision) {
+            (Some(expected), Some(revision)) => format!("{}: scope '{}' is at revision {}, but the change was prepared against revision {}.", ERROR_NAME, name, revision, expected),
+            _ => format!("{}: scope '{}' has changed since the change was prepared.", ERROR_NAME, name),
+        },
     };
 
     write!(f, "{}", err)
@@ -39,14 +211,14 @@ fn format_error_message(f: &mut Formatter<'_>, case: &ScopeErrorCase, name: &Str
 
 impl Debug for ScopeError {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
-        format_error_message(f, &self.case, &self.name)
+        format_error_message(f, &self.case, &self.name, &self.metadata)
     }
 }
 
 impl Display for ScopeError {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
-        format_error_message(f, &self.case, &self.name)
+        format_error_message(f, &self.case, &self.name, &self.metadata)
     }
 }
 
-impl std::error::Error for ScopeError {}
+impl core::error::Error for ScopeError {}
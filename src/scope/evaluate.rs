@@ -0,0 +1,365 @@
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+use crate::common::error::ErrorKind;
+use crate::common::telemetry;
+use crate::scope::error::{ScopeError, ScopeErrorCase};
+use crate::scope::path::PermissionPath;
+use crate::scope::Scope;
+
+impl Scope {
+    /**
+        Get the mask of bits that satisfy the permission at `path` (relative to this scope): the permission's own
+        bit and the bit of every permission that implies it, leaving out permissions denied in this tree. The mask of
+        a denied permission is 0. A value of the permission's scope is allowed when it shares any bit with the mask;
+        for a permission that spilled into a continuation, that is the value of the continuation. For a composite
+        permission the mask is the union of the masks of its members, and a value must share a bit with the mask
+        of every member, which `check_value` takes care of. Fails with UnknownScope or UnknownPermission if the path
+        does not exist.
+     */
+    pub fn grant_mask(&self, path: &str) -> Result<u64, ErrorKind> {
+        return self.resolve_masks(path).map(|(_, masks)| masks.into_iter().fold(0, |mask, member| mask | member));
+    }
+
+    /**
+        Find the scope defining the permission at `path`, or the continuation it spilled into, together with the
+        masks a value must share a bit with: the permission's grant mask, or one per member of a composite.
+     */
+    fn resolve_masks(&self, path: &str) -> Result<(&Scope, Vec<u64>), ErrorKind> {
+        let requested = PermissionPath::parse(path);
+        let scope = match self.find_scope(&requested.scope) {
+            Some(scope) => scope,
+            None => return Err(ErrorKind::ScopeError(ScopeError::new(ScopeErrorCase::UnknownScope, &requested.scope.to_string())))
+        };
+
+        let name = scope.resolve_name(&requested.permission);
+        if let Some(members) = scope.composites.get(&name) {
+            return Ok((scope, members.iter().map(|member| scope.implied_mask(member)).collect()));
+        }
+
+        let scope = scope.continuation_holding(&name).unwrap_or(scope);
+        if !scope.permissions.contains_key(&name) {
+            return Err(ErrorKind::ScopeError(ScopeError::new(ScopeErrorCase::UnknownPermission, &requested.permission)));
+        }
+
+        return Ok((scope, vec![scope.implied_mask(&name)]));
+    }
+
+    /** Get the bits of a permission of this scope and of every permission implying it, without denied ones. */
+    fn implied_mask(&self, name: &str) -> u64 {
+        let Some(permission) = self.permissions.get(name) else {
+            return 0;
+        };
+
+        // conditions need a context, so conditioned permissions satisfy nothing, as in `check`
+        let mask = self.permissions.values()
+            .filter(|other| !self.is_conditioned(&other.name))
+            .filter(|other| self.implies_through(&other.name, name, |implied| !self.is_conditioned(implied)))
+            .fold(permission.value, |mask, other| mask | other.value);
+
+        // denial overrides grants and implications, as in `check`
+        return match permission.denied || self.is_conditioned(name) {
+            true => 0,
+            false => mask & !self.as_deny_u64()
+        }
+    }
+
+    /**
+        Check the permission at `path` against many stored values of the scope that defines it, resolving the path
+        once and testing each value with a single AND. As with `check`, an unknown path denies every value.
+     */
+    pub fn check_many(&self, path: &str, values: &[u64]) -> Vec<bool> {
+        let masks = self.resolve_masks(path).map(|(_, masks)| masks).unwrap_or_default();
+        let results: Vec<bool> = values.iter().map(|value| satisfies(*value, &masks)).collect();
+
+        let allowed = results.iter().filter(|allowed| **allowed).count() as u64;
+        telemetry::record_checks(allowed, results.len() as u64 - allowed);
+
+        return results;
+    }
+}
+
+/**
+    PermissionMatrix holds the result of `evaluate_matrix`: one row per principal and one column per path, in the
+    order they were given.
+ */
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PermissionMatrix {
+    principals: Vec<String>,
+    paths: Vec<String>,
+    cells: Vec<bool>
+}
+
+impl PermissionMatrix {
+    pub fn principals(&self) -> &[String] {
+        return &self.principals;
+    }
+
+    pub fn paths(&self) -> &[String] {
+        return &self.paths;
+    }
+
+    /** Get the results for one principal, one per path. */
+    pub fn row(&self, row: usize) -> Option<&[bool]> {
+        let width = self.paths.len();
+        if row >= self.principals.len() {
+            return None;
+        }
+
+        return Some(&self.cells[row * width..(row + 1) * width]);
+    }
+
+    /** Get the result for one principal and path by their positions. */
+    pub fn get(&self, row: usize, column: usize) -> Option<bool> {
+        if column >= self.paths.len() {
+            return None;
+        }
+
+        return self.row(row).map(|cells| cells[column]);
+    }
+
+    /** Get the result for one principal and path by their IDs. */
+    pub fn allowed(&self, principal: &str, path: &str) -> Option<bool> {
+        let row = self.principals.iter().position(|id| id == principal)?;
+        let column = self.paths.iter().position(|other| other == path)?;
+
+        return self.get(row, column);
+    }
+}
+
+/**
+    Evaluate every path for every principal, for "who can do what" tables. Each principal supplies the value of
+    every scope of `schema` in the order of `encode_values` (depth-first, child scopes in name order), as carried
+    by grant tokens. Paths are resolved to masks once, so each cell costs a single AND.
+ */
+pub fn evaluate_matrix(schema: &Scope, principals: &[(&str, &[u64])], paths: &[&str]) -> Result<PermissionMatrix, ErrorKind> {
+    let scopes = schema.scopes_in_encoding_order();
+
+    let mut columns: Vec<(usize, Vec<u64>)> = Vec::with_capacity(paths.len());
+    for path in paths {
+        let (scope, masks) = schema.resolve_masks(path)?;
+        let index = scopes.iter()
+            .position(|candidate| core::ptr::eq(*candidate, scope))
+            .expect("every resolved scope is in encoding order");
+        columns.push((index, masks));
+    }
+
+    let mut cells: Vec<bool> = Vec::with_capacity(principals.len() * paths.len());
+    for (id, values) in principals {
+        if values.len() != scopes.len() {
+            return Err(ErrorKind::ScopeError(ScopeError::new(ScopeErrorCase::InvalidEncoding, id)));
+        }
+        cells.extend(columns.iter().map(|(index, masks)| satisfies(values[*index], masks)));
+    }
+
+    return Ok(PermissionMatrix {
+        principals: principals.iter().map(|(id, _)| id.to_string()).collect(),
+        paths: paths.iter().map(|path| path.to_string()).collect(),
+        cells
+    });
+}
+
+/**
+    Check the permission at `path` against a stored value without building a granted scope. `schema` only supplies
+    the permission definitions; `value` is the value of the scope that defines the permission, as produced by
+    `as_u64`, or the word of its continuation from `as_words` if the permission spilled over. Implications are
+    honoured as in `Scope::check`, but unknown paths are errors rather than denials.
+ */
+pub fn check_value(schema: &Scope, path: &str, value: u64) -> Result<bool, ErrorKind> {
+    let (_, masks) = schema.resolve_masks(path)?;
+    let allowed = satisfies(value, &masks);
+    telemetry::record_check(allowed);
+
+    return Ok(allowed);
+}
+
+/**
+    Check several permissions of the same scope against one stored value, in the order given. Every path is
+    resolved before any is checked, so a single unknown path fails the whole batch.
+ */
+pub fn check_values(schema: &Scope, paths: &[&str], value: u64) -> Result<Vec<bool>, ErrorKind> {
+    let masks = paths.iter()
+        .map(|path| schema.resolve_masks(path).map(|(_, masks)| masks))
+        .collect::<Result<Vec<Vec<u64>>, ErrorKind>>()?;
+
+    return Ok(masks.into_iter()
+        .map(|masks| {
+            let allowed = satisfies(value, &masks);
+            telemetry::record_check(allowed);
+            allowed
+        })
+        .collect());
+}
+
+/** Check whether a value shares a bit with every mask, so a composite needs all of its members. */
+fn satisfies(value: u64, masks: &[u64]) -> bool {
+    return !masks.is_empty() && masks.iter().all(|mask| value & mask != 0);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get_schema() -> Scope {
+        let mut scope = Scope::new("APP");
+
+        if let Err(_) = scope
+            .add_permission("READ")
+            .and_then(|sc| sc.add_permission("WRITE"))
+            .and_then(|sc| sc.add_permission("ADMIN"))
+            .and_then(|sc| sc.add_implication("ADMIN", "WRITE"))
+            .and_then(|sc| sc.add_scope("billing")) {
+            assert!(false);
+        }
+
+        match scope.scope("billing") {
+            Some(billing) => {
+                if let Err(_) = billing.add_permission("VIEW").and_then(|sc| sc.add_permission("REFUND")) {
+                    assert!(false);
+                }
+            },
+            None => assert!(false)
+        }
+
+        return scope;
+    }
+
+    #[test]
+    fn test_check_value() {
+        let schema = get_schema();
+
+        assert_eq!(check_value(&schema, "READ", 0b001).ok(), Some(true));
+        assert_eq!(check_value(&schema, "WRITE", 0b001).ok(), Some(false));
+        // ADMIN implies WRITE
+        assert_eq!(check_value(&schema, "WRITE", 0b100).ok(), Some(true));
+        assert_eq!(check_value(&schema, "billing:REFUND", 0b10).ok(), Some(true));
+        assert_eq!(check_value(&schema, "billing:VIEW", 0b10).ok(), Some(false));
+
+        // the schema itself is never consulted for grants
+        assert_eq!(schema.check("READ"), false);
+    }
+
+    #[test]
+    fn test_check_value_honours_denials() {
+        let mut schema = get_schema();
+        if let Err(_) = schema.add_implication("ADMIN", "READ").and_then(|sc| sc.deny("READ")) {
+            assert!(false);
+        }
+
+        let mut scope = schema.clone();
+        if let Err(_) = scope.grant("ADMIN") {
+            assert!(false);
+        }
+        assert_eq!(scope.check("READ"), false);
+        assert_eq!(check_value(&schema, "READ", scope.as_u64()).ok(), Some(false));
+        assert_eq!(check_value(&schema, "WRITE", scope.as_u64()).ok(), Some(true));
+        assert_eq!(schema.grant_mask("READ").ok(), Some(0));
+
+        // a denied implying permission no longer satisfies what it implies
+        if let Err(_) = schema.clear_denial("READ").and_then(|sc| sc.deny("ADMIN")) {
+            assert!(false);
+        }
+        assert_eq!(check_value(&schema, "WRITE", 0b100).ok(), Some(false));
+        assert_eq!(check_value(&schema, "READ", 0b101).ok(), Some(true));
+    }
+
+    #[test]
+    fn test_check_value_composites() {
+        let mut schema = get_schema();
+        if let Err(_) = schema.add_composite("ALL", &["READ", "WRITE"]) {
+            assert!(false);
+        }
+
+        let mut scope = schema.clone();
+        if let Err(_) = scope.grant("ALL") {
+            assert!(false);
+        }
+        assert_eq!(scope.check("ALL"), true);
+        assert_eq!(check_value(&schema, "ALL", scope.as_u64()).ok(), Some(true));
+
+        // every member is needed, though ADMIN stands in for WRITE
+        assert_eq!(schema.grant_mask("ALL").ok(), Some(0b111));
+        assert_eq!(check_value(&schema, "ALL", 0b001).ok(), Some(false));
+        assert_eq!(check_value(&schema, "ALL", 0b101).ok(), Some(true));
+        assert_eq!(check_values(&schema, &["ALL", "READ"], 0b010).ok(), Some(vec![false, false]));
+        assert_eq!(schema.check_many("ALL", &[0b011, 0b110]), vec![true, false]);
+
+        match evaluate_matrix(&schema, &[("user", &[0b011, 0])], &["ALL"]) {
+            Ok(matrix) => assert_eq!(matrix.allowed("user", "ALL"), Some(true)),
+            Err(_) => assert!(false)
+        }
+    }
+
+    #[test]
+    fn test_check_value_unknown_path() {
+        let schema = get_schema();
+
+        match check_value(&schema, "DELETE", u64::MAX) {
+            Err(ErrorKind::ScopeError(err)) => assert_eq!(err.case(), ScopeErrorCase::UnknownPermission),
+            _ => assert!(false)
+        }
+        match check_value(&schema, "reports:VIEW", u64::MAX) {
+            Err(ErrorKind::ScopeError(err)) => assert_eq!(err.case(), ScopeErrorCase::UnknownScope),
+            _ => assert!(false)
+        }
+    }
+
+    #[test]
+    fn test_evaluate_matrix() {
+        let schema = get_schema();
+        // encoding order is APP, then billing
+        let principals: [(&str, &[u64]); 3] = [
+            ("alice", &[0b001, 0b00]),
+            ("bob", &[0b100, 0b10]),
+            ("carol", &[0b000, 0b01])
+        ];
+
+        match evaluate_matrix(&schema, &principals, &["READ", "WRITE", "billing:REFUND"]) {
+            Ok(matrix) => {
+                assert_eq!(matrix.row(0), Some(&[true, false, false][..]));
+                assert_eq!(matrix.row(1), Some(&[false, true, true][..]));
+                assert_eq!(matrix.row(2), Some(&[false, false, false][..]));
+                assert_eq!(matrix.row(3), None);
+                assert_eq!(matrix.allowed("bob", "WRITE"), Some(true));
+                assert_eq!(matrix.allowed("dave", "WRITE"), None);
+                assert_eq!(matrix.get(0, 3), None);
+            },
+            Err(_) => assert!(false)
+        }
+
+        let short: [(&str, &[u64]); 1] = [("alice", &[0b001])];
+        match evaluate_matrix(&schema, &short, &["READ"]) {
+            Err(ErrorKind::ScopeError(err)) => assert_eq!(err.case(), ScopeErrorCase::InvalidEncoding),
+            _ => assert!(false)
+        }
+    }
+
+    #[test]
+    fn test_check_many() {
+        let schema = get_schema();
+        let values: Vec<u64> = (0..1_000).map(|value| value % 8).collect();
+
+        let results = schema.check_many("WRITE", &values);
+        assert_eq!(results.len(), values.len());
+        for (value, allowed) in values.iter().zip(&results) {
+            // WRITE is bit 1, and ADMIN (bit 2) implies it
+            assert_eq!(*allowed, value & 0b110 != 0);
+        }
+
+        assert_eq!(schema.check_many("billing:VIEW", &[0b01, 0b10]), [true, false]);
+        assert_eq!(schema.check_many("DELETE", &[u64::MAX]), [false]);
+    }
+
+    #[test]
+    fn test_check_values() {
+        let schema = get_schema();
+
+        match check_values(&schema, &["READ", "WRITE", "ADMIN"], 0b100) {
+            Ok(results) => assert_eq!(results, [false, true, true]),
+            Err(_) => assert!(false)
+        }
+        if let Ok(_) = check_values(&schema, &["READ", "DELETE"], 0b001) {
+            assert!(false);
+        }
+    }
+}
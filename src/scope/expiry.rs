@@ -0,0 +1,149 @@
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use crate::audit::{AuditOp, AuditRecord};
+use crate::common::error::ErrorKind;
+use crate::scope::guard::MutationContext;
+use crate::scope::path::{PermissionPath, ScopePath};
+use crate::scope::Scope;
+
+/** Actor named in the audit records of grants revoked by `sweep_expired`. */
+pub const EXPIRY_ACTOR: &str = "bitperm:expiry";
+
+impl Scope {
+    /**
+        Grant a permission of this scope until `expires_at`, after which `sweep_expired` revokes it. Times use the
+        caller's clock, conventionally seconds since the Unix epoch like token validation. Granting a permission that
+        is already granted replaces its expiry, so a time-limited grant can be renewed; a plain `grant` or `revoke`
+        clears it. The expiry is carried through tuple and JSON exports.
+     */
+    pub fn grant_until(&mut self, name: &str, expires_at: u64) -> Result<&mut Scope, ErrorKind> {
+        let name = self.find_permission_name(name)?;
+        if !self.is_granted(&name) {
+            self.try_grant(&name, &MutationContext::new())?;
+        }
+
+        if let Some(permission) = self.permissions.get_mut(&name) {
+            permission.expires_at = Some(expires_at);
+        }
+        self.touch();
+
+        return Ok(self);
+    }
+
+    /** Get the time at which the grant of a permission of this scope lapses, if it was granted with `grant_until`. */
+    pub fn expires_at(&self, name: &str) -> Option<u64> {
+        return self.permissions.get(&self.resolve_name(name)).and_then(|permission| permission.expires_at);
+    }
+
+    /**
+        Revoke every time-limited grant of this scope and its child scopes that has expired at `now`, returning an
+        audit record for each, in the order of `encode_values`, with `EXPIRY_ACTOR` as the actor. Expired grants are
+        revoked even where a plain `revoke` would be refused, since the grant is no longer valid either way. Call it
+        from a timer to sweep in the background; the number of records is the number of grants cleaned up.
+     */
+    pub fn sweep_expired(&mut self, now: u64) -> Vec<AuditRecord> {
+        let mut expired: Vec<(ScopePath, Vec<String>)> = Vec::new();
+        for scope in self.scopes_in_encoding_order() {
+            let mut names: Vec<String> = scope.permissions.values()
+                .filter(|permission| permission.has() && permission.expires_at.is_some_and(|expires_at| expires_at <= now))
+                .map(|permission| permission.name.clone())
+                .collect();
+
+            if !names.is_empty() {
+                names.sort();
+                expired.push((scope.path.relative_to(&self.path), names));
+            }
+        }
+
+        let mut records: Vec<AuditRecord> = Vec::new();
+        for (path, names) in expired {
+            let is_root = path.is_empty();
+            let scope = match self.resolve_scope_mut(&path) {
+                Ok(scope) => scope,
+                Err(_) => continue
+            };
+
+            for name in names {
+                let before = scope.as_u64();
+                if let Some(permission) = scope.permissions.get_mut(&name) {
+                    permission.has_permission = false;
+                    permission.expires_at = None;
+                }
+
+                records.push(AuditRecord {
+                    timestamp: now,
+                    actor: EXPIRY_ACTOR.to_string(),
+                    op: AuditOp::Revoke,
+                    path: PermissionPath::new(scope.path.clone(), &name).to_string(),
+                    before,
+                    after: scope.as_u64()
+                });
+            }
+            if !is_root {
+                scope.touch();
+            }
+        }
+        if !records.is_empty() {
+            self.touch();
+        }
+
+        return records;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::fixtures::billing_scope;
+    use crate::{assert_granted, assert_not_granted, assert_ok};
+
+    #[test]
+    fn test_sweep_expired() {
+        let mut scope = billing_scope();
+        assert_ok!(scope.grant_until("WRITE", 100).and_then(|sc| sc.grant("READ")));
+        if let Some(billing) = scope.scope("billing") {
+            assert_ok!(billing.grant_until("REFUND", 200));
+        }
+
+        assert_eq!(scope.sweep_expired(99).len(), 0);
+
+        let records = scope.sweep_expired(100);
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].path, "APP:WRITE");
+        assert_eq!((records[0].before, records[0].after), (0b11, 0b01));
+        assert_eq!(records[0].actor, EXPIRY_ACTOR);
+        assert_not_granted!(scope, "WRITE");
+        assert_granted!(scope, "READ");
+        assert_granted!(scope, "billing:REFUND");
+
+        let records = scope.sweep_expired(1_000);
+        assert_eq!(records.iter().map(|record| record.path.as_str()).collect::<Vec<&str>>(), ["APP.billing:REFUND"]);
+        assert_eq!(scope.sweep_expired(u64::MAX).len(), 0);
+    }
+
+    #[test]
+    fn test_expiry_is_renewed_and_cleared() {
+        let mut scope = billing_scope();
+        assert_ok!(scope.grant_until("READ", 100).and_then(|sc| sc.grant_until("READ", 500)));
+        assert_eq!(scope.expires_at("READ"), Some(500));
+        assert_eq!(scope.sweep_expired(100).len(), 0);
+
+        // a plain grant after revoking is permanent
+        assert_ok!(scope.revoke("READ").and_then(|sc| sc.grant("READ")));
+        assert_eq!(scope.expires_at("READ"), None);
+        assert_eq!(scope.sweep_expired(u64::MAX).len(), 0);
+    }
+
+    #[test]
+    fn test_expiry_round_trips_through_json() {
+        let mut scope = billing_scope();
+        assert_ok!(scope.grant_until("WRITE", 100));
+
+        let mut imported = Scope::from_json(scope.as_json());
+        assert_eq!(imported.expires_at("WRITE"), Some(100));
+        assert_eq!(imported.sweep_expired(100).len(), 1);
+
+        // schema-only exports carry no grant state at all
+        assert_eq!(Scope::from_json(scope.as_schema_json()).expires_at("WRITE"), None);
+    }
+}
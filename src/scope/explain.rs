@@ -0,0 +1,189 @@
+use alloc::string::String;
+use alloc::vec::Vec;
+use crate::scope::path::{PermissionPath, ScopePath};
+use crate::scope::Scope;
+
+/** Decision describes why `Scope::explain` allowed or denied a permission path. */
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Decision {
+    /** The permission's own bit is set. */
+    Granted,
+    /** The permission's bit is not set, but the named granted permission implies it. */
+    Implied(String),
+    /** The permission is virtual, and its rule was evaluated against the other grants of the scope. */
+    Computed,
+    /** The permission is composite, and is held when every permission it combines is granted or implied. */
+    Composite,
+    /** The permission is explicitly denied, which overrides any grant or implication of it. */
+    Denied,
+    /** The permission has conditions, which cannot hold without a context, so `check` never allows it. */
+    Conditioned,
+    /** The permission exists but is neither granted nor implied. */
+    NotGranted,
+    /** No permission with this name (or alias) exists in the matched scope. */
+    UnknownPermission,
+    /** The scope part of the path does not lead to a child scope. */
+    UnknownScope
+}
+
+/** Explanation is a structured trace of a single permission check. */
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Explanation {
+    /** The path that was checked, as parsed. */
+    pub requested: PermissionPath,
+    /** Whether `Scope::check` would allow the path. */
+    pub allowed: bool,
+    /** The full path of the scope the check was evaluated in, if one was found. */
+    pub scope: Option<ScopePath>,
+    /** The name of the permission the check resolved to, after following aliases. */
+    pub permission: Option<String>,
+    /** Set when the requested name is an alias of `permission`. */
+    pub via_alias: bool,
+    /** The bit of the resolved permission within its scope. */
+    pub bit: Option<u8>,
+    pub decision: Decision
+}
+
+impl Scope {
+    /**
+        Explain the outcome of `check(path)`: which scope and permission the path resolved to, which bit
+        was consulted, and whether the permission is held directly or through an implication.
+     */
+    pub fn explain(&self, path: &str) -> Explanation {
+        let requested = PermissionPath::parse(path);
+        let mut explanation = Explanation {
+            requested: requested.clone(),
+            allowed: false,
+            scope: None,
+            permission: None,
+            via_alias: false,
+            bit: None,
+            decision: Decision::UnknownScope
+        };
+
+        let scope = match self.find_scope(&requested.scope) {
+            Some(scope) => scope,
+            None => return explanation
+        };
+        let name = scope.resolve_name(&requested.permission);
+        // a permission that spilled over is explained by the continuation holding it
+        let scope = scope.continuation_holding(&name).unwrap_or(scope);
+        explanation.scope = Some(scope.path.clone());
+
+        if let Some(held) = scope.evaluate_virtual(&name) {
+            explanation.allowed = held;
+            explanation.permission = Some(name);
+            explanation.decision = Decision::Computed;
+            return explanation;
+        }
+        if let Some(held) = scope.evaluate_composite(&name) {
+            explanation.allowed = held;
+            explanation.permission = Some(name);
+            explanation.decision = Decision::Composite;
+            return explanation;
+        }
+
+        let permission = match scope.permissions.get(&name) {
+            Some(permission) => permission,
+            None => {
+                explanation.decision = Decision::UnknownPermission;
+                return explanation;
+            }
+        };
+        explanation.via_alias = name != scope.policy.normalize(&requested.permission);
+        explanation.bit = Some(permission.value.trailing_zeros() as u8);
+        explanation.permission = Some(name);
+
+        if permission.denied {
+            explanation.decision = Decision::Denied;
+            return explanation;
+        }
+        if scope.is_conditioned(&permission.name) {
+            explanation.decision = Decision::Conditioned;
+            return explanation;
+        }
+        if permission.has() {
+            explanation.allowed = true;
+            explanation.decision = Decision::Granted;
+            return explanation;
+        }
+
+        // report the implying permission that sorts first so the explanation is stable
+        let mut implying: Vec<&String> = scope.permissions.values()
+            .filter(|granted| granted.has() && !scope.is_conditioned(&granted.name))
+            .filter(|granted| {
+                return scope.implies_through(&granted.name, &permission.name, |implied| !scope.is_conditioned(implied));
+            })
+            .map(|granted| &granted.name)
+            .collect();
+        implying.sort();
+
+        explanation.decision = match implying.first() {
+            Some(granted) => {
+                explanation.allowed = true;
+                Decision::Implied((*granted).clone())
+            },
+            None => Decision::NotGranted
+        };
+
+        return explanation;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::string::ToString;
+
+    fn get_explain_scope() -> Scope {
+        let mut scope = Scope::new("APP");
+
+        if let Err(_) = scope
+            .add_permission("READ")
+            .and_then(|sc| sc.add_permission("WRITE"))
+            .and_then(|sc| sc.add_permission("ADMIN"))
+            .and_then(|sc| sc.add_implication("ADMIN", "WRITE"))
+            .and_then(|sc| sc.add_implication("WRITE", "READ"))
+            .and_then(|sc| sc.grant("ADMIN"))
+            .and_then(|sc| sc.rename_permission("ADMIN", "OWNER", true))
+            .and_then(|sc| sc.add_scope("billing")) {
+            assert!(false);
+        }
+
+        return scope;
+    }
+
+    #[test]
+    fn test_explain_direct_and_implied() {
+        let scope = get_explain_scope();
+
+        let direct = scope.explain("ADMIN");
+        assert_eq!(direct.allowed, true);
+        assert_eq!(direct.decision, Decision::Granted);
+        assert_eq!(direct.permission, Some("OWNER".to_string()));
+        assert_eq!(direct.via_alias, true);
+        assert_eq!(direct.bit, Some(2));
+        assert_eq!(direct.scope, Some(ScopePath::from_segments(&["APP"])));
+
+        let implied = scope.explain("READ");
+        assert_eq!(implied.allowed, true);
+        assert_eq!(implied.decision, Decision::Implied("OWNER".to_string()));
+        assert_eq!(implied.bit, Some(0));
+        assert_eq!(implied.allowed, scope.check("READ"));
+    }
+
+    #[test]
+    fn test_explain_denials() {
+        let scope = get_explain_scope();
+
+        let unknown_scope = scope.explain("payroll:READ");
+        assert_eq!(unknown_scope.allowed, false);
+        assert_eq!(unknown_scope.decision, Decision::UnknownScope);
+        assert_eq!(unknown_scope.scope, None);
+
+        let unknown_permission = scope.explain("billing:PAY");
+        assert_eq!(unknown_permission.decision, Decision::UnknownPermission);
+        assert_eq!(unknown_permission.scope, Some(ScopePath::from_segments(&["APP", "billing"])));
+        assert_eq!(unknown_permission.bit, None);
+    }
+}
@@ -0,0 +1,232 @@
+use alloc::string::ToString;
+use serde::{Deserialize, Serialize};
+use crate::common::error::ErrorKind;
+use crate::scope::error::{ScopeError, ScopeErrorCase, ScopeErrorMetadata};
+use crate::scope::Scope;
+
+const FNV_OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+const FNV_PRIME: u64 = 0x100000001b3;
+
+/**
+    VersionedValue pairs the value of a scope with the fingerprint of the schema it was produced under,
+    so a stored value is never interpreted against a schema whose bits mean something else.
+ */
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct VersionedValue {
+    pub schema_hash: u64,
+    pub value: u64
+}
+
+/** Incremental 64-bit FNV-1a hash. */
+struct Fnv1a(u64);
+
+impl Fnv1a {
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 ^= *byte as u64;
+            self.0 = self.0.wrapping_mul(FNV_PRIME);
+        }
+    }
+}
+
+impl Scope {
+    /**
+        Get a hash of the schema of this scope and its child scopes: the path of every scope and the name and shift
+        of every permission. Grant state, rules and metadata are ignored, so two trees with the same fingerprint
+        interpret values identically.
+     */
+    pub fn fingerprint(&self) -> u64 {
+        let mut hash = Fnv1a(FNV_OFFSET_BASIS);
+
+        for scope in self.scopes_in_encoding_order() {
+            hash.write(scope.path.to_string().as_bytes());
+            hash.write(&[0xff]);
+
+            for shift in 0..scope.next_permission_shift {
+                if let Some(permission) = scope.permissions.values().find(|permission| permission.value == 1u64 << shift) {
+                    hash.write(permission.name.as_bytes());
+                    hash.write(&[0x00, shift]);
+                }
+            }
+            hash.write(&[0xfe]);
+        }
+
+        return hash.0;
+    }
+
+    /**
+        Get a hash of the revision of this scope and every child scope, which changes whenever anything in the tree
+        changes, including changes made directly on a child scope. Use it to tell whether results derived from the
+        tree are still current; unlike `revision`, it does not grow steadily.
+     */
+    pub fn tree_revision(&self) -> u64 {
+        let mut hash = Fnv1a(FNV_OFFSET_BASIS);
+        for scope in self.scopes_in_encoding_order() {
+            hash.write(&scope.revision.to_le_bytes());
+        }
+
+        return hash.0;
+    }
+
+    /** Get the value of this scope together with the fingerprint of its schema, for storage. */
+    pub fn versioned_value(&self) -> VersionedValue {
+        return VersionedValue {
+            schema_hash: self.fingerprint(),
+            value: self.as_u64()
+        };
+    }
+
+    /**
+        Set the grant state of this scope from a stored value, failing with SchemaMismatch if the value was
+        produced under a different schema, or UndefinedBits if it sets bits that have no permission.
+     */
+    pub fn apply_value(&mut self, versioned: &VersionedValue) -> Result<&mut Scope, ErrorKind> {
+        self.verify_fingerprint(versioned.schema_hash)?;
+        self.apply_u64(versioned.value)?;
+
+        return Ok(self);
+    }
+
+    /**
+        Fail with SchemaMismatch unless this scope has the expected fingerprint. Call this before applying values
+        that travel without their schema, such as encoded values or grant notation.
+     */
+    pub fn verify_fingerprint(&self, expected: u64) -> Result<(), ErrorKind> {
+        let fingerprint = self.fingerprint();
+
+        if fingerprint != expected {
+            return Err(ErrorKind::ScopeError(ScopeError::with_metadata(
+                ScopeErrorCase::SchemaMismatch,
+                &self.path.to_string(),
+                ScopeErrorMetadata {
+                    expected_fingerprint: Some(expected),
+                    fingerprint: Some(fingerprint),
+                    ..ScopeErrorMetadata::new()
+                }
+            )));
+        }
+
+        return Ok(());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get_schema() -> Scope {
+        let mut scope = Scope::new("APP");
+
+        if let Err(_) = scope
+            .add_permission("READ")
+            .and_then(|sc| sc.add_permission("WRITE"))
+            .and_then(|sc| sc.add_scope("billing")) {
+            assert!(false);
+        }
+
+        return scope;
+    }
+
+    #[test]
+    fn test_fingerprint_ignores_grants() {
+        let mut scope = get_schema();
+        let fingerprint = scope.fingerprint();
+
+        if let Err(_) = scope.grant("WRITE") {
+            assert!(false);
+        }
+        assert_eq!(scope.fingerprint(), fingerprint);
+
+        if let Err(_) = scope.add_permission("DELETE") {
+            assert!(false);
+        }
+        assert_ne!(scope.fingerprint(), fingerprint);
+    }
+
+    #[test]
+    fn test_fingerprint_is_stable() {
+        let mut reordered = Scope::new("APP");
+        if let Err(_) = reordered
+            .add_scope("billing")
+            .and_then(|sc| sc.add_permission("READ"))
+            .and_then(|sc| sc.add_permission("WRITE")) {
+            assert!(false);
+        }
+        assert_eq!(reordered.fingerprint(), get_schema().fingerprint());
+
+        // services on different releases compare fingerprints, so the hash must never change for the same schema
+        assert_eq!(get_schema().fingerprint(), 0x9f9cfd306afb1d12);
+    }
+
+    #[test]
+    fn test_apply_versioned_value() {
+        let mut source = get_schema();
+        if let Err(_) = source.grant("WRITE") {
+            assert!(false);
+        }
+        let stored = source.versioned_value();
+
+        let mut target = get_schema();
+        if let Err(_) = target.apply_value(&stored) {
+            assert!(false);
+        }
+        assert_eq!(target.check("WRITE"), true);
+    }
+
+    #[test]
+    fn test_apply_value_rejects_schema_drift() {
+        let stored = get_schema().versioned_value();
+
+        // READ and WRITE swap bits in the drifted schema
+        let mut drifted = Scope::new("APP");
+        if let Err(_) = drifted
+            .add_permission("WRITE")
+            .and_then(|sc| sc.add_permission("READ"))
+            .and_then(|sc| sc.add_scope("billing")) {
+            assert!(false);
+        }
+
+        match drifted.apply_value(&stored) {
+            Ok(_) => assert!(false),
+            Err(ErrorKind::ScopeError(err)) => {
+                assert_eq!(err.case(), ScopeErrorCase::SchemaMismatch);
+                assert_eq!(err.metadata().expected_fingerprint(), Some(stored.schema_hash));
+                assert_eq!(err.metadata().fingerprint(), Some(drifted.fingerprint()));
+            },
+            Err(ErrorKind::PermissionError(_)) => assert!(false)
+        }
+    }
+
+    #[test]
+    fn test_imports_verify_fingerprint() {
+        // one permission per scope, as exports do not yet keep the order of several permissions stable
+        let mut schema = Scope::new("APP");
+        if let Err(_) = schema.add_permission("READ").and_then(|sc| sc.add_scope("billing")) {
+            assert!(false);
+        }
+
+        let fingerprint = schema.fingerprint();
+        let json = schema.as_json();
+
+        match Scope::from_json_verified(json.clone(), Some(fingerprint)) {
+            Ok(scope) => assert_eq!(scope.check("billing:READ"), false),
+            Err(_) => assert!(false)
+        }
+        assert_eq!(Scope::from_json_verified(json.clone(), None).is_ok(), true);
+
+        match Scope::from_json_verified(json, Some(fingerprint ^ 1)) {
+            Ok(_) => assert!(false),
+            Err(ErrorKind::ScopeError(err)) => {
+                assert_eq!(err.case(), ScopeErrorCase::SchemaMismatch);
+                assert_eq!(err.metadata().expected_fingerprint(), Some(fingerprint ^ 1));
+            },
+            Err(ErrorKind::PermissionError(_)) => assert!(false)
+        }
+
+        match Scope::from_json_verified(serde_json::json!({ "name": "APP" }), None) {
+            Ok(_) => assert!(false),
+            Err(ErrorKind::ScopeError(err)) => assert_eq!(err.case(), ScopeErrorCase::InvalidEncoding),
+            Err(ErrorKind::PermissionError(_)) => assert!(false)
+        }
+    }
+}
@@ -0,0 +1,367 @@
+use alloc::collections::BTreeMap;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use serde::{Deserialize, Serialize};
+use serde_json::{from_value, to_value, Value};
+use crate::audit::AuditOp;
+use crate::common::error::ErrorKind;
+use crate::scope::error::{ScopeError, ScopeErrorCase};
+use crate::scope::guard::MutationContext;
+use crate::scope::order::SortOrder;
+use crate::scope::path::{PermissionPath, ScopePath};
+use crate::scope::Scope;
+
+/** GrantFormat selects how `grant_map_with` writes the grants of each scope. */
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum GrantFormat {
+    /** The names of the granted permissions, which stay valid when permissions are added or bits move. */
+    #[default]
+    Names,
+    /** The value of the scope, as produced by `as_u64`, which is smaller but tied to the bits of the schema. */
+    Values
+}
+
+/** GrantEntry holds the grants of one scope in a GrantMap, either by name or as a value. */
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum GrantEntry {
+    Names(Vec<String>),
+    Value(u64)
+}
+
+/**
+    GrantMap holds the grant state of a tree without its schema, as a map from full scope path to the grants of that
+    scope. Only scopes with at least one grant are listed, so the map is compact enough to store per user and can be
+    applied onto the schema later with `apply_grant_map`. It serializes to a plain JSON object, e.g.
+    `{"APP": ["READ"], "APP.billing": ["PAY"]}`.
+ */
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct GrantMap(pub BTreeMap<String, GrantEntry>);
+
+impl GrantMap {
+    /** Get the grants of a scope by its full path. */
+    pub fn get(&self, path: &str) -> Option<&GrantEntry> {
+        return self.0.get(path);
+    }
+
+    /** Convert this map into its JSON representation. */
+    pub fn to_json(&self) -> Value {
+        return to_value(self).unwrap_or(Value::Null);
+    }
+
+    /** Convert a map from its JSON representation, failing with InvalidEncoding on malformed input. */
+    pub fn try_from_json(value: Value) -> Result<GrantMap, ErrorKind> {
+        return from_value(value).map_err(|err| {
+            ErrorKind::ScopeError(ScopeError::new(ScopeErrorCase::InvalidEncoding, &err.to_string()))
+        });
+    }
+}
+
+/**
+    GrantSet holds only the grant bits of a tree: the value of every scope with at least one grant, keyed by full
+    scope path. It is the smallest form of per-user state, serializing to a plain JSON object such as
+    `{"APP": 2, "APP.billing": 2}`, and is applied onto the schema later with `apply_grant_set`. Like any value, it
+    is tied to the bits of the schema it was taken from; store the schema fingerprint alongside it.
+ */
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct GrantSet(pub BTreeMap<String, u64>);
+
+impl GrantSet {
+    /** Get the value of a scope by its full path, which is 0 for scopes without grants. */
+    pub fn get(&self, path: &str) -> u64 {
+        return self.0.get(path).copied().unwrap_or(0);
+    }
+
+    /** Convert this set into its JSON representation. */
+    pub fn to_json(&self) -> Value {
+        return to_value(self).unwrap_or(Value::Null);
+    }
+
+    /** Convert a set from its JSON representation, failing with InvalidEncoding on malformed input. */
+    pub fn try_from_json(value: Value) -> Result<GrantSet, ErrorKind> {
+        return from_value(value).map_err(|err| {
+            ErrorKind::ScopeError(ScopeError::new(ScopeErrorCase::InvalidEncoding, &err.to_string()))
+        });
+    }
+}
+
+impl From<GrantSet> for GrantMap {
+    fn from(set: GrantSet) -> GrantMap {
+        return GrantMap(set.0.into_iter().map(|(path, value)| (path, GrantEntry::Value(value))).collect());
+    }
+}
+
+/**
+    GrantImportReport lists which paths passed to `import_grant_paths` were granted, which were not found and which
+    a mutation guard refused.
+ */
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct GrantImportReport {
+    pub granted: Vec<String>,
+    pub unknown: Vec<String>,
+    pub rejected: Vec<String>
+}
+
+impl GrantImportReport {
+    /** Whether every path was found and granted. */
+    pub fn is_complete(&self) -> bool {
+        return self.unknown.is_empty() && self.rejected.is_empty();
+    }
+}
+
+impl Scope {
+    /**
+        Grant every permission in a flat list of paths relative to this scope, such as `["users.READ",
+        "admin.billing.REFUND"]`, the shape most external systems store grants in. The last segment of each path is
+        the permission, and `admin.billing:REFUND` is accepted too. Paths that do not name a permission that can be
+        granted are skipped and listed in the report instead of failing the import, as are paths whose grant a
+        mutation guard refuses. Existing grants are kept, and like importing a tuple this restores stored state
+        without checking rules.
+     */
+    pub fn import_grant_paths(&mut self, paths: &[&str]) -> GrantImportReport {
+        let mut report = GrantImportReport::default();
+        let mut changes: Vec<(ScopePath, u64)> = Vec::new();
+
+        for path in paths {
+            let requested = PermissionPath::parse(path);
+            let found = self.find_scope(&requested.scope).and_then(|scope| {
+                let permission = scope.permissions.get(&scope.resolve_name(&requested.permission))?;
+                return Some((scope, permission));
+            });
+
+            match found {
+                Some((scope, permission)) => {
+                    let refused = !permission.has()
+                        && scope.check_guards(AuditOp::Grant, &permission.name, &MutationContext::new()).is_err();
+                    if refused {
+                        report.rejected.push(path.to_string());
+                        continue;
+                    }
+
+                    let bit = permission.value;
+                    match changes.iter_mut().find(|(scope, _)| *scope == requested.scope) {
+                        Some((_, value)) => *value |= bit,
+                        None => changes.push((requested.scope, bit))
+                    }
+                    report.granted.push(path.to_string());
+                },
+                None => report.unknown.push(path.to_string())
+            }
+        }
+
+        for (path, value) in changes {
+            if let Ok(scope) = self.resolve_scope_mut(&path) {
+                let value = scope.as_u64() | value;
+                // every bit was looked up in this scope and passed its guards above, so applying it cannot fail
+                let _ = scope.apply_u64_unguarded(value);
+            }
+        }
+
+        return report;
+    }
+
+    /** Export the grants of this scope and its child scopes by permission name, without the schema. */
+    pub fn grant_map(&self) -> GrantMap {
+        return self.grant_map_with(GrantFormat::Names);
+    }
+
+    /** Export the grants of this scope and its child scopes in the given format, without the schema. */
+    pub fn grant_map_with(&self, format: GrantFormat) -> GrantMap {
+        let mut grants = BTreeMap::new();
+
+        for scope in self.scopes_in_encoding_order() {
+            let value = scope.as_u64();
+            if value == 0 {
+                continue;
+            }
+
+            let entry = match format {
+                GrantFormat::Names => GrantEntry::Names(scope.permissions_in_order(SortOrder::Shift).into_iter()
+                    .filter(|permission| permission.has())
+                    .map(|permission| permission.name.clone())
+                    .collect()),
+                GrantFormat::Values => GrantEntry::Value(value)
+            };
+            grants.insert(scope.path.to_string(), entry);
+        }
+
+        return GrantMap(grants);
+    }
+
+    /** Export the grant bits of this scope and its child scopes, without the schema. */
+    pub fn grant_set(&self) -> GrantSet {
+        return GrantSet(self.scopes_in_encoding_order().into_iter()
+            .map(|scope| (scope.path.to_string(), scope.as_u64()))
+            .filter(|(_, value)| *value != 0)
+            .collect());
+    }
+
+    /**
+        Replace the grants of this scope and its child scopes with those in `set`, so scopes the set does not list
+        end up with no grants. Fails like `apply_grant_map`, without changing anything.
+     */
+    pub fn apply_grant_set(&mut self, set: &GrantSet) -> Result<&mut Scope, ErrorKind> {
+        return self.apply_grant_map(&GrantMap::from(set.clone()));
+    }
+
+    /**
+        Replace the grants of this scope and its child scopes with those in `map`, so scopes the map does not list
+        end up with no grants. Every entry is validated before anything is changed: an unknown scope path fails with
+        UnknownScope, an unknown permission name with UnknownPermission, a value with bits that no permission is
+        assigned to with UndefinedBits and a change refused by a mutation guard with GuardRejected. Like importing
+        a tuple, this restores stored state and does not check rules.
+     */
+    pub fn apply_grant_map(&mut self, map: &GrantMap) -> Result<&mut Scope, ErrorKind> {
+        let mut changes: Vec<(ScopePath, u64)> = Vec::new();
+        {
+            let scopes = self.scopes_in_encoding_order();
+
+            for path in map.0.keys() {
+                if !scopes.iter().any(|scope| scope.path.to_string() == *path) {
+                    return Err(ErrorKind::ScopeError(ScopeError::new(ScopeErrorCase::UnknownScope, path)));
+                }
+            }
+
+            for scope in scopes {
+                let path = scope.path.to_string();
+                let value = match map.get(&path) {
+                    None => 0,
+                    Some(GrantEntry::Value(value)) => {
+                        if value & !scope.defined_bits() != 0 {
+                            return Err(ErrorKind::ScopeError(ScopeError::new(ScopeErrorCase::UndefinedBits, &path)));
+                        }
+                        *value
+                    },
+                    Some(GrantEntry::Names(names)) => {
+                        let mut value = 0u64;
+                        for name in names {
+                            match scope.permissions.get(&scope.resolve_name(name)) {
+                                Some(permission) => value |= permission.value,
+                                None => return Err(ErrorKind::ScopeError(ScopeError::new(ScopeErrorCase::UnknownPermission, name)))
+                            }
+                        }
+                        value
+                    }
+                };
+                scope.check_guards_for_value(value, &MutationContext::new())?;
+                changes.push((scope.path.relative_to(&self.path), value));
+            }
+        }
+
+        for (path, value) in changes {
+            self.resolve_scope_mut(&path)?.apply_u64_unguarded(value)?;
+        }
+
+        return Ok(self);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use crate::{assert_granted, assert_not_granted, assert_ok, assert_scope_err};
+    use crate::testing::fixtures::billing_scope;
+
+    fn get_scope() -> Scope {
+        let mut scope = billing_scope();
+        assert_ok!(scope.add_scope("reports"));
+
+        return scope;
+    }
+
+    fn get_granted_scope() -> Scope {
+        let mut scope = get_scope();
+        assert_ok!(scope.grant("WRITE"));
+        if let Some(billing) = scope.scope("billing") {
+            assert_ok!(billing.grant("PAY"));
+        }
+
+        return scope;
+    }
+
+    #[test]
+    fn test_grant_map() {
+        let scope = get_granted_scope();
+
+        assert_eq!(scope.grant_map().to_json(), json!({ "APP": ["WRITE"], "APP.billing": ["PAY"] }));
+        assert_eq!(scope.grant_map_with(GrantFormat::Values).to_json(), json!({ "APP": 2, "APP.billing": 2 }));
+        assert_eq!(get_scope().grant_map(), GrantMap::default());
+    }
+
+    #[test]
+    fn test_apply_grant_map() {
+        let granted = get_granted_scope();
+
+        for format in [GrantFormat::Names, GrantFormat::Values] {
+            let stored = granted.grant_map_with(format).to_json();
+            let map = assert_ok!(GrantMap::try_from_json(stored));
+
+            let mut schema = get_scope();
+            // grants the map does not list are cleared
+            assert_ok!(schema.grant("READ"));
+            assert_ok!(schema.apply_grant_map(&map));
+
+            assert_granted!(schema, "WRITE");
+            assert_granted!(schema, "billing:PAY");
+            assert_not_granted!(schema, "READ");
+            assert_eq!(schema.grant_map(), granted.grant_map());
+        }
+    }
+
+    #[test]
+    fn test_grant_set() {
+        let granted = get_granted_scope();
+        let set = granted.grant_set();
+        assert_eq!(set.to_json(), json!({ "APP": 2, "APP.billing": 2 }));
+        assert_eq!(set.get("APP.reports"), 0);
+
+        let mut schema = get_scope();
+        assert_ok!(schema.grant("READ"));
+        assert_ok!(schema.apply_grant_set(&assert_ok!(GrantSet::try_from_json(set.to_json()))));
+        assert_eq!(schema.grant_set(), set);
+        assert_not_granted!(schema, "READ");
+
+        let invalid = assert_ok!(GrantSet::try_from_json(json!({ "APP": 1, "APP.billing": 8 })));
+        assert_scope_err!(schema.apply_grant_set(&invalid), ScopeErrorCase::UndefinedBits);
+        assert_scope_err!(GrantSet::try_from_json(json!({ "APP": ["READ"] })), ScopeErrorCase::InvalidEncoding);
+    }
+
+    #[test]
+    fn test_import_grant_paths() {
+        let mut scope = get_scope();
+        assert_ok!(scope.grant("READ"));
+
+        let report = scope.import_grant_paths(&["WRITE", "billing.PAY", "billing:VIEW", "billing.VOID", "admin.billing.PAY"]);
+
+        assert_eq!(report.granted, ["WRITE", "billing.PAY", "billing:VIEW"]);
+        assert_eq!(report.unknown, ["billing.VOID", "admin.billing.PAY"]);
+        assert_eq!(report.is_complete(), false);
+        assert_granted!(scope, "READ");
+        assert_granted!(scope, "WRITE");
+        assert_granted!(scope, "billing:PAY");
+        assert_granted!(scope, "billing:VIEW");
+
+        assert_eq!(scope.import_grant_paths(&[]), GrantImportReport::default());
+    }
+
+    #[test]
+    fn test_apply_grant_map_rejects_unknown_entries() {
+        let mut schema = get_scope();
+
+        let map = assert_ok!(GrantMap::try_from_json(json!({ "APP": ["READ"], "APP.admin": ["VIEW"] })));
+        assert_scope_err!(schema.apply_grant_map(&map), ScopeErrorCase::UnknownScope);
+
+        let map = assert_ok!(GrantMap::try_from_json(json!({ "APP": ["READ"], "APP.billing": ["VOID"] })));
+        assert_scope_err!(schema.apply_grant_map(&map), ScopeErrorCase::UnknownPermission);
+
+        let map = assert_ok!(GrantMap::try_from_json(json!({ "APP": 1, "APP.billing": 8 })));
+        assert_scope_err!(schema.apply_grant_map(&map), ScopeErrorCase::UndefinedBits);
+
+        // nothing is applied when any entry is rejected
+        assert_not_granted!(schema, "READ");
+        assert_scope_err!(GrantMap::try_from_json(json!({ "APP": "READ" })), ScopeErrorCase::InvalidEncoding);
+    }
+}
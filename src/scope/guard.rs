@@ -0,0 +1,287 @@
+use alloc::collections::BTreeMap;
+use alloc::string::{String, ToString};
+use alloc::sync::Arc;
+use crate::audit::AuditOp;
+use crate::common::error::ErrorKind;
+use crate::scope::error::{ScopeError, ScopeErrorCase, ScopeErrorMetadata};
+use crate::scope::order::SortOrder;
+use crate::scope::path::PermissionPath;
+use crate::scope::Scope;
+
+/** Mutation describes a grant or revocation that is about to be made, as seen by a mutation guard. */
+pub struct Mutation<'a> {
+    pub op: AuditOp,
+    /** Full path of the permission, e.g. `APP.billing:REFUND`. */
+    pub path: String,
+    pub permission: &'a str,
+    pub tags: &'a [String],
+    /** The scope the permission belongs to, before the change is made. */
+    pub scope: &'a Scope
+}
+
+/**
+    MutationContext carries what the caller knows about a change, such as who makes it and why, for mutation guards
+    to decide on. Attributes are free-form, e.g. a `ticket` naming the change request that approved it.
+ */
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct MutationContext {
+    pub actor: Option<String>,
+    /** Time of the change on the caller's clock, conventionally seconds since the Unix epoch. */
+    pub timestamp: Option<u64>,
+    pub attributes: BTreeMap<String, String>
+}
+
+impl MutationContext {
+    pub fn new() -> MutationContext {
+        return MutationContext::default();
+    }
+
+    pub fn with_actor(mut self, actor: &str) -> MutationContext {
+        self.actor = Some(actor.to_string());
+        return self;
+    }
+
+    pub fn with_timestamp(mut self, timestamp: u64) -> MutationContext {
+        self.timestamp = Some(timestamp);
+        return self;
+    }
+
+    pub fn with_attribute(mut self, key: &str, value: &str) -> MutationContext {
+        self.attributes.insert(key.to_string(), value.to_string());
+        return self;
+    }
+
+    pub fn attribute(&self, key: &str) -> Option<&str> {
+        return self.attributes.get(key).map(|value| value.as_str());
+    }
+}
+
+/**
+    MutationGuard decides whether a grant or revocation may be made, returning the reason for refusing it as the
+    error. Guards are shared between a scope, its child scopes and their clones.
+ */
+pub type MutationGuard = Arc<dyn Fn(&Mutation, &MutationContext) -> Result<(), String> + Send + Sync>;
+
+impl Scope {
+    /**
+        Register a guard that is consulted before every grant and revocation made in this scope and its child scopes,
+        including child scopes added later. Besides `grant`, `revoke` and their variants, this covers changes that
+        set many bits at once, such as `apply_u64`, `apply_claims`, `apply_grant_map`, `import_grant_paths` and
+        merges, which consult the guards for every permission they grant or revoke. Registering a guard under a name
+        already in use replaces it. Guards are not exported, so imports building a new tree do not run them, and
+        neither does `sweep_expired`, which is not made on behalf of a caller.
+     */
+    pub fn add_guard<F>(&mut self, name: &str, guard: F) -> &mut Scope
+        where F: Fn(&Mutation, &MutationContext) -> Result<(), String> + Send + Sync + 'static {
+        let guard: MutationGuard = Arc::new(guard);
+        self.register_guard(name, guard);
+
+        return self;
+    }
+
+    fn register_guard(&mut self, name: &str, guard: MutationGuard) {
+        for scope in self.scopes.values_mut() {
+            scope.register_guard(name, guard.clone());
+        }
+
+        match self.guards.iter_mut().find(|(existing, _)| existing == name) {
+            Some(entry) => entry.1 = guard,
+            None => self.guards.push((name.to_string(), guard))
+        }
+    }
+
+    /** Remove a guard from this scope and its child scopes, returning whether this scope had it. */
+    pub fn remove_guard(&mut self, name: &str) -> bool {
+        for scope in self.scopes.values_mut() {
+            scope.remove_guard(name);
+        }
+
+        let count = self.guards.len();
+        self.guards.retain(|(existing, _)| existing != name);
+
+        return self.guards.len() != count;
+    }
+
+    /** List the names of the guards consulted by this scope, in the order they run. */
+    pub fn guard_names(&self) -> impl Iterator<Item = &str> {
+        return self.guards.iter().map(|(name, _)| name.as_str());
+    }
+
+    /**
+        Run every guard of this scope against a change to the permission with the given key, failing with
+        GuardRejected on the first guard that refuses it.
+     */
+    pub(crate) fn check_guards(&self, op: AuditOp, name: &str, context: &MutationContext) -> Result<(), ErrorKind> {
+        if self.guards.is_empty() {
+            return Ok(());
+        }
+
+        let tags: &[String] = match self.permissions.get(name) {
+            Some(permission) => &permission.tags,
+            None => &[]
+        };
+        let mutation = Mutation {
+            op,
+            path: PermissionPath::new(self.path.clone(), name).to_string(),
+            permission: name,
+            tags,
+            scope: self
+        };
+
+        for (guard_name, guard) in &self.guards {
+            if let Err(reason) = guard(&mutation, context) {
+                return Err(ErrorKind::ScopeError(ScopeError::with_metadata(
+                    ScopeErrorCase::GuardRejected,
+                    &mutation.path,
+                    ScopeErrorMetadata {
+                        related: Some(guard_name.clone()),
+                        reason: Some(reason),
+                        ..ScopeErrorMetadata::new()
+                    }
+                )));
+            }
+        }
+
+        return Ok(());
+    }
+
+    /**
+        Run every guard of this scope against each grant and revocation that setting its value to `value` would make,
+        in shift order, failing with GuardRejected on the first one refused.
+     */
+    pub(crate) fn check_guards_for_value(&self, value: u64, context: &MutationContext) -> Result<(), ErrorKind> {
+        if self.guards.is_empty() {
+            return Ok(());
+        }
+
+        for permission in self.permissions_in_order(SortOrder::Shift) {
+            let granted = value & permission.value == permission.value;
+            if granted != permission.has() {
+                let op = match granted {
+                    true => AuditOp::Grant,
+                    false => AuditOp::Revoke
+                };
+                self.check_guards(op, &permission.name, context)?;
+            }
+        }
+
+        return Ok(());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::format;
+    use alloc::vec::Vec;
+    use crate::{assert_granted, assert_not_granted, assert_ok, assert_scope_err};
+    use crate::testing::fixtures::billing_scope;
+
+    fn get_scope() -> Scope {
+        let mut scope = billing_scope();
+        if let Some(refund) = scope.scope("billing").and_then(|billing| billing.permission("REFUND")) {
+            refund.tags.push("dangerous".to_string());
+        }
+
+        return scope;
+    }
+
+    fn business_hours(mutation: &Mutation, context: &MutationContext) -> Result<(), String> {
+        if mutation.op != AuditOp::Grant || !mutation.tags.iter().any(|tag| tag == "dangerous") {
+            return Ok(());
+        }
+
+        return match context.timestamp.map(|timestamp| timestamp / 3600 % 24) {
+            Some(hour) if (9..17).contains(&hour) => Ok(()),
+            _ => Err(format!("{} may only be granted during business hours", mutation.path))
+        }
+    }
+
+    #[test]
+    fn test_guard_rejects_dangerous_grants() {
+        let mut scope = get_scope();
+        scope.add_guard("business-hours", business_hours);
+        assert_ok!(scope.add_scope("reports"));
+
+        let night = MutationContext::new().with_timestamp(3 * 3600);
+        let noon = MutationContext::new().with_timestamp(12 * 3600);
+        if let Some(billing) = scope.scope("billing") {
+            match billing.grant_in("REFUND", &night) {
+                Err(ErrorKind::ScopeError(err)) => {
+                    assert_eq!(err.case(), ScopeErrorCase::GuardRejected);
+                    assert_eq!(err.metadata().related(), Some("business-hours"));
+                    assert_eq!(err.metadata().reason(), Some("APP.billing:REFUND may only be granted during business hours"));
+                },
+                _ => panic!("the guard did not reject the grant")
+            }
+            assert_scope_err!(billing.grant("REFUND"), ScopeErrorCase::GuardRejected);
+            assert_not_granted!(billing, "REFUND");
+
+            assert_ok!(billing.grant_in("REFUND", &noon));
+            assert_ok!(billing.revoke_in("REFUND", &night));
+        }
+
+        // untagged permissions pass, and child scopes added later inherit the guard
+        assert_ok!(scope.grant("READ"));
+        assert_eq!(scope.scope("reports").map(|reports| reports.guard_names().collect::<Vec<&str>>()), Some(alloc::vec!["business-hours"]));
+    }
+
+    #[test]
+    fn test_guard_requires_ticket() {
+        let mut scope = get_scope();
+        scope.add_guard("ticket", |_, context| match context.attribute("ticket") {
+            Some(_) => Ok(()),
+            None => Err("a ticket is required".to_string())
+        });
+
+        assert_scope_err!(scope.grant("READ"), ScopeErrorCase::GuardRejected);
+        assert_ok!(scope.grant_in("READ", &MutationContext::new().with_attribute("ticket", "OPS-12")));
+        assert_granted!(scope, "READ");
+
+        assert_scope_err!(scope.revoke("READ"), ScopeErrorCase::GuardRejected);
+        assert_eq!(scope.remove_guard("ticket"), true);
+        assert_ok!(scope.revoke("READ"));
+        assert_eq!(scope.remove_guard("ticket"), false);
+    }
+
+    #[test]
+    fn test_guard_covers_bulk_changes() {
+        use crate::role::{Role, RoleSet};
+        use crate::scope::encoding::Radix;
+        use crate::scope::merge::ConflictKind;
+
+        let mut scope = get_scope();
+        let base = scope.clone();
+        let mut theirs = scope.clone();
+        assert_ok!(theirs.grant_path("billing:REFUND"));
+        scope.add_guard("frozen", |mutation, _| match mutation.tags.iter().any(|tag| tag == "dangerous") {
+            true => Err(format!("{} is frozen", mutation.path)),
+            false => Ok(())
+        });
+
+        if let Some(billing) = scope.scope("billing") {
+            assert_scope_err!(billing.apply_u64(0b100), ScopeErrorCase::GuardRejected);
+        }
+        assert_scope_err!(scope.apply_grant_map(&theirs.grant_map()), ScopeErrorCase::GuardRejected);
+        let encoded = theirs.encode_values(Radix::Base36);
+        assert_scope_err!(scope.apply_encoded_values(&encoded, Radix::Base36), ScopeErrorCase::GuardRejected);
+        assert_scope_err!(scope.grant_from_oauth_scopes("billing:refund"), ScopeErrorCase::GuardRejected);
+
+        let mut roles = RoleSet::new();
+        assert_ok!(roles.define(Role::new("refunder").with_path("READ").with_path("billing:REFUND")));
+        assert_scope_err!(roles.assign(&mut scope, "refunder"), ScopeErrorCase::GuardRejected);
+        assert_not_granted!(scope, "READ");
+
+        let report = scope.import_grant_paths(&["READ", "billing.REFUND"]);
+        assert_eq!(report.granted, ["READ"]);
+        assert_eq!(report.rejected, ["billing.REFUND"]);
+        assert_granted!(scope, "READ");
+
+        let merged = Scope::merge3(&base, &scope, &theirs);
+        assert_eq!(merged.conflicts.len(), 1usize);
+        assert_eq!(merged.conflicts[0].path, "APP.billing:REFUND");
+        assert_eq!(matches!(merged.conflicts[0].kind, ConflictKind::Rejected { .. }), true);
+        assert_not_granted!(merged.merged, "billing:REFUND");
+        assert_not_granted!(scope, "billing:REFUND");
+    }
+}
@@ -0,0 +1,122 @@
+use alloc::string::{String, ToString};
+use uuid::Uuid;
+use crate::common::error::ErrorKind;
+use crate::scope::error::{ScopeError, ScopeErrorCase, ScopeErrorMetadata};
+use crate::scope::path::PermissionPath;
+use crate::scope::Scope;
+
+impl Scope {
+    /**
+        Assign a stable identifier to a permission. The identifier is independent of the permission's name and bit,
+        is kept across renames and is carried through tuple and JSON exports, so external systems can reference the
+        permission robustly. Fails with DuplicateId if another permission of this scope already has the identifier.
+     */
+    pub fn assign_id(&mut self, name: &str, id: Uuid) -> Result<&mut Scope, ErrorKind> {
+        let name = self.find_permission_name(name)?;
+
+        if let Some(holder) = self.permissions.values().find(|permission| permission.id == Some(id) && permission.name != name) {
+            return Err(ErrorKind::ScopeError(ScopeError::with_metadata(
+                ScopeErrorCase::DuplicateId,
+                &id.to_string(),
+                ScopeErrorMetadata {
+                    related: Some(holder.name.clone()),
+                    ..ScopeErrorMetadata::new()
+                }
+            )));
+        }
+
+        if let Some(permission) = self.permissions.get_mut(&name) {
+            permission.id = Some(id);
+        }
+        self.touch();
+
+        return Ok(self);
+    }
+
+    /** Get the stable identifier of a permission of this scope, if one was assigned. */
+    pub fn permission_id(&self, name: &str) -> Option<Uuid> {
+        return self.permissions.get(&self.resolve_name(name)).and_then(|permission| permission.id);
+    }
+
+    /**
+        Find the permission with the given identifier in this scope or its child scopes, returning its current path
+        relative to this scope in the form accepted by `check`.
+     */
+    pub fn find_by_id(&self, id: Uuid) -> Option<String> {
+        for scope in self.scopes_in_encoding_order() {
+            if let Some(permission) = scope.permissions.values().find(|permission| permission.id == Some(id)) {
+                return Some(PermissionPath::new(scope.path.relative_to(&self.path), &permission.name).to_string());
+            }
+        }
+
+        return None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const READ_ID: Uuid = Uuid::from_u128(0x6f1c_2a4e_8d3b_4f7a_9c21_5e0b_7a3d_1f42);
+    const REFUND_ID: Uuid = Uuid::from_u128(0x0b7e_95c4_1a62_4d08_b3f9_c8e2_64a1_5d90);
+
+    fn get_scope() -> Scope {
+        let mut scope = Scope::new("APP");
+        if let Err(_) = scope
+            .add_permission("READ")
+            .and_then(|sc| sc.add_permission("WRITE"))
+            .and_then(|sc| sc.assign_id("READ", READ_ID))
+            .and_then(|sc| sc.add_scope("billing")) {
+            assert!(false);
+        }
+
+        if let Some(billing) = scope.scope("billing") {
+            if let Err(_) = billing.add_permission("REFUND").and_then(|sc| sc.assign_id("REFUND", REFUND_ID)) {
+                assert!(false);
+            }
+        }
+
+        return scope;
+    }
+
+    #[test]
+    fn test_id_survives_rename() {
+        let mut scope = get_scope();
+        if let Err(_) = scope.rename_permission("READ", "VIEW", false) {
+            assert!(false);
+        }
+
+        assert_eq!(scope.permission_id("VIEW"), Some(READ_ID));
+        assert_eq!(scope.permission_id("WRITE"), None);
+        assert_eq!(scope.find_by_id(READ_ID).as_deref(), Some("VIEW"));
+        assert_eq!(scope.find_by_id(REFUND_ID).as_deref(), Some("billing:REFUND"));
+        assert_eq!(scope.find_by_id(Uuid::nil()), None);
+    }
+
+    #[test]
+    fn test_id_round_trips_through_json() {
+        let scope = get_scope();
+        let imported = Scope::from_json(scope.as_json());
+
+        assert_eq!(imported.find_by_id(READ_ID).as_deref(), Some("READ"));
+        assert_eq!(imported.find_by_id(REFUND_ID).as_deref(), Some("billing:REFUND"));
+    }
+
+    #[test]
+    fn test_duplicate_id_rejected() {
+        let mut scope = get_scope();
+
+        match scope.assign_id("WRITE", READ_ID) {
+            Err(ErrorKind::ScopeError(err)) => {
+                assert_eq!(err.case(), ScopeErrorCase::DuplicateId);
+                assert_eq!(err.metadata().related(), Some("READ"));
+            },
+            _ => assert!(false)
+        }
+
+        // reassigning a permission its own identifier is not a conflict
+        if let Err(_) = scope.assign_id("READ", READ_ID) {
+            assert!(false);
+        }
+    }
+}
@@ -0,0 +1,100 @@
+use alloc::vec::Vec;
+use crate::permission::Permission;
+use crate::scope::order::SortOrder;
+use crate::scope::Scope;
+
+impl Scope {
+    /** Iterate over the permissions defined directly on this scope, in the order they were added. */
+    pub fn permissions(&self) -> impl Iterator<Item = &Permission> {
+        return self.permissions_in_order(SortOrder::Insertion).into_iter();
+    }
+
+    /**
+        Iterate mutably over the permissions defined directly on this scope, in the order they were added. Like
+        `permission`, changes made through the iterator bypass rules and guards; the revision advances when it is created.
+     */
+    pub fn permissions_mut(&mut self) -> impl Iterator<Item = &mut Permission> {
+        self.touch();
+        let order = &self.permission_order;
+        let mut permissions: Vec<&mut Permission> = self.permissions.values_mut().collect();
+        permissions.sort_by_key(|permission| order.iter().position(|name| *name == permission.name));
+
+        return permissions.into_iter();
+    }
+
+    /** Iterate over the direct child scopes of this scope, in the order they were added. */
+    pub fn scopes(&self) -> impl Iterator<Item = &Scope> {
+        return self.scopes_in_order(SortOrder::Insertion).into_iter();
+    }
+
+    /** Iterate mutably over the direct child scopes of this scope, in the order they were added. */
+    pub fn scopes_mut(&mut self) -> impl Iterator<Item = &mut Scope> {
+        let order = &self.scope_order;
+        let mut scopes: Vec<&mut Scope> = self.scopes.values_mut().collect();
+        scopes.sort_by_key(|scope| order.iter().position(|name| *name == scope.name));
+
+        return scopes.into_iter();
+    }
+
+    /**
+        Iterate over the permissions of this scope (but not its child scopes) that are granted directly, in the
+        order they were added. Permissions only held through an implication are not included.
+     */
+    pub fn iter_granted(&self) -> impl Iterator<Item = &Permission> {
+        return self.permissions().filter(|permission| permission.has());
+    }
+
+    /** Iterate mutably over the permissions of this scope that are granted directly, in the order they were added. */
+    pub fn iter_granted_mut(&mut self) -> impl Iterator<Item = &mut Permission> {
+        return self.permissions_mut().filter(|permission| permission.has());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::string::String;
+    use crate::assert_ok;
+
+    fn get_scope() -> Scope {
+        let mut scope = Scope::new("APP");
+        assert_ok!(scope.add_permission("WRITE").and_then(|sc| sc.add_permission("READ")).and_then(|sc| sc.add_permission("ADMIN")));
+        assert_ok!(scope.add_implication("ADMIN", "READ").and_then(|sc| sc.grant("WRITE")).and_then(|sc| sc.grant("ADMIN")));
+        assert_ok!(scope.add_scope("reports").and_then(|sc| sc.add_scope("billing")));
+
+        return scope;
+    }
+
+    fn names<'a>(permissions: impl Iterator<Item = &'a Permission>) -> Vec<String> {
+        return permissions.map(|permission| permission.name.clone()).collect();
+    }
+
+    #[test]
+    fn test_iterate_in_insertion_order() {
+        let scope = get_scope();
+
+        assert_eq!(names(scope.permissions()), ["WRITE", "READ", "ADMIN"]);
+        assert_eq!(names(scope.iter_granted()), ["WRITE", "ADMIN"]);
+        assert_eq!(scope.scopes().map(|child| child.name()).collect::<Vec<&str>>(), ["reports", "billing"]);
+    }
+
+    #[test]
+    fn test_iterate_mutably() {
+        let mut scope = get_scope();
+
+        for permission in scope.permissions_mut() {
+            permission.description = Some(String::from("documented"));
+        }
+        assert_eq!(scope.permissions().all(|permission| permission.description.is_some()), true);
+
+        for permission in scope.iter_granted_mut() {
+            permission.has_permission = false;
+        }
+        assert_eq!(scope.as_u64(), 0);
+
+        for child in scope.scopes_mut() {
+            assert_ok!(child.add_permission("VIEW"));
+        }
+        assert_eq!(scope.scopes().all(|child| child.permissions().count() == 1), true);
+    }
+}
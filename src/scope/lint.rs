@@ -0,0 +1,203 @@
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use crate::scope::path::ScopePath;
+use crate::scope::Scope;
+
+/** LintKind describes what is wrong with the grant state of a permission. */
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum LintKind {
+    /** The permission is granted but the named permission it requires is not. */
+    MissingRequirement(String),
+    /**
+        The permission is held, directly or through an implication, at the same time as the named permission it is
+        mutually exclusive with. Each pair is reported once, from the permission whose name sorts first.
+     */
+    MutualExclusion(String),
+    /** The permission is deprecated but still granted, with the replacement hint if one was given. */
+    DeprecatedInUse(Option<String>)
+}
+
+/** LintIssue is a single problem found by `Scope::lint`. */
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LintIssue {
+    pub path: ScopePath,
+    pub permission: String,
+    pub kind: LintKind
+}
+
+impl Scope {
+    /**
+        Inspect this scope and all of its child scopes for grant states that violate declared constraints. Issues
+        are listed scope by scope, each scope before its children, and permission by permission in the order they
+        were added, so the report is the same on every run.
+     */
+    pub fn lint(&self) -> Vec<LintIssue> {
+        let mut issues: Vec<LintIssue> = vec![];
+        for scope in self.walk_scopes() {
+            scope.lint_flat(&mut issues);
+        }
+
+        return issues;
+    }
+
+    /** Inspect only the permissions defined directly on this scope. */
+    fn lint_flat(&self, issues: &mut Vec<LintIssue>) {
+        for permission in self.permissions() {
+            // exclusions are about held permissions, so a pair is reported however either side of it is held
+            if self.is_effective(&permission.name) {
+                for excluded in self.exclusions(&permission.name) {
+                    if permission.name < *excluded && self.is_effective(excluded) {
+                        issues.push(LintIssue {
+                            path: self.path.clone(),
+                            permission: permission.name.clone(),
+                            kind: LintKind::MutualExclusion(excluded.clone())
+                        });
+                    }
+                }
+            }
+
+            if !permission.has() {
+                continue;
+            }
+
+            if let Some(replacement) = self.deprecations.get(&permission.name) {
+                issues.push(LintIssue {
+                    path: self.path.clone(),
+                    permission: permission.name.clone(),
+                    kind: LintKind::DeprecatedInUse(replacement.clone())
+                });
+            }
+
+            for required in self.requirements(&permission.name) {
+                if !self.is_granted(required) {
+                    issues.push(LintIssue {
+                        path: self.path.clone(),
+                        permission: permission.name.clone(),
+                        kind: LintKind::MissingRequirement(required.clone())
+                    });
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::string::ToString;
+    use super::*;
+
+    #[test]
+    fn test_lint_clean_scope() {
+        let mut scope = Scope::new("TEST_SCOPE");
+
+        if let Ok(_) = scope
+            .add_permission("READ")
+            .and_then(|sc| sc.add_permission("DELETE"))
+            .and_then(|sc| sc.add_requirement("DELETE", "READ"))
+            .and_then(|sc| sc.grant("READ"))
+            .and_then(|sc| sc.grant("DELETE")) {
+            assert_eq!(scope.lint().is_empty(), true);
+        } else {
+            assert!(false);
+        }
+    }
+
+    #[test]
+    fn test_lint_missing_requirement_in_child_scope() {
+        let mut scope = Scope::new("TEST_SCOPE");
+        let _ = scope.add_scope("CHILD_SCOPE");
+
+        if let Some(child_scope) = scope.scope("CHILD_SCOPE") {
+            if let Err(_) = child_scope
+                .add_permission("READ")
+                .and_then(|sc| sc.add_permission("DELETE"))
+                .and_then(|sc| sc.add_requirement("DELETE", "READ")) {
+                assert!(false);
+            }
+
+            // bypass the scope-level checks to produce an invalid grant state
+            if let Some(permission) = child_scope.permission("DELETE") {
+                let _ = permission.grant();
+            }
+        }
+
+        let issues = scope.lint();
+        assert_eq!(issues.len(), 1usize);
+        assert_eq!(issues[0].path.to_string(), "TEST_SCOPE.CHILD_SCOPE");
+        assert_eq!(issues[0].permission, "DELETE");
+        assert_eq!(issues[0].kind, LintKind::MissingRequirement("READ".to_string()));
+    }
+
+    #[test]
+    fn test_lint_deprecated_in_use() {
+        let mut scope = Scope::new("TEST_SCOPE");
+
+        if let Err(_) = scope
+            .add_permission("EDIT")
+            .and_then(|sc| sc.add_permission("WRITE"))
+            .and_then(|sc| sc.grant("EDIT"))
+            .and_then(|sc| sc.deprecate("EDIT", Some("WRITE"))) {
+            assert!(false);
+        }
+
+        let issues = scope.lint();
+        assert_eq!(issues.len(), 1usize);
+        assert_eq!(issues[0].permission, "EDIT");
+        assert_eq!(issues[0].kind, LintKind::DeprecatedInUse(Some("WRITE".to_string())));
+
+        // once migrated, the deprecated permission no longer shows up
+        let _ = scope.revoke("EDIT").and_then(|sc| sc.grant("WRITE"));
+        assert_eq!(scope.lint().is_empty(), true);
+    }
+
+    #[test]
+    fn test_lint_mutual_exclusion() {
+        let mut scope = Scope::new("TEST_SCOPE");
+
+        if let Err(_) = scope
+            .add_permission("SUBMITTER")
+            .and_then(|sc| sc.add_permission("APPROVER"))
+            .and_then(|sc| sc.add_exclusion("SUBMITTER", "APPROVER")) {
+            assert!(false);
+        }
+
+        // bypass the scope-level checks to produce an invalid grant state
+        for name in ["SUBMITTER", "APPROVER"] {
+            if let Some(permission) = scope.permission(name) {
+                let _ = permission.grant();
+            }
+        }
+
+        let issues = scope.lint();
+        assert_eq!(issues.len(), 1usize);
+        assert_eq!(issues[0].permission, "APPROVER");
+        assert_eq!(issues[0].kind, LintKind::MutualExclusion("SUBMITTER".to_string()));
+    }
+
+    #[test]
+    fn test_lint_mutual_exclusion_through_implication() {
+        let mut scope = Scope::new("TEST_SCOPE");
+
+        if let Err(_) = scope
+            .add_permission("SUBMITTER")
+            .and_then(|sc| sc.add_permission("APPROVER"))
+            .and_then(|sc| sc.add_permission("ADMIN"))
+            .and_then(|sc| sc.add_implication("ADMIN", "APPROVER"))
+            .and_then(|sc| sc.add_exclusion("SUBMITTER", "APPROVER")) {
+            assert!(false);
+        }
+
+        // only SUBMITTER and ADMIN are granted, so APPROVER, which sorts first, is held through ADMIN
+        for name in ["SUBMITTER", "ADMIN"] {
+            if let Some(permission) = scope.permission(name) {
+                let _ = permission.grant();
+            }
+        }
+
+        let issues = scope.lint();
+        assert_eq!(issues.len(), 1usize);
+        assert_eq!(issues[0].permission, "APPROVER");
+        assert_eq!(issues[0].kind, LintKind::MutualExclusion("SUBMITTER".to_string()));
+    }
+}
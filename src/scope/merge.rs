@@ -0,0 +1,419 @@
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+use crate::audit::AuditOp;
+use crate::permission::Permission;
+use crate::scope::guard::MutationContext;
+use crate::scope::path::{PermissionPath, ScopePath};
+use crate::scope::Scope;
+
+/** ConflictKind describes why a permission could not be merged cleanly. */
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ConflictKind {
+    /** The permission has a different shift on each side, so stored values disagree on its bit. */
+    ShiftMismatch { ours: u8, theirs: u8 },
+    /** The permission was added on both sides with different grant states. */
+    GrantChanged { ours: bool, theirs: bool },
+    /**
+        The permission or child scope only exists in `theirs` and could not be added to the merged tree, e.g. as our
+        scope is full or uses the name for something else. `reason` is the message of the error adding it.
+     */
+    NotAdded { reason: String },
+    /**
+        A mutation guard of ours refused the grant or revocation the merge would take from `theirs`, so the merged
+        tree keeps our grant state. `reason` is the message of the error the guard was rejected with.
+     */
+    Rejected { reason: String }
+}
+
+/** MergeConflict is a permission whose merged state had to be chosen rather than derived. */
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MergeConflict {
+    /** The full permission path in the merged tree, e.g. `APP.billing:PAY`, or the scope path of a scope not added. */
+    pub path: String,
+    pub kind: ConflictKind
+}
+
+/** Resolution is the side a custom merge strategy picks for a grant conflict. */
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Resolution {
+    Ours,
+    Theirs,
+    /** Keep our grant state and report the conflict as unresolved. */
+    Unresolved
+}
+
+/**
+    MergeStrategy decides grant conflicts during a merge so that routine conflicts do not need a human.
+    Shift mismatches and additions that failed cannot be resolved by picking a side and are always reported as
+    unresolved.
+ */
+#[derive(Clone, Copy, Default)]
+pub enum MergeStrategy<'a> {
+    /** Keep our grant state and report every conflict as unresolved. */
+    #[default]
+    Manual,
+    PreferOurs,
+    PreferTheirs,
+    /** Grant the permission if either side grants it. */
+    PreferGranted,
+    Custom(&'a dyn Fn(&MergeConflict) -> Resolution)
+}
+
+impl MergeStrategy<'_> {
+    /** Get the grant state chosen for a grant conflict, or None if the conflict stays unresolved. */
+    fn resolve(&self, conflict: &MergeConflict) -> Option<bool> {
+        let (ours, theirs) = match conflict.kind {
+            ConflictKind::GrantChanged { ours, theirs } => (ours, theirs),
+            ConflictKind::ShiftMismatch { .. } | ConflictKind::NotAdded { .. } | ConflictKind::Rejected { .. } => {
+                return None;
+            }
+        };
+
+        let resolution = match self {
+            MergeStrategy::Manual => Resolution::Unresolved,
+            MergeStrategy::PreferOurs => Resolution::Ours,
+            MergeStrategy::PreferTheirs => Resolution::Theirs,
+            MergeStrategy::PreferGranted => return Some(ours || theirs),
+            MergeStrategy::Custom(resolve) => resolve(conflict)
+        };
+
+        return match resolution {
+            Resolution::Ours => Some(ours),
+            Resolution::Theirs => Some(theirs),
+            Resolution::Unresolved => None
+        }
+    }
+}
+
+/** MergeResult holds a merged tree and the conflicts encountered while producing it. */
+pub struct MergeResult {
+    pub merged: Scope,
+    /** Conflicts that need a decision; the merged tree keeps our side for these. */
+    pub conflicts: Vec<MergeConflict>,
+    /** Conflicts that the merge strategy decided. */
+    pub resolved: Vec<MergeConflict>
+}
+
+impl MergeResult {
+    pub fn is_clean(&self) -> bool {
+        return self.conflicts.is_empty();
+    }
+}
+
+impl Scope {
+    /**
+        Merge two trees edited concurrently from a common `base`, like a three-way merge of files. The result
+        starts from `ours`; child scopes and permissions added in `theirs` are added after ours (keeping their
+        relative order), and grant changes made only in `theirs` are applied. Conflicts are reported for
+        permissions whose shift differs between the merged tree and `theirs`, for permissions added on both sides
+        with different grant states, which keep our grant state, and for permissions and child scopes of `theirs`
+        that cannot be added to the merged tree, which are left out. Grant changes taken from `theirs` are checked
+        against the mutation guards of `ours`, and those refused keep our grant state and are reported as conflicts
+        too. Rules are taken from `ours`.
+     */
+    pub fn merge3(base: &Scope, ours: &Scope, theirs: &Scope) -> MergeResult {
+        return Scope::merge3_with(base, ours, theirs, MergeStrategy::Manual);
+    }
+
+    /** Merge like `merge3`, letting `strategy` decide grant conflicts. */
+    pub fn merge3_with(base: &Scope, ours: &Scope, theirs: &Scope, strategy: MergeStrategy) -> MergeResult {
+        return Scope::merge_trees(Some(base), ours, theirs, strategy);
+    }
+
+    /**
+        Merge two trees without a common base. Everything `theirs` adds is merged as in `merge3`, but as there is
+        no way to tell which side changed a grant, every permission granted on only one side is a conflict.
+     */
+    pub fn merge(ours: &Scope, theirs: &Scope) -> MergeResult {
+        return Scope::merge_with(ours, theirs, MergeStrategy::Manual);
+    }
+
+    /** Merge like `merge`, letting `strategy` decide grant conflicts. */
+    pub fn merge_with(ours: &Scope, theirs: &Scope, strategy: MergeStrategy) -> MergeResult {
+        return Scope::merge_trees(None, ours, theirs, strategy);
+    }
+
+    fn merge_trees(base: Option<&Scope>, ours: &Scope, theirs: &Scope, strategy: MergeStrategy) -> MergeResult {
+        let mut result = MergeResult { merged: ours.clone(), conflicts: vec![], resolved: vec![] };
+        let mut stack: Vec<ScopePath> = vec![ScopePath::new()];
+
+        while let Some(relative) = stack.pop() {
+            let theirs_scope = match theirs.find_scope(&relative) {
+                Some(scope) => scope,
+                None => continue
+            };
+            let target = match result.merged.resolve_scope_mut(&relative) {
+                Ok(scope) => scope,
+                Err(_) => continue
+            };
+
+            let base_scope = base.and_then(|base| base.find_scope(&relative));
+            let (conflicts, resolved) = (&mut result.conflicts, &mut result.resolved);
+            target.merge_flat(base_scope, ours.find_scope(&relative), theirs_scope, strategy, conflicts, resolved);
+
+            for child in theirs_scope.scopes.values() {
+                if !target.scopes.contains_key(&child.name) {
+                    if let Err(err) = target.add_scope(&child.name) {
+                        result.conflicts.push(MergeConflict {
+                            path: target.path.child(&child.name).to_string(),
+                            kind: ConflictKind::NotAdded { reason: err.to_string() }
+                        });
+                        continue;
+                    }
+                }
+                stack.push(relative.child(&child.name));
+            }
+        }
+
+        result.merged.touch();
+        result.conflicts.sort_by(|a, b| a.path.cmp(&b.path));
+        result.resolved.sort_by(|a, b| a.path.cmp(&b.path));
+
+        return result;
+    }
+
+    /** Merge the permissions of a single scope from `theirs` into this merged scope. */
+    fn merge_flat(
+        &mut self,
+        base: Option<&Scope>,
+        ours: Option<&Scope>,
+        theirs: &Scope,
+        strategy: MergeStrategy,
+        conflicts: &mut Vec<MergeConflict>,
+        resolved: &mut Vec<MergeConflict>
+    ) {
+        let mut theirs_permissions: Vec<&Permission> = theirs.permissions.values().collect();
+        theirs_permissions.sort_by_key(|permission| permission.value);
+
+        for theirs_permission in theirs_permissions {
+            let name = &theirs_permission.name;
+            let path = PermissionPath::new(self.path.clone(), name).to_string();
+            if !self.permissions.contains_key(name) {
+                if let Err(err) = self.add_permission(name) {
+                    conflicts.push(MergeConflict { path, kind: ConflictKind::NotAdded { reason: err.to_string() } });
+                    continue;
+                }
+            }
+
+            let base_granted = base.and_then(|scope| scope.permissions.get(name)).map(|permission| permission.has());
+            let ours_granted = ours.and_then(|scope| scope.permissions.get(name)).map(|permission| permission.has());
+            let theirs_granted = theirs_permission.has();
+
+            let (value, current) = match self.permissions.get(name) {
+                Some(permission) => (permission.value, permission.has()),
+                None => continue
+            };
+
+            if value != theirs_permission.value {
+                conflicts.push(MergeConflict {
+                    path: path.clone(),
+                    kind: ConflictKind::ShiftMismatch {
+                        ours: value.trailing_zeros() as u8,
+                        theirs: theirs_permission.value.trailing_zeros() as u8
+                    }
+                });
+            }
+
+            let granted = match (base_granted, ours_granted) {
+                (_, None) => theirs_granted,
+                // only one side can differ from a base grant state, so take the side that changed
+                (Some(base_granted), Some(ours_granted)) => match base_granted == ours_granted {
+                    true => theirs_granted,
+                    false => ours_granted
+                },
+                (None, Some(ours_granted)) if ours_granted == theirs_granted => ours_granted,
+                (None, Some(ours_granted)) => {
+                    let conflict = MergeConflict {
+                        path: path.clone(),
+                        kind: ConflictKind::GrantChanged { ours: ours_granted, theirs: theirs_granted }
+                    };
+
+                    match strategy.resolve(&conflict) {
+                        Some(granted) => {
+                            resolved.push(conflict);
+                            granted
+                        },
+                        None => {
+                            conflicts.push(conflict);
+                            ours_granted
+                        }
+                    }
+                }
+            };
+            if granted == current {
+                continue;
+            }
+
+            let op = match granted {
+                true => AuditOp::Grant,
+                false => AuditOp::Revoke
+            };
+            if let Err(err) = self.check_guards(op, name, &MutationContext::new()) {
+                conflicts.push(MergeConflict { path, kind: ConflictKind::Rejected { reason: err.to_string() } });
+                continue;
+            }
+            if let Some(permission) = self.permissions.get_mut(name) {
+                permission.has_permission = granted;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get_base_scope() -> Scope {
+        let mut scope = Scope::new("APP");
+
+        if let Err(_) = scope
+            .add_permission("READ")
+            .and_then(|sc| sc.add_permission("WRITE"))
+            .and_then(|sc| sc.grant("WRITE"))
+            .and_then(|sc| sc.add_scope("billing")) {
+            assert!(false);
+        }
+
+        return scope;
+    }
+
+    #[test]
+    fn test_merge3_combines_both_sides() {
+        let base = get_base_scope();
+        let mut ours = base.clone();
+        let mut theirs = base.clone();
+
+        if let Err(_) = ours.grant("READ") {
+            assert!(false);
+        }
+        if let Err(_) = theirs.revoke("WRITE").and_then(|sc| sc.add_scope("audit")) {
+            assert!(false);
+        }
+        if let Some(audit) = theirs.scope("audit") {
+            if let Err(_) = audit.add_permission("VIEW").and_then(|sc| sc.grant("VIEW")) {
+                assert!(false);
+            }
+        }
+
+        let result = Scope::merge3(&base, &ours, &theirs);
+
+        assert_eq!(result.is_clean(), true);
+        assert_eq!(result.merged.check("READ"), true);
+        assert_eq!(result.merged.check("WRITE"), false);
+        assert_eq!(result.merged.check("audit:VIEW"), true);
+        assert_eq!(result.merged.check("billing:VIEW"), false);
+    }
+
+    #[test]
+    fn test_merge3_reports_conflicts() {
+        let base = get_base_scope();
+        let mut ours = base.clone();
+        let mut theirs = base.clone();
+
+        if let Err(_) = ours.add_permission("DELETE").and_then(|sc| sc.add_permission("EXPORT")) {
+            assert!(false);
+        }
+        if let Err(_) = theirs.add_permission("EXPORT").and_then(|sc| sc.grant("EXPORT")) {
+            assert!(false);
+        }
+
+        let result = Scope::merge3(&base, &ours, &theirs);
+
+        assert_eq!(result.conflicts, vec![
+            MergeConflict {
+                path: "APP:EXPORT".to_string(),
+                kind: ConflictKind::ShiftMismatch { ours: 3, theirs: 2 }
+            },
+            MergeConflict {
+                path: "APP:EXPORT".to_string(),
+                kind: ConflictKind::GrantChanged { ours: false, theirs: true }
+            },
+        ]);
+        assert_eq!(result.merged.check("EXPORT"), false);
+    }
+
+    fn get_diverged_scopes() -> (Scope, Scope) {
+        let mut ours = Scope::new("APP");
+        let mut theirs = Scope::new("APP");
+
+        for scope in [&mut ours, &mut theirs] {
+            if let Err(_) = scope.add_permission("READ").and_then(|sc| sc.add_permission("WRITE")) {
+                assert!(false);
+            }
+        }
+        if let Err(_) = ours.grant("READ") {
+            assert!(false);
+        }
+        if let Err(_) = theirs.grant("WRITE") {
+            assert!(false);
+        }
+
+        return (ours, theirs);
+    }
+
+    #[test]
+    fn test_merge_reports_additions_that_fail() {
+        let base = get_base_scope();
+        let mut ours = base.clone();
+        let mut theirs = base.clone();
+
+        // our side used the names theirs adds for a scope and a permission respectively
+        if let Err(_) = ours.add_scope("EXPORT").and_then(|sc| sc.add_permission("audit")) {
+            assert!(false);
+        }
+        if let Err(_) = theirs
+            .add_permission("EXPORT")
+            .and_then(|sc| sc.grant("EXPORT"))
+            .and_then(|sc| sc.add_scope("audit")) {
+            assert!(false);
+        }
+
+        let result = Scope::merge3_with(&base, &ours, &theirs, MergeStrategy::PreferTheirs);
+
+        assert_eq!(result.is_clean(), false);
+        let paths: Vec<&str> = result.conflicts.iter().map(|conflict| conflict.path.as_str()).collect();
+        assert_eq!(paths, ["APP.audit", "APP:EXPORT"]);
+        assert_eq!(result.conflicts.iter().all(|conflict| matches!(conflict.kind, ConflictKind::NotAdded { .. })), true);
+        assert_eq!(result.merged.check("EXPORT"), false);
+        assert_eq!(result.merged.scopes().count(), 2usize);
+    }
+
+    #[test]
+    fn test_merge_strategies() {
+        let (ours, theirs) = get_diverged_scopes();
+
+        let manual = Scope::merge(&ours, &theirs);
+        assert_eq!(manual.conflicts.len(), 2usize);
+        assert_eq!(manual.resolved.len(), 0usize);
+        assert_eq!(manual.merged.as_u64(), 0b01);
+
+        let preferred = Scope::merge_with(&ours, &theirs, MergeStrategy::PreferTheirs);
+        assert_eq!(preferred.is_clean(), true);
+        assert_eq!(preferred.resolved.len(), 2usize);
+        assert_eq!(preferred.merged.as_u64(), 0b10);
+
+        let granted = Scope::merge_with(&ours, &theirs, MergeStrategy::PreferGranted);
+        assert_eq!(granted.merged.as_u64(), 0b11);
+
+        let ours_only = Scope::merge_with(&ours, &theirs, MergeStrategy::PreferOurs);
+        assert_eq!(ours_only.merged.as_u64(), 0b01);
+    }
+
+    #[test]
+    fn test_merge_custom_strategy() {
+        let (ours, theirs) = get_diverged_scopes();
+
+        // accept their grants of WRITE automatically, but leave anything else for review
+        let resolve = |conflict: &MergeConflict| match conflict.path.as_str() {
+            "APP:WRITE" => Resolution::Theirs,
+            _ => Resolution::Unresolved
+        };
+        let result = Scope::merge_with(&ours, &theirs, MergeStrategy::Custom(&resolve));
+
+        assert_eq!(result.resolved.len(), 1usize);
+        assert_eq!(result.conflicts.len(), 1usize);
+        assert_eq!(result.conflicts[0].path, "APP:READ");
+        assert_eq!(result.merged.as_u64(), 0b11);
+    }
+}
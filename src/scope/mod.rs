@@ -1,42 +1,247 @@
+mod alias;
+mod algebra;
+#[cfg(feature = "postcard")]
+pub mod binary;
+mod bulk;
+mod check;
+mod composite;
+mod deny;
+pub mod compact;
+pub mod constraint;
+pub mod context;
+pub mod deprecation;
+mod diagram;
+mod docs;
+pub mod encoding;
+pub mod explain;
+pub mod expiry;
 pub mod error;
+pub mod evaluate;
+pub mod fingerprint;
+pub mod grant_map;
+pub mod guard;
+#[cfg(feature = "uuid")]
+mod identity;
+mod iter;
+pub mod lint;
+pub mod merge;
+mod namespace;
+mod oauth;
+pub mod named;
+pub mod notation;
+pub mod order;
+pub mod overflow;
+pub mod patch;
+pub mod path;
+pub mod policy;
+pub mod preview;
+mod reference;
+mod serialize;
+pub mod simulate;
+pub mod snapshot;
+pub mod table;
+pub mod view;
+pub mod virtual_permission;
+pub mod walk;
+#[cfg(feature = "regex")]
+mod search;
 mod conversion;
 
-use std::collections::HashMap;
+pub use crate::scope::conversion::{PermissionEntry, ScopeMetadata, ScopeTuple};
+
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt;
+use core::fmt::{Debug, Formatter};
+use hashbrown::HashMap;
 use serde_json::Value;
 use crate::common::error::ErrorKind;
-use crate::permission::{Permission};
-use crate::scope::conversion::ScopeTuple;
+use crate::common::telemetry::{self, Timer};
+use crate::common::trace::{trace_event, trace_span};
+use crate::permission::{Permission, ValueCeiling, MAX_VALUE};
+use crate::permission::error::{PermissionError, PermissionErrorCase, PermissionErrorMetadata};
 use crate::scope::error::{ScopeError, ScopeErrorCase};
+use crate::scope::guard::{MutationContext, MutationGuard};
+use crate::scope::constraint::RequirementMode;
+use crate::scope::context::EvalCondition;
+use crate::scope::deprecation::{DeprecationMode, DeprecationWarning};
+use crate::scope::order::{ExportOptions, SortOrder};
+use crate::scope::overflow::OverflowMode;
+use crate::scope::path::ScopePath;
+use crate::scope::policy::ValidationPolicy;
+use crate::scope::virtual_permission::VirtualRule;
+
+/** The deepest level of child scopes accepted when expanding a scope from tuple form by default. */
+pub const DEFAULT_MAX_DEPTH: usize = 64;
 
 pub struct Scope {
     name: String,
     permissions: HashMap<String, Permission>,
     next_permission_shift: u8,
     scopes: HashMap<String, Scope>,
+    ceiling: ValueCeiling,
+    path: ScopePath,
+    policy: ValidationPolicy,
+    requirements: HashMap<String, Vec<String>>,
+    requirement_mode: RequirementMode,
+    implications: HashMap<String, Vec<String>>,
+    exclusions: HashMap<String, Vec<String>>,
+    required_sets: Vec<(String, Vec<String>)>,
+    deprecations: HashMap<String, Option<String>>,
+    deprecation_mode: DeprecationMode,
+    overflow_mode: OverflowMode,
+    warnings: Vec<DeprecationWarning>,
+    aliases: HashMap<String, String>,
+    references: Vec<(String, String)>,
+    /** Permissions of this scope referenced from an enclosing scope, with how many levels up that scope is. */
+    referenced: Vec<(usize, String)>,
+    virtuals: HashMap<String, VirtualRule>,
+    composites: HashMap<String, Vec<String>>,
+    guards: Vec<(String, MutationGuard)>,
+    conditions: HashMap<String, Vec<(String, EvalCondition)>>,
+    revision: u64,
+    permission_order: Vec<String>,
+    scope_order: Vec<String>,
 }
 
 impl Scope {
+    /** Creates a new scope whose values are safe to use in JS. */
     pub fn new(name: &str) -> Scope {
+        return Scope::with_ceiling(name, ValueCeiling::JsSafe);
+    }
+
+    /**
+        Creates a new scope whose permission values may not exceed the given ceiling.
+        Child scopes added to this scope inherit the same ceiling.
+     */
+    pub fn with_ceiling(name: &str, ceiling: ValueCeiling) -> Scope {
         return Scope {
             name: name.to_string(),
             permissions: HashMap::new(),
             next_permission_shift: 0,
-            scopes: HashMap::new()
+            scopes: HashMap::new(),
+            ceiling,
+            path: ScopePath::from_segments(&[name]),
+            policy: ValidationPolicy::new(),
+            requirements: HashMap::new(),
+            requirement_mode: RequirementMode::Reject,
+            implications: HashMap::new(),
+            exclusions: HashMap::new(),
+            required_sets: vec![],
+            deprecations: HashMap::new(),
+            deprecation_mode: DeprecationMode::Warn,
+            overflow_mode: OverflowMode::default(),
+            warnings: vec![],
+            aliases: HashMap::new(),
+            references: vec![],
+            referenced: vec![],
+            virtuals: HashMap::new(),
+            composites: HashMap::new(),
+            guards: vec![],
+            conditions: HashMap::new(),
+            revision: 0,
+            permission_order: vec![],
+            scope_order: vec![]
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        return &self.name;
+    }
+
+    /** Get the full path of this scope, starting from the root scope it was added to. */
+    pub fn path(&self) -> &ScopePath {
+        return &self.path;
+    }
+
+    /** Get the policy used to validate names added to this scope. */
+    pub fn policy(&self) -> &ValidationPolicy {
+        return &self.policy;
+    }
+
+    /** Replace the name validation policy for this scope and all of its child scopes. */
+    pub fn set_policy(&mut self, policy: ValidationPolicy) -> &mut Scope {
+        for scope in self.scopes.values_mut() {
+            scope.set_policy(policy.clone());
+        }
+        self.policy = policy;
+
+        return self;
+    }
+
+    /**
+        Get the revision of this scope, which increases whenever a permission, child scope, grant or rule is
+        changed through it, including through the references handed out by `permission`. Changes made directly on
+        a child scope only advance the child's revision; use `tree_revision` to cover the whole tree.
+     */
+    pub fn revision(&self) -> u64 {
+        return self.revision;
+    }
+
+    /** Record that this scope was modified. */
+    pub(crate) fn touch(&mut self) {
+        self.revision = self.revision.saturating_add(1);
+    }
+
+    /** Get the value ceiling enforced when adding permissions to this scope. */
+    pub fn ceiling(&self) -> ValueCeiling {
+        return self.ceiling;
+    }
+
+    /**
+        Change the value ceiling of this scope only, e.g. to let a scope that is never exported to JS use all 64
+        bits while the rest of the tree stays JS-safe. Existing child scopes keep their own ceiling and child scopes
+        added later inherit this one. The ceiling is recorded in exports. Fails with MaxValue if a permission of
+        this scope already exceeds the new ceiling.
+     */
+    pub fn set_ceiling(&mut self, ceiling: ValueCeiling) -> Result<&mut Scope, ErrorKind> {
+        if let Some(permission) = self.permissions.values().find(|permission| permission.value > ceiling.max_value()) {
+            return Err(ErrorKind::PermissionError(PermissionError::new(
+                PermissionErrorCase::MaxValue,
+                &permission.name,
+                PermissionErrorMetadata {
+                    shift: Some(permission.value.trailing_zeros() as u8),
+                    ceiling: Some(ceiling.max_value()),
+                    ..PermissionErrorMetadata::new()
+                }
+            )));
         }
+
+        self.ceiling = ceiling;
+        self.touch();
+
+        return Ok(self);
     }
 
     /** Find a permission within this user scope and **/
     pub fn add_permission(&mut self, name: &str) -> Result<&mut Scope, ErrorKind> {
-        return match self.validate_name(&name.to_string()) {
+        let normalized = self.policy.normalize(name).into_owned();
+        let name = normalized.as_str();
+
+        return match self.validate_name(name) {
             Ok(_) => {
-                let new_perm = Permission::new(name, self.next_permission_shift);
+                let new_perm = Permission::with_ceiling(name, self.next_permission_shift, self.ceiling);
 
                 return match new_perm {
                     Ok(perm) => {
                         self.permissions.insert(name.to_string(), perm);
-                        self.next_permission_shift = self.next_permission_shift + 1;
+                        self.permission_order.push(name.to_string());
+                        self.next_permission_shift += 1;
+                        self.touch();
+                        trace_event!(debug, path = %self.path, permission = name, "permission added");
                         return Ok(self);
                     },
+                    Err(ErrorKind::PermissionError(_)) if self.overflow_mode == OverflowMode::Spill && !self.has_capacity(1) => {
+                        self.spill_permission(name)?;
+                        self.touch();
+                        trace_event!(debug, path = %self.path, permission = name, "permission spilled into a continuation");
+                        return Ok(self);
+                    },
+                    // point operators at the exact scope that ran out of room
+                    Err(ErrorKind::PermissionError(err)) => Err(ErrorKind::PermissionError(
+                        err.in_scope(self.path.to_string(), self.ceiling.max_shift() as usize + 1)
+                    )),
                     Err(err) => Err(err)
                 }
             },
@@ -45,10 +250,15 @@ impl Scope {
     }
 
     pub fn add_scope(&mut self, name: &str) -> Result<&mut Scope, ErrorKind> {
-        return match self.validate_name(&name.to_string()) {
+        let normalized = self.policy.normalize(name).into_owned();
+        let name = normalized.as_str();
+
+        return match self.validate_name(name) {
             Ok(_) => {
-                let new_scope = Scope::new(name);
-                self.scopes.insert(name.to_string(), new_scope);
+                let new_scope = self.child_template(name);
+                self.insert_scope(new_scope);
+                self.touch();
+                trace_event!(debug, path = %self.path, scope = name, "scope added");
 
                 Ok(self)
             },
@@ -56,9 +266,28 @@ impl Scope {
         }
     }
 
-    /** Verify that the name given is not already contained within existing. **/
-    pub fn validate_name(&self, name: &String) -> Result<(), ErrorKind> {
-        let perm_unique = !self.permissions.is_empty() && self.permissions.contains_key(name);
+    /** Create an empty child scope that inherits the ceiling, policy, modes and guards of this scope. */
+    fn child_template(&self, name: &str) -> Scope {
+        let mut new_scope = Scope::with_ceiling(name, self.ceiling);
+        new_scope.path = self.path.child(name);
+        new_scope.policy = self.policy.clone();
+        new_scope.requirement_mode = self.requirement_mode;
+        new_scope.deprecation_mode = self.deprecation_mode;
+        new_scope.overflow_mode = self.overflow_mode;
+        new_scope.guards = self.guards.clone();
+
+        return new_scope;
+    }
+
+    /** Verify that the name given is allowed by the validation policy and not already contained within existing. **/
+    pub fn validate_name(&self, name: &str) -> Result<(), ErrorKind> {
+        if !self.policy.allows(name) {
+            return Err(ErrorKind::ScopeError(ScopeError::new(ScopeErrorCase::InvalidName, name)));
+        }
+        self.validate_not_alias(name)?;
+
+        let perm_unique = self.permissions.contains_key(name) || self.virtuals.contains_key(name)
+            || self.composites.contains_key(name) || self.continuation_holding(name).is_some();
         let scope_unique = !self.scopes.is_empty() && self.scopes.contains_key(name);
 
         return match (!perm_unique, !scope_unique) {
@@ -69,13 +298,59 @@ impl Scope {
         }
     }
 
-    /** Get a permission by name. */
+    /**
+        Get the number of permissions that can still be added before the shift limit of this scope's own value is
+        reached, after which further permissions spill into a continuation or are rejected, as the overflow mode
+        decides.
+     */
+    pub fn remaining_capacity(&self) -> usize {
+        return (self.ceiling.max_shift() as usize + 1).saturating_sub(self.next_permission_shift as usize);
+    }
+
+    /** Check whether `n` more permissions fit in this scope's own value without exceeding the shift limit. */
+    pub fn has_capacity(&self, n: usize) -> bool {
+        return n <= self.remaining_capacity();
+    }
+
+    /** Get a permission by name for modification, advancing the revision of this scope. */
     pub fn permission(&mut self, name: &str) -> Option<&mut Permission> {
         if self.permissions.is_empty() {
             return None
         }
 
-        self.permissions.get_mut(name)
+        let name = self.resolve_name(name);
+        if !self.permissions.contains_key(&name) {
+            return None;
+        }
+
+        // the permission may be granted or revoked through the reference, so handing it out counts as a change
+        self.touch();
+        self.permissions.get_mut(&name)
+    }
+
+    /** Get a permission by name without requiring mutable access. */
+    pub fn permission_ref(&self, name: &str) -> Option<&Permission> {
+        return self.permissions.get(&self.resolve_name(name));
+    }
+
+    /** Get a direct child scope by name without requiring mutable access. */
+    pub fn scope_ref(&self, name: &str) -> Option<&Scope> {
+        return self.child_scope(name);
+    }
+
+    /** Iterate over the permissions defined directly on this scope, in no particular order. */
+    pub(crate) fn permission_values(&self) -> impl Iterator<Item = &Permission> {
+        return self.permissions.values();
+    }
+
+    /** Iterate over the direct child scopes of this scope, in no particular order. */
+    pub(crate) fn child_scopes(&self) -> impl Iterator<Item = &Scope> {
+        return self.scopes.values();
+    }
+
+    /** Get a direct child scope by name without requiring mutable access. */
+    pub(crate) fn child_scope(&self, name: &str) -> Option<&Scope> {
+        return self.scopes.get(self.policy.normalize(name).as_ref());
     }
 
     /** Get a scope by name. */
@@ -84,7 +359,8 @@ impl Scope {
             return None
         }
 
-        self.scopes.get_mut(name)
+        let name = self.policy.normalize(name);
+        self.scopes.get_mut(name.as_ref())
     }
 
     /**
@@ -96,102 +372,526 @@ impl Scope {
 
         for permission in self.permissions.values() {
             if permission.has() {
-                value = value | permission.value;
+                value |= permission.value;
             }
         }
 
         return value;
     }
 
-    pub fn as_tuple(&self) -> ScopeTuple {
-        let mut permissions_vector: Vec<String> = vec![];
-        let mut scopes_vector: Vec<ScopeTuple> = vec![];
+    /**
+        Set the grant state of every permission in this scope (but not its child scopes) from a numeric value,
+        such as one produced by `as_u64` and stored in a database. Fails with UndefinedBits, without changing
+        anything, if the value sets bits that no permission is assigned to, and with GuardRejected if a mutation
+        guard refuses one of the grants or revocations it makes.
+     */
+    pub fn apply_u64(&mut self, value: u64) -> Result<&mut Scope, ErrorKind> {
+        return self.apply_u64_in(value, &MutationContext::new());
+    }
 
-        let mut i = 0;
-        for (name,_) in &self.permissions {
-            permissions_vector.insert(i, name.clone());
-            i += 1;
+    /** Set the grant state of this scope like `apply_u64`, passing `context` to the mutation guards of this scope. */
+    pub fn apply_u64_in(&mut self, value: u64, context: &MutationContext) -> Result<&mut Scope, ErrorKind> {
+        if value & !self.defined_bits() != 0 {
+            return Err(ErrorKind::ScopeError(ScopeError::new(ScopeErrorCase::UndefinedBits, &self.path.to_string())));
+        }
+        self.check_guards_for_value(value, context)?;
+
+        return self.apply_u64_unguarded(value);
+    }
+
+    /**
+        Set the grant state of this scope from a value without consulting its guards, for trees that are derived
+        rather than changed on behalf of a caller, or whose changes were already checked against the guards.
+     */
+    pub(crate) fn apply_u64_unguarded(&mut self, value: u64) -> Result<&mut Scope, ErrorKind> {
+        if value & !self.defined_bits() != 0 {
+            return Err(ErrorKind::ScopeError(ScopeError::new(ScopeErrorCase::UndefinedBits, &self.path.to_string())));
         }
 
-        i = 0;
-        for (_, scope) in &self.scopes {
-            scopes_vector.insert(i, scope.as_tuple()); // recursive collapse
+        for permission in self.permissions.values_mut() {
+            permission.has_permission = value & permission.value == permission.value;
+            if !permission.has_permission {
+                permission.expires_at = None;
+            }
         }
+        self.touch();
 
-        return ScopeTuple (self.name.clone(), self.as_u64(), permissions_vector, scopes_vector);
+        return Ok(self);
     }
 
-    pub fn as_json(&self) -> Value {
-        self.as_tuple().to_json()
+    /**
+        Verify that every permission in this scope and its child scopes can be represented in JS,
+        regardless of the ceiling the scope was created with. JS-facing exporters should call this
+        before handing values over to the JSVM.
+     */
+    pub fn validate_js_safe(&self) -> Result<(), ErrorKind> {
+        for permission in self.permissions.values() {
+            if permission.value > MAX_VALUE {
+                return Err(ErrorKind::PermissionError(PermissionError::new(
+                    PermissionErrorCase::MaxValue,
+                    &permission.name,
+                    PermissionErrorMetadata {
+                        shift: Some(permission.value.trailing_zeros() as u8),
+                        ceiling: Some(MAX_VALUE),
+                        ..PermissionErrorMetadata::new()
+                    }
+                )));
+            }
+        }
+
+        for scope in self.scopes.values() {
+            scope.validate_js_safe()?;
+        }
+
+        return Ok(());
     }
 
-    pub fn from_json(val: Value) -> Scope {
-        Scope::from(ScopeTuple::from(val))
+    /**
+        Collapse this scope and all of its child scopes into tuple form. The tree is walked with an
+        explicit stack rather than recursion so that deeply nested scopes cannot overflow the call stack.
+     */
+    pub fn as_tuple(&self) -> ScopeTuple {
+        return self.as_tuple_with(&ExportOptions::default());
     }
-}
 
-impl Clone for ScopeTuple {
-    fn clone(&self) -> Self {
-        return ScopeTuple(self.0.clone(), self.1.clone(), self.2.clone(), self.3.clone());
+    /** Collapse this scope and all of its child scopes into tuple form, listing names in the order chosen by `options`. */
+    pub fn as_tuple_with(&self, options: &ExportOptions) -> ScopeTuple {
+        let _span = trace_span!("bitperm.as_tuple", scope = %self.path);
+        let mut stack = vec![(self, self.scopes_in_order(options.order).into_iter(), Vec::<ScopeTuple>::new())];
+
+        loop {
+            let next_child = match stack.last_mut() {
+                Some((_, children, _)) => children.next(),
+                None => unreachable!("the root scope is only popped once its tuple is complete")
+            };
+
+            match next_child {
+                Some(child) => stack.push((child, child.scopes_in_order(options.order).into_iter(), Vec::new())),
+                None => {
+                    if let Some((scope, _, child_tuples)) = stack.pop() {
+                        let tuple = scope.as_flat_tuple(child_tuples, options);
+
+                        match stack.last_mut() {
+                            Some((_, _, siblings)) => siblings.push(tuple),
+                            None => return tuple // finished collapsing the root scope
+                        }
+                    }
+                }
+            }
+        }
     }
-}
 
-impl From<ScopeTuple> for Scope {
-    fn from(ScopeTuple (name, permission_number, permission_names, child_scopes): ScopeTuple) -> Self {
-        let mut permissions = HashMap::<String, Permission>::new();
-        let mut scopes = HashMap::<String, Scope>::new();
+    /** Collapse only this scope into tuple form, using child tuples that have already been collapsed. */
+    fn as_flat_tuple(&self, scopes_vector: Vec<ScopeTuple>, options: &ExportOptions) -> ScopeTuple {
+        let permissions_vector: Vec<PermissionEntry> = self.permissions_in_order(options.order).into_iter()
+            .map(|permission| PermissionEntry::Placed(permission.name.clone(), permission.value.trailing_zeros() as u8))
+            .collect();
 
-        let mut i = 0;
-        let permission_count = permission_names.len();
-        let scope_count = child_scopes.len();
+        let mut metadata = self.as_metadata();
+        if !options.revisions {
+            metadata.revision = 0;
+        }
+        if options.schema_only {
+            metadata.revision = 0;
+            metadata.expirations.clear();
+            metadata.denied = 0;
+            return ScopeTuple (self.name.clone(), 0, permissions_vector, scopes_vector, metadata);
+        }
+
+        return ScopeTuple (self.name.clone(), self.as_u64(), permissions_vector, scopes_vector, metadata);
+    }
+
+    /** Collect the schema rules declared on this scope (but not its child scopes) in a stable order. */
+    fn as_metadata(&self) -> ScopeMetadata {
+        let mut metadata = ScopeMetadata::new();
 
-        // populate a hashmap with k-v pairs of (name, permission)
-        let r_expand_permissions: Result<(), ()> = loop {
-            if i >= permission_count {
-                break Ok(());
+        for (permission, requirements) in &self.requirements {
+            for required in requirements {
+                metadata.requirements.push((permission.clone(), required.clone()));
+            }
+        }
+        for (permission, implications) in &self.implications {
+            for implied in implications {
+                metadata.implications.push((permission.clone(), implied.clone()));
             }
+        }
 
-            if let Ok(mut perm) = Permission::new(permission_names[i].as_str(), i as u8) {
-                if permission_number & perm.value == perm.value {
-                    let _ = perm.grant(); // we have the numeric amount, so grant the permission in expanded form
+        for (permission, exclusions) in &self.exclusions {
+            for excluded in exclusions {
+                // exclusions are symmetric, so only export each pair once
+                if permission < excluded {
+                    metadata.exclusions.push((permission.clone(), excluded.clone()));
                 }
+            }
+        }
 
-                permissions.insert(permission_names[i].clone(), perm);
-            } else {
-                break Err(());
+        metadata.required_sets = self.required_sets.clone();
+        for (permission, replacement) in &self.deprecations {
+            metadata.deprecations.push((permission.clone(), replacement.clone()));
+        }
+        for (alias, target) in &self.aliases {
+            metadata.aliases.push((alias.clone(), target.clone()));
+        }
+        metadata.references = self.references.clone();
+        for (composite, members) in &self.composites {
+            metadata.composites.push((composite.clone(), members.clone()));
+        }
+        for permission in self.permissions.values() {
+            if let Some(description) = &permission.description {
+                metadata.descriptions.push((permission.name.clone(), description.clone()));
+            }
+            if !permission.tags.is_empty() {
+                metadata.tags.push((permission.name.clone(), permission.tags.clone()));
+            }
+            if let (true, Some(expires_at)) = (permission.has(), permission.expires_at) {
+                metadata.expirations.push((permission.name.clone(), expires_at));
             }
+            #[cfg(feature = "uuid")]
+            if let Some(id) = permission.id {
+                metadata.ids.push((permission.name.clone(), id));
+            }
+        }
 
-            i += 1;
-        };
+        metadata.requirements.sort();
+        metadata.implications.sort();
+        metadata.exclusions.sort();
+        metadata.composites.sort();
+        metadata.deprecations.sort();
+        metadata.revision = self.revision;
+        metadata.denied = self.as_deny_u64();
+        metadata.ceiling = self.ceiling;
+        metadata.aliases.sort();
+        metadata.descriptions.sort();
+        metadata.tags.sort();
+        metadata.expirations.sort();
+        #[cfg(feature = "uuid")]
+        metadata.ids.sort();
+
+        return metadata;
+    }
+
+    /** Declare the schema rules carried by a tuple on this scope. */
+    fn apply_metadata(&mut self, metadata: ScopeMetadata) -> Result<(), ErrorKind> {
+        for (permission, required) in metadata.requirements {
+            self.add_requirement(&permission, &required)?;
+        }
+        for (permission, implied) in metadata.implications {
+            self.add_implication(&permission, &implied)?;
+        }
+        for (first, second) in metadata.exclusions {
+            self.add_exclusion(&first, &second)?;
+        }
+        for (set, permissions) in metadata.required_sets {
+            let permissions: Vec<&str> = permissions.iter().map(|permission| permission.as_str()).collect();
+            self.add_required_set(&set, &permissions)?;
+        }
+        for (composite, members) in metadata.composites {
+            let members: Vec<&str> = members.iter().map(|member| member.as_str()).collect();
+            self.add_composite(&composite, &members)?;
+        }
+        for (permission, replacement) in metadata.deprecations {
+            self.deprecate(&permission, replacement.as_deref())?;
+        }
+        for (alias, target) in metadata.aliases {
+            let target = self.find_permission_name(&target)?;
+            self.aliases.insert(alias, target);
+        }
+        // references may point into child scopes, which are only attached after this scope is expanded
+        self.references = metadata.references;
+        for (permission, description) in metadata.descriptions {
+            self.describe(&permission, &description)?;
+        }
+        for (permission, tags) in metadata.tags {
+            for tag in tags {
+                self.tag(&permission, &tag)?;
+            }
+        }
+        for (permission, expires_at) in metadata.expirations {
+            let name = self.find_permission_name(&permission)?;
+            if let Some(permission) = self.permissions.get_mut(&name) {
+                permission.expires_at = Some(expires_at);
+            }
+        }
+        #[cfg(feature = "uuid")]
+        for (permission, id) in metadata.ids {
+            self.assign_id(&permission, id)?;
+        }
+
+        return Ok(());
+    }
+
+    /**
+        Expand a scope and all of its child scopes from tuple form, failing if child scopes are nested
+        more than `max_depth` levels below the root. The tree is rebuilt with an explicit stack rather than
+        recursion so that malicious or pathological inputs cannot overflow the call stack.
+     */
+    pub fn from_tuple(tuple: ScopeTuple, max_depth: usize) -> Result<Scope, ErrorKind> {
+        let _span = trace_span!("bitperm.from_tuple", scope = %tuple.0, max_depth);
+        let timer = Timer::start();
+
+        let result = Scope::expand_tuple_tree(tuple, max_depth);
+        telemetry::record_import(timer, result.is_ok());
+        #[cfg(feature = "tracing")]
+        if let Err(err) = &result {
+            trace_event!(warn, error = %crate::common::trace::error_message(err), "scope import failed");
+        }
+
+        return result;
+    }
+
+    /**
+        Expand a scope from tuple form like `from_tuple`, and when `expected_fingerprint` is given, fail with
+        SchemaMismatch unless the imported schema has that fingerprint.
+     */
+    pub fn from_tuple_verified(tuple: ScopeTuple, max_depth: usize, expected_fingerprint: Option<u64>) -> Result<Scope, ErrorKind> {
+        let scope = Scope::from_tuple(tuple, max_depth)?;
 
-        if r_expand_permissions.is_err() {
-            panic!("Unable to transform scope tuple into scope: failed to expand permissions.")
+        if let Some(expected) = expected_fingerprint {
+            scope.verify_fingerprint(expected)?;
         }
 
-        i = 0;
-        let r_expand_scopes: Result<(), ()> = loop {
-            if i >= scope_count {
-                break Ok(())
+        return Ok(scope);
+    }
+
+    fn expand_tuple_tree(tuple: ScopeTuple, max_depth: usize) -> Result<Scope, ErrorKind> {
+        let (root, child_tuples) = Scope::expand_flat_tuple(tuple, None)?;
+        let mut stack = vec![(root, child_tuples.into_iter())];
+
+        loop {
+            let next_child = match stack.last_mut() {
+                Some((_, children)) => children.next(),
+                None => unreachable!("the root scope is only popped once it is fully expanded")
+            };
+
+            match next_child {
+                Some(child_tuple) => {
+                    if stack.len() > max_depth {
+                        return Err(ErrorKind::ScopeError(ScopeError::new(ScopeErrorCase::MaxDepth, &child_tuple.0)));
+                    }
+
+                    let parent = stack.last().map(|(parent, _)| parent);
+                    let (mut child, grandchild_tuples) = Scope::expand_flat_tuple(child_tuple, parent)?;
+                    if let Some(parent) = parent {
+                        child.path = parent.path.child(&child.name);
+                    }
+
+                    stack.push((child, grandchild_tuples.into_iter()));
+                },
+                None => {
+                    if let Some((scope, _)) = stack.pop() {
+                        match stack.last_mut() {
+                            Some((parent, _)) => parent.insert_scope(scope),
+                            None => {
+                                // final constructed scope is expanded from tuple form
+                                let mut scope = scope;
+                                scope.mark_references();
+                                return Ok(scope);
+                            }
+                        }
+                    }
+                }
             }
+        }
+    }
 
-            let ScopeTuple (n,p, r, c) = child_scopes[i].clone();
-            let child = Scope::from(ScopeTuple(n.clone(), p, r, c));
+    /**
+        Expand only the top level of a tuple into a scope, handing back its child tuples unexpanded. The parent the
+        scope is expanded under, if any, decides whether its name may be that of a continuation.
+     */
+    fn expand_flat_tuple(ScopeTuple (name, permission_number, entries, child_scopes, metadata): ScopeTuple, parent: Option<&Scope>) -> Result<(Scope, Vec<ScopeTuple>), ErrorKind> {
+        let mut permissions = HashMap::<String, Permission>::new();
 
-            scopes.insert(n.to_string(), child);
+        // entries without a recorded shift take the bit of their position, as in tuples written before shifts were
+        let placed: Vec<(String, u8)> = entries.into_iter().enumerate()
+            .map(|(i, entry)| {
+                let shift = entry.shift().unwrap_or(i as u8);
+                (entry.name().to_string(), shift)
+            })
+            .collect();
+        let width = placed.iter().map(|(_, shift)| *shift as usize + 1).max().unwrap_or(0);
+
+        // imported names follow the same policy as names added one by one, so every name stays addressable by path
+        let policy = ValidationPolicy::new();
+        let continuation = parent.map(|parent| parent.is_continuation(&name)).unwrap_or(false);
+        let names = core::iter::once(&name).filter(|_| !continuation)
+            .chain(placed.iter().map(|(permission_name, _)| permission_name));
+        for imported in names {
+            if !policy.allows(imported) {
+                return Err(ErrorKind::ScopeError(ScopeError::new(ScopeErrorCase::InvalidName, imported)));
+            }
+        }
 
-            i += 1;
+        // tuples written before ceilings were recorded only lift the JS-safe one when they cannot fit within it
+        let ceiling = if metadata.ceiling == ValueCeiling::Full || width > ValueCeiling::JsSafe.max_shift() as usize + 1 {
+            ValueCeiling::Full
+        } else {
+            ValueCeiling::JsSafe
         };
 
-        if r_expand_scopes.is_err() {
-            panic!("Unable to transform scope tuple into scope: failed to expand child scopes.")
+        // populate a hashmap with k-v pairs of (name, permission), keeping the order of the tuple
+        let mut permission_order: Vec<String> = Vec::with_capacity(placed.len());
+        let mut assigned: u64 = 0;
+        for (permission_name, shift) in placed {
+            let mut perm = Permission::with_ceiling(permission_name.as_str(), shift, ceiling)?;
+            if assigned & perm.value != 0 {
+                return Err(ErrorKind::ScopeError(ScopeError::new(ScopeErrorCase::DuplicateShift, &permission_name)));
+            }
+            assigned |= perm.value;
+            if permission_number & perm.value == perm.value {
+                let _ = perm.grant(); // we have the numeric amount, so grant the permission in expanded form
+            }
+
+            permission_order.push(permission_name.clone());
+            permissions.insert(permission_name, perm);
         }
 
-        let mut scope = Scope::new(name.as_str());
+        let mut scope = Scope::with_ceiling(name.as_str(), ceiling);
         scope.permissions = permissions;
-        scope.next_permission_shift = permission_count as u8;
-        scope.scopes = scopes;
+        scope.permission_order = permission_order;
+        scope.next_permission_shift = width as u8;
+        let revision = metadata.revision;
+        let denied = metadata.denied;
+        scope.apply_metadata(metadata)?;
+        scope.apply_deny_u64(denied)?;
+        scope.revision = revision; // declaring the rules above is not a change to the exported scope
+
+        return Ok((scope, child_scopes));
+    }
 
-        scope // final constructed scope is expanded from tuple form
+    pub fn as_json(&self) -> Value {
+        self.as_tuple().to_json()
+    }
+
+    /** Export to JSON, the same as `as_json`, for symmetry with `try_from_json`. */
+    pub fn to_json(&self) -> Value {
+        return self.as_json();
+    }
+
+    /** Export to JSON, listing names in the order chosen by `options`. */
+    pub fn as_json_with(&self, options: &ExportOptions) -> Value {
+        self.as_tuple_with(options).to_json()
+    }
+
+    /**
+        Export only the schema to JSON: names, bits, hierarchy and rules, but no values and no revision, so the
+        artifact shared between services never carries the permissions of whoever the scope was granted to.
+     */
+    pub fn as_schema_json(&self) -> Value {
+        self.as_json_with(&ExportOptions { schema_only: true, ..ExportOptions::default() })
+    }
+
+    /** Import a scope from JSON. Panics if the JSON is not a valid export; use `try_from_json` for untrusted input. */
+    pub fn from_json(val: Value) -> Scope {
+        Scope::from(ScopeTuple::from(val))
+    }
+
+    /**
+        Import a scope from JSON produced by `to_json` or `as_json`, failing with InvalidEncoding instead of
+        panicking if it is malformed, or with the error of the first permission or rule that cannot be restored.
+     */
+    pub fn try_from_json(val: Value) -> Result<Scope, ErrorKind> {
+        return Scope::from_json_verified(val, None);
+    }
+
+    /**
+        Import a scope from JSON without panicking on malformed input, and when `expected_fingerprint` is given,
+        fail with SchemaMismatch unless the imported schema has that fingerprint.
+     */
+    pub fn from_json_verified(val: Value, expected_fingerprint: Option<u64>) -> Result<Scope, ErrorKind> {
+        return Scope::from_tuple_verified(ScopeTuple::try_from_json(val)?, DEFAULT_MAX_DEPTH, expected_fingerprint);
+    }
+}
+
+impl Clone for ScopeTuple {
+    fn clone(&self) -> Self {
+        return ScopeTuple(self.0.clone(), self.1, self.2.clone(), self.3.clone(), self.4.clone());
+    }
+}
+
+impl From<ScopeTuple> for Scope {
+    fn from(value: ScopeTuple) -> Self {
+        return match Scope::from_tuple(value, DEFAULT_MAX_DEPTH) {
+            Ok(scope) => scope,
+            Err(ErrorKind::PermissionError(err)) => panic!("Unable to transform scope tuple into scope: {}", err),
+            Err(ErrorKind::ScopeError(err)) => panic!("Unable to transform scope tuple into scope: {}", err)
+        }
+    }
+}
+
+impl Scope {
+    /** Copy this scope without any of its child scopes. */
+    fn clone_flat(&self) -> Scope {
+        return Scope {
+            name: self.name.clone(),
+            permissions: self.permissions.clone(),
+            next_permission_shift: self.next_permission_shift,
+            scopes: HashMap::new(),
+            ceiling: self.ceiling,
+            path: self.path.clone(),
+            policy: self.policy.clone(),
+            requirements: self.requirements.clone(),
+            requirement_mode: self.requirement_mode,
+            implications: self.implications.clone(),
+            exclusions: self.exclusions.clone(),
+            required_sets: self.required_sets.clone(),
+            deprecations: self.deprecations.clone(),
+            deprecation_mode: self.deprecation_mode,
+            overflow_mode: self.overflow_mode,
+            warnings: self.warnings.clone(),
+            aliases: self.aliases.clone(),
+            references: self.references.clone(),
+            referenced: self.referenced.clone(),
+            virtuals: self.virtuals.clone(),
+            composites: self.composites.clone(),
+            guards: self.guards.clone(),
+            conditions: self.conditions.clone(),
+            revision: self.revision,
+            permission_order: self.permission_order.clone(),
+            scope_order: vec![]
+        }
+    }
+}
+
+impl Clone for Scope {
+    fn clone(&self) -> Self {
+        // copy the tree with an explicit stack, attaching each copy to its parent once its children are done
+        let mut stack = vec![(self.clone_flat(), self.scopes_in_order(SortOrder::Insertion).into_iter())];
+
+        loop {
+            let next_child = match stack.last_mut() {
+                Some((_, children)) => children.next(),
+                None => unreachable!("the root scope is only popped once it is fully copied")
+            };
+
+            match next_child {
+                Some(child) => stack.push((child.clone_flat(), child.scopes_in_order(SortOrder::Insertion).into_iter())),
+                None => {
+                    if let Some((scope, _)) = stack.pop() {
+                        match stack.last_mut() {
+                            Some((parent, _)) => parent.insert_scope(scope),
+                            None => return scope
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl Drop for Scope {
+    fn drop(&mut self) {
+        // tear down child scopes with an explicit stack so deeply nested trees cannot overflow the call stack
+        let mut stack: Vec<Scope> = self.scopes.drain().map(|(_, scope)| scope).collect();
+        while let Some(mut scope) = stack.pop() {
+            stack.extend(scope.scopes.drain().map(|(_, child)| child));
+        }
+    }
+}
+
+// the JSON form shows the whole tree, including grants and rules, without recursing through Debug
+impl Debug for Scope {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "Scope({})", self.as_json())
     }
 }
 
@@ -203,7 +903,8 @@ impl From<Scope> for ScopeTuple {
 
 #[cfg(test)]
 mod tests {
-    use crate::permission::MAX_VALUE;
+    use crate::{assert_ok, assert_scope_err};
+    use crate::permission::{MAX_SHIFT, MAX_VALUE};
     use super::*;
 
     #[test]
@@ -279,7 +980,7 @@ mod tests {
                 }
             }
 
-            i = i + 1
+            i += 1
         }
 
         // check that all 31 are there and properly named
@@ -319,6 +1020,55 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_scope_add_invalid_names() {
+        let mut scope = Scope::new("TEST_SCOPE");
+
+        for name in vec!["", "USER.READ", "USER:READ", "READ WRITE", "READ\t"] {
+            match scope.add_permission(name) {
+                Ok(_) => assert!(false), // should not accept a name that would make paths ambiguous
+                Err(kind) => match kind {
+                    ErrorKind::ScopeError(err) => assert_eq!(err.case(), ScopeErrorCase::InvalidName),
+                    ErrorKind::PermissionError(_) => assert!(false),
+                }
+            }
+
+            assert!(scope.add_scope(name).is_err());
+        }
+
+        assert_eq!(scope.permissions.is_empty(), true);
+        assert_eq!(scope.scopes.is_empty(), true);
+    }
+
+    #[test]
+    fn test_scope_permissive_policy_inherited() {
+        let mut scope = Scope::new("TEST_SCOPE");
+        scope.set_policy(ValidationPolicy::permissive());
+
+        if let Ok(_) = scope.add_scope("CHILD SCOPE") {
+            match scope.scope("CHILD SCOPE") {
+                Some(child_scope) => assert!(child_scope.add_permission("CAN READ").is_ok()),
+                None => assert!(false)
+            }
+        } else {
+            assert!(false);
+        }
+    }
+
+    #[cfg(feature = "unicode")]
+    #[test]
+    fn test_scope_normalized_names_collide() {
+        let mut scope = Scope::new("TEST_SCOPE");
+        let mut policy = ValidationPolicy::new();
+        policy.normalize_nfc = true;
+        scope.set_policy(policy);
+
+        assert!(scope.add_permission("CAF\u{00C9}").is_ok());
+        // the decomposed form is visually identical and must be rejected as a duplicate
+        assert!(scope.add_permission("CAFE\u{0301}").is_err());
+        assert!(scope.permission("CAFE\u{0301}").is_some());
+    }
+
     #[test]
     fn test_get_permission_exists_some() {
         match
@@ -432,7 +1182,7 @@ mod tests {
             assert_eq!(value, expected);
             assert!(value < MAX_VALUE);
 
-            i = i + 1;
+            i += 1;
         }
     }
 
@@ -524,10 +1274,334 @@ mod tests {
                 }
             }
 
-            i = i + 1;
+            i += 1;
         }
 
         assert_eq!(scope.as_u64(), get_test_scope_value(scope.permissions.len() as u8));
     }
 
+    #[test]
+    fn test_apply_u64_round_trip() {
+        let mut scope = Scope::new("TEST_SCOPE");
+        assert_ok!(scope.add_permission("READ").and_then(|sc| sc.add_permission("WRITE")).and_then(|sc| sc.add_permission("EXECUTE")));
+        assert_ok!(scope.grant("READ").and_then(|sc| sc.grant("EXECUTE")));
+        let stored = scope.as_u64();
+
+        let mut loaded = scope.clone();
+        assert_ok!(loaded.apply_u64(0).and_then(|sc| sc.apply_u64(stored)));
+        assert_eq!(loaded.as_u64(), 0b101);
+        assert_eq!(loaded.permission_ref("WRITE").map(|permission| permission.has()), Some(false));
+
+        assert_scope_err!(loaded.apply_u64(0b1000), ScopeErrorCase::UndefinedBits);
+        assert_eq!(loaded.as_u64(), 0b101);
+    }
+
+    #[test]
+    fn test_has_capacity_empty_scope() {
+        let scope = Scope::new("TEST_SCOPE");
+
+        assert_eq!(scope.remaining_capacity(), MAX_SHIFT as usize + 1);
+        assert_eq!(scope.has_capacity(0), true);
+        assert_eq!(scope.has_capacity(MAX_SHIFT as usize + 1), true);
+        assert_eq!(scope.has_capacity(MAX_SHIFT as usize + 2), false);
+    }
+
+    #[test]
+    fn test_has_capacity_full_ceiling() {
+        let scope = Scope::with_ceiling("TEST_SCOPE", ValueCeiling::Full);
+
+        assert_eq!(scope.remaining_capacity(), 64);
+        assert_eq!(scope.has_capacity(64), true);
+        assert_eq!(scope.has_capacity(65), false);
+    }
+
+    #[test]
+    fn test_full_ceiling_add_64_permissions() {
+        let mut scope = Scope::with_ceiling("TEST_SCOPE", ValueCeiling::Full);
+        scope.set_overflow_mode(OverflowMode::Reject);
+        let mut i = 0;
+
+        loop {
+            if i >= 64 {
+                break;
+            }
+
+            let name = format!("TEST_PERMISSION_{}", i);
+            assert!(scope.add_permission(name.as_str()).is_ok());
+            i += 1;
+        }
+
+        assert_eq!(scope.permissions.len(), 64usize);
+        assert!(scope.add_permission("ONE_TOO_MANY").is_err());
+
+        // a full-width scope cannot be handed over to JS
+        assert!(scope.validate_js_safe().is_err());
+    }
+
+    #[test]
+    fn test_schema_json_omits_grants() {
+        let mut scope = Scope::new("APP");
+        if let Err(_) = scope
+            .add_permission("READ")
+            .and_then(|sc| sc.add_permission("WRITE"))
+            .and_then(|sc| sc.add_implication("WRITE", "READ"))
+            .and_then(|sc| sc.add_scope("billing"))
+            .and_then(|sc| sc.grant("WRITE")) {
+            assert!(false);
+        }
+        if let Some(billing) = scope.scope("billing") {
+            if let Err(_) = billing.add_permission("REFUND").and_then(|sc| sc.grant("REFUND")) {
+                assert!(false);
+            }
+        }
+
+        let schema = scope.as_schema_json();
+        assert_eq!(schema[1], 0);
+        assert_eq!(schema[3][0][1], 0);
+        assert_eq!(schema[4].get("revision"), None);
+
+        // the schema itself is unchanged, and only the grants are lost on the way
+        let mut imported = Scope::from_json(schema);
+        assert_eq!(imported.as_u64(), 0);
+        assert_eq!(imported.check("billing:REFUND"), false);
+        assert_eq!(imported.fingerprint(), scope.fingerprint());
+        if let Err(_) = imported.grant("WRITE") {
+            assert!(false);
+        }
+        assert_eq!(imported.check("READ"), true);
+    }
+
+    #[test]
+    fn test_scope_opts_out_of_js_safe_ceiling() {
+        let mut scope = Scope::new("APP");
+        if let Err(_) = scope
+            .add_permission("READ")
+            .and_then(|sc| sc.add_scope("internal"))
+            .and_then(|sc| sc.add_scope("public")) {
+            assert!(false);
+        }
+
+        match scope.scope("internal") {
+            Some(internal) => {
+                if let Err(_) = internal.set_ceiling(ValueCeiling::Full) {
+                    assert!(false);
+                }
+                for i in 0..60 {
+                    assert!(internal.add_permission(format!("FLAG_{}", i).as_str()).is_ok());
+                }
+
+                // the ceiling cannot be lowered below the permissions already added
+                match internal.set_ceiling(ValueCeiling::JsSafe) {
+                    Err(ErrorKind::PermissionError(err)) => assert_eq!(err.case(), PermissionErrorCase::MaxValue),
+                    _ => assert!(false)
+                }
+            },
+            None => assert!(false)
+        }
+        match scope.scope("public") {
+            Some(public) => assert_eq!(public.ceiling(), ValueCeiling::JsSafe),
+            None => assert!(false)
+        }
+        assert_eq!(scope.ceiling(), ValueCeiling::JsSafe);
+
+        // the ceiling travels with the export, even for scopes that would fit a JS-safe one
+        let mut imported = Scope::from_json(scope.as_json());
+        assert_eq!(imported.ceiling(), ValueCeiling::JsSafe);
+        match imported.scope("internal") {
+            Some(internal) => assert_eq!(internal.ceiling(), ValueCeiling::Full),
+            None => assert!(false)
+        }
+        match imported.scope("public") {
+            Some(public) => {
+                assert_eq!(public.ceiling(), ValueCeiling::JsSafe);
+                if let Err(_) = public.set_ceiling(ValueCeiling::Full) {
+                    assert!(false);
+                }
+            },
+            None => assert!(false)
+        }
+        match Scope::from_json(imported.as_json()).scope("public") {
+            Some(public) => assert_eq!(public.ceiling(), ValueCeiling::Full),
+            None => assert!(false)
+        }
+    }
+
+    #[test]
+    fn test_child_scope_inherits_ceiling() {
+        let mut scope = Scope::with_ceiling("TEST_SCOPE", ValueCeiling::Full);
+
+        if let Ok(_) = scope.add_scope("CHILD_SCOPE") {
+            match scope.scope("CHILD_SCOPE") {
+                Some(child_scope) => assert_eq!(child_scope.ceiling(), ValueCeiling::Full),
+                None => assert!(false)
+            }
+        } else {
+            assert!(false);
+        }
+    }
+
+    #[test]
+    fn test_validate_js_safe_ok() {
+        let mut scope = Scope::new("TEST_SCOPE");
+
+        if let Ok(_) = scope
+            .add_permission("READ")
+            .and_then(|sc| sc.add_permission("WRITE")) {
+            assert!(scope.validate_js_safe().is_ok());
+        } else {
+            assert!(false);
+        }
+    }
+
+    /** Build a chain of scopes nested `depth` levels below a root scope. */
+    fn get_nested_scope_tuple(depth: usize) -> ScopeTuple {
+        let mut tuple = ScopeTuple(format!("SCOPE_{}", depth), 0, vec![], vec![], ScopeMetadata::new());
+        let mut i = depth;
+
+        while i > 0 {
+            i -= 1;
+            tuple = ScopeTuple(format!("SCOPE_{}", i), 0, vec![], vec![tuple], ScopeMetadata::new());
+        }
+
+        return tuple;
+    }
+
+    #[test]
+    fn test_from_tuple_within_max_depth() {
+        match Scope::from_tuple(get_nested_scope_tuple(10), 10) {
+            Ok(scope) => {
+                let mut depth = 0;
+                let mut current = &scope;
+                while let Some(child) = current.scopes.get(format!("SCOPE_{}", depth + 1).as_str()) {
+                    current = child;
+                    depth += 1;
+                }
+
+                assert_eq!(depth, 10);
+                assert_eq!(current.path().len(), 11usize);
+            },
+            Err(_) => assert!(false)
+        }
+    }
+
+    #[test]
+    fn test_from_tuple_err_exceeded_max_depth() {
+        match Scope::from_tuple(get_nested_scope_tuple(11), 10) {
+            Ok(_) => assert!(false),
+            Err(kind) => match kind {
+                ErrorKind::ScopeError(err) => assert_eq!(err.case(), ScopeErrorCase::MaxDepth),
+                ErrorKind::PermissionError(_) => assert!(false),
+            }
+        }
+    }
+
+    #[test]
+    fn test_as_tuple_deeply_nested() {
+        // far deeper than the default limit, collapsed and expanded without recursion
+        let depth = 5_000;
+        let tuple = get_nested_scope_tuple(depth);
+
+        match Scope::from_tuple(tuple, depth) {
+            Ok(scope) => {
+                let mut collapsed = scope.as_tuple();
+                let mut levels = 0;
+                while let Some(child) = collapsed.3.pop() {
+                    collapsed = child;
+                    levels += 1;
+                }
+
+                assert_eq!(levels, depth);
+            },
+            Err(_) => assert!(false)
+        }
+    }
+
+    #[test]
+    fn test_child_scope_path() {
+        let mut scope = Scope::new("TEST_SCOPE");
+
+        if let Ok(_) = scope.add_scope("CHILD_SCOPE") {
+            match scope.scope("CHILD_SCOPE") {
+                Some(child_scope) => assert_eq!(child_scope.path().to_string(), "TEST_SCOPE.CHILD_SCOPE"),
+                None => assert!(false)
+            }
+        } else {
+            assert!(false);
+        }
+    }
+
+    #[test]
+    fn test_capacity_error_includes_scope_path() {
+        let mut scope = Scope::new("TEST_SCOPE");
+        let _ = scope.add_scope("CHILD_SCOPE");
+
+        if let Some(child_scope) = scope.scope("CHILD_SCOPE") {
+            child_scope.set_overflow_mode(OverflowMode::Reject);
+            while child_scope.has_capacity(1) {
+                let name = format!("TEST_PERMISSION_{}", child_scope.permissions.len());
+                assert!(child_scope.add_permission(name.as_str()).is_ok());
+            }
+
+            match child_scope.add_permission("ONE_TOO_MANY") {
+                Ok(_) => assert!(false),
+                Err(kind) => match kind {
+                    ErrorKind::PermissionError(err) => {
+                        assert_eq!(err.metadata().scope_path(), Some("TEST_SCOPE.CHILD_SCOPE"));
+                        assert_eq!(err.metadata().capacity(), Some(MAX_SHIFT as usize + 1));
+                        assert!(err.to_string().contains("TEST_SCOPE.CHILD_SCOPE"));
+                    },
+                    ErrorKind::ScopeError(_) => assert!(false),
+                }
+            }
+        } else {
+            assert!(false);
+        }
+    }
+
+    #[test]
+    fn test_has_capacity_matches_add_permission() {
+        let mut scope = Scope::new("TEST_SCOPE");
+        scope.set_overflow_mode(OverflowMode::Reject);
+        let mut i = 0;
+
+        // fill the scope until it reports no remaining capacity
+        while scope.has_capacity(1) {
+            let name = format!("TEST_PERMISSION_{}", i);
+            assert!(scope.add_permission(name.as_str()).is_ok());
+            i += 1;
+        }
+
+        assert_eq!(scope.remaining_capacity(), 0);
+        assert_eq!(scope.permissions.len(), MAX_SHIFT as usize + 1);
+
+        // the next addition must fail because the scope is full
+        match scope.add_permission("ONE_TOO_MANY") {
+            Ok(_) => assert!(false),
+            Err(kind) => match kind {
+                ErrorKind::PermissionError(_) => assert!(true),
+                ErrorKind::ScopeError(_) => assert!(false),
+            }
+        }
+    }
+
+    #[test]
+    fn test_clone_is_deep_and_independent() {
+        let mut scope = match Scope::from_tuple(get_nested_scope_tuple(1_000), 1_000) {
+            Ok(scope) => scope,
+            Err(_) => return assert!(false)
+        };
+        if let Err(_) = scope.add_permission("READ") {
+            assert!(false);
+        }
+
+        let copy = scope.clone();
+        if let Err(_) = scope.grant("READ") {
+            assert!(false);
+        }
+
+        assert_eq!(copy.check("READ"), false);
+        assert_eq!(scope.check("READ"), true);
+        assert_eq!(copy.revision() + 1, scope.revision());
+        assert_eq!(copy.find_scope(&ScopePath::parse("SCOPE_1.SCOPE_2")).is_some(), true);
+    }
 }
\ No newline at end of file
@@ -0,0 +1,347 @@
+use alloc::string::{String, ToString};
+use alloc::format;
+use alloc::vec;
+use alloc::vec::Vec;
+use serde_json::{Map, Value};
+use crate::scope::order::SortOrder;
+use crate::scope::Scope;
+
+/** The order fields are written in by `to_json_pretty`, by their snake_case names. Other fields follow by name. */
+const FIELD_ORDER: [&str; 9] = ["schema", "granted_permissions", "name", "path", "value", "shift", "granted", "permissions", "child_scopes"];
+
+/** FieldCase selects how multi-word field names are written in the named JSON format. */
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum FieldCase {
+    /** `child_scopes`, for Rust and Python consumers. */
+    #[default]
+    Snake,
+    /** `childScopes`, for JS consumers. */
+    Camel
+}
+
+impl FieldCase {
+    /** Write a snake_case field name in this case. */
+    pub(crate) fn field(&self, name: &str) -> String {
+        return match self {
+            FieldCase::Snake => name.to_string(),
+            FieldCase::Camel => {
+                let mut field = String::with_capacity(name.len());
+                let mut upper = false;
+                for c in name.chars() {
+                    match (c, upper) {
+                        ('_', _) => upper = true,
+                        (c, true) => {
+                            field.extend(c.to_uppercase());
+                            upper = false;
+                        },
+                        (c, false) => field.push(c)
+                    }
+                }
+                field
+            }
+        }
+    }
+}
+
+/** GrantLayout selects where the named JSON format puts grant state. */
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum GrantLayout {
+    /** Every scope carries its `value` and every permission its `granted` flag. */
+    #[default]
+    Embedded,
+    /**
+        The tree only describes the schema, under `schema`, and grants are listed next to it under
+        `granted_permissions` as a map from full scope path to the names of the granted permissions.
+     */
+    Separate,
+    /** Grant state is left out entirely and only the schema is written, for sharing it between services. */
+    Omitted
+}
+
+/** NamedJsonOptions configures the named JSON format. */
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct NamedJsonOptions {
+    pub case: FieldCase,
+    pub grants: GrantLayout,
+    pub order: SortOrder
+}
+
+impl Scope {
+    /**
+        Export to JSON with named fields instead of the positional tuple form: every scope is an object with its
+        `name`, `path`, `permissions` and `child_scopes`, and every permission an object with its `name` and `shift`.
+        The format is meant for APIs and UIs; rules are only carried by `as_json`.
+     */
+    pub fn as_named_json(&self) -> Value {
+        return self.as_named_json_with(&NamedJsonOptions::default());
+    }
+
+    /** Export to the named JSON format with the field casing, grant layout and order chosen by `options`. */
+    pub fn as_named_json_with(&self, options: &NamedJsonOptions) -> Value {
+        let tree = self.named_tree(options);
+
+        return match options.grants {
+            GrantLayout::Embedded | GrantLayout::Omitted => tree,
+            GrantLayout::Separate => {
+                let mut granted = Map::new();
+                for scope in self.scopes_in_encoding_order() {
+                    let names: Vec<Value> = scope.permissions_in_order(options.order).into_iter()
+                        .filter(|permission| permission.has())
+                        .map(|permission| Value::from(permission.name.clone()))
+                        .collect();
+                    if !names.is_empty() {
+                        granted.insert(scope.path.to_string(), Value::Array(names));
+                    }
+                }
+
+                let mut document = Map::new();
+                document.insert(options.case.field("schema"), tree);
+                document.insert(options.case.field("granted_permissions"), Value::Object(granted));
+                Value::Object(document)
+            }
+        }
+    }
+
+    /**
+        Export to the named JSON format as an indented string for schemas committed to version control: fields are
+        always written in the same order (`name`, `path`, `value`, `permissions`, `child_scopes`) and permissions by
+        shift, so the output only changes where the tree does and diffs stay readable.
+     */
+    pub fn to_json_pretty(&self) -> String {
+        return self.to_json_pretty_with(&NamedJsonOptions::default());
+    }
+
+    /** Export to the named JSON format as an indented string with fields in a fixed order, configured by `options`. */
+    pub fn to_json_pretty_with(&self, options: &NamedJsonOptions) -> String {
+        let order: Vec<String> = FIELD_ORDER.iter().map(|field| options.case.field(field)).collect();
+        let mut pretty = String::new();
+        write_pretty(&mut pretty, &self.as_named_json_with(options), &order, 0);
+        pretty.push('\n');
+
+        return pretty;
+    }
+
+    /** Build the nested scope objects with an explicit stack, so deeply nested trees cannot overflow the call stack. */
+    fn named_tree(&self, options: &NamedJsonOptions) -> Value {
+        let mut stack = vec![(self, self.scopes_in_order(options.order).into_iter(), Vec::<Value>::new())];
+
+        loop {
+            let next_child = match stack.last_mut() {
+                Some((_, children, _)) => children.next(),
+                None => unreachable!("the root scope is only popped once its object is complete")
+            };
+
+            match next_child {
+                Some(child) => stack.push((child, child.scopes_in_order(options.order).into_iter(), Vec::new())),
+                None => {
+                    if let Some((scope, _, children)) = stack.pop() {
+                        let object = scope.named_object(children, options);
+
+                        match stack.last_mut() {
+                            Some((_, _, siblings)) => siblings.push(object),
+                            None => return object
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /** Build the object for this scope alone, using child objects that have already been built. */
+    fn named_object(&self, children: Vec<Value>, options: &NamedJsonOptions) -> Value {
+        let embedded = options.grants == GrantLayout::Embedded;
+        let mut permissions: Vec<Value> = vec![];
+
+        for permission in self.permissions_in_order(options.order) {
+            let mut object = Map::new();
+            object.insert(options.case.field("name"), Value::from(permission.name.clone()));
+            object.insert(options.case.field("shift"), Value::from(permission.value.trailing_zeros()));
+            if embedded {
+                object.insert(options.case.field("granted"), Value::from(permission.has()));
+            }
+            permissions.push(Value::Object(object));
+        }
+
+        let mut object = Map::new();
+        object.insert(options.case.field("name"), Value::from(self.name.clone()));
+        object.insert(options.case.field("path"), Value::from(self.path.to_string()));
+        if embedded {
+            object.insert(options.case.field("value"), Value::from(self.as_u64()));
+        }
+        object.insert(options.case.field("permissions"), Value::Array(permissions));
+        object.insert(options.case.field("child_scopes"), Value::Array(children));
+
+        return Value::Object(object);
+    }
+}
+
+/** Write a JSON value indented by two spaces per level, with object fields in `order` and then by name. */
+fn write_pretty(pretty: &mut String, value: &Value, order: &[String], depth: usize) {
+    let indent = "  ".repeat(depth + 1);
+    let closing = "  ".repeat(depth);
+
+    match value {
+        Value::Array(items) if !items.is_empty() => {
+            pretty.push('[');
+            for (i, item) in items.iter().enumerate() {
+                pretty.push_str(if i == 0 { "\n" } else { ",\n" });
+                pretty.push_str(&indent);
+                write_pretty(pretty, item, order, depth + 1);
+            }
+            pretty.push_str(&format!("\n{}]", closing));
+        },
+        Value::Object(fields) if !fields.is_empty() => {
+            let mut keys: Vec<&String> = fields.keys().collect();
+            keys.sort_by_key(|key| (order.iter().position(|field| field == *key).unwrap_or(order.len()), *key));
+
+            pretty.push('{');
+            for (i, key) in keys.into_iter().enumerate() {
+                pretty.push_str(if i == 0 { "\n" } else { ",\n" });
+                pretty.push_str(&format!("{}{}: ", indent, Value::from(key.as_str())));
+                write_pretty(pretty, &fields[key], order, depth + 1);
+            }
+            pretty.push_str(&format!("\n{}}}", closing));
+        },
+        other => pretty.push_str(&other.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use crate::assert_ok;
+
+    fn get_scope() -> Scope {
+        let mut scope = Scope::new("APP");
+        assert_ok!(scope.add_permission("READ").and_then(|sc| sc.add_scope("billing")));
+
+        if let Some(billing) = scope.scope("billing") {
+            assert_ok!(billing.add_permission("VIEW").and_then(|sc| sc.add_permission("PAY")).and_then(|sc| sc.grant("PAY")));
+        }
+
+        return scope;
+    }
+
+    #[test]
+    fn test_named_json_embedded() {
+        assert_eq!(get_scope().as_named_json(), json!({
+            "name": "APP",
+            "path": "APP",
+            "value": 0,
+            "permissions": [{ "name": "READ", "shift": 0, "granted": false }],
+            "child_scopes": [{
+                "name": "billing",
+                "path": "APP.billing",
+                "value": 2,
+                "permissions": [
+                    { "name": "VIEW", "shift": 0, "granted": false },
+                    { "name": "PAY", "shift": 1, "granted": true }
+                ],
+                "child_scopes": []
+            }]
+        }));
+    }
+
+    #[test]
+    fn test_named_json_camel_case_separate_grants() {
+        let options = NamedJsonOptions { case: FieldCase::Camel, grants: GrantLayout::Separate, ..NamedJsonOptions::default() };
+
+        assert_eq!(get_scope().as_named_json_with(&options), json!({
+            "schema": {
+                "name": "APP",
+                "path": "APP",
+                "permissions": [{ "name": "READ", "shift": 0 }],
+                "childScopes": [{
+                    "name": "billing",
+                    "path": "APP.billing",
+                    "permissions": [{ "name": "VIEW", "shift": 0 }, { "name": "PAY", "shift": 1 }],
+                    "childScopes": []
+                }]
+            },
+            "grantedPermissions": { "APP.billing": ["PAY"] }
+        }));
+    }
+
+    #[test]
+    fn test_named_json_omitted() {
+        let options = NamedJsonOptions { grants: GrantLayout::Omitted, ..NamedJsonOptions::default() };
+
+        assert_eq!(get_scope().as_named_json_with(&options), json!({
+            "name": "APP",
+            "path": "APP",
+            "permissions": [{ "name": "READ", "shift": 0 }],
+            "child_scopes": [{
+                "name": "billing",
+                "path": "APP.billing",
+                "permissions": [{ "name": "VIEW", "shift": 0 }, { "name": "PAY", "shift": 1 }],
+                "child_scopes": []
+            }]
+        }));
+    }
+
+    #[test]
+    fn test_json_pretty() {
+        let mut scope = get_scope();
+        assert_ok!(scope.add_scope("admin"));
+
+        assert_eq!(scope.to_json_pretty(), concat!(
+            "{\n",
+            "  \"name\": \"APP\",\n",
+            "  \"path\": \"APP\",\n",
+            "  \"value\": 0,\n",
+            "  \"permissions\": [\n",
+            "    {\n",
+            "      \"name\": \"READ\",\n",
+            "      \"shift\": 0,\n",
+            "      \"granted\": false\n",
+            "    }\n",
+            "  ],\n",
+            "  \"child_scopes\": [\n",
+            "    {\n",
+            "      \"name\": \"billing\",\n",
+            "      \"path\": \"APP.billing\",\n",
+            "      \"value\": 2,\n",
+            "      \"permissions\": [\n",
+            "        {\n",
+            "          \"name\": \"VIEW\",\n",
+            "          \"shift\": 0,\n",
+            "          \"granted\": false\n",
+            "        },\n",
+            "        {\n",
+            "          \"name\": \"PAY\",\n",
+            "          \"shift\": 1,\n",
+            "          \"granted\": true\n",
+            "        }\n",
+            "      ],\n",
+            "      \"child_scopes\": []\n",
+            "    },\n",
+            "    {\n",
+            "      \"name\": \"admin\",\n",
+            "      \"path\": \"APP.admin\",\n",
+            "      \"value\": 0,\n",
+            "      \"permissions\": [],\n",
+            "      \"child_scopes\": []\n",
+            "    }\n",
+            "  ]\n",
+            "}\n"
+        ));
+    }
+
+    #[test]
+    fn test_json_pretty_is_valid_json() {
+        let options = NamedJsonOptions { case: FieldCase::Camel, grants: GrantLayout::Separate, ..NamedJsonOptions::default() };
+        let scope = get_scope();
+        let pretty = scope.to_json_pretty_with(&options);
+
+        assert_eq!(pretty.starts_with("{\n  \"schema\": {\n    \"name\": \"APP\""), true);
+        assert_eq!(serde_json::from_str::<Value>(&pretty).ok(), Some(scope.as_named_json_with(&options)));
+    }
+
+    #[test]
+    fn test_field_case() {
+        assert_eq!(FieldCase::Camel.field("granted_permissions"), "grantedPermissions");
+        assert_eq!(FieldCase::Camel.field("name"), "name");
+        assert_eq!(FieldCase::Snake.field("child_scopes"), "child_scopes");
+    }
+}
@@ -0,0 +1,141 @@
+use alloc::vec;
+use alloc::string::ToString;
+use alloc::vec::Vec;
+use crate::common::error::ErrorKind;
+use crate::scope::error::{ScopeError, ScopeErrorCase};
+use crate::scope::order::SortOrder;
+use crate::scope::path::ScopePath;
+use crate::scope::Scope;
+
+impl Scope {
+    /**
+        Mount a whole tree under a namespace of this scope, so schemas from several products can share one registry.
+        The namespace is a child scope, created if it does not exist yet, and the tree keeps its own name below it:
+        mounting `APP` under `acme` makes its permissions addressable as `acme.APP.billing:READ`. Fails with
+        ScopeExists if the namespace already holds a tree of the same name.
+     */
+    pub fn mount(&mut self, namespace: &str, mut tree: Scope) -> Result<&mut Scope, ErrorKind> {
+        let namespace = self.policy.normalize(namespace).into_owned();
+        if self.child_scope(&namespace).is_none() {
+            self.add_scope(&namespace)?;
+        }
+
+        let target = self.resolve_scope_mut(&ScopePath::from_segments(&[&namespace]))?;
+        if target.scopes.contains_key(&tree.name) || target.permissions.contains_key(&tree.name) {
+            return Err(ErrorKind::ScopeError(ScopeError::new(ScopeErrorCase::ScopeExists, &tree.name)));
+        }
+
+        tree.rebase(&target.path);
+        target.insert_scope(tree);
+        target.touch();
+
+        return Ok(self);
+    }
+
+    /**
+        Detach a tree mounted under a namespace and return it as a root scope again. The namespace is removed once
+        it holds nothing else. Fails with UnknownScope if no tree of that name is mounted there.
+     */
+    pub fn unmount(&mut self, namespace: &str, name: &str) -> Result<Scope, ErrorKind> {
+        let namespace = self.policy.normalize(namespace).into_owned();
+        let target = self.resolve_scope_mut(&ScopePath::from_segments(&[&namespace]))?;
+
+        let mut tree = match target.remove_scope(name) {
+            Some(tree) => tree,
+            None => return Err(ErrorKind::ScopeError(ScopeError::new(ScopeErrorCase::UnknownScope, &target.path.child(name).to_string())))
+        };
+        target.touch();
+        tree.rebase(&ScopePath::new());
+
+        if target.scopes.is_empty() && target.permissions.is_empty() {
+            self.remove_scope(&namespace);
+        }
+        self.touch();
+
+        return Ok(tree);
+    }
+
+    /** Get the names of the trees mounted under a namespace, in the order they were mounted. */
+    pub fn mounted(&self, namespace: &str) -> Vec<&str> {
+        return match self.child_scope(self.policy.normalize(namespace).as_ref()) {
+            Some(target) => target.scopes_in_order(SortOrder::Insertion).into_iter().map(|scope| scope.name.as_str()).collect(),
+            None => vec![]
+        }
+    }
+
+    /** Rewrite the stored path of this scope and every child scope for a new parent path. */
+    fn rebase(&mut self, parent: &ScopePath) {
+        self.path = parent.child(&self.name);
+
+        let mut stack: Vec<&mut Scope> = vec![self];
+        while let Some(scope) = stack.pop() {
+            let path = scope.path.clone();
+            for child in scope.scopes.values_mut() {
+                child.path = path.child(&child.name);
+                stack.push(child);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{assert_granted, assert_not_granted, assert_ok, assert_scope_err};
+
+    fn get_product(name: &str) -> Scope {
+        let mut scope = Scope::new(name);
+        assert_ok!(scope.add_permission("READ").and_then(|sc| sc.add_scope("billing")));
+
+        if let Some(billing) = scope.scope("billing") {
+            assert_ok!(billing.add_permission("REFUND").and_then(|sc| sc.grant("REFUND")));
+        }
+
+        return scope;
+    }
+
+    #[test]
+    fn test_mount_same_names_in_different_namespaces() {
+        let mut registry = Scope::new("REGISTRY");
+
+        assert_ok!(registry.mount("acme", get_product("APP")).and_then(|sc| sc.mount("globex", get_product("APP"))));
+
+        assert_granted!(registry, "acme.APP.billing:REFUND");
+        assert_granted!(registry, "globex.APP.billing:REFUND");
+        assert_not_granted!(registry, "acme.APP:READ");
+        assert_eq!(registry.mounted("acme"), ["APP"]);
+
+        match registry.find_scope(&ScopePath::parse("acme.APP.billing")) {
+            Some(billing) => assert_eq!(billing.path().to_string(), "REGISTRY.acme.APP.billing"),
+            None => assert!(false)
+        }
+
+        assert_scope_err!(registry.mount("acme", get_product("APP")), ScopeErrorCase::ScopeExists);
+    }
+
+    #[test]
+    fn test_unmount_restores_root() {
+        let mut registry = Scope::new("REGISTRY");
+        assert_ok!(registry.mount("acme", get_product("APP")).and_then(|sc| sc.mount("acme", get_product("CRM"))));
+
+        match registry.unmount("acme", "APP") {
+            Ok(mut app) => {
+                assert_eq!(app.path().to_string(), "APP");
+                assert_granted!(app, "billing:REFUND");
+                match app.scope("billing") {
+                    Some(billing) => assert_eq!(billing.path().to_string(), "APP.billing"),
+                    None => assert!(false)
+                }
+            },
+            Err(_) => assert!(false)
+        }
+        assert_eq!(registry.mounted("acme"), ["CRM"]);
+
+        // the namespace goes away with its last tree
+        assert_ok!(registry.unmount("acme", "CRM"));
+        assert_eq!(registry.mounted("acme").is_empty(), true);
+        assert_eq!(registry.child_scope("acme").is_none(), true);
+
+        assert_scope_err!(registry.unmount("acme", "CRM"), ScopeErrorCase::UnknownScope);
+    }
+}
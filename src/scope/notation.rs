@@ -0,0 +1,223 @@
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+use crate::common::error::ErrorKind;
+use crate::scope::error::{ScopeError, ScopeErrorCase};
+use crate::scope::guard::MutationContext;
+use crate::scope::path::ScopePath;
+use crate::scope::Scope;
+
+/** Marks a granted permission in grant notation. */
+pub const GRANTED_MARK: char = 'x';
+/** Separates the grants of consecutive scopes in grant notation. */
+pub const SCOPE_MARK: char = '/';
+/** Stands for a scope without any permissions in grant notation. */
+pub const EMPTY_MARK: char = '-';
+
+impl Scope {
+    /**
+        Format the grant state of the whole tree in a compact notation modelled on chess FEN. Scopes are written
+        depth-first with child scopes in name order and separated by `/`. Within a scope, permissions are listed
+        by shift: a granted permission is written as `x` and a run of permissions that are not granted as its
+        length, so `1x2` means only the second of four permissions is granted. Bits of removed permissions count as
+        permissions that are not granted, and a scope that never had any permissions is `-`.
+     */
+    pub fn format_grants(&self) -> String {
+        let mut notation = String::new();
+
+        for (i, scope) in self.scopes_in_encoding_order().into_iter().enumerate() {
+            if i > 0 {
+                notation.push(SCOPE_MARK);
+            }
+            notation.push_str(&scope.format_flat_grants());
+        }
+
+        return notation;
+    }
+
+    fn format_flat_grants(&self) -> String {
+        // parsing reads the scope by its assigned width, which removing permissions does not shrink
+        if self.assigned_width() == 0 {
+            return EMPTY_MARK.to_string();
+        }
+
+        let mut notation = String::new();
+        let mut run = 0usize;
+        let value = self.as_u64();
+
+        for shift in 0..self.assigned_width() {
+            if value & (1u64 << shift) != 0 {
+                if run > 0 {
+                    notation.push_str(&run.to_string());
+                    run = 0;
+                }
+                notation.push(GRANTED_MARK);
+            } else {
+                run += 1;
+            }
+        }
+        if run > 0 {
+            notation.push_str(&run.to_string());
+        }
+
+        return notation;
+    }
+
+    /**
+        Restore the grant state of the whole tree from a string produced by `format_grants`. The notation must
+        describe exactly the permissions of every scope in the tree, and may not grant the bit of a removed
+        permission. Every scope is validated before any is changed, so nothing is changed if the notation fails.
+     */
+    pub fn parse_grants(&mut self, notation: &str) -> Result<&mut Scope, ErrorKind> {
+        let invalid = || ErrorKind::ScopeError(ScopeError::new(ScopeErrorCase::InvalidEncoding, notation));
+        let parts: Vec<&str> = notation.split(SCOPE_MARK).collect();
+
+        let mut changes: Vec<(ScopePath, u64)> = vec![];
+        {
+            let scopes = self.scopes_in_encoding_order();
+            if parts.len() != scopes.len() {
+                return Err(invalid());
+            }
+
+            for (scope, part) in scopes.into_iter().zip(parts) {
+                let value = parse_flat_grants(part, scope.assigned_width()).ok_or_else(invalid)?;
+                if value & !scope.defined_bits() != 0 {
+                    return Err(ErrorKind::ScopeError(ScopeError::new(ScopeErrorCase::UndefinedBits, &scope.path.to_string())));
+                }
+                scope.check_guards_for_value(value, &MutationContext::new())?;
+                changes.push((scope.path.relative_to(&self.path), value));
+            }
+        }
+
+        for (path, value) in changes {
+            self.resolve_scope_mut(&path)?.apply_u64_unguarded(value)?;
+        }
+
+        return Ok(self);
+    }
+}
+
+/** Parse the notation of a single scope with `count` permissions into its value, or None if it is not canonical. */
+fn parse_flat_grants(part: &str, count: usize) -> Option<u64> {
+    if count == 0 {
+        return match part == EMPTY_MARK.to_string() {
+            true => Some(0),
+            false => None
+        }
+    }
+
+    let mut value = 0u64;
+    let mut shift = 0usize;
+    let mut chars = part.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if c == GRANTED_MARK {
+            if shift >= count {
+                return None;
+            }
+            value |= 1u64 << shift;
+            shift += 1;
+        } else if c.is_ascii_digit() && c != '0' {
+            let mut run = c.to_digit(10)? as usize;
+            while let Some(digit) = chars.peek().and_then(|next| next.to_digit(10)) {
+                run = run.checked_mul(10)?.checked_add(digit as usize)?;
+                chars.next();
+            }
+            shift = shift.checked_add(run)?;
+        } else {
+            return None;
+        }
+    }
+
+    return match shift == count {
+        true => Some(value),
+        false => None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get_notation_scope() -> Scope {
+        let mut scope = Scope::new("APP");
+
+        if let Err(_) = scope
+            .add_permission("READ")
+            .and_then(|sc| sc.add_permission("WRITE"))
+            .and_then(|sc| sc.add_permission("DELETE"))
+            .and_then(|sc| sc.add_permission("EXPORT"))
+            .and_then(|sc| sc.grant("WRITE"))
+            .and_then(|sc| sc.add_scope("billing"))
+            .and_then(|sc| sc.add_scope("audit")) {
+            assert!(false);
+        }
+        match scope.scope("billing") {
+            Some(billing) => {
+                if let Err(_) = billing.add_permission("VIEW").and_then(|sc| sc.grant("VIEW")) {
+                    assert!(false);
+                }
+            },
+            None => assert!(false)
+        }
+
+        return scope;
+    }
+
+    #[test]
+    fn test_format_grants() {
+        let scope = get_notation_scope();
+
+        assert_eq!(scope.format_grants(), "1x2/-/x");
+    }
+
+    #[test]
+    fn test_parse_grants() {
+        let mut scope = get_notation_scope();
+
+        if let Err(_) = scope.parse_grants("x2x/-/1") {
+            assert!(false);
+        }
+        assert_eq!(scope.format_grants(), "x2x/-/1");
+        assert_eq!(scope.check("READ"), true);
+        assert_eq!(scope.check("WRITE"), false);
+        assert_eq!(scope.check("EXPORT"), true);
+        assert_eq!(scope.check("billing:VIEW"), false);
+    }
+
+    #[test]
+    fn test_parse_grants_is_strict() {
+        let mut scope = get_notation_scope();
+
+        for notation in ["1x2/-", "1x2/-/x/x", "1x3/-/x", "1x1/-/x", "01x2/-/x", "1y2/-/x", "1x2/0/x", "xxxxx/-/x"] {
+            assert_eq!(scope.parse_grants(notation).is_err(), true);
+        }
+        assert_eq!(scope.format_grants(), "1x2/-/x");
+    }
+
+    #[test]
+    fn test_removed_permissions_round_trip() {
+        let mut scope = get_notation_scope();
+        if let Some(billing) = scope.scope("billing") {
+            if let Err(_) = billing.remove_permission("VIEW") {
+                assert!(false);
+            }
+        }
+        if let Err(_) = scope.remove_permission("DELETE") {
+            assert!(false);
+        }
+
+        let notation = scope.format_grants();
+        assert_eq!(notation, "1x2/-/1");
+        if let Err(_) = scope.parse_grants(&notation) {
+            assert!(false);
+        }
+
+        // granting the bit of a removed permission fails without changing any scope, even those before it
+        match scope.parse_grants("x2x/-/x") {
+            Err(ErrorKind::ScopeError(err)) => assert_eq!(err.case(), ScopeErrorCase::UndefinedBits),
+            _ => assert!(false)
+        }
+        assert_eq!(scope.format_grants(), "1x2/-/1");
+    }
+}
@@ -0,0 +1,115 @@
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+use crate::common::error::ErrorKind;
+use crate::scope::error::{ScopeError, ScopeErrorCase};
+use crate::scope::path::{PermissionPath, ScopePath};
+use crate::scope::Scope;
+
+impl Scope {
+    /**
+        Format the permissions granted directly in this scope and its child scopes as an OAuth2 scope string, such
+        as `read billing:read billing:write`. Each entry is the path of a permission relative to this scope with the
+        permission name in lowercase, and entries are separated by single spaces in the order of `walk`.
+     */
+    pub fn to_oauth_scopes(&self) -> String {
+        let entries: Vec<String> = self.walk()
+            .filter(|(_, permission)| permission.has())
+            .map(|(path, permission)| PermissionPath::new(path.relative_to(&self.path), &permission.name.to_lowercase()).to_string())
+            .collect();
+
+        return entries.join(" ");
+    }
+
+    /**
+        Grant every permission listed in an OAuth2 scope string written by `to_oauth_scopes`, as issued by an
+        authorization server. Permission names are matched regardless of case and existing grants are kept. It is a
+        single change: an entry naming an unknown scope fails with UnknownScope, one naming an unknown permission
+        with UnknownPermission, and nothing is granted unless every entry can be.
+     */
+    pub fn grant_from_oauth_scopes(&mut self, scopes: &str) -> Result<&mut Scope, ErrorKind> {
+        let mut changes: Vec<(ScopePath, Vec<String>)> = Vec::new();
+
+        for entry in scopes.split_whitespace() {
+            let requested = PermissionPath::parse(entry);
+            let scope = match self.find_scope(&requested.scope) {
+                Some(scope) => scope,
+                None => return Err(ErrorKind::ScopeError(ScopeError::new(ScopeErrorCase::UnknownScope, entry)))
+            };
+            let name = match scope.oauth_permission_name(&requested.permission) {
+                Some(name) => name,
+                None => return Err(ErrorKind::ScopeError(ScopeError::new(ScopeErrorCase::UnknownPermission, entry)))
+            };
+
+            match changes.iter_mut().find(|(path, _)| *path == requested.scope) {
+                Some((_, names)) => names.push(name),
+                None => changes.push((requested.scope, vec![name]))
+            }
+        }
+
+        let mut working = self.clone();
+        for (path, names) in changes {
+            let names: Vec<&str> = names.iter().map(|name| name.as_str()).collect();
+            working.resolve_scope_mut(&path)?.grant_many(&names)?;
+        }
+        *self = working;
+
+        return Ok(self);
+    }
+
+    /** Find the permission of this scope or its continuations named by an OAuth2 scope entry, preferring an exact match. */
+    fn oauth_permission_name(&self, requested: &str) -> Option<String> {
+        let resolved = self.resolve_name(requested);
+        if self.permissions.contains_key(&resolved) || self.continuation_holding(&resolved).is_some() {
+            return Some(resolved);
+        }
+
+        let requested = requested.to_lowercase();
+        let mut candidates = self.permissions()
+            .chain(self.continuations().into_iter().flat_map(|continuation| continuation.permissions()));
+
+        return candidates.find(|permission| permission.name.to_lowercase() == requested).map(|permission| permission.name.clone());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{assert_granted, assert_not_granted, assert_ok, assert_scope_err};
+
+    fn get_scope() -> Scope {
+        let mut scope = Scope::new("APP");
+        assert_ok!(scope.add_permission("READ").and_then(|sc| sc.add_scope("billing")));
+        if let Some(billing) = scope.scope("billing") {
+            assert_ok!(billing.add_permission("READ").and_then(|sc| sc.add_permission("WRITE")).and_then(|sc| sc.add_permission("REFUND")));
+            assert_ok!(billing.add_requirement("WRITE", "READ"));
+        }
+
+        return scope;
+    }
+
+    #[test]
+    fn test_oauth_scopes_round_trip() {
+        let mut scope = get_scope();
+        assert_eq!(scope.to_oauth_scopes(), "");
+
+        // WRITE requires READ, which is listed after it
+        assert_ok!(scope.grant_from_oauth_scopes("read  billing:write billing:read"));
+        assert_eq!(scope.to_oauth_scopes(), "read billing:read billing:write");
+
+        let mut restored = get_scope();
+        assert_ok!(restored.grant_from_oauth_scopes(&scope.to_oauth_scopes()));
+        assert_eq!(restored.to_oauth_scopes(), scope.to_oauth_scopes());
+        assert_granted!(restored, "billing:WRITE");
+        assert_not_granted!(restored, "billing:REFUND");
+    }
+
+    #[test]
+    fn test_unknown_oauth_scopes_are_rejected() {
+        let mut scope = get_scope();
+
+        assert_scope_err!(scope.grant_from_oauth_scopes("read billing:delete"), ScopeErrorCase::UnknownPermission);
+        assert_scope_err!(scope.grant_from_oauth_scopes("read reports:read"), ScopeErrorCase::UnknownScope);
+        assert_not_granted!(scope, "READ");
+    }
+}
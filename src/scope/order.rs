@@ -0,0 +1,145 @@
+use alloc::vec::Vec;
+use crate::permission::Permission;
+use crate::scope::Scope;
+
+/** SortOrder selects how permissions and child scopes are listed in exports. */
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SortOrder {
+    /**
+        Permissions by bit, lowest first. Child scopes have no bit, so they are listed in insertion order.
+        Exports record the bit of every permission, so every order round-trips values.
+     */
+    #[default]
+    Shift,
+    /** Permissions and child scopes by name, for diffs that should not depend on how a schema was built. */
+    Name,
+    /** Permissions and child scopes in the order they were added or imported. */
+    Insertion
+}
+
+/** ExportOptions configures tuple and JSON exports. */
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ExportOptions {
+    pub order: SortOrder,
+    /**
+        Leave out all grant state, so the export can be shared without revealing anyone's permissions: every value
+        is written as 0 and the revision, which grants advance, as 0 too.
+     */
+    pub schema_only: bool,
+    /**
+        Write the revision of every scope, so a copy imported elsewhere reports the same `tree_revision` and can
+        base patches on it. Off by default, keeping revisions out of ordinary exports.
+     */
+    pub revisions: bool
+}
+
+impl Scope {
+    /** List the permissions of this scope in the given order. */
+    pub(crate) fn permissions_in_order(&self, order: SortOrder) -> Vec<&Permission> {
+        let mut permissions: Vec<&Permission> = self.permission_order.iter()
+            .filter_map(|name| self.permissions.get(name))
+            .collect();
+
+        match order {
+            SortOrder::Shift => permissions.sort_by_key(|permission| permission.value),
+            SortOrder::Name => permissions.sort_by(|a, b| a.name.cmp(&b.name)),
+            SortOrder::Insertion => {}
+        }
+
+        return permissions;
+    }
+
+    /** List the child scopes of this scope in the given order. */
+    pub(crate) fn scopes_in_order(&self, order: SortOrder) -> Vec<&Scope> {
+        let mut scopes: Vec<&Scope> = self.scope_order.iter()
+            .filter_map(|name| self.scopes.get(name))
+            .collect();
+
+        if order == SortOrder::Name {
+            scopes.sort_by(|a, b| a.name.cmp(&b.name));
+        }
+
+        return scopes;
+    }
+
+    /** Attach a child scope, remembering when it was added. A scope replacing one of the same name keeps its place. */
+    pub(crate) fn insert_scope(&mut self, scope: Scope) {
+        if !self.scopes.contains_key(&scope.name) {
+            self.scope_order.push(scope.name.clone());
+        }
+        self.scopes.insert(scope.name.clone(), scope);
+    }
+
+    /** Detach a child scope, forgetting its place in the insertion order. */
+    pub(crate) fn remove_scope(&mut self, name: &str) -> Option<Scope> {
+        self.scope_order.retain(|existing| existing != name);
+
+        return self.scopes.remove(name);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::string::String;
+    use alloc::vec;
+    use crate::scope::ScopeTuple;
+
+    fn get_scope() -> Scope {
+        let mut scope = Scope::new("APP");
+        if let Err(_) = scope
+            .add_permission("WRITE")
+            .and_then(|sc| sc.add_permission("ADMIN"))
+            .and_then(|sc| sc.add_permission("READ"))
+            .and_then(|sc| sc.add_scope("reports"))
+            .and_then(|sc| sc.add_scope("billing")) {
+            assert!(false);
+        }
+
+        return scope;
+    }
+
+    fn exported_names(scope: &Scope, order: SortOrder) -> (Vec<String>, Vec<String>) {
+        let ScopeTuple(_, _, permissions, scopes, _) = scope.as_tuple_with(&ExportOptions { order, ..ExportOptions::default() });
+
+        let permissions = permissions.iter().map(|entry| entry.name().to_string()).collect();
+
+        return (permissions, scopes.into_iter().map(|ScopeTuple(name, ..)| name).collect());
+    }
+
+    #[test]
+    fn test_export_orders() {
+        let scope = get_scope();
+
+        assert_eq!(exported_names(&scope, SortOrder::Shift), (vec!["WRITE".into(), "ADMIN".into(), "READ".into()], vec!["reports".into(), "billing".into()]));
+        assert_eq!(exported_names(&scope, SortOrder::Name), (vec!["ADMIN".into(), "READ".into(), "WRITE".into()], vec!["billing".into(), "reports".into()]));
+        assert_eq!(exported_names(&scope, SortOrder::Insertion), exported_names(&scope, SortOrder::Shift));
+    }
+
+    #[test]
+    fn test_shift_order_round_trips_values() {
+        let mut scope = get_scope();
+        if let Err(_) = scope.grant("READ") {
+            assert!(false);
+        }
+
+        // the default export lists permissions by bit, so importing assigns every permission its original bit
+        for _ in 0..8 {
+            let imported = Scope::from_json(scope.as_json());
+            assert_eq!(imported.as_u64(), scope.as_u64());
+            assert_eq!(imported.check("READ"), true);
+            assert_eq!(exported_names(&imported, SortOrder::Insertion), exported_names(&scope, SortOrder::Insertion));
+        }
+    }
+
+    #[test]
+    fn test_rename_keeps_insertion_order() {
+        let mut scope = get_scope();
+        if let Err(_) = scope.rename_permission("ADMIN", "OWNER", false) {
+            assert!(false);
+        }
+
+        assert_eq!(exported_names(&scope, SortOrder::Insertion).0, ["WRITE", "OWNER", "READ"]);
+        assert_eq!(exported_names(&scope.clone(), SortOrder::Insertion), exported_names(&scope, SortOrder::Insertion));
+    }
+}
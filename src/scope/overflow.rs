@@ -0,0 +1,275 @@
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+use crate::common::error::ErrorKind;
+use crate::scope::error::{ScopeError, ScopeErrorCase};
+use crate::scope::order::SortOrder;
+use crate::scope::path::ScopePath;
+use crate::scope::Scope;
+
+/**
+    Marker placed between a scope name and the number of one of its continuations, e.g. `APP#2`. Every validation
+    policy rejects it in names, so only continuations created by spilling are named this way.
+ */
+pub const CONTINUATION_MARKER: char = '#';
+
+/**
+    OverflowMode decides what happens when a permission is added to a scope that has no bits left. Scopes spill by
+    default, so the number of permissions of a scope is not capped by the width of a single value.
+ */
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OverflowMode {
+    /** Refuse to add the permission, failing with MaxValue, for scopes whose grants must fit in a single value. */
+    Reject,
+    /**
+        Add the permission to a continuation scope named after the full scope, e.g. `APP#2`, creating it when the
+        previous continuation is full as well. Continuations are exported as child scopes with their own value.
+     */
+    #[default]
+    Spill
+}
+
+impl Scope {
+    /** Get the mode used when a permission is added to this scope after it has run out of bits. */
+    pub fn overflow_mode(&self) -> OverflowMode {
+        return self.overflow_mode;
+    }
+
+    /**
+        Set the mode used when permissions are added to this scope and all of its child scopes after they have run
+        out of bits. Like the requirement mode, it is not exported.
+     */
+    pub fn set_overflow_mode(&mut self, mode: OverflowMode) -> &mut Scope {
+        for scope in self.scopes.values_mut() {
+            scope.set_overflow_mode(mode);
+        }
+        self.overflow_mode = mode;
+
+        return self;
+    }
+
+    /**
+        List the continuation scopes holding permissions that spilled over from this scope, in order. Checks,
+        grants and revocations of a spilled permission made through this scope are passed on to its continuation,
+        but rules such as requirements and implications only apply between permissions of the same segment.
+     */
+    pub fn continuations(&self) -> Vec<&Scope> {
+        return self.scopes_in_order(SortOrder::Insertion).into_iter()
+            .filter(|scope| self.is_continuation(&scope.name))
+            .collect();
+    }
+
+    /**
+        Get the value of this scope followed by the value of each of its continuations, one word per segment, so a
+        scope holding more permissions than fit in a single value can still be stored and sent as numbers. Word `i`
+        holds the permissions of segment `i + 1` (`APP`, `APP#2`, ...), each within the ceiling of the scope. A
+        scope that never filled up, or that was set to `OverflowMode::Reject`, gives a single word.
+     */
+    pub fn as_words(&self) -> Vec<u64> {
+        let mut words: Vec<u64> = vec![self.as_u64()];
+        words.extend(self.continuations().iter().map(|continuation| continuation.as_u64()));
+
+        return words;
+    }
+
+    /**
+        Set the grants of this scope and its continuations from words produced by `as_words`. Missing trailing
+        words revoke every permission of their segment. Fails with UndefinedBits, without changing anything, if a
+        word sets bits of a segment that does not exist or that no permission is assigned to.
+     */
+    pub fn apply_words(&mut self, words: &[u64]) -> Result<&mut Scope, ErrorKind> {
+        let segments: Vec<String> = self.continuations().iter().map(|continuation| continuation.name.clone()).collect();
+        if words.iter().skip(segments.len() + 1).any(|word| *word != 0) {
+            return Err(ErrorKind::ScopeError(ScopeError::new(ScopeErrorCase::UndefinedBits, &self.path.to_string())));
+        }
+
+        let mut working = self.clone();
+        working.apply_u64(words.first().copied().unwrap_or(0))?;
+        for (index, name) in segments.iter().enumerate() {
+            let word = words.get(index + 1).copied().unwrap_or(0);
+            working.resolve_scope_mut(&ScopePath::parse(name))?.apply_u64(word)?;
+        }
+        *self = working;
+
+        return Ok(self);
+    }
+
+    /** Check whether a direct child scope name is one of the continuations of this scope. */
+    pub(crate) fn is_continuation(&self, name: &str) -> bool {
+        return match name.strip_prefix(self.name.as_str()).and_then(|suffix| suffix.strip_prefix(CONTINUATION_MARKER)) {
+            Some(number) => number.parse::<u32>().map(|number| number >= 2).unwrap_or(false),
+            None => false
+        }
+    }
+
+    /** Get the continuation of this scope defining a permission, by its resolved name. */
+    pub(crate) fn continuation_holding(&self, name: &str) -> Option<&Scope> {
+        return self.continuations().into_iter().find(|scope| scope.permissions.contains_key(name));
+    }
+
+    /** Get the continuation of this scope defining a permission mutably, by its resolved name. */
+    pub(crate) fn continuation_holding_mut(&mut self, name: &str) -> Option<&mut Scope> {
+        let holder = self.continuation_holding(name).map(|scope| scope.name.clone())?;
+
+        return self.scopes.get_mut(&holder);
+    }
+
+    /** Add a permission to the last continuation of this scope, starting a new one when it is full. */
+    pub(crate) fn spill_permission(&mut self, name: &str) -> Result<(), ErrorKind> {
+        let last: Option<String> = self.continuations().last()
+            .filter(|scope| scope.has_capacity(1))
+            .map(|scope| scope.name.clone());
+
+        let holder = match last {
+            Some(holder) => holder,
+            None => {
+                let holder = format!("{}{}{}", self.name, CONTINUATION_MARKER, self.continuations().len() + 2);
+                let continuation = self.child_template(&holder);
+                self.insert_scope(continuation);
+                holder
+            }
+        };
+
+        return match self.scopes.get_mut(&holder) {
+            Some(continuation) => continuation.add_permission(name).map(|_| ()),
+            None => unreachable!("the continuation was found or inserted above")
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use crate::{assert_granted, assert_not_granted, assert_ok, assert_scope_err};
+    use crate::permission::ValueCeiling;
+
+    fn get_full_scope() -> Scope {
+        let mut scope = Scope::new("APP");
+        let capacity = scope.remaining_capacity();
+        for index in 0..capacity {
+            assert_ok!(scope.add_permission(&format!("P{}", index)));
+        }
+
+        return scope;
+    }
+
+    #[test]
+    fn test_full_scope_spills_by_default() {
+        let mut scope = get_full_scope();
+
+        assert_eq!(scope.overflow_mode(), OverflowMode::Spill);
+        assert_ok!(scope.add_permission("EXTRA").and_then(|sc| sc.grant("EXTRA")));
+        assert_eq!(scope.continuations().len(), 1);
+        assert_eq!(scope.as_words(), [0, 1]);
+    }
+
+    #[test]
+    fn test_full_scope_rejects_when_asked() {
+        let mut scope = get_full_scope();
+        scope.set_overflow_mode(OverflowMode::Reject);
+
+        assert_eq!(matches!(scope.add_permission("EXTRA"), Err(ErrorKind::PermissionError(_))), true);
+        assert_eq!(scope.continuations().len(), 0);
+        assert_eq!(scope.as_words(), [0]);
+    }
+
+    #[test]
+    fn test_spill_into_continuations() {
+        let mut scope = Scope::with_ceiling("APP", ValueCeiling::Full);
+        scope.set_overflow_mode(OverflowMode::Spill);
+        for index in 0..(64 * 2 + 1) {
+            assert_ok!(scope.add_permission(&format!("P{}", index)));
+        }
+
+        let continuations: Vec<String> = scope.continuations().iter().map(|continuation| continuation.name().to_string()).collect();
+        assert_eq!(continuations, ["APP#2", "APP#3"]);
+        assert_eq!(scope.continuations()[1].permissions_in_order(SortOrder::Insertion).len(), 1);
+
+        assert_ok!(scope.grant("P64").and_then(|sc| sc.grant("P128")));
+        assert_granted!(scope, "P64");
+        assert_granted!(scope, "APP#3:P128");
+        assert_not_granted!(scope, "P65");
+        assert_eq!(scope.continuations()[0].as_u64(), 1);
+
+        assert_ok!(scope.revoke("P64"));
+        assert_not_granted!(scope, "P64");
+        assert_scope_err!(scope.add_permission("P70"), ScopeErrorCase::PermissionExists);
+    }
+
+    #[test]
+    fn test_words_span_continuations() {
+        let mut scope = Scope::with_ceiling("APP", ValueCeiling::Full);
+        scope.set_overflow_mode(OverflowMode::Spill);
+        for index in 0..(64 * 2 + 1) {
+            assert_ok!(scope.add_permission(&format!("P{}", index)));
+        }
+        assert_ok!(scope.grant("P0").and_then(|sc| sc.grant("P65")).and_then(|sc| sc.grant("P128")));
+        assert_eq!(scope.as_words(), [1, 0b10, 1]);
+
+        let mut restored = scope.clone();
+        assert_ok!(restored.apply_words(&[0, 0b10]));
+        assert_eq!(restored.as_words(), [0, 0b10, 0]);
+        assert_granted!(restored, "P65");
+
+        assert_scope_err!(restored.apply_words(&[1, 0, 0b10]), ScopeErrorCase::UndefinedBits);
+        assert_scope_err!(restored.apply_words(&[1, 0, 0, 1]), ScopeErrorCase::UndefinedBits);
+        assert_eq!(restored.as_words(), [0, 0b10, 0]);
+        assert_ok!(restored.apply_words(&[1, 0, 1, 0]));
+        assert_eq!(restored.as_words(), [1, 0, 1]);
+    }
+
+    #[test]
+    fn test_spilled_permissions_are_visible() {
+        use crate::scope::context::EvalContext;
+        use crate::scope::evaluate::{check_value, evaluate_matrix};
+
+        let mut scope = get_full_scope();
+        scope.set_overflow_mode(OverflowMode::Spill);
+        assert_ok!(scope.add_permission("EXTRA").and_then(|sc| sc.add_permission("MORE")).and_then(|sc| sc.grant("EXTRA")));
+
+        assert_eq!(scope.has("EXTRA"), true);
+        assert_eq!(scope.has("MORE"), false);
+        assert_eq!(scope.has("*"), true);
+        assert_eq!(scope.has_all("*"), false);
+        assert_eq!(scope.check_in("EXTRA", &EvalContext::new()), true);
+
+        // stored values of a spilled permission are the words of its continuation
+        assert_eq!(scope.grant_mask("MORE").ok(), Some(0b10));
+        assert_eq!(check_value(&scope, "EXTRA", scope.as_words()[1]).ok(), Some(true));
+        assert_eq!(check_value(&scope, "MORE", scope.as_words()[1]).ok(), Some(false));
+
+        let words = scope.as_words();
+        let matrix = assert_ok!(evaluate_matrix(&scope, &[("user", &words)], &["EXTRA", "MORE"]));
+        assert_eq!(matrix.row(0), Some(&[true, false][..]));
+    }
+
+    #[test]
+    fn test_continuations_are_exported() {
+        let mut scope = get_full_scope();
+        scope.set_overflow_mode(OverflowMode::Spill);
+        assert_ok!(scope.add_permission("EXTRA").and_then(|sc| sc.grant("EXTRA")));
+
+        let exported = scope.as_json();
+        assert_eq!(exported[3][0][0], json!("APP#2"));
+        assert_eq!(exported[3][0][1], json!(1));
+
+        let imported = assert_ok!(Scope::try_from_json(exported));
+        assert_granted!(imported, "EXTRA");
+    }
+
+    #[test]
+    fn test_continuation_marker_is_reserved() {
+        let mut scope = Scope::new("APP");
+        assert_scope_err!(scope.add_scope("APP#2"), ScopeErrorCase::InvalidName);
+        assert_scope_err!(scope.add_permission("READ#2"), ScopeErrorCase::InvalidName);
+        assert_eq!(scope.continuations().len(), 0);
+
+        // only children named after their parent may carry the marker on import
+        let forged = json!(["APP", 0, [], [["OTHER#2", 0, [], []]]]);
+        assert_scope_err!(Scope::try_from_json(forged), ScopeErrorCase::InvalidName);
+        let forged = json!(["APP#2", 0, [], []]);
+        assert_scope_err!(Scope::try_from_json(forged), ScopeErrorCase::InvalidName);
+    }
+}
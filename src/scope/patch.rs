@@ -0,0 +1,194 @@
+use alloc::string::String;
+use serde::{Deserialize, Serialize};
+use crate::common::error::ErrorKind;
+use crate::scope::error::{ScopeError, ScopeErrorCase, ScopeErrorMetadata};
+use crate::scope::path::{PermissionPath, ScopePath};
+use crate::scope::Scope;
+
+/**
+    Operation is a single change to a scope tree. Paths are relative to the scope the operation is applied to:
+    permission paths look like `billing:READ`, scope paths like `billing.invoices`.
+ */
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum Operation {
+    AddPermission { path: String },
+    AddScope { path: String },
+    Grant { path: String },
+    Revoke { path: String }
+}
+
+impl Scope {
+    /**
+        Apply a list of operations as a single change. The operations are applied to a copy of the tree, which
+        only replaces this scope once every operation has succeeded, so a failing patch leaves the tree untouched.
+     */
+    pub fn apply_patch(&mut self, operations: &[Operation]) -> Result<&mut Scope, ErrorKind> {
+        let mut working = self.clone();
+
+        for operation in operations {
+            working.apply_operation(operation)?;
+        }
+        working.touch();
+        *self = working;
+
+        return Ok(self);
+    }
+
+    /**
+        Apply a list of operations only if the tree is still at `revision`, the `tree_revision` the caller based
+        its changes on, so a concurrent change anywhere in the tree, including directly on a child scope, rejects
+        the patch with a StaleRevision error and nothing is changed.
+     */
+    pub fn apply_patch_if_revision(&mut self, revision: u64, operations: &[Operation]) -> Result<&mut Scope, ErrorKind> {
+        let current = self.tree_revision();
+        if current != revision {
+            return Err(ErrorKind::ScopeError(ScopeError::with_metadata(
+                ScopeErrorCase::StaleRevision,
+                &self.name,
+                ScopeErrorMetadata {
+                    expected_revision: Some(revision),
+                    revision: Some(current),
+                    ..ScopeErrorMetadata::new()
+                }
+            )));
+        }
+
+        return self.apply_patch(operations);
+    }
+
+    /** Apply a single operation in place, without the copy that makes `apply_patch` atomic. */
+    pub(crate) fn apply_operation(&mut self, operation: &Operation) -> Result<(), ErrorKind> {
+        match operation {
+            Operation::AddPermission { path } => {
+                let path = PermissionPath::parse(path);
+                self.resolve_scope_mut(&path.scope)?.add_permission(&path.permission)?;
+            },
+            Operation::AddScope { path } => {
+                let path = ScopePath::parse(path);
+                let (parent, name) = match path.split_last() {
+                    Some(split) => split,
+                    None => return Err(ErrorKind::ScopeError(ScopeError::new(ScopeErrorCase::InvalidName, "")))
+                };
+                self.resolve_scope_mut(&parent)?.add_scope(name)?;
+            },
+            Operation::Grant { path } => {
+                self.apply_linked(path, true)?;
+            },
+            Operation::Revoke { path } => {
+                self.apply_linked(path, false)?;
+            }
+        };
+
+        return Ok(());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::string::ToString;
+    use alloc::vec;
+    use serde_json::json;
+
+    fn get_patched_scope() -> Scope {
+        let mut scope = Scope::new("APP");
+
+        let patch = vec![
+            Operation::AddPermission { path: "READ".to_string() },
+            Operation::AddScope { path: "billing".to_string() },
+            Operation::AddPermission { path: "billing:PAY".to_string() },
+            Operation::Grant { path: "billing:PAY".to_string() },
+        ];
+        if let Err(_) = scope.apply_patch(&patch) {
+            assert!(false);
+        }
+
+        return scope;
+    }
+
+    #[test]
+    fn test_apply_patch() {
+        let scope = get_patched_scope();
+
+        assert_eq!(scope.check("billing:PAY"), true);
+        assert_eq!(scope.check("READ"), false);
+        assert_eq!(scope.revision(), 3u64);
+    }
+
+    #[test]
+    fn test_failed_patch_changes_nothing() {
+        let mut scope = get_patched_scope();
+        let revision = scope.revision();
+
+        let patch = vec![
+            Operation::Grant { path: "READ".to_string() },
+            Operation::Revoke { path: "payroll:RUN".to_string() },
+        ];
+        match scope.apply_patch(&patch) {
+            Ok(_) => assert!(false),
+            Err(ErrorKind::ScopeError(err)) => assert_eq!(err.case(), ScopeErrorCase::UnknownScope),
+            Err(ErrorKind::PermissionError(_)) => assert!(false)
+        }
+
+        assert_eq!(scope.check("READ"), false);
+        assert_eq!(scope.revision(), revision);
+    }
+
+    #[test]
+    fn test_stale_revision_is_rejected() {
+        let mut scope = get_patched_scope();
+        let revision = scope.tree_revision();
+        let patch = vec![Operation::Grant { path: "READ".to_string() }];
+
+        // another editor changes the tree first
+        if let Err(_) = scope.apply_patch_if_revision(revision, &[Operation::AddPermission { path: "WRITE".to_string() }]) {
+            assert!(false);
+        }
+
+        match scope.apply_patch_if_revision(revision, &patch) {
+            Ok(_) => assert!(false),
+            Err(ErrorKind::ScopeError(err)) => {
+                assert_eq!(err.case(), ScopeErrorCase::StaleRevision);
+                assert_eq!(err.metadata().expected_revision(), Some(revision));
+                assert_eq!(err.metadata().revision(), Some(scope.tree_revision()));
+            },
+            Err(ErrorKind::PermissionError(_)) => assert!(false)
+        }
+        assert_eq!(scope.check("READ"), false);
+    }
+
+    #[test]
+    fn test_stale_revision_covers_the_tree() {
+        // a change made directly on a child scope
+        let mut scope = get_patched_scope();
+        let revision = scope.tree_revision();
+        if let Some(billing) = scope.scope("billing") {
+            if let Err(_) = billing.add_permission("REFUND") {
+                assert!(false);
+            }
+        }
+        if let Ok(_) = scope.apply_patch_if_revision(revision, &[Operation::Grant { path: "READ".to_string() }]) {
+            assert!(false);
+        }
+
+        // and a grant made through a permission reference
+        let revision = scope.tree_revision();
+        if let Some(read) = scope.permission("READ") {
+            let _ = read.grant();
+        }
+        assert_ne!(scope.tree_revision(), revision);
+        if let Ok(_) = scope.apply_patch_if_revision(revision, &[Operation::Revoke { path: "READ".to_string() }]) {
+            assert!(false);
+        }
+    }
+
+    #[test]
+    fn test_operation_json() {
+        let operation = Operation::Grant { path: "billing:PAY".to_string() };
+        let value = json!({ "op": "grant", "path": "billing:PAY" });
+
+        assert_eq!(serde_json::to_value(&operation).ok(), Some(value.clone()));
+        assert_eq!(serde_json::from_value::<Operation>(value).ok(), Some(operation));
+    }
+}
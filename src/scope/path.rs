@@ -0,0 +1,172 @@
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt;
+use core::fmt::{Display, Formatter};
+
+/** Separator placed between scope names when a path is rendered as a string. */
+pub const SCOPE_SEPARATOR: char = '.';
+
+/** Separator placed between a scope path and a permission name. */
+pub const PERMISSION_SEPARATOR: char = ':';
+
+/** Matches any single scope name or any permission name in patterns accepted by `Scope::has`. */
+pub const WILDCARD: &str = "*";
+
+/** ScopePath is the chain of scope names leading from a root scope down to a nested scope. */
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct ScopePath(Vec<String>);
+
+impl ScopePath {
+    /** Creates an empty path. */
+    pub fn new() -> ScopePath {
+        return ScopePath(Vec::new());
+    }
+
+    /** Creates a path from a list of scope names ordered from the root downward. */
+    pub fn from_segments(segments: &[&str]) -> ScopePath {
+        return ScopePath(segments.iter().map(|segment| segment.to_string()).collect());
+    }
+
+    /** Parse a string of scope names joined by the scope separator. An empty string is an empty path. */
+    pub fn parse(path: &str) -> ScopePath {
+        if path.is_empty() {
+            return ScopePath::new();
+        }
+
+        return ScopePath(path.split(SCOPE_SEPARATOR).map(|segment| segment.to_string()).collect());
+    }
+
+    /** Get a new path that extends this one by a single child scope. */
+    pub fn child(&self, name: &str) -> ScopePath {
+        let mut segments = self.0.clone();
+        segments.push(name.to_string());
+
+        return ScopePath(segments);
+    }
+
+    /** Split off the last scope name, returning the path of its parent and the name. */
+    pub fn split_last(&self) -> Option<(ScopePath, &str)> {
+        let (last, parent) = self.0.split_last()?;
+
+        return Some((ScopePath(parent.to_vec()), last.as_str()));
+    }
+
+    /** Get this path relative to one of its ancestors, which must be a prefix of it. */
+    pub(crate) fn relative_to(&self, ancestor: &ScopePath) -> ScopePath {
+        return ScopePath(self.0.iter().skip(ancestor.len()).cloned().collect());
+    }
+
+    /** Get the scope names in this path, ordered from the root downward. */
+    pub fn segments(&self) -> &[String] {
+        return &self.0;
+    }
+
+    /** Get the number of scopes in this path. */
+    pub fn len(&self) -> usize {
+        return self.0.len();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.0.is_empty();
+    }
+}
+
+impl Display for ScopePath {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        for (i, segment) in self.0.iter().enumerate() {
+            if i > 0 {
+                write!(f, "{}", SCOPE_SEPARATOR)?;
+            }
+            write!(f, "{}", segment)?;
+        }
+
+        return Ok(());
+    }
+}
+
+/**
+    PermissionPath addresses a permission relative to a scope, e.g. `billing.invoices:READ`.
+    The scope path and permission name may also be separated by the scope separator (`billing.invoices.READ`),
+    in which case the last segment is taken as the permission name.
+ */
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct PermissionPath {
+    pub scope: ScopePath,
+    pub permission: String
+}
+
+impl PermissionPath {
+    pub fn new(scope: ScopePath, permission: &str) -> PermissionPath {
+        return PermissionPath {
+            scope,
+            permission: permission.to_string()
+        }
+    }
+
+    /** Parse a path string into the scope path and permission name it addresses. */
+    pub fn parse(path: &str) -> PermissionPath {
+        let split_at = match path.rfind(PERMISSION_SEPARATOR) {
+            Some(i) => Some(i),
+            None => path.rfind(SCOPE_SEPARATOR)
+        };
+
+        return match split_at {
+            Some(i) => PermissionPath::new(ScopePath::parse(&path[..i]), &path[i + 1..]),
+            None => PermissionPath::new(ScopePath::new(), path)
+        }
+    }
+}
+
+impl Display for PermissionPath {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        if self.scope.is_empty() {
+            return write!(f, "{}", self.permission);
+        }
+
+        write!(f, "{}{}{}", self.scope, PERMISSION_SEPARATOR, self.permission)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::string::ToString;
+    use super::*;
+
+    #[test]
+    fn test_empty_path() {
+        let path = ScopePath::new();
+
+        assert_eq!(path.is_empty(), true);
+        assert_eq!(path.to_string(), "");
+    }
+
+    #[test]
+    fn test_child_path_display() {
+        let path = ScopePath::from_segments(&["USER"]).child("PROFILE").child("SETTINGS");
+
+        assert_eq!(path.len(), 3usize);
+        assert_eq!(path.to_string(), "USER.PROFILE.SETTINGS");
+    }
+
+    #[test]
+    fn test_parse_scope_path() {
+        assert_eq!(ScopePath::parse(""), ScopePath::new());
+        assert_eq!(ScopePath::parse("USER.PROFILE"), ScopePath::from_segments(&["USER", "PROFILE"]));
+    }
+
+    #[test]
+    fn test_parse_permission_path() {
+        let path = PermissionPath::parse("billing.invoices:READ");
+        assert_eq!(path.scope, ScopePath::from_segments(&["billing", "invoices"]));
+        assert_eq!(path.permission, "READ");
+        assert_eq!(path.to_string(), "billing.invoices:READ");
+
+        // the last segment is the permission when no permission separator is present
+        assert_eq!(PermissionPath::parse("billing.invoices.READ"), path);
+
+        let local = PermissionPath::parse("READ");
+        assert_eq!(local.scope.is_empty(), true);
+        assert_eq!(local.permission, "READ");
+        assert_eq!(local.to_string(), "READ");
+    }
+}
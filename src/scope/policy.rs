@@ -0,0 +1,131 @@
+use alloc::borrow::Cow;
+#[cfg(feature = "unicode")]
+use unicode_normalization::{is_nfc, UnicodeNormalization};
+use crate::scope::overflow::CONTINUATION_MARKER;
+use crate::scope::path::{PERMISSION_SEPARATOR, SCOPE_SEPARATOR, WILDCARD};
+
+/** ValidationPolicy controls which names are accepted when adding permissions and child scopes. */
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ValidationPolicy {
+    /** Reject names containing the scope or permission separator, which would make paths ambiguous. */
+    pub reject_separators: bool,
+    /** Reject names containing whitespace or control characters. */
+    pub reject_whitespace: bool,
+    /** Normalize names to Unicode NFC when they are added or looked up, so visually identical names collide. */
+    #[cfg(feature = "unicode")]
+    pub normalize_nfc: bool,
+}
+
+impl ValidationPolicy {
+    /** Creates the default policy, which rejects every name that could not be addressed by a path. */
+    pub fn new() -> ValidationPolicy {
+        return ValidationPolicy {
+            reject_separators: true,
+            reject_whitespace: true,
+            #[cfg(feature = "unicode")]
+            normalize_nfc: false,
+        }
+    }
+
+    /** Creates a policy that only rejects empty names, the wildcard and names containing the continuation marker. */
+    pub fn permissive() -> ValidationPolicy {
+        return ValidationPolicy {
+            reject_separators: false,
+            reject_whitespace: false,
+            #[cfg(feature = "unicode")]
+            normalize_nfc: false,
+        }
+    }
+
+    /** Get the form of a name that is stored and looked up under this policy. */
+    pub fn normalize<'a>(&self, name: &'a str) -> Cow<'a, str> {
+        #[cfg(feature = "unicode")]
+        if self.normalize_nfc && !is_nfc(name) {
+            return Cow::Owned(name.nfc().collect());
+        }
+
+        return Cow::Borrowed(name);
+    }
+
+    /**
+        Check whether a name satisfies this policy. The wildcard `*` is never allowed as a name, as patterns passed to
+        `Scope::has` would match it as any name, and neither are names containing the continuation marker `#`, as
+        they would be mistaken for the continuations of a scope in `OverflowMode::Spill`.
+     */
+    pub fn allows(&self, name: &str) -> bool {
+        if name.is_empty() || name == WILDCARD {
+            return false;
+        }
+
+        return name.chars().all(|c| {
+            if c == CONTINUATION_MARKER {
+                return false;
+            }
+            if self.reject_separators && (c == SCOPE_SEPARATOR || c == PERMISSION_SEPARATOR) {
+                return false;
+            }
+            if self.reject_whitespace && (c.is_whitespace() || c.is_control()) {
+                return false;
+            }
+
+            return true;
+        });
+    }
+}
+
+impl Default for ValidationPolicy {
+    fn default() -> Self {
+        ValidationPolicy::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_policy() {
+        let policy = ValidationPolicy::new();
+
+        assert_eq!(policy.allows("READ"), true);
+        assert_eq!(policy.allows("TEST_PERMISSION_1"), true);
+        assert_eq!(policy.allows(""), false);
+        assert_eq!(policy.allows("USER.READ"), false);
+        assert_eq!(policy.allows("USER:READ"), false);
+        assert_eq!(policy.allows("READ WRITE"), false);
+        assert_eq!(policy.allows("READ\n"), false);
+        assert_eq!(policy.allows("READ\u{0007}"), false);
+        assert_eq!(policy.allows("APP#2"), false);
+        assert_eq!(policy.allows("*"), false);
+        assert_eq!(policy.allows("READ*"), true);
+    }
+
+    #[test]
+    fn test_permissive_policy() {
+        let policy = ValidationPolicy::permissive();
+
+        assert_eq!(policy.allows("USER.READ"), true);
+        assert_eq!(policy.allows("READ WRITE"), true);
+        assert_eq!(policy.allows(""), false);
+        assert_eq!(policy.allows("APP#2"), false);
+        assert_eq!(policy.allows("*"), false);
+    }
+
+    #[test]
+    fn test_normalize_disabled_by_default() {
+        let policy = ValidationPolicy::new();
+
+        // "e" followed by a combining acute accent is left untouched
+        assert_eq!(policy.normalize("CAFE\u{0301}"), "CAFE\u{0301}");
+    }
+
+    #[cfg(feature = "unicode")]
+    #[test]
+    fn test_normalize_nfc() {
+        let mut policy = ValidationPolicy::new();
+        policy.normalize_nfc = true;
+
+        assert_eq!(policy.normalize("CAF\u{0045}\u{0301}"), "CAF\u{00C9}");
+        assert_eq!(policy.normalize("CAF\u{00C9}"), "CAF\u{00C9}");
+    }
+}
@@ -0,0 +1,156 @@
+use crate::analysis::escalation::{diff_grants, EscalationReport};
+use crate::common::error::ErrorKind;
+use crate::scope::guard::MutationContext;
+use crate::scope::patch::Operation;
+use crate::scope::Scope;
+
+/** ApplyMode selects whether a change is made or only previewed. */
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ApplyMode {
+    /** Make the change and report what changed. */
+    #[default]
+    Commit,
+    /** Leave the tree untouched and report what would change. */
+    DryRun
+}
+
+impl Scope {
+    /**
+        Grant a permission like `grant`, reporting every permission whose effective state changes, including
+        requirements granted along with it and permissions it implies. With `ApplyMode::DryRun` nothing is
+        changed and no grant is counted in metrics, but the same errors are returned.
+     */
+    pub fn grant_with(&mut self, name: &str, mode: ApplyMode) -> Result<EscalationReport, ErrorKind> {
+        return match mode {
+            ApplyMode::Commit => self.commit_and_diff(|scope| scope.grant(name).map(|_| ())),
+            ApplyMode::DryRun => self.preview(|scope| scope.try_grant(name, &MutationContext::new()))
+        }
+    }
+
+    /** Revoke a permission like `revoke`, reporting every permission whose effective state changes. */
+    pub fn revoke_with(&mut self, name: &str, mode: ApplyMode) -> Result<EscalationReport, ErrorKind> {
+        return match mode {
+            ApplyMode::Commit => self.commit_and_diff(|scope| scope.revoke(name).map(|_| ())),
+            ApplyMode::DryRun => self.preview(|scope| scope.try_revoke(name, &MutationContext::new()))
+        }
+    }
+
+    /**
+        Apply a patch like `apply_patch`, reporting every permission whose effective state changes. A dry run fails
+        on the first failing operation just like the real patch would; use `simulate` to see every failure.
+     */
+    pub fn apply_patch_with(&mut self, operations: &[Operation], mode: ApplyMode) -> Result<EscalationReport, ErrorKind> {
+        return match mode {
+            ApplyMode::Commit => self.commit_and_diff(|scope| scope.apply_patch(operations).map(|_| ())),
+            ApplyMode::DryRun => self.preview(|scope| {
+                for operation in operations {
+                    scope.apply_operation(operation)?;
+                }
+                return Ok(());
+            })
+        }
+    }
+
+    fn commit_and_diff<F>(&mut self, change: F) -> Result<EscalationReport, ErrorKind>
+        where F: FnOnce(&mut Scope) -> Result<(), ErrorKind> {
+        let before = self.clone();
+        change(self)?;
+
+        return Ok(diff_grants(&before, self));
+    }
+
+    fn preview<F>(&self, change: F) -> Result<EscalationReport, ErrorKind>
+        where F: FnOnce(&mut Scope) -> Result<(), ErrorKind> {
+        let mut working = self.clone();
+        change(&mut working)?;
+
+        return Ok(diff_grants(self, &working));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::string::ToString;
+    use alloc::vec::Vec;
+    use crate::analysis::escalation::ChangeKind;
+    use crate::scope::constraint::RequirementMode;
+    use crate::scope::error::ScopeErrorCase;
+    use crate::{assert_granted, assert_not_granted, assert_ok, assert_scope_err};
+
+    fn get_scope() -> Scope {
+        let mut scope = Scope::new("APP");
+        assert_ok!(scope
+            .add_permission("READ")
+            .and_then(|sc| sc.add_permission("WRITE"))
+            .and_then(|sc| sc.add_permission("ADMIN"))
+            .and_then(|sc| sc.add_permission("AUDIT"))
+            .and_then(|sc| sc.add_requirement("WRITE", "READ"))
+            .and_then(|sc| sc.add_implication("ADMIN", "AUDIT")));
+        scope.set_requirement_mode(RequirementMode::AutoGrant);
+
+        return scope;
+    }
+
+    fn changed(report: &EscalationReport) -> Vec<(&str, ChangeKind)> {
+        return report.changes.iter().map(|change| (change.path.as_str(), change.kind)).collect();
+    }
+
+    #[test]
+    fn test_dry_run_reports_cascades() {
+        let mut scope = get_scope();
+        let revision = scope.revision();
+
+        match scope.grant_with("WRITE", ApplyMode::DryRun) {
+            Ok(report) => assert_eq!(changed(&report), [("APP:READ", ChangeKind::Granted), ("APP:WRITE", ChangeKind::Granted)]),
+            Err(_) => assert!(false)
+        }
+        match scope.grant_with("ADMIN", ApplyMode::DryRun) {
+            Ok(report) => assert_eq!(changed(&report), [("APP:ADMIN", ChangeKind::Granted), ("APP:AUDIT", ChangeKind::Granted)]),
+            Err(_) => assert!(false)
+        }
+
+        assert_not_granted!(scope, "WRITE");
+        assert_not_granted!(scope, "READ");
+        assert_eq!(scope.revision(), revision);
+
+        // dry runs fail exactly like the real change
+        assert_scope_err!(scope.revoke_with("DELETE", ApplyMode::DryRun), ScopeErrorCase::UnknownPermission);
+    }
+
+    #[test]
+    fn test_commit_reports_changes() {
+        let mut scope = get_scope();
+
+        match scope.grant_with("ADMIN", ApplyMode::Commit) {
+            Ok(report) => assert_eq!(changed(&report).len(), 2),
+            Err(_) => assert!(false)
+        }
+        assert_granted!(scope, "AUDIT");
+
+        match scope.revoke_with("ADMIN", ApplyMode::DryRun) {
+            Ok(report) => assert_eq!(changed(&report), [("APP:ADMIN", ChangeKind::Revoked), ("APP:AUDIT", ChangeKind::Revoked)]),
+            Err(_) => assert!(false)
+        }
+        assert_granted!(scope, "ADMIN");
+    }
+
+    #[test]
+    fn test_patch_dry_run() {
+        let mut scope = get_scope();
+        let patch = [
+            Operation::AddScope { path: "billing".to_string() },
+            Operation::AddPermission { path: "billing:PAY".to_string() },
+            Operation::Grant { path: "billing:PAY".to_string() }
+        ];
+
+        match scope.apply_patch_with(&patch, ApplyMode::DryRun) {
+            Ok(report) => assert_eq!(changed(&report), [("APP.billing:PAY", ChangeKind::Granted)]),
+            Err(_) => assert!(false)
+        }
+        assert_eq!(scope.child_scope("billing").is_none(), true);
+
+        assert_ok!(scope.apply_patch_with(&patch, ApplyMode::Commit));
+        assert_granted!(scope, "billing:PAY");
+    }
+}
@@ -0,0 +1,285 @@
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+use crate::common::error::ErrorKind;
+use crate::scope::error::{ScopeError, ScopeErrorCase};
+use crate::scope::path::{PermissionPath, ScopePath};
+use crate::scope::Scope;
+
+impl Scope {
+    /**
+        Declare that the permissions at `path` and `target` (relative to this scope, e.g. `billing:VIEW` and
+        `reports:INVOICES`) are the same capability exposed in two places. Granting or revoking either through
+        `grant_path` or `revoke_path` on this scope, or through a patch applied to it, changes both. References
+        are declared on a scope containing both permissions and are carried through tuple and JSON exports.
+     */
+    pub fn add_reference(&mut self, path: &str, target: &str) -> Result<&mut Scope, ErrorKind> {
+        let path = self.canonical_path(path)?;
+        let target = self.canonical_path(target)?;
+
+        let declared = self.references.iter()
+            .any(|(first, second)| (*first == path && *second == target) || (*first == target && *second == path));
+        if path != target && !declared {
+            self.mark_referenced(&path);
+            self.mark_referenced(&target);
+            self.references.push((path, target));
+        }
+
+        self.touch();
+        return Ok(self);
+    }
+
+    /**
+        Get every path kept in sync with the permission at `path` by references declared on this scope, directly
+        or through other references, in the form accepted by `check`. Unknown paths have no references.
+     */
+    pub fn references(&self, path: &str) -> Vec<String> {
+        let path = match self.canonical_path(path) {
+            Ok(path) => path,
+            Err(_) => return vec![]
+        };
+
+        return self.linked_paths(&path).into_iter().filter(|linked| *linked != path).collect();
+    }
+
+    /**
+        Grant the permission at `path` (relative to this scope) along with every permission referencing it.
+        Referenced permissions that are already granted are left as they are. Nothing is changed if any of
+        the grants fails.
+     */
+    pub fn grant_path(&mut self, path: &str) -> Result<&mut Scope, ErrorKind> {
+        return self.set_linked(path, true);
+    }
+
+    /** Revoke the permission at `path` (relative to this scope) along with every permission referencing it. */
+    pub fn revoke_path(&mut self, path: &str) -> Result<&mut Scope, ErrorKind> {
+        return self.set_linked(path, false);
+    }
+
+    fn set_linked(&mut self, path: &str, grant: bool) -> Result<&mut Scope, ErrorKind> {
+        let mut working = self.clone();
+        working.apply_linked(path, grant)?;
+        working.touch();
+        *self = working;
+
+        return Ok(self);
+    }
+
+    /**
+        Grant or revoke the permission at `path` and every permission referencing it in place, stopping at the
+        first failure. Callers working on a copy of the tree decide whether to keep the result.
+     */
+    pub(crate) fn apply_linked(&mut self, path: &str, grant: bool) -> Result<(), ErrorKind> {
+        let requested = self.canonical_path(path)?;
+
+        for linked in self.linked_paths(&requested) {
+            let linked_path = PermissionPath::parse(&linked);
+            let scope = self.resolve_scope_mut(&linked_path.scope)?;
+
+            // only the requested permission reports a grant or revocation that changes nothing
+            if linked != requested && scope.is_granted(&scope.find_permission_name(&linked_path.permission)?) == grant {
+                continue;
+            }
+            match grant {
+                true => scope.grant(&linked_path.permission)?,
+                false => scope.revoke(&linked_path.permission)?
+            };
+        }
+
+        return Ok(());
+    }
+
+    /**
+        Record on the scope defining the permission at `path` that a reference declared on this scope points at it,
+        so the permission is not renamed from below without the reference following.
+     */
+    fn mark_referenced(&mut self, path: &str) {
+        let path = PermissionPath::parse(path);
+        if path.scope.is_empty() {
+            return;
+        }
+
+        let distance = path.scope.len();
+        if let Ok(scope) = self.resolve_scope_mut(&path.scope) {
+            scope.referenced.push((distance, path.permission));
+        }
+    }
+
+    /** Mark the permissions every reference in the tree points at, once an imported tree is fully attached. */
+    pub(crate) fn mark_references(&mut self) {
+        let declared: Vec<(ScopePath, String)> = self.walk_scopes()
+            .flat_map(|scope| {
+                let path = scope.path.relative_to(&self.path);
+                scope.references.iter()
+                    .flat_map(|(first, second)| [first.clone(), second.clone()])
+                    .map(move |endpoint| (path.clone(), endpoint))
+            })
+            .collect();
+
+        for (path, endpoint) in declared {
+            if let Ok(scope) = self.resolve_scope_mut(&path) {
+                scope.mark_referenced(&endpoint);
+            }
+        }
+    }
+
+    /** Point the references of this scope at the permission at `path` to its new name. */
+    pub(crate) fn rename_in_references(&mut self, path: &ScopePath, old: &str, new: &str) {
+        let old = PermissionPath::new(path.clone(), old).to_string();
+        let new = PermissionPath::new(path.clone(), new).to_string();
+
+        for (first, second) in self.references.iter_mut() {
+            for endpoint in [first, second] {
+                if *endpoint == old {
+                    *endpoint = new.clone();
+                }
+            }
+        }
+    }
+
+    /** Resolve a permission path to the form references are stored in, following aliases. */
+    fn canonical_path(&self, path: &str) -> Result<String, ErrorKind> {
+        let requested = PermissionPath::parse(path);
+        let scope = match self.find_scope(&requested.scope) {
+            Some(scope) => scope,
+            None => return Err(ErrorKind::ScopeError(ScopeError::new(ScopeErrorCase::UnknownScope, &requested.scope.to_string())))
+        };
+        let name = scope.find_permission_name(&requested.permission)?;
+
+        return Ok(PermissionPath::new(scope.path.relative_to(&self.path), &name).to_string());
+    }
+
+    /** Collect a path and every path reachable from it through references, starting with the path itself. */
+    fn linked_paths(&self, path: &str) -> Vec<String> {
+        let mut linked: Vec<String> = vec![path.to_string()];
+        let mut index = 0;
+
+        while index < linked.len() {
+            for (first, second) in &self.references {
+                let other = match (*first == linked[index], *second == linked[index]) {
+                    (true, _) => second,
+                    (_, true) => first,
+                    _ => continue
+                };
+                if !linked.contains(other) {
+                    linked.push(other.clone());
+                }
+            }
+            index += 1;
+        }
+
+        return linked;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{assert_granted, assert_not_granted, assert_ok, assert_scope_err};
+    use crate::scope::patch::Operation;
+
+    fn get_scope() -> Scope {
+        let mut scope = Scope::new("APP");
+        if let Err(_) = scope
+            .add_permission("ADMIN")
+            .and_then(|sc| sc.add_scope("billing"))
+            .and_then(|sc| sc.add_scope("reports")) {
+            assert!(false);
+        }
+
+        for (child, permission) in [("billing", "VIEW"), ("reports", "INVOICES")] {
+            match scope.scope(child) {
+                Some(child) => {
+                    assert_ok!(child.add_permission(permission));
+                },
+                None => assert!(false)
+            }
+        }
+
+        assert_ok!(scope.add_reference("billing:VIEW", "reports:INVOICES"));
+
+        return scope;
+    }
+
+    #[test]
+    fn test_reference_grants_both() {
+        let mut scope = get_scope();
+
+        assert_ok!(scope.grant_path("reports:INVOICES"));
+        assert_granted!(scope, "billing:VIEW");
+        assert_granted!(scope, "reports:INVOICES");
+
+        assert_ok!(scope.revoke_path("billing:VIEW"));
+        assert_not_granted!(scope, "billing:VIEW");
+        assert_not_granted!(scope, "reports:INVOICES");
+
+        // unrelated permissions are granted on their own
+        assert_ok!(scope.grant_path("ADMIN"));
+        assert_not_granted!(scope, "billing:VIEW");
+    }
+
+    #[test]
+    fn test_references_are_transitive() {
+        let mut scope = get_scope();
+        assert_ok!(scope.add_reference("ADMIN", "reports:INVOICES"));
+
+        assert_eq!(scope.references("billing:VIEW"), ["reports:INVOICES", "ADMIN"]);
+        assert_eq!(scope.references("DELETE").is_empty(), true);
+
+        assert_ok!(scope.apply_patch(&[Operation::Grant { path: "billing:VIEW".into() }]));
+        assert_granted!(scope, "ADMIN");
+    }
+
+    #[test]
+    fn test_reference_to_unknown_permission() {
+        let mut scope = get_scope();
+
+        assert_scope_err!(scope.add_reference("billing:VIEW", "reports:EXPORT"), ScopeErrorCase::UnknownPermission);
+        assert_scope_err!(scope.add_reference("billing:VIEW", "audit:EXPORT"), ScopeErrorCase::UnknownScope);
+    }
+
+    #[test]
+    fn test_failed_grant_changes_nothing() {
+        let mut scope = get_scope();
+        assert_ok!(scope.grant_path("billing:VIEW"));
+
+        // granting a permission that is already held fails, and the referenced permission is left granted
+        if let Ok(_) = scope.grant_path("reports:INVOICES") {
+            assert!(false);
+        }
+        assert_granted!(scope, "billing:VIEW");
+    }
+
+    #[test]
+    fn test_renames_follow_references() {
+        let mut scope = get_scope();
+        assert_ok!(scope.add_reference("ADMIN", "reports:INVOICES"));
+
+        // the reference is declared on the root, so billing cannot rename VIEW on its own
+        match scope.scope("billing") {
+            Some(billing) => assert_scope_err!(billing.rename_permission("VIEW", "SEE", false), ScopeErrorCase::Referenced),
+            None => assert!(false)
+        }
+
+        assert_ok!(scope.rename_permission("billing:VIEW", "SEE", false).and_then(|sc| sc.rename_permission("ADMIN", "OWNER", false)));
+        assert_eq!(scope.references("billing:SEE"), ["reports:INVOICES", "OWNER"]);
+        assert_ok!(scope.grant_path("reports:INVOICES"));
+        assert_granted!(scope, "billing:SEE");
+        assert_granted!(scope, "OWNER");
+
+        // the restriction survives an export
+        let mut imported = Scope::from_json(scope.as_json());
+        match imported.scope("reports") {
+            Some(reports) => assert_scope_err!(reports.rename_permission("INVOICES", "BILLS", false), ScopeErrorCase::Referenced),
+            None => assert!(false)
+        }
+    }
+
+    #[test]
+    fn test_references_round_trip_through_json() {
+        let mut imported = Scope::from_json(get_scope().as_json());
+
+        assert_ok!(imported.grant_path("billing:VIEW"));
+        assert_granted!(imported, "reports:INVOICES");
+    }
+}
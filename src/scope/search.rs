@@ -0,0 +1,99 @@
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use regex::Regex;
+use crate::common::error::ErrorKind;
+use crate::scope::error::{ScopeError, ScopeErrorCase};
+use crate::scope::path::PermissionPath;
+use crate::scope::Scope;
+
+impl Scope {
+    /**
+        List the path of every permission defined on this scope or its child scopes that matches a regular
+        expression, e.g. `DELETE|PURGE`. Paths are relative to this scope in the form accepted by `check`, listed
+        depth-first with child scopes in name order and permissions in bit order. The pattern is matched anywhere
+        in the path unless anchored. Fails with InvalidPattern if the pattern does not compile.
+     */
+    pub fn grep(&self, pattern: &str) -> Result<Vec<String>, ErrorKind> {
+        let regex = match Regex::new(pattern) {
+            Ok(regex) => regex,
+            Err(_) => return Err(ErrorKind::ScopeError(ScopeError::new(ScopeErrorCase::InvalidPattern, pattern)))
+        };
+
+        let mut matched: Vec<String> = Vec::new();
+        for scope in self.scopes_in_encoding_order() {
+            let relative = scope.path.relative_to(&self.path);
+            let mut permissions: Vec<&str> = scope.permissions.keys().map(|name| name.as_str()).collect();
+            permissions.sort_by_key(|name| scope.permissions[*name].value);
+
+            for name in permissions {
+                let path = PermissionPath::new(relative.clone(), name).to_string();
+                if regex.is_match(&path) {
+                    matched.push(path);
+                }
+            }
+        }
+
+        return Ok(matched);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get_scope() -> Scope {
+        let mut scope = Scope::new("APP");
+        if let Err(_) = scope
+            .add_permission("READ")
+            .and_then(|sc| sc.add_permission("DELETE"))
+            .and_then(|sc| sc.add_scope("billing"))
+            .and_then(|sc| sc.add_scope("archive")) {
+            assert!(false);
+        }
+
+        if let Some(billing) = scope.scope("billing") {
+            if let Err(_) = billing.add_permission("VIEW").and_then(|sc| sc.add_permission("PURGE")) {
+                assert!(false);
+            }
+        }
+        if let Some(archive) = scope.scope("archive") {
+            if let Err(_) = archive.add_permission("DELETE_FOREVER") {
+                assert!(false);
+            }
+        }
+
+        return scope;
+    }
+
+    #[test]
+    fn test_grep() {
+        let scope = get_scope();
+
+        match scope.grep("DELETE|PURGE") {
+            Ok(paths) => assert_eq!(paths, ["DELETE", "archive:DELETE_FOREVER", "billing:PURGE"]),
+            Err(_) => assert!(false)
+        }
+        match scope.grep("^billing:") {
+            Ok(paths) => assert_eq!(paths, ["billing:VIEW", "billing:PURGE"]),
+            Err(_) => assert!(false)
+        }
+        match scope.grep("WRITE") {
+            Ok(paths) => assert_eq!(paths.is_empty(), true),
+            Err(_) => assert!(false)
+        }
+    }
+
+    #[test]
+    fn test_grep_paths_are_relative() {
+        let mut scope = get_scope();
+
+        match scope.scope("billing").map(|billing| billing.grep("PURGE")) {
+            Some(Ok(paths)) => assert_eq!(paths, ["PURGE"]),
+            _ => assert!(false)
+        }
+        match scope.grep("(") {
+            Err(ErrorKind::ScopeError(err)) => assert_eq!(err.case(), ScopeErrorCase::InvalidPattern),
+            _ => assert!(false)
+        }
+    }
+}
@@ -0,0 +1,129 @@
+use alloc::string::String;
+use alloc::vec::Vec;
+use serde::de::Error as _;
+use serde::ser::SerializeStruct;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use crate::scope::conversion::{PermissionEntry, ScopeMetadata, ScopeTuple};
+use crate::scope::order::SortOrder;
+use crate::scope::{Scope, DEFAULT_MAX_DEPTH};
+
+/** A permission as written by the serde implementation of `Scope`. */
+#[derive(Serialize, Deserialize)]
+struct PermissionObject {
+    name: String,
+    shift: u8
+}
+
+/** A scope as read by the serde implementation of `Scope`. */
+#[derive(Deserialize)]
+struct ScopeObject {
+    name: String,
+    #[serde(default)]
+    value: u64,
+    #[serde(default)]
+    permissions: Vec<PermissionObject>,
+    #[serde(default)]
+    scopes: Vec<ScopeObject>,
+    #[serde(default)]
+    metadata: ScopeMetadata
+}
+
+impl From<ScopeObject> for ScopeTuple {
+    fn from(object: ScopeObject) -> ScopeTuple {
+        return ScopeTuple(
+            object.name,
+            object.value,
+            object.permissions.into_iter().map(|permission| PermissionEntry::Placed(permission.name, permission.shift)).collect(),
+            object.scopes.into_iter().map(ScopeTuple::from).collect(),
+            object.metadata
+        );
+    }
+}
+
+/**
+    Scopes serialize as objects with named fields, `{ "name", "value", "permissions", "scopes", "metadata" }`, every
+    permission as `{ "name", "shift" }`, and `metadata` only when the scope declares rules. It is the readable
+    counterpart of the tuple form written by `as_json`, which stays the compact choice for storage and the wire.
+ */
+impl Serialize for Scope {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let metadata = self.as_metadata();
+        let permissions: Vec<PermissionObject> = self.permissions_in_order(SortOrder::Shift).into_iter()
+            .map(|permission| PermissionObject { name: permission.name.clone(), shift: permission.value.trailing_zeros() as u8 })
+            .collect();
+        let scopes = self.scopes_in_order(SortOrder::Insertion);
+
+        let mut object = serializer.serialize_struct("Scope", 5)?;
+        object.serialize_field("name", &self.name)?;
+        object.serialize_field("value", &self.as_u64())?;
+        object.serialize_field("permissions", &permissions)?;
+        object.serialize_field("scopes", &scopes)?;
+        match metadata.is_empty() {
+            true => object.skip_field("metadata")?,
+            false => object.serialize_field("metadata", &metadata)?
+        }
+
+        return object.end();
+    }
+}
+
+/** Scopes deserialize from the object form written by their `Serialize` implementation, up to `DEFAULT_MAX_DEPTH` deep. */
+impl<'de> Deserialize<'de> for Scope {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Scope, D::Error> {
+        let object = ScopeObject::deserialize(deserializer)?;
+
+        return Scope::from_tuple(ScopeTuple::from(object), DEFAULT_MAX_DEPTH).map_err(D::Error::custom);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::{from_value, json, to_value};
+    use crate::{assert_granted, assert_ok, assert_schema_eq};
+
+    fn get_scope() -> Scope {
+        let mut scope = Scope::new("APP");
+        assert_ok!(scope.add_permission("READ").and_then(|sc| sc.add_permission("WRITE")).and_then(|sc| sc.add_scope("billing")));
+        assert_ok!(scope.add_implication("WRITE", "READ").and_then(|sc| sc.grant("WRITE")));
+        if let Some(billing) = scope.scope("billing") {
+            assert_ok!(billing.add_permission("PAY"));
+        }
+
+        return scope;
+    }
+
+    #[test]
+    fn test_serialize_as_object() {
+        let value = match to_value(get_scope()) {
+            Ok(value) => value,
+            Err(err) => panic!("serialization failed: {}", err)
+        };
+
+        assert_eq!(value["name"], json!("APP"));
+        assert_eq!(value["value"], json!(2));
+        assert_eq!(value["permissions"], json!([{ "name": "READ", "shift": 0 }, { "name": "WRITE", "shift": 1 }]));
+        assert_eq!(value["metadata"]["implications"], json!([["WRITE", "READ"]]));
+        assert_eq!(value["scopes"][0]["name"], json!("billing"));
+        assert_eq!(value["scopes"][0]["permissions"], json!([{ "name": "PAY", "shift": 0 }]));
+        assert_eq!(value["scopes"][0]["scopes"], json!([]));
+    }
+
+    #[test]
+    fn test_deserialize_round_trip() {
+        let scope = get_scope();
+        let imported: Scope = match to_value(&scope).and_then(from_value) {
+            Ok(imported) => imported,
+            Err(err) => panic!("round trip failed: {}", err)
+        };
+
+        assert_schema_eq!(imported, scope);
+        assert_granted!(imported, "READ");
+        assert_eq!(imported.implications("WRITE"), ["READ"]);
+
+        let minimal: Result<Scope, _> = from_value(json!({ "name": "APP", "value": 1, "permissions": [{ "name": "READ", "shift": 0 }] }));
+        assert_eq!(minimal.map(|scope| scope.check("READ")).unwrap_or(false), true);
+        let clashing: Result<Scope, _> = from_value(json!({ "name": "APP", "permissions": [{ "name": "READ", "shift": 0 }, { "name": "WRITE", "shift": 0 }] }));
+        assert_eq!(clashing.is_err(), true);
+    }
+}
@@ -0,0 +1,140 @@
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+use crate::analysis::escalation::{diff_grants, EscalationReport};
+use crate::common::error::ErrorKind;
+use crate::scope::order::SortOrder;
+use crate::scope::patch::Operation;
+use crate::scope::path::PermissionPath;
+use crate::scope::Scope;
+
+/** SimulationFailure records an operation that could not be applied during a simulation. */
+pub struct SimulationFailure {
+    /** The position of the operation in the simulated list. */
+    pub index: usize,
+    pub operation: Operation,
+    pub error: ErrorKind
+}
+
+/** SimulationResult describes what applying a list of operations would do, without having done it. */
+pub struct SimulationResult {
+    /** The tree as it would be after every operation that succeeded. */
+    pub scope: Scope,
+    /** Full paths of the scopes and permissions that would be added, in encoding order. */
+    pub added: Vec<String>,
+    /** Permissions whose effective state would change, classified as by `diff_grants`. */
+    pub changes: EscalationReport,
+    /** Operations that would fail, in order. The changes above leave them out. */
+    pub failures: Vec<SimulationFailure>
+}
+
+impl SimulationResult {
+    /** Check whether `apply_patch` with the same operations would succeed. */
+    pub fn succeeds(&self) -> bool {
+        return self.failures.is_empty();
+    }
+}
+
+impl Scope {
+    /**
+        Preview a list of operations: apply them to a copy of this tree and report what would be added, which grants
+        would change and which operations would fail, leaving this tree untouched. Unlike `apply_patch`, a failing
+        operation does not stop the simulation, so every failure is reported at once.
+     */
+    pub fn simulate(&self, operations: &[Operation]) -> SimulationResult {
+        let mut working = self.clone();
+        let mut failures: Vec<SimulationFailure> = vec![];
+
+        for (index, operation) in operations.iter().enumerate() {
+            if let Err(error) = working.apply_operation(operation) {
+                failures.push(SimulationFailure { index, operation: operation.clone(), error });
+            }
+        }
+
+        return SimulationResult {
+            added: self.added_paths(&working),
+            changes: diff_grants(self, &working),
+            scope: working,
+            failures
+        };
+    }
+
+    /** List the full paths of the scopes and permissions of `other` that this tree does not have. */
+    fn added_paths(&self, other: &Scope) -> Vec<String> {
+        let mut added: Vec<String> = vec![];
+
+        for scope in other.scopes_in_encoding_order() {
+            let existing = self.find_scope(&scope.path.relative_to(&other.path));
+            if existing.is_none() {
+                added.push(scope.path.to_string());
+            }
+
+            for permission in scope.permissions_in_order(SortOrder::Shift) {
+                if existing.is_none_or(|existing| !existing.permissions.contains_key(&permission.name)) {
+                    added.push(PermissionPath::new(scope.path.clone(), &permission.name).to_string());
+                }
+            }
+        }
+
+        return added;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analysis::escalation::ChangeKind;
+    use crate::scope::error::ScopeErrorCase;
+    use crate::{assert_not_granted, assert_ok};
+
+    fn get_scope() -> Scope {
+        let mut scope = Scope::new("APP");
+        assert_ok!(scope.add_permission("READ").and_then(|sc| sc.add_permission("WRITE")).and_then(|sc| sc.grant("WRITE")));
+
+        return scope;
+    }
+
+    #[test]
+    fn test_simulate_reports_changes() {
+        let scope = get_scope();
+        let revision = scope.revision();
+
+        let result = scope.simulate(&[
+            Operation::AddScope { path: "billing".to_string() },
+            Operation::AddPermission { path: "billing:PAY".to_string() },
+            Operation::Grant { path: "billing:PAY".to_string() },
+            Operation::Revoke { path: "WRITE".to_string() }
+        ]);
+
+        assert_eq!(result.succeeds(), true);
+        assert_eq!(result.added, ["APP.billing", "APP.billing:PAY"]);
+        let changes: Vec<(&str, ChangeKind)> = result.changes.changes.iter().map(|change| (change.path.as_str(), change.kind)).collect();
+        assert_eq!(changes, [("APP.billing:PAY", ChangeKind::Granted), ("APP:WRITE", ChangeKind::Revoked)]);
+
+        // the real tree is untouched
+        assert_not_granted!(scope, "billing:PAY");
+        assert_eq!(scope.check("WRITE"), true);
+        assert_eq!(scope.revision(), revision);
+    }
+
+    #[test]
+    fn test_simulate_reports_every_failure() {
+        let scope = get_scope();
+
+        let result = scope.simulate(&[
+            Operation::Grant { path: "WRITE".to_string() },
+            Operation::Grant { path: "READ".to_string() },
+            Operation::Grant { path: "payroll:RUN".to_string() }
+        ]);
+
+        assert_eq!(result.succeeds(), false);
+        assert_eq!(result.failures.iter().map(|failure| failure.index).collect::<Vec<usize>>(), [0, 2]);
+        match &result.failures[1].error {
+            ErrorKind::ScopeError(err) => assert_eq!(err.case(), ScopeErrorCase::UnknownScope),
+            _ => assert!(false)
+        }
+        // operations that would succeed are still previewed
+        assert_eq!(result.changes.changes.iter().any(|change| change.path == "APP:READ" && change.kind == ChangeKind::Granted), true);
+        assert_eq!(result.scope.check("READ"), true);
+    }
+}
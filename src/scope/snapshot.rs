@@ -0,0 +1,84 @@
+use alloc::sync::Arc;
+use core::ops::Deref;
+use crate::scope::view::ScopeRef;
+use crate::scope::Scope;
+
+/**
+    ScopeSnapshot is an immutable copy of a scope tree taken at one moment. Clones share the same tree, so it is
+    cheap to hand to every request, and it is `Send + Sync`, so it can be kept in the shared state of a web server.
+    It offers every check and export of `Scope` through `Deref`; changes are made to the live scope, from which a
+    new snapshot is taken.
+ */
+#[derive(Clone, Debug)]
+pub struct ScopeSnapshot {
+    scope: Arc<Scope>
+}
+
+impl ScopeSnapshot {
+    /** Get a read-only view of the snapshot, for navigating to child scopes and permissions. */
+    pub fn view(&self) -> ScopeRef<'_> {
+        return ScopeRef::new(&self.scope);
+    }
+
+    /** Check whether two snapshots share the same tree, i.e. one was cloned from the other. */
+    pub fn ptr_eq(&self, other: &ScopeSnapshot) -> bool {
+        return Arc::ptr_eq(&self.scope, &other.scope);
+    }
+
+    /** Get a live, mutable copy of the snapshot, e.g. to prepare the next version of the tree. */
+    pub fn to_scope(&self) -> Scope {
+        return self.scope.as_ref().clone();
+    }
+}
+
+impl Deref for ScopeSnapshot {
+    type Target = Scope;
+
+    fn deref(&self) -> &Scope {
+        return &self.scope;
+    }
+}
+
+impl From<Scope> for ScopeSnapshot {
+    fn from(scope: Scope) -> ScopeSnapshot {
+        return ScopeSnapshot { scope: Arc::new(scope) };
+    }
+}
+
+impl Scope {
+    /**
+        Take an immutable snapshot of this scope and its child scopes. The whole tree is copied while it is borrowed,
+        so the snapshot never mixes states from before and after a change.
+     */
+    pub fn snapshot(&self) -> ScopeSnapshot {
+        return ScopeSnapshot::from(self.clone());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{assert_granted, assert_not_granted, assert_ok};
+
+    fn assert_send_sync<T: Send + Sync>() {}
+
+    #[test]
+    fn test_snapshot_is_frozen() {
+        assert_send_sync::<ScopeSnapshot>();
+
+        let mut scope = Scope::new("APP");
+        assert_ok!(scope.add_permission("READ").and_then(|sc| sc.add_scope("billing")));
+        let snapshot = scope.snapshot();
+        assert_ok!(scope.grant("READ"));
+
+        let shared = snapshot.clone();
+        assert_eq!(shared.ptr_eq(&snapshot), true);
+        assert_not_granted!(shared, "READ");
+        assert_eq!(shared.view().scope("billing").map(|billing| billing.name().len()), Some(7));
+
+        let next = scope.snapshot();
+        assert_granted!(next, "READ");
+        assert_eq!(next.ptr_eq(&snapshot), false);
+        assert_eq!(next.to_scope().as_u64(), 1);
+    }
+}
@@ -0,0 +1,146 @@
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use serde::{Deserialize, Serialize};
+use crate::scope::order::SortOrder;
+use crate::scope::path::PermissionPath;
+use crate::scope::Scope;
+
+/**
+    PermissionRow maps one permission to an integer ID, for systems that key permissions by number. The field names
+    are part of the CSV and JSON exports and must not change.
+ */
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PermissionRow {
+    pub id: u64,
+    /** Full path of the permission, including the root scope, e.g. `APP.billing:READ`. */
+    pub path: String,
+    pub shift: u8,
+    pub value: u64
+}
+
+/** IdTable lists every permission of a tree with its integer ID, in the order of `encode_values`. */
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IdTable {
+    pub rows: Vec<PermissionRow>
+}
+
+impl IdTable {
+    /** Get the row for a permission by its full path. */
+    pub fn row(&self, path: &str) -> Option<&PermissionRow> {
+        return self.rows.iter().find(|row| row.path == path);
+    }
+
+    /** Render the table as CSV with an `id,path,shift,value` header. */
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from("id,path,shift,value\n");
+        for row in &self.rows {
+            csv.push_str(&format!("{},{},{},{}\n", row.id, escape_csv(&row.path), row.shift, row.value));
+        }
+
+        return csv;
+    }
+}
+
+fn escape_csv(field: &str) -> String {
+    if !field.contains([',', '"', '\n', '\r']) {
+        return field.to_string();
+    }
+
+    return format!("\"{}\"", field.replace('"', "\"\""));
+}
+
+impl Scope {
+    /** Number every permission of this scope and its child scopes from 1, in the order of `encode_values`. */
+    pub fn id_table(&self) -> IdTable {
+        return self.id_table_from(&IdTable::default());
+    }
+
+    /**
+        Number every permission of this scope and its child scopes, keeping the ID of every path already in
+        `previous`. New permissions are numbered after the highest ID in `previous`, so IDs of removed or renamed
+        permissions are never reused.
+     */
+    pub fn id_table_from(&self, previous: &IdTable) -> IdTable {
+        let mut next_id = previous.rows.iter().map(|row| row.id).max().unwrap_or(0) + 1;
+        let mut rows: Vec<PermissionRow> = Vec::new();
+
+        for scope in self.scopes_in_encoding_order() {
+            for permission in scope.permissions_in_order(SortOrder::Shift) {
+                let path = PermissionPath::new(scope.path.clone(), &permission.name).to_string();
+                let id = match previous.row(&path) {
+                    Some(row) => row.id,
+                    None => {
+                        let id = next_id;
+                        next_id += 1;
+                        id
+                    }
+                };
+
+                rows.push(PermissionRow {
+                    id,
+                    path,
+                    shift: permission.value.trailing_zeros() as u8,
+                    value: permission.value
+                });
+            }
+        }
+
+        return IdTable { rows };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get_scope() -> Scope {
+        let mut scope = Scope::new("APP");
+        if let Err(_) = scope
+            .add_permission("READ")
+            .and_then(|sc| sc.add_permission("WRITE"))
+            .and_then(|sc| sc.add_scope("billing")) {
+            assert!(false);
+        }
+
+        if let Some(billing) = scope.scope("billing") {
+            if let Err(_) = billing.add_permission("REFUND") {
+                assert!(false);
+            }
+        }
+
+        return scope;
+    }
+
+    #[test]
+    fn test_id_table() {
+        let table = get_scope().id_table();
+
+        assert_eq!(table.to_csv(), "id,path,shift,value\n1,APP:READ,0,1\n2,APP:WRITE,1,2\n3,APP.billing:REFUND,0,1\n");
+    }
+
+    #[test]
+    fn test_id_table_keeps_previous_ids() {
+        let mut scope = get_scope();
+        let first = scope.id_table();
+
+        if let Err(_) = scope.rename_permission("WRITE", "EDIT", false).and_then(|sc| sc.add_permission("DELETE")) {
+            assert!(false);
+        }
+        let second = scope.id_table_from(&first);
+
+        assert_eq!(second.row("APP:READ").map(|row| row.id), Some(1));
+        assert_eq!(second.row("APP.billing:REFUND").map(|row| row.id), Some(3));
+        // new paths are numbered after every ID already handed out
+        assert_eq!(second.row("APP:EDIT").map(|row| row.id), Some(4));
+        assert_eq!(second.row("APP:DELETE").map(|row| row.id), Some(5));
+        assert_eq!(second.row("APP:WRITE"), None);
+    }
+
+    #[test]
+    fn test_csv_escaping() {
+        assert_eq!(escape_csv("APP:READ"), "APP:READ");
+        assert_eq!(escape_csv("a,b"), "\"a,b\"");
+        assert_eq!(escape_csv("say \"hi\""), "\"say \"\"hi\"\"\"");
+    }
+}
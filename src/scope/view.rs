@@ -0,0 +1,96 @@
+use alloc::vec::Vec;
+use core::ops::Deref;
+use crate::permission::Permission;
+use crate::scope::order::SortOrder;
+use crate::scope::Scope;
+
+/**
+    ScopeRef is a read-only view of a scope. It offers every check and export of `Scope` through `Deref`, and
+    navigates to permissions and child scopes without the `&mut` that `Scope::permission` and `Scope::scope` need,
+    so a tree shared behind an `Arc` can be inspected freely.
+ */
+#[derive(Clone, Copy, Debug)]
+pub struct ScopeRef<'a> {
+    scope: &'a Scope
+}
+
+impl<'a> ScopeRef<'a> {
+    pub fn new(scope: &'a Scope) -> ScopeRef<'a> {
+        return ScopeRef { scope };
+    }
+
+    /** Get a permission of this scope by name, following aliases. */
+    pub fn permission(&self, name: &str) -> Option<&'a Permission> {
+        return self.scope.permission_ref(name);
+    }
+
+    /** Get a direct child scope by name. */
+    pub fn scope(&self, name: &str) -> Option<ScopeRef<'a>> {
+        return self.scope.scope_ref(name).map(ScopeRef::new);
+    }
+
+    /** List the permissions of this scope in the order they were added. */
+    pub fn permissions(&self) -> Vec<&'a Permission> {
+        return self.scope.permissions_in_order(SortOrder::Insertion);
+    }
+
+    /** List the direct child scopes of this scope in the order they were added. */
+    pub fn scopes(&self) -> Vec<ScopeRef<'a>> {
+        return self.scope.scopes_in_order(SortOrder::Insertion).into_iter().map(ScopeRef::new).collect();
+    }
+
+    /** Get the scope this view reads from. */
+    pub fn get(&self) -> &'a Scope {
+        return self.scope;
+    }
+}
+
+impl Deref for ScopeRef<'_> {
+    type Target = Scope;
+
+    fn deref(&self) -> &Scope {
+        return self.scope;
+    }
+}
+
+impl<'a> From<&'a Scope> for ScopeRef<'a> {
+    fn from(scope: &'a Scope) -> ScopeRef<'a> {
+        return ScopeRef::new(scope);
+    }
+}
+
+impl Scope {
+    /** Get a read-only view of this scope. */
+    pub fn view(&self) -> ScopeRef<'_> {
+        return ScopeRef::new(self);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::string::String;
+    use alloc::sync::Arc;
+    use crate::assert_ok;
+
+    #[test]
+    fn test_view_of_shared_scope() {
+        let mut scope = Scope::new("APP");
+        assert_ok!(scope.add_permission("READ").and_then(|sc| sc.add_scope("billing")).and_then(|sc| sc.grant("READ")));
+        if let Some(billing) = scope.scope("billing") {
+            assert_ok!(billing.add_permission("PAY").and_then(|sc| sc.add_permission("REFUND")).and_then(|sc| sc.grant("REFUND")));
+        }
+
+        let shared = Arc::new(scope);
+        let view = shared.view();
+        assert_eq!(view.check("billing:REFUND"), true);
+        assert_eq!(view.permission("READ").map(|permission| permission.has()), Some(true));
+        assert_eq!(view.permission("WRITE").is_none(), true);
+
+        let billing = view.scope("billing").unwrap_or_else(|| panic!("billing is missing"));
+        assert_eq!(billing.as_u64(), 0b10);
+        assert_eq!(billing.permissions().iter().map(|permission| permission.name.clone()).collect::<Vec<String>>(), ["PAY", "REFUND"]);
+        assert_eq!(view.scopes().len(), 1);
+        assert_eq!(view.scope("payroll").is_none(), true);
+    }
+}
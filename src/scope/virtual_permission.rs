@@ -0,0 +1,121 @@
+use alloc::sync::Arc;
+use crate::common::error::ErrorKind;
+use crate::scope::error::{ScopeError, ScopeErrorCase};
+use crate::scope::Scope;
+
+/** VirtualRule decides whether a virtual permission is held, given the scope it is declared on. */
+pub type VirtualRule = Arc<dyn Fn(&Scope) -> bool + Send + Sync>;
+
+impl Scope {
+    /**
+        Declare a virtual permission whose state is computed by `rule` from the other grants of this scope, e.g.
+        `ADMIN` held whenever every other permission is. Virtual permissions have no bit: they are answered by
+        `check`, `has` and `explain`, but never granted, stored in values or exported. The rule must not check
+        the virtual permission itself.
+     */
+    pub fn add_virtual<F>(&mut self, name: &str, rule: F) -> Result<&mut Scope, ErrorKind>
+        where F: Fn(&Scope) -> bool + Send + Sync + 'static {
+        let name = self.policy.normalize(name).into_owned();
+        self.validate_name(&name)?;
+
+        self.virtuals.insert(name, Arc::new(rule));
+        self.touch();
+
+        return Ok(self);
+    }
+
+    /** Remove a virtual permission, returning whether it existed. */
+    pub fn remove_virtual(&mut self, name: &str) -> bool {
+        let removed = self.virtuals.remove(self.policy.normalize(name).as_ref()).is_some();
+        if removed {
+            self.touch();
+        }
+
+        return removed;
+    }
+
+    /** Check whether a name is a virtual permission of this scope. */
+    pub fn is_virtual(&self, name: &str) -> bool {
+        return self.virtuals.contains_key(self.policy.normalize(name).as_ref());
+    }
+
+    /** Get the mask of every bit assigned to a permission of this scope. */
+    pub fn defined_bits(&self) -> u64 {
+        return self.permissions.values().fold(0u64, |mask, permission| mask | permission.value);
+    }
+
+    /** Evaluate a virtual permission of this scope, or None if `name` is not virtual. */
+    pub(crate) fn evaluate_virtual(&self, name: &str) -> Option<bool> {
+        return self.virtuals.get(name).map(|rule| rule(self));
+    }
+
+    /** Fail if a grant or revocation addresses a virtual permission, whose state cannot be set. */
+    pub(crate) fn reject_virtual(&self, name: &str) -> Result<(), ErrorKind> {
+        let name = self.policy.normalize(name);
+
+        return match self.virtuals.contains_key(name.as_ref()) {
+            true => Err(ErrorKind::ScopeError(ScopeError::new(ScopeErrorCase::VirtualPermission, &name))),
+            false => Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{assert_granted, assert_not_granted, assert_ok, assert_scope_err};
+    use crate::scope::explain::Decision;
+
+    fn get_scope() -> Scope {
+        let mut scope = Scope::new("APP");
+        if let Err(_) = scope
+            .add_permission("READ")
+            .and_then(|sc| sc.add_permission("WRITE"))
+            .and_then(|sc| sc.add_virtual("ADMIN", |scope| scope.as_u64() == scope.defined_bits())) {
+            assert!(false);
+        }
+
+        return scope;
+    }
+
+    #[test]
+    fn test_virtual_permission_is_computed() {
+        let mut scope = get_scope();
+        assert_eq!(scope.is_virtual("ADMIN"), true);
+        assert_not_granted!(scope, "ADMIN");
+
+        assert_ok!(scope.grant("READ").and_then(|sc| sc.grant("WRITE")));
+        assert_granted!(scope, "ADMIN");
+        assert_eq!(scope.has("ADMIN"), true);
+
+        let explanation = scope.explain("ADMIN");
+        assert_eq!(explanation.allowed, true);
+        assert_eq!(explanation.decision, Decision::Computed);
+        assert_eq!(explanation.bit, None);
+    }
+
+    #[test]
+    fn test_virtual_permission_is_not_exported() {
+        let mut scope = get_scope();
+        assert_ok!(scope.grant("READ").and_then(|sc| sc.grant("WRITE")));
+
+        assert_eq!(scope.as_u64(), 0b11);
+        let imported = Scope::from_json(scope.as_json());
+        assert_eq!(imported.is_virtual("ADMIN"), false);
+        assert_not_granted!(imported, "ADMIN");
+        // copies keep the rule
+        assert_eq!(scope.clone().check("ADMIN"), true);
+    }
+
+    #[test]
+    fn test_virtual_permission_cannot_be_granted() {
+        let mut scope = get_scope();
+
+        assert_scope_err!(scope.grant("ADMIN"), ScopeErrorCase::VirtualPermission);
+        assert_scope_err!(scope.add_permission("ADMIN"), ScopeErrorCase::PermissionExists);
+
+        assert_eq!(scope.remove_virtual("ADMIN"), true);
+        assert_not_granted!(scope, "ADMIN");
+        assert_ok!(scope.add_permission("ADMIN"));
+    }
+}
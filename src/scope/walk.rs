@@ -0,0 +1,124 @@
+use alloc::vec;
+use alloc::vec::Vec;
+use crate::permission::Permission;
+use crate::scope::order::SortOrder;
+use crate::scope::path::ScopePath;
+use crate::scope::Scope;
+
+/**
+    WalkScopes visits a scope and every scope below it depth-first, each scope before its children and children in
+    the order they were added. It keeps its own stack, so deeply nested trees cannot overflow the call stack.
+ */
+pub struct WalkScopes<'a> {
+    stack: Vec<&'a Scope>
+}
+
+impl<'a> Iterator for WalkScopes<'a> {
+    type Item = &'a Scope;
+
+    fn next(&mut self) -> Option<&'a Scope> {
+        let scope = self.stack.pop()?;
+        self.stack.extend(scope.scopes_in_order(SortOrder::Insertion).into_iter().rev());
+
+        return Some(scope);
+    }
+}
+
+/**
+    Walk visits every permission of a scope tree together with the full path of the scope defining it, scope by
+    scope in the order of `WalkScopes` and permissions in the order they were added.
+ */
+pub struct Walk<'a> {
+    scopes: WalkScopes<'a>,
+    current: Option<(&'a ScopePath, vec::IntoIter<&'a Permission>)>
+}
+
+impl<'a> Iterator for Walk<'a> {
+    type Item = (ScopePath, &'a Permission);
+
+    fn next(&mut self) -> Option<(ScopePath, &'a Permission)> {
+        loop {
+            if let Some((path, permissions)) = &mut self.current {
+                if let Some(permission) = permissions.next() {
+                    return Some(((*path).clone(), permission));
+                }
+            }
+
+            let scope = self.scopes.next()?;
+            self.current = Some((&scope.path, scope.permissions_in_order(SortOrder::Insertion).into_iter()));
+        }
+    }
+}
+
+impl Scope {
+    /**
+        Iterate over every permission of this scope and all of its child scopes, at any depth, paired with the full
+        path of the scope it is defined on. Filter on `Permission::has` to audit every direct grant in the tree.
+     */
+    pub fn walk(&self) -> Walk<'_> {
+        return Walk { scopes: self.walk_scopes(), current: None };
+    }
+
+    /** Iterate over this scope and all of its child scopes at any depth, each scope before its children. */
+    pub fn walk_scopes(&self) -> WalkScopes<'_> {
+        return WalkScopes { stack: vec![self] };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::format;
+    use alloc::string::{String, ToString};
+    use crate::assert_ok;
+    use crate::scope::DEFAULT_MAX_DEPTH;
+
+    fn get_scope() -> Scope {
+        let mut scope = Scope::new("APP");
+        assert_ok!(scope.add_permission("READ").and_then(|sc| sc.add_scope("billing")).and_then(|sc| sc.add_scope("admin")));
+        if let Some(billing) = scope.scope("billing") {
+            assert_ok!(billing.add_permission("PAY").and_then(|sc| sc.add_permission("REFUND")).and_then(|sc| sc.grant("REFUND")));
+            assert_ok!(billing.add_scope("invoices"));
+            if let Some(invoices) = billing.scope("invoices") {
+                assert_ok!(invoices.add_permission("EXPORT").and_then(|sc| sc.grant("EXPORT")));
+            }
+        }
+
+        return scope;
+    }
+
+    #[test]
+    fn test_walk_tree() {
+        let scope = get_scope();
+
+        let scopes: Vec<String> = scope.walk_scopes().map(|scope| scope.path().to_string()).collect();
+        assert_eq!(scopes, ["APP", "APP.billing", "APP.billing.invoices", "APP.admin"]);
+
+        let permissions: Vec<String> = scope.walk().map(|(path, permission)| format!("{}:{}", path, permission.name)).collect();
+        assert_eq!(permissions, ["APP:READ", "APP.billing:PAY", "APP.billing:REFUND", "APP.billing.invoices:EXPORT"]);
+
+        let granted: Vec<String> = scope.walk()
+            .filter(|(_, permission)| permission.has())
+            .map(|(path, permission)| format!("{}:{}", path, permission.name))
+            .collect();
+        assert_eq!(granted, ["APP.billing:REFUND", "APP.billing.invoices:EXPORT"]);
+    }
+
+    #[test]
+    fn test_walk_deep_tree() {
+        let mut scope = Scope::new("ROOT");
+        let mut current = &mut scope;
+        for depth in 0..(DEFAULT_MAX_DEPTH * 4) {
+            let name = format!("level{}", depth);
+            assert_ok!(current.add_scope(&name));
+            current = match current.scope(&name) {
+                Some(child) => child,
+                None => panic!("child scope is missing")
+            };
+        }
+        assert_ok!(current.add_permission("DEEP"));
+
+        assert_eq!(scope.walk_scopes().count(), DEFAULT_MAX_DEPTH * 4 + 1);
+        assert_eq!(scope.walk().map(|(path, _)| path.len()).collect::<Vec<usize>>(), [DEFAULT_MAX_DEPTH * 4 + 1]);
+    }
+}
@@ -0,0 +1,129 @@
+use alloc::string::{String, ToString};
+use std::future::Future;
+use std::sync::{Arc, Mutex, MutexGuard};
+use tokio::task::{spawn_blocking, JoinError};
+use crate::scope::Scope;
+use crate::store::ScopeStore;
+
+/**
+    AsyncScopeStore loads and saves scopes without blocking the async runtime, for services built on axum, tonic
+    and the like. Implementations share state between requests, so saving only takes `&self`.
+ */
+pub trait AsyncScopeStore {
+    type Error;
+
+    /** Load the scope saved under `key`, or None if nothing was saved under it. */
+    fn load(&self, key: &str) -> impl Future<Output = Result<Option<Scope>, Self::Error>> + Send;
+
+    /** Save `scope` under `key`, replacing whatever was saved under it before. */
+    fn save(&self, key: &str, scope: &Scope) -> impl Future<Output = Result<(), Self::Error>> + Send;
+
+    /** Remove the scope saved under `key`, returning whether there was one. */
+    fn remove(&self, key: &str) -> impl Future<Output = Result<bool, Self::Error>> + Send;
+}
+
+/** BlockingStoreError is returned by `BlockingStore` when the store fails or its blocking task does not finish. */
+#[derive(Debug)]
+pub enum BlockingStoreError<E> {
+    Store(E),
+    Task(JoinError)
+}
+
+/**
+    BlockingStore adapts a blocking `ScopeStore` to `AsyncScopeStore` by running every call on tokio's blocking
+    thread pool, one call at a time. Clones share the same store.
+ */
+pub struct BlockingStore<S> {
+    store: Arc<Mutex<S>>
+}
+
+impl<S> BlockingStore<S> {
+    pub fn new(store: S) -> BlockingStore<S> {
+        return BlockingStore {
+            store: Arc::new(Mutex::new(store))
+        };
+    }
+
+    /** Lock the wrapped store for direct, blocking access. */
+    pub fn lock(&self) -> MutexGuard<'_, S> {
+        return lock(&self.store);
+    }
+}
+
+impl<S> Clone for BlockingStore<S> {
+    fn clone(&self) -> BlockingStore<S> {
+        return BlockingStore {
+            store: self.store.clone()
+        };
+    }
+}
+
+// a panic in one call must not make the store unusable for every later request
+fn lock<S>(store: &Mutex<S>) -> MutexGuard<'_, S> {
+    return store.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+}
+
+/** Run `call` with the store on the blocking thread pool. */
+async fn run_blocking<S, T, F>(store: &Arc<Mutex<S>>, call: F) -> Result<T, BlockingStoreError<S::Error>>
+    where S: ScopeStore + Send + 'static, S::Error: Send + 'static, T: Send + 'static,
+          F: FnOnce(&mut S) -> Result<T, S::Error> + Send + 'static {
+    let store = store.clone();
+
+    return match spawn_blocking(move || call(&mut lock(&store))).await {
+        Ok(result) => result.map_err(BlockingStoreError::Store),
+        Err(err) => Err(BlockingStoreError::Task(err))
+    }
+}
+
+impl<S> AsyncScopeStore for BlockingStore<S> where S: ScopeStore + Send + 'static, S::Error: Send + 'static {
+    type Error = BlockingStoreError<S::Error>;
+
+    fn load(&self, key: &str) -> impl Future<Output = Result<Option<Scope>, Self::Error>> + Send {
+        let key: String = key.to_string();
+
+        return run_blocking(&self.store, move |store| store.load(&key));
+    }
+
+    fn save(&self, key: &str, scope: &Scope) -> impl Future<Output = Result<(), Self::Error>> + Send {
+        let key: String = key.to_string();
+        let scope = scope.clone();
+
+        return run_blocking(&self.store, move |store| store.save(&key, &scope));
+    }
+
+    fn remove(&self, key: &str) -> impl Future<Output = Result<bool, Self::Error>> + Send {
+        let key: String = key.to_string();
+
+        return run_blocking(&self.store, move |store| store.remove(&key));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::runtime::Builder;
+    use crate::store::MemoryStore;
+    use crate::{assert_granted, assert_ok};
+
+    #[test]
+    fn test_blocking_store() {
+        let runtime = Builder::new_current_thread().build().unwrap_or_else(|err| panic!("no runtime: {}", err));
+        let store = BlockingStore::new(MemoryStore::new());
+
+        let mut scope = Scope::new("APP");
+        assert_ok!(scope.add_permission("READ").and_then(|sc| sc.grant("READ")));
+
+        runtime.block_on(async {
+            assert_eq!(store.save("alice", &scope).await.is_ok(), true);
+
+            match store.clone().load("alice").await {
+                Ok(Some(loaded)) => assert_granted!(loaded, "READ"),
+                _ => panic!("the saved scope was not loaded")
+            }
+            assert_eq!(store.load("bob").await.ok().map(|loaded| loaded.is_none()), Some(true));
+            assert_eq!(store.remove("alice").await.ok(), Some(true));
+        });
+
+        assert_eq!(store.lock().is_empty(), true);
+    }
+}
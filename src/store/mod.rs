@@ -0,0 +1,105 @@
+/*!
+    Persistence for scopes, e.g. the grant state of each user. `ScopeStore` is implemented by blocking backends and
+    `MemoryStore` keeps exported scopes in memory for tests and caches. With the `async` feature, `AsyncScopeStore`
+    lets async services persist grant changes, and `BlockingStore` adapts any `ScopeStore` to it.
+ */
+
+use alloc::collections::BTreeMap;
+use alloc::string::{String, ToString};
+use serde_json::Value;
+use crate::common::error::ErrorKind;
+use crate::scope::order::ExportOptions;
+use crate::scope::Scope;
+
+#[cfg(feature = "async")]
+pub mod async_store;
+
+/**
+    ScopeStore loads and saves scopes under a key, such as the ID of the user the scope was granted to. Stores keep
+    scopes in their exported form, so virtual permission rules have to be added again after loading.
+ */
+pub trait ScopeStore {
+    type Error;
+
+    /** Load the scope saved under `key`, or None if nothing was saved under it. */
+    fn load(&self, key: &str) -> Result<Option<Scope>, Self::Error>;
+
+    /** Save `scope` under `key`, replacing whatever was saved under it before. */
+    fn save(&mut self, key: &str, scope: &Scope) -> Result<(), Self::Error>;
+
+    /** Remove the scope saved under `key`, returning whether there was one. */
+    fn remove(&mut self, key: &str) -> Result<bool, Self::Error>;
+}
+
+/** MemoryStore keeps the JSON export of every saved scope in memory. */
+#[derive(Clone, Debug, Default)]
+pub struct MemoryStore {
+    scopes: BTreeMap<String, Value>
+}
+
+impl MemoryStore {
+    pub fn new() -> MemoryStore {
+        return MemoryStore::default();
+    }
+
+    /** Get the number of saved scopes. */
+    pub fn len(&self) -> usize {
+        return self.scopes.len();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.scopes.is_empty();
+    }
+}
+
+impl ScopeStore for MemoryStore {
+    type Error = ErrorKind;
+
+    fn load(&self, key: &str) -> Result<Option<Scope>, ErrorKind> {
+        return match self.scopes.get(key) {
+            Some(value) => Scope::from_json_verified(value.clone(), None).map(Some),
+            None => Ok(None)
+        }
+    }
+
+    fn save(&mut self, key: &str, scope: &Scope) -> Result<(), ErrorKind> {
+        // keep revisions, so patches based on a loaded scope are checked against the saved one
+        let options = ExportOptions { revisions: true, ..ExportOptions::default() };
+        self.scopes.insert(key.to_string(), scope.as_json_with(&options));
+
+        return Ok(());
+    }
+
+    fn remove(&mut self, key: &str) -> Result<bool, ErrorKind> {
+        return Ok(self.scopes.remove(key).is_some());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{assert_granted, assert_ok};
+
+    #[test]
+    fn test_memory_store() {
+        let mut store = MemoryStore::new();
+        let mut scope = Scope::new("APP");
+        assert_ok!(scope.add_permission("READ").and_then(|sc| sc.grant("READ")));
+
+        assert_eq!(assert_ok!(store.load("alice")).is_none(), true);
+        assert_ok!(store.save("alice", &scope));
+
+        match assert_ok!(store.load("alice")) {
+            Some(loaded) => {
+                assert_granted!(loaded, "READ");
+                assert_eq!(loaded.revision(), scope.revision());
+            },
+            None => panic!("the saved scope was not loaded")
+        }
+
+        assert_eq!(store.len(), 1);
+        assert_eq!(assert_ok!(store.remove("alice")), true);
+        assert_eq!(assert_ok!(store.remove("alice")), false);
+        assert_eq!(store.is_empty(), true);
+    }
+}
@@ -0,0 +1,209 @@
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use crate::common::error::ErrorKind;
+use crate::scope::explain::Decision;
+use crate::scope::order::SortOrder;
+use crate::scope::path::PermissionPath;
+use crate::scope::Scope;
+
+/**
+    Assert that `scope.check(path)` allows the path. On failure the message carries the `explain` trace: the
+    scope and permission the path resolved to and why it was denied.
+ */
+#[macro_export]
+macro_rules! assert_granted {
+    ($scope:expr, $path:expr $(,)?) => {
+        if let Some(message) = $crate::testing::assert::check_failure(&$scope, $path, true) {
+            panic!("{}", message);
+        }
+    };
+}
+
+/** Assert that `scope.check(path)` denies the path, reporting which permission granted or implied it on failure. */
+#[macro_export]
+macro_rules! assert_not_granted {
+    ($scope:expr, $path:expr $(,)?) => {
+        if let Some(message) = $crate::testing::assert::check_failure(&$scope, $path, false) {
+            panic!("{}", message);
+        }
+    };
+}
+
+/**
+    Assert that two scopes have the same schema: the same scope paths and the same permission names on the same
+    bits, as compared by `fingerprint`. On failure the message lists the lines only found on either side.
+ */
+#[macro_export]
+macro_rules! assert_schema_eq {
+    ($left:expr, $right:expr $(,)?) => {
+        if let Some(message) = $crate::testing::assert::schema_diff(&$left, &$right) {
+            panic!("{}", message);
+        }
+    };
+}
+
+/** Unwrap an `Ok` result, panicking with the error's message otherwise. Evaluates to the value. */
+#[macro_export]
+macro_rules! assert_ok {
+    ($result:expr $(,)?) => {
+        match $result {
+            Ok(value) => value,
+            Err(err) => panic!("assertion failed: expected Ok, got error: {}", $crate::testing::assert::error_message(&err))
+        }
+    };
+}
+
+/** Assert that a result is a ScopeError of the given case, e.g. `assert_scope_err!(scope.grant("X"), ScopeErrorCase::UnknownPermission)`. */
+#[macro_export]
+macro_rules! assert_scope_err {
+    ($result:expr, $case:expr $(,)?) => {
+        match $result {
+            Err($crate::common::error::ErrorKind::ScopeError(err)) if err.case() == $case => {},
+            Err(err) => panic!("assertion failed: expected {:?}, got error: {}", $case, $crate::testing::assert::error_message(&err)),
+            Ok(_) => panic!("assertion failed: expected {:?}, got Ok", $case)
+        }
+    };
+}
+
+/** Get the message of the error wrapped by an ErrorKind. */
+pub fn error_message(err: &ErrorKind) -> String {
+    return match err {
+        ErrorKind::PermissionError(err) => format!("{}", err),
+        ErrorKind::ScopeError(err) => format!("{}", err)
+    }
+}
+
+/** Describe why `scope.check(path)` did not return `expected`, or None if it did. */
+pub fn check_failure(scope: &Scope, path: &str, expected: bool) -> Option<String> {
+    let explanation = scope.explain(path);
+    if explanation.allowed == expected {
+        return None;
+    }
+
+    let mut message = match expected {
+        true => format!("assertion failed: expected `{}` to be granted in {}", path, scope.path()),
+        false => format!("assertion failed: expected `{}` not to be granted in {}", path, scope.path())
+    };
+    let reason = match &explanation.decision {
+        Decision::Granted => String::from("granted directly"),
+        Decision::Implied(by) => format!("implied by {}", by),
+        Decision::Computed => String::from("computed by a virtual permission rule"),
+        Decision::Composite => String::from("composite of other permissions"),
+        Decision::Denied => String::from("explicitly denied"),
+        Decision::Conditioned => String::from("conditioned, so it needs a context"),
+        Decision::NotGranted => String::from("not granted"),
+        Decision::UnknownPermission => String::from("no such permission"),
+        Decision::UnknownScope => String::from("no such scope")
+    };
+    message.push_str(&format!("\n  decision: {}", reason));
+    if let Some(resolved) = &explanation.scope {
+        message.push_str(&format!("\n  scope: {}", resolved));
+    }
+    if let Some(permission) = &explanation.permission {
+        message.push_str(&format!("\n  permission: {}", permission));
+        if explanation.via_alias {
+            message.push_str(" (via alias)");
+        }
+    }
+    if let Some(bit) = explanation.bit {
+        message.push_str(&format!("\n  bit: {}", bit));
+    }
+
+    return Some(message);
+}
+
+/** List the schema of a scope one line per scope and permission, in encoding order. */
+fn schema_lines(scope: &Scope) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+
+    for child in scope.scopes_in_encoding_order() {
+        lines.push(format!("scope {}", child.path()));
+        for permission in child.permissions_in_order(SortOrder::Shift) {
+            let path = PermissionPath::new(child.path().clone(), &permission.name);
+            lines.push(format!("permission {} bit {}", path, permission.value.trailing_zeros()));
+        }
+    }
+
+    return lines;
+}
+
+/** Describe how the schemas of two scopes differ, or None if they are the same. */
+pub fn schema_diff(left: &Scope, right: &Scope) -> Option<String> {
+    let left_lines = schema_lines(left);
+    let right_lines = schema_lines(right);
+    if left_lines == right_lines {
+        return None;
+    }
+
+    let mut message = String::from("assertion failed: schemas differ (- left, + right)");
+    for line in left_lines.iter().filter(|line| !right_lines.contains(line)) {
+        message.push_str(&format!("\n  - {}", line));
+    }
+    for line in right_lines.iter().filter(|line| !left_lines.contains(line)) {
+        message.push_str(&format!("\n  + {}", line));
+    }
+
+    return Some(message);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scope::error::ScopeErrorCase;
+
+    fn get_scope() -> Scope {
+        let mut scope = Scope::new("APP");
+        crate::assert_ok!(scope
+            .add_permission("READ")
+            .and_then(|sc| sc.add_permission("WRITE"))
+            .and_then(|sc| sc.add_implication("WRITE", "READ"))
+            .and_then(|sc| sc.grant("WRITE")));
+
+        return scope;
+    }
+
+    #[test]
+    fn test_check_failure_messages() {
+        let scope = get_scope();
+
+        assert_eq!(check_failure(&scope, "READ", true), None);
+        assert_eq!(
+            check_failure(&scope, "READ", false).as_deref(),
+            Some("assertion failed: expected `READ` not to be granted in APP\n  decision: implied by WRITE\n  scope: APP\n  permission: READ\n  bit: 0")
+        );
+        assert_eq!(
+            check_failure(&scope, "billing:READ", true).as_deref(),
+            Some("assertion failed: expected `billing:READ` to be granted in APP\n  decision: no such scope")
+        );
+    }
+
+    #[test]
+    fn test_schema_diff() {
+        let scope = get_scope();
+        let mut renamed = scope.clone();
+        crate::assert_ok!(renamed.rename_permission("WRITE", "EDIT", false));
+
+        assert_eq!(schema_diff(&scope, &scope.clone()), None);
+        assert_eq!(
+            schema_diff(&scope, &renamed).as_deref(),
+            Some("assertion failed: schemas differ (- left, + right)\n  - permission APP:WRITE bit 1\n  + permission APP:EDIT bit 1")
+        );
+    }
+
+    #[test]
+    fn test_macros() {
+        let mut scope = get_scope();
+
+        crate::assert_granted!(scope, "WRITE");
+        crate::assert_not_granted!(scope, "DELETE");
+        crate::assert_schema_eq!(scope, Scope::from_json(scope.as_json()));
+        crate::assert_scope_err!(scope.grant("DELETE"), ScopeErrorCase::UnknownPermission);
+    }
+
+    #[test]
+    #[should_panic(expected = "expected `READ` not to be granted")]
+    fn test_assert_not_granted_panics() {
+        crate::assert_not_granted!(get_scope(), "READ");
+    }
+}
@@ -0,0 +1,123 @@
+use alloc::format;
+use alloc::vec;
+use alloc::vec::Vec;
+use crate::scope::path::ScopePath;
+use crate::scope::Scope;
+
+/** The permissions of every scope built by `crud_scope`, in bit order. */
+pub const CRUD_PERMISSIONS: [&str; 4] = ["CREATE", "READ", "UPDATE", "DELETE"];
+
+/** The permissions of the root scope built by `billing_scope`, in bit order. */
+pub const APP_PERMISSIONS: [&str; 2] = ["READ", "WRITE"];
+
+/** The permissions of the `billing` child scope built by `billing_scope`, in bit order. */
+pub const BILLING_PERMISSIONS: [&str; 3] = ["VIEW", "PAY", "REFUND"];
+
+/** The permissions of every scope built by `deep_tree`, in bit order. */
+pub const TREE_PERMISSIONS: [&str; 2] = ["READ", "WRITE"];
+
+/** Build a root scope named `APP` with CREATE, READ, UPDATE and DELETE on bits 0 to 3, none of them granted. */
+pub fn crud_scope() -> Scope {
+    let mut scope = Scope::new("APP");
+    add_permissions(&mut scope, &CRUD_PERMISSIONS);
+
+    return scope;
+}
+
+/**
+    Build a root scope named `APP` with READ and WRITE on bits 0 and 1, and a child scope `billing` with VIEW, PAY
+    and REFUND on bits 0 to 2, none of them granted. Most tests need no more than a root and one child scope.
+ */
+pub fn billing_scope() -> Scope {
+    let mut scope = Scope::new("APP");
+    add_permissions(&mut scope, &APP_PERMISSIONS);
+
+    let billing = scope.add_scope("billing")
+        .ok()
+        .and_then(|scope| scope.scope("billing"))
+        .unwrap_or_else(|| panic!("fixture scope names are unique"));
+    add_permissions(billing, &BILLING_PERMISSIONS);
+
+    return scope;
+}
+
+/**
+    Build a root scope named `ROOT` with `width` child scopes per scope, `depth` levels deep, named `s0`, `s1`, ...
+    at every level (e.g. `s0.s1:READ`). Every scope has READ and WRITE on bits 0 and 1, none of them granted. The
+    tree has `width^1 + ... + width^depth` scopes below the root, so keep both small outside of benchmarks.
+ */
+pub fn deep_tree(depth: usize, width: usize) -> Scope {
+    let mut root = Scope::new("ROOT");
+    add_permissions(&mut root, &TREE_PERMISSIONS);
+
+    let mut level: Vec<ScopePath> = vec![ScopePath::new()];
+    for _ in 0..depth {
+        let mut next_level: Vec<ScopePath> = Vec::with_capacity(level.len() * width);
+
+        for path in level {
+            let parent = root.resolve_scope_mut(&path)
+                .unwrap_or_else(|_| panic!("every fixture scope is added before its children"));
+
+            for index in 0..width {
+                let name = format!("s{}", index);
+                let child = parent.add_scope(&name)
+                    .ok()
+                    .and_then(|parent| parent.scope(&name))
+                    .unwrap_or_else(|| panic!("fixture scope names are unique"));
+                add_permissions(child, &TREE_PERMISSIONS);
+                next_level.push(path.child(&name));
+            }
+        }
+        level = next_level;
+    }
+
+    return root;
+}
+
+fn add_permissions(scope: &mut Scope, names: &[&str]) {
+    for name in names {
+        if scope.add_permission(name).is_err() {
+            panic!("fixture permission names are valid and unique");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{assert_granted, assert_not_granted, assert_ok};
+
+    #[test]
+    fn test_crud_scope() {
+        let mut scope = crud_scope();
+        assert_eq!(scope.as_u64(), 0);
+
+        assert_ok!(scope.grant("UPDATE"));
+        assert_eq!(scope.as_u64(), 0b0100);
+        assert_granted!(scope, "UPDATE");
+        assert_not_granted!(scope, "DELETE");
+    }
+
+    #[test]
+    fn test_billing_scope() {
+        let scope = billing_scope();
+        assert_eq!(scope.as_u64(), 0);
+        assert_eq!(scope.grant_mask("WRITE").ok(), Some(0b10));
+        assert_eq!(scope.grant_mask("billing:REFUND").ok(), Some(0b100));
+    }
+
+    #[test]
+    fn test_deep_tree() {
+        let tree = deep_tree(3, 2);
+
+        // 2 + 4 + 8 scopes below the root
+        assert_eq!(tree.scopes_in_encoding_order().len(), 15);
+        assert_eq!(tree.find_scope(&ScopePath::parse("s1.s0.s1")).is_some(), true);
+        assert_eq!(tree.find_scope(&ScopePath::parse("s1.s0.s1.s0")).is_none(), true);
+        assert_eq!(tree.grant_mask("s1.s0.s1:WRITE").ok(), Some(0b10));
+
+        assert_eq!(deep_tree(0, 5).scopes_in_encoding_order().len(), 1);
+        // a single chain deeper than the default import depth builds without recursion
+        assert_eq!(deep_tree(200, 1).scopes_in_encoding_order().len(), 201);
+    }
+}
@@ -0,0 +1,10 @@
+/*!
+    Helpers for testing code built on bitperm: assertion macros with readable failure messages, shared fixture
+    trees, and with the `test-util` feature, proptest strategies for random schemas and grant states. Meant for
+    dev-dependencies only.
+ */
+
+pub mod assert;
+pub mod fixtures;
+#[cfg(feature = "test-util")]
+pub mod strategy;
@@ -0,0 +1,132 @@
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use proptest::collection::{btree_map, btree_set};
+use proptest::prelude::*;
+use crate::scope::{PermissionEntry, Scope, ScopeMetadata, ScopeTuple, DEFAULT_MAX_DEPTH};
+
+/** The name of the root scope of every generated schema. */
+pub const ROOT_NAME: &str = "ROOT";
+
+/** SchemaParams bounds the shape of the schemas generated by `schema_with`. */
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SchemaParams {
+    /** The deepest level of child scopes below the root. */
+    pub max_depth: u32,
+    /** The most child scopes of any one scope. */
+    pub max_children: usize,
+    /** The most permissions of any one scope, at most 53 so values stay JS-safe. */
+    pub max_permissions: usize
+}
+
+impl Default for SchemaParams {
+    fn default() -> SchemaParams {
+        return SchemaParams {
+            max_depth: 3,
+            max_children: 3,
+            max_permissions: 8
+        };
+    }
+}
+
+/** Generate schemas with the default bounds: a root scope named `ROOT` with nothing granted. */
+pub fn schema() -> BoxedStrategy<Scope> {
+    return schema_with(SchemaParams::default());
+}
+
+/**
+    Generate schemas within `params`. Permission names are upper case and child scope names lower case, so they
+    never collide, and every name is accepted by the default validation policy. Shrinking removes child scopes
+    and permissions.
+ */
+pub fn schema_with(params: SchemaParams) -> BoxedStrategy<Scope> {
+    let max_children = params.max_children;
+    let permissions = btree_set("[A-Z][A-Z_]{0,7}", 0..=params.max_permissions.min(53))
+        .prop_map(|names| names.into_iter().map(PermissionEntry::Named).collect::<Vec<PermissionEntry>>());
+
+    let leaf = permissions.clone()
+        .prop_map(|names| ScopeTuple(String::new(), 0, names, vec![], ScopeMetadata::new()));
+    let size = (max_children as u32).saturating_pow(params.max_depth).max(1);
+
+    return leaf
+        .prop_recursive(params.max_depth, size, max_children as u32, move |inner| {
+            (permissions.clone(), btree_map("[a-z][a-z_]{0,7}", inner, 0..=max_children))
+                .prop_map(|(names, children)| {
+                    let children = children.into_iter()
+                        .map(|(name, ScopeTuple(_, value, permissions, scopes, metadata))| ScopeTuple(name, value, permissions, scopes, metadata))
+                        .collect();
+                    ScopeTuple(String::new(), 0, names, children, ScopeMetadata::new())
+                })
+        })
+        .prop_map(|mut tuple| {
+            tuple.0 = String::from(ROOT_NAME);
+            Scope::from_tuple(tuple, DEFAULT_MAX_DEPTH).unwrap_or_else(|_| panic!("generated schemas are always valid"))
+        })
+        .boxed();
+}
+
+/**
+    Generate grant states for a copy of `schema`: every scope gets a random value over the bits its permissions
+    use. Bits are set directly, so requirements and exclusions declared on `schema` are not enforced. Shrinking
+    revokes permissions.
+ */
+pub fn granted(schema: Scope) -> BoxedStrategy<Scope> {
+    let values: Vec<BoxedStrategy<u64>> = schema.scopes_in_encoding_order().into_iter()
+        .map(|scope| {
+            let mask = scope.defined_bits();
+            any::<u64>().prop_map(move |value| value & mask).boxed()
+        })
+        .collect();
+
+    return values
+        .prop_map(move |values| {
+            let mut scope = schema.clone();
+            scope.apply_values_in_encoding_order(&values, ROOT_NAME)
+                .unwrap_or_else(|_| panic!("generated values only set defined bits"));
+            scope
+        })
+        .boxed();
+}
+
+/** Generate schemas with the default bounds together with a random grant state. */
+pub fn scope_with_grants() -> BoxedStrategy<Scope> {
+    return schema().prop_flat_map(granted).boxed();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::string::ToString;
+    use crate::scope::encoding::Radix;
+    use crate::scope::path::PermissionPath;
+
+    proptest! {
+        #[test]
+        fn test_generated_scopes_round_trip(scope in scope_with_grants()) {
+            let imported = Scope::from_json(scope.as_json());
+
+            prop_assert_eq!(imported.fingerprint(), scope.fingerprint());
+            prop_assert_eq!(imported.encode_values(Radix::Base62), scope.encode_values(Radix::Base62));
+        }
+
+        #[test]
+        fn test_generated_values_match_checks(scope in scope_with_grants()) {
+            for child in scope.scopes_in_encoding_order() {
+                for permission in child.permission_values() {
+                    let path = PermissionPath::new(child.path().relative_to(scope.path()), &permission.name).to_string();
+                    prop_assert_eq!(scope.check(&path), child.as_u64() & permission.value != 0);
+                }
+            }
+        }
+
+        #[test]
+        fn test_schema_respects_bounds(scope in schema_with(SchemaParams { max_depth: 2, max_children: 2, max_permissions: 4 })) {
+            prop_assert_eq!(scope.name(), ROOT_NAME);
+            for child in scope.scopes_in_encoding_order() {
+                prop_assert!(child.path().len() <= 3);
+                prop_assert!(child.permission_values().count() <= 4);
+                prop_assert_eq!(child.as_u64(), 0);
+            }
+        }
+    }
+}
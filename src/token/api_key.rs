@@ -0,0 +1,110 @@
+use alloc::string::{String, ToString};
+use serde::{Deserialize, Serialize};
+use crate::common::error::ErrorKind;
+use crate::scope::Scope;
+use crate::token::{invalid_token, sign, verify};
+use crate::token::validation::Validation;
+
+/**
+    ApiKey is a machine credential: a signed token granting a selection of permissions, issued under an ID the
+    service can look up, revoke or show in listings. The token names the key ID as its subject, so a token cannot be
+    presented under another key's record. Store the record, hand the token to the client.
+ */
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ApiKey {
+    pub key_id: String,
+    pub token: String,
+    /** Time from which the key is no longer accepted, in seconds since the Unix epoch. */
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<u64>
+}
+
+impl ApiKey {
+    /**
+        Verify the token of this key and return a copy of `schema` holding exactly the grants of the key, ready for
+        `check`. Fails with InvalidToken if the signature, expiry or audience does not verify, or if the token was
+        issued under another key ID, and with SchemaMismatch if it was issued under another schema.
+     */
+    pub fn decode(&self, schema: &Scope, key: &[u8], validation: &Validation) -> Result<Scope, ErrorKind> {
+        let claims = verify(&self.token, key, validation)?;
+        if claims.sub.as_deref() != Some(self.key_id.as_str()) {
+            return Err(invalid_token("issued under another key ID"));
+        }
+
+        let mut scope = schema.clone();
+        scope.apply_claims(&claims)?;
+
+        return Ok(scope);
+    }
+}
+
+impl Scope {
+    /**
+        Issue an API key granting only the permissions at `paths` (relative to this scope), each of which this scope
+        must hold, as with `delegate`. The key ID is chosen by the caller, e.g. a random identifier from their own
+        generator, and the key stops verifying at `expires_at` if one is given.
+     */
+    pub fn issue_api_key(&self, key_id: &str, paths: &[&str], expires_at: Option<u64>, key: &[u8]) -> Result<ApiKey, ErrorKind> {
+        let mut claims = self.delegate(paths)?;
+        claims.exp = expires_at;
+        claims.sub = Some(key_id.to_string());
+
+        return Ok(ApiKey {
+            key_id: key_id.to_string(),
+            token: sign(&claims, key),
+            expires_at
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scope::error::ScopeErrorCase;
+    use crate::{assert_granted, assert_not_granted, assert_ok, assert_scope_err};
+
+    const KEY: &[u8] = b"signing key used only by tests";
+
+    fn get_scope() -> Scope {
+        let mut scope = Scope::new("APP");
+        assert_ok!(scope.add_permission("READ").and_then(|sc| sc.add_permission("WRITE")).and_then(|sc| sc.add_scope("billing")));
+        if let Some(billing) = scope.scope("billing") {
+            assert_ok!(billing.add_permission("EXPORT"));
+        }
+
+        return scope;
+    }
+
+    fn get_issuer() -> Scope {
+        let mut scope = get_scope();
+        assert_ok!(scope.grant("READ").and_then(|sc| sc.grant("WRITE")));
+        if let Some(billing) = scope.scope("billing") {
+            assert_ok!(billing.grant("EXPORT"));
+        }
+
+        return scope;
+    }
+
+    #[test]
+    fn test_api_key_round_trip() {
+        let api_key = assert_ok!(get_issuer().issue_api_key("ci-exporter", &["billing:EXPORT"], Some(2_000), KEY));
+        assert_eq!(api_key.expires_at, Some(2_000));
+
+        let scope = assert_ok!(api_key.decode(&get_scope(), KEY, &Validation::at(1_000)));
+        assert_granted!(scope, "billing:EXPORT");
+        assert_not_granted!(scope, "READ");
+
+        assert_scope_err!(api_key.decode(&get_scope(), KEY, &Validation::at(2_000)), ScopeErrorCase::InvalidToken);
+    }
+
+    #[test]
+    fn test_api_key_is_bound_to_its_id() {
+        let issuer = get_issuer();
+        let api_key = assert_ok!(issuer.issue_api_key("ci-exporter", &["READ"], None, KEY));
+        let relabelled = ApiKey { key_id: "admin".to_string(), ..api_key.clone() };
+
+        assert_scope_err!(relabelled.decode(&get_scope(), KEY, &Validation::at(0)), ScopeErrorCase::InvalidToken);
+        assert_scope_err!(api_key.decode(&get_scope(), b"another key", &Validation::at(0)), ScopeErrorCase::InvalidToken);
+        assert_scope_err!(issuer.issue_api_key("ci-exporter", &["billing:REFUND"], None, KEY), ScopeErrorCase::UnknownPermission);
+    }
+}
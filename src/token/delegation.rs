@@ -0,0 +1,153 @@
+use alloc::string::ToString;
+use alloc::vec;
+use alloc::vec::Vec;
+use crate::common::error::ErrorKind;
+use crate::scope::error::{ScopeError, ScopeErrorCase};
+use crate::scope::order::SortOrder;
+use crate::scope::path::PermissionPath;
+use crate::scope::Scope;
+use crate::token::validation::Validation;
+use crate::token::{invalid_token, verify, GrantClaims};
+
+impl Scope {
+    /**
+        Build claims that grant only the permissions at `paths` (relative to this scope), for sharing limited access.
+        Each permission must be granted in this scope, or the delegation fails with NotHeld; implied permissions
+        cannot be delegated on their own. The claims record the delegator's values at the time of delegation; use
+        `apply_delegated_token` to check them against what the delegator still holds. Set `exp` on the claims
+        before signing to make the delegation expire.
+     */
+    pub fn delegate(&self, paths: &[&str]) -> Result<GrantClaims, ErrorKind> {
+        let scopes = self.scopes_in_encoding_order();
+        let mut values: Vec<u64> = vec![0; scopes.len()];
+
+        for path in paths {
+            let requested = PermissionPath::parse(path);
+            let target = match self.find_scope(&requested.scope) {
+                Some(target) => target,
+                None => return Err(ErrorKind::ScopeError(ScopeError::new(ScopeErrorCase::UnknownScope, &requested.scope.to_string())))
+            };
+            let name = target.find_permission_name(&requested.permission)?;
+            if !target.is_granted(&name) {
+                return Err(ErrorKind::ScopeError(ScopeError::new(ScopeErrorCase::NotHeld, &PermissionPath::new(target.path().clone(), &name).to_string())));
+            }
+
+            let index = scopes.iter().position(|scope| scope.path() == target.path());
+            let value = target.permissions_in_order(SortOrder::Insertion).into_iter()
+                .find(|permission| permission.name == name)
+                .map(|permission| permission.value);
+            if let (Some(index), Some(value)) = (index, value) {
+                values[index] |= value;
+            }
+        }
+
+        let held = self.grant_claims();
+        return Ok(GrantClaims {
+            values,
+            delegated_from: Some(held.values.clone()),
+            ..held
+        });
+    }
+
+    /**
+        Verify a delegated token produced from `delegate`, validate its claims and apply the grant state it carries,
+        checking every delegated value against the current grants of `delegator`, the scope of the principal who
+        delegated them as known to this service. Grants the delegator has lost since are not passed on: the token
+        is rejected with InvalidToken, as it is when it is not a delegated token or was delegated under another
+        schema than that of the delegator.
+     */
+    pub fn apply_delegated_token(&mut self, token: &str, key: &[u8], validation: &Validation, delegator: &Scope) -> Result<&mut Scope, ErrorKind> {
+        let claims = verify(token, key, validation)?;
+        if claims.delegated_from.is_none() {
+            return Err(invalid_token("not a delegated token"));
+        }
+        if delegator.fingerprint() != claims.schema_hash {
+            return Err(invalid_token("delegated under another schema"));
+        }
+
+        let held: Vec<u64> = delegator.scopes_in_encoding_order().iter().map(|scope| scope.as_u64()).collect();
+        let is_subset = held.len() == claims.values.len()
+            && claims.values.iter().zip(&held).all(|(value, held)| value & !held == 0);
+        if !is_subset {
+            return Err(invalid_token("delegation exceeds the delegator's grants"));
+        }
+
+        return self.apply_claims(&claims);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::fixtures::billing_scope;
+    use crate::token::{encode_part, sign, verify, PART_SEPARATOR};
+    use crate::token::validation::Validation;
+    use crate::{assert_granted, assert_not_granted, assert_ok, assert_scope_err};
+
+    const KEY: &[u8] = b"signing key used only by tests";
+
+    fn get_delegator() -> Scope {
+        let mut scope = billing_scope();
+        assert_ok!(scope.grant("READ").and_then(|sc| sc.grant("WRITE")));
+        if let Some(billing) = scope.scope("billing") {
+            assert_ok!(billing.grant("VIEW"));
+        }
+
+        return scope;
+    }
+
+    #[test]
+    fn test_delegate_subset() {
+        let delegator = get_delegator();
+        let claims = assert_ok!(delegator.delegate(&["READ", "billing:VIEW"]));
+        assert_eq!(claims.values, [0b01, 0b01]);
+        assert_eq!(claims.delegated_from, Some(vec![0b11, 0b01]));
+
+        let mut delegate = billing_scope();
+        assert_ok!(delegate.apply_delegated_token(&sign(&claims, KEY), KEY, &Validation::at(0), &delegator));
+        assert_granted!(delegate, "READ");
+        assert_granted!(delegate, "billing:VIEW");
+        assert_not_granted!(delegate, "WRITE");
+    }
+
+    #[test]
+    fn test_delegation_follows_the_delegator() {
+        let mut delegator = get_delegator();
+        let token = sign(&assert_ok!(delegator.delegate(&["READ", "billing:VIEW"])), KEY);
+
+        // the delegator lost a grant after delegating it, so the token no longer passes it on
+        assert_ok!(delegator.revoke_path("billing:VIEW"));
+        let mut delegate = billing_scope();
+        assert_scope_err!(delegate.apply_delegated_token(&token, KEY, &Validation::at(0), &delegator), ScopeErrorCase::InvalidToken);
+        assert_not_granted!(delegate, "READ");
+
+        // tokens that were never delegated are not accepted in place of delegated ones
+        let direct = get_delegator().to_signed_token(KEY);
+        assert_scope_err!(delegate.apply_delegated_token(&direct, KEY, &Validation::at(0), &delegator), ScopeErrorCase::InvalidToken);
+    }
+
+    #[test]
+    fn test_delegate_rejects_permissions_not_held() {
+        let delegator = get_delegator();
+
+        assert_scope_err!(delegator.delegate(&["billing:REFUND"]), ScopeErrorCase::NotHeld);
+        assert_scope_err!(delegator.delegate(&["billing:VOID"]), ScopeErrorCase::UnknownPermission);
+        assert_scope_err!(delegator.delegate(&["payroll:RUN"]), ScopeErrorCase::UnknownScope);
+    }
+
+    #[test]
+    fn test_verification_rejects_widened_delegation() {
+        let delegator = get_delegator();
+        let claims = assert_ok!(delegator.delegate(&["READ"]));
+
+        // a token signed with more than the delegator held never verifies
+        let widened = GrantClaims { values: vec![0b01, 0b10], ..claims.clone() };
+        assert_scope_err!(verify(&sign(&widened, KEY), KEY, &Validation::at(0)), ScopeErrorCase::InvalidToken);
+
+        let token = sign(&claims, KEY);
+        let parts: Vec<&str> = token.split(PART_SEPARATOR).collect();
+        let forged_claims = GrantClaims { delegated_from: Some(vec![u64::MAX, u64::MAX]), ..widened };
+        let forged = [parts[0], &encode_part(&serde_json::to_vec(&forged_claims).unwrap_or_default()), parts[2]].join(".");
+        assert_scope_err!(verify(&forged, KEY, &Validation::at(0)), ScopeErrorCase::InvalidToken);
+    }
+}
@@ -0,0 +1,234 @@
+use alloc::string::{String, ToString};
+use chacha20poly1305::aead::{Aead, KeyInit, Payload};
+use chacha20poly1305::{Key, XChaCha20Poly1305, XNonce};
+use serde::{Deserialize, Serialize};
+use crate::common::error::ErrorKind;
+use crate::scope::Scope;
+use crate::token::keyring::{unknown_key, Keyring};
+use crate::token::validation::Validation;
+use crate::token::{decode_part, encode_part, invalid_token, key_id, split_token, PART_SEPARATOR};
+
+/** Algorithm named in the header of encrypted tokens: XChaCha20-Poly1305. */
+pub const ENCRYPTION_ALGORITHM: &str = "XC20P";
+
+/** Length in bytes of an encryption key. */
+pub const KEY_LEN: usize = 32;
+
+/** Length in bytes of a nonce. */
+pub const NONCE_LEN: usize = 24;
+
+#[derive(Serialize, Deserialize)]
+struct EncryptedHeader {
+    enc: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    kid: Option<String>
+}
+
+fn encrypt_with_header(signed_token: &str, key: &[u8; KEY_LEN], nonce: &[u8; NONCE_LEN], kid: Option<&str>) -> String {
+    let header = EncryptedHeader { enc: ENCRYPTION_ALGORITHM.to_string(), kid: kid.map(|kid| kid.to_string()) };
+    let encoded_header = encode_part(&serde_json::to_vec(&header).expect("token headers always serialize"));
+
+    let ciphertext = XChaCha20Poly1305::new(Key::from_slice(key))
+        .encrypt(XNonce::from_slice(nonce), Payload { msg: signed_token.as_bytes(), aad: encoded_header.as_bytes() })
+        .expect("encrypting in memory cannot fail");
+
+    let mut token = encoded_header;
+    token.push(PART_SEPARATOR);
+    token.push_str(&encode_part(nonce));
+    token.push(PART_SEPARATOR);
+    token.push_str(&encode_part(&ciphertext));
+
+    return token;
+}
+
+/**
+    Encrypt a signed token, producing `header.nonce.ciphertext` with every part base64url encoded without padding.
+    The header is authenticated along with the ciphertext. A nonce must never be used twice with the same key;
+    nonces are long enough to be drawn at random for every token.
+ */
+pub fn encrypt(signed_token: &str, key: &[u8; KEY_LEN], nonce: &[u8; NONCE_LEN]) -> String {
+    return encrypt_with_header(signed_token, key, nonce, None);
+}
+
+/** Encrypt a signed token with the active key of a keyring, naming the key in the header. */
+pub fn encrypt_with(signed_token: &str, keyring: &Keyring<[u8; KEY_LEN]>, nonce: &[u8; NONCE_LEN]) -> Result<String, ErrorKind> {
+    let (id, key) = keyring.active()?;
+
+    return Ok(encrypt_with_header(signed_token, key, nonce, Some(id)));
+}
+
+fn decrypt_with_keys<'a>(token: &str, keys: impl IntoIterator<Item = &'a [u8; KEY_LEN]>) -> Result<String, ErrorKind> {
+    let [header, nonce, ciphertext] = split_token(token)?;
+
+    let parsed: EncryptedHeader = serde_json::from_slice(&decode_part(header)?).map_err(|_| invalid_token("malformed"))?;
+    if parsed.enc != ENCRYPTION_ALGORITHM {
+        return Err(invalid_token("unsupported algorithm"));
+    }
+
+    let nonce = decode_part(nonce)?;
+    if nonce.len() != NONCE_LEN {
+        return Err(invalid_token("malformed"));
+    }
+    let ciphertext = decode_part(ciphertext)?;
+
+    let plaintext = keys.into_iter()
+        .find_map(|key| XChaCha20Poly1305::new(Key::from_slice(key))
+            .decrypt(XNonce::from_slice(&nonce), Payload { msg: &ciphertext, aad: header.as_bytes() })
+            .ok())
+        .ok_or_else(|| invalid_token("decryption failed"))?;
+
+    return String::from_utf8(plaintext).map_err(|_| invalid_token("malformed"));
+}
+
+/** Decrypt a token produced by `encrypt`, returning the signed token it wraps. */
+pub fn decrypt(token: &str, key: &[u8; KEY_LEN]) -> Result<String, ErrorKind> {
+    return decrypt_with_keys(token, [key]);
+}
+
+/**
+    Decrypt a token against a keyring, returning the signed token it wraps. A token naming a key is decrypted with
+    that key alone, failing with UnknownKey if the ring no longer holds it; otherwise every key is tried.
+ */
+pub fn decrypt_with(token: &str, keyring: &Keyring<[u8; KEY_LEN]>) -> Result<String, ErrorKind> {
+    return match key_id(token)? {
+        Some(id) => decrypt_with_keys(token, [keyring.get(&id).ok_or_else(|| unknown_key(&id))?]),
+        None => decrypt_with_keys(token, keyring.keys())
+    }
+}
+
+impl Scope {
+    /**
+        Export the grant state of this scope and every child scope as a token signed with `signing_key` and then
+        encrypted with `encryption_key`, for storage on clients that must not read or alter it.
+     */
+    pub fn to_encrypted_token(&self, signing_key: &[u8], encryption_key: &[u8; KEY_LEN], nonce: &[u8; NONCE_LEN]) -> String {
+        return encrypt(&self.to_signed_token(signing_key), encryption_key, nonce);
+    }
+
+    /**
+        Decrypt and verify a token produced by `to_encrypted_token`, validate its claims and apply the grant state
+        it carries.
+     */
+    pub fn apply_encrypted_token(&mut self, token: &str, signing_key: &[u8], encryption_key: &[u8; KEY_LEN], validation: &Validation) -> Result<&mut Scope, ErrorKind> {
+        let signed = decrypt(token, encryption_key)?;
+
+        return self.apply_signed_token(&signed, signing_key, validation);
+    }
+
+    /** Export the grant state of this scope as a token signed and encrypted with the active keys of two keyrings. */
+    pub fn to_encrypted_token_with<K: AsRef<[u8]>>(&self, signing: &Keyring<K>, encryption: &Keyring<[u8; KEY_LEN]>, nonce: &[u8; NONCE_LEN]) -> Result<String, ErrorKind> {
+        return encrypt_with(&self.to_signed_token_with(signing)?, encryption, nonce);
+    }
+
+    /** Decrypt and verify a token against two keyrings, validate its claims and apply the grant state it carries. */
+    pub fn apply_encrypted_token_with<K: AsRef<[u8]>>(&mut self, token: &str, signing: &Keyring<K>, encryption: &Keyring<[u8; KEY_LEN]>, validation: &Validation) -> Result<&mut Scope, ErrorKind> {
+        let signed = decrypt_with(token, encryption)?;
+
+        return self.apply_signed_token_with(&signed, signing, validation);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec::Vec;
+    use crate::scope::error::ScopeErrorCase;
+
+    const SIGNING_KEY: &[u8] = b"signing key used only by tests";
+    const ENCRYPTION_KEY: &[u8; KEY_LEN] = b"encryption key used only by test";
+    const NONCE: &[u8; NONCE_LEN] = b"nonce used only by tests";
+
+    fn get_scope() -> Scope {
+        let mut scope = Scope::new("APP");
+        if let Err(_) = scope.add_permission("READ").and_then(|sc| sc.add_permission("WRITE")) {
+            assert!(false);
+        }
+
+        return scope;
+    }
+
+    #[test]
+    fn test_encrypted_token_round_trip() {
+        let mut scope = get_scope();
+        if let Err(_) = scope.grant("WRITE") {
+            assert!(false);
+        }
+
+        let token = scope.to_encrypted_token(SIGNING_KEY, ENCRYPTION_KEY, NONCE);
+        // the claims must not be readable from the token
+        assert_eq!(token.contains(&encode_part(b"schema_hash")), false);
+
+        let mut restored = get_scope();
+        match restored.apply_encrypted_token(&token, SIGNING_KEY, ENCRYPTION_KEY, &Validation::at(0)) {
+            Ok(_) => {
+                assert_eq!(restored.check("WRITE"), true);
+                assert_eq!(restored.check("READ"), false);
+            },
+            Err(_) => assert!(false)
+        }
+    }
+
+    #[test]
+    fn test_encrypted_token_rejects_tampering_and_wrong_keys() {
+        let token = get_scope().to_encrypted_token(SIGNING_KEY, ENCRYPTION_KEY, NONCE);
+        let parts: Vec<&str> = token.split(PART_SEPARATOR).collect();
+
+        // flip one byte of the ciphertext
+        let mut ciphertext = decode_part(parts[2]).unwrap_or_default();
+        assert_eq!(ciphertext.is_empty(), false);
+        ciphertext[0] ^= 1;
+        let tampered = [parts[0], parts[1], &encode_part(&ciphertext)].join(".");
+
+        let mut restored = get_scope();
+        let attempts: [(&str, &[u8], &[u8; KEY_LEN]); 3] = [
+            (&tampered, SIGNING_KEY, ENCRYPTION_KEY),
+            (&token, SIGNING_KEY, b"some other encryption key 32byte"),
+            (&token, b"some other signing key", ENCRYPTION_KEY)
+        ];
+        for (candidate, signing_key, encryption_key) in attempts {
+            match restored.apply_encrypted_token(candidate, signing_key, encryption_key, &Validation::at(0)) {
+                Ok(_) => assert!(false),
+                Err(ErrorKind::ScopeError(err)) => assert_eq!(err.case(), ScopeErrorCase::InvalidToken),
+                Err(_) => assert!(false)
+            }
+        }
+    }
+
+    #[test]
+    fn test_encrypted_token_key_rotation() {
+        let mut signing: Keyring<&[u8]> = Keyring::new();
+        signing.insert("sig-1", SIGNING_KEY);
+        let mut encryption: Keyring<[u8; KEY_LEN]> = Keyring::new();
+        encryption.insert("enc-1", *ENCRYPTION_KEY);
+
+        let mut scope = get_scope();
+        if let Err(_) = scope.grant("READ") {
+            assert!(false);
+        }
+        let old_token = scope.to_encrypted_token_with(&signing, &encryption, NONCE).unwrap_or_default();
+        assert_eq!(key_id(&old_token).ok(), Some(Some("enc-1".to_string())));
+
+        // rotate the encryption key; tokens under the old key must still decrypt
+        encryption.insert("enc-2", *b"replacement encryption key 32byt");
+        if let Err(_) = encryption.activate("enc-2") {
+            assert!(false);
+        }
+        let new_token = scope.to_encrypted_token_with(&signing, &encryption, NONCE).unwrap_or_default();
+        assert_eq!(key_id(&new_token).ok(), Some(Some("enc-2".to_string())));
+
+        for token in [&old_token, &new_token] {
+            let mut restored = get_scope();
+            match restored.apply_encrypted_token_with(token, &signing, &encryption, &Validation::at(0)) {
+                Ok(_) => assert_eq!(restored.check("READ"), true),
+                Err(_) => assert!(false)
+            }
+        }
+
+        encryption.remove("enc-1");
+        match get_scope().apply_encrypted_token_with(&old_token, &signing, &encryption, &Validation::at(0)) {
+            Ok(_) => assert!(false),
+            Err(ErrorKind::ScopeError(err)) => assert_eq!(err.case(), ScopeErrorCase::UnknownKey),
+            Err(_) => assert!(false)
+        }
+    }
+}
@@ -0,0 +1,136 @@
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt;
+use core::fmt::{Debug, Formatter};
+use crate::common::error::ErrorKind;
+use crate::scope::error::{ScopeError, ScopeErrorCase};
+
+/**
+    Keyring holds the keys used to sign or encrypt tokens, each under an ID that is written into the header of the
+    tokens it produces. New tokens use the active key; every key in the ring can still verify or decrypt, so a key
+    can be rotated by inserting and activating its replacement, then removing it once its tokens have expired.
+ */
+#[derive(Clone)]
+pub struct Keyring<K> {
+    keys: Vec<(String, K)>,
+    active: Option<String>
+}
+
+pub(crate) fn unknown_key(id: &str) -> ErrorKind {
+    return ErrorKind::ScopeError(ScopeError::new(ScopeErrorCase::UnknownKey, id));
+}
+
+impl<K> Keyring<K> {
+    pub fn new() -> Keyring<K> {
+        return Keyring {
+            keys: Vec::new(),
+            active: None
+        };
+    }
+
+    /** Add a key, replacing any key with the same ID. The first key added becomes the active key. */
+    pub fn insert(&mut self, id: &str, key: K) -> &mut Keyring<K> {
+        match self.keys.iter_mut().find(|(existing, _)| existing == id) {
+            Some(entry) => entry.1 = key,
+            None => self.keys.push((id.to_string(), key))
+        }
+        if self.active.is_none() {
+            self.active = Some(id.to_string());
+        }
+
+        return self;
+    }
+
+    /** Use the key with the given ID for new tokens. */
+    pub fn activate(&mut self, id: &str) -> Result<&mut Keyring<K>, ErrorKind> {
+        if self.get(id).is_none() {
+            return Err(unknown_key(id));
+        }
+        self.active = Some(id.to_string());
+
+        return Ok(self);
+    }
+
+    /** Remove a key so tokens it produced are no longer accepted. Removing the active key leaves none active. */
+    pub fn remove(&mut self, id: &str) -> Option<K> {
+        let index = self.keys.iter().position(|(existing, _)| existing == id)?;
+        if self.active.as_deref() == Some(id) {
+            self.active = None;
+        }
+
+        return Some(self.keys.remove(index).1);
+    }
+
+    /** Get a key by ID. */
+    pub fn get(&self, id: &str) -> Option<&K> {
+        return self.keys.iter().find(|(existing, _)| existing == id).map(|(_, key)| key);
+    }
+
+    /** Get the ID and key used for new tokens, failing with UnknownKey if no key is active. */
+    pub fn active(&self) -> Result<(&str, &K), ErrorKind> {
+        let id = self.active.as_deref().ok_or_else(|| unknown_key(""))?;
+
+        return self.get(id).map(|key| (id, key)).ok_or_else(|| unknown_key(id));
+    }
+
+    /** Get the ID of every key in the ring, in the order they were added. */
+    pub fn key_ids(&self) -> impl Iterator<Item = &str> {
+        return self.keys.iter().map(|(id, _)| id.as_str());
+    }
+
+    pub(crate) fn keys(&self) -> impl Iterator<Item = &K> {
+        return self.keys.iter().map(|(_, key)| key);
+    }
+}
+
+impl<K> Default for Keyring<K> {
+    fn default() -> Keyring<K> {
+        return Keyring::new();
+    }
+}
+
+// key material must never end up in logs
+impl<K> Debug for Keyring<K> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Keyring")
+            .field("key_ids", &self.key_ids().collect::<Vec<&str>>())
+            .field("active", &self.active)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::format;
+
+    #[test]
+    fn test_keyring_rotation() {
+        let mut keyring: Keyring<Vec<u8>> = Keyring::new();
+        assert_eq!(keyring.active().is_err(), true);
+
+        keyring.insert("2024-01", b"old".to_vec()).insert("2024-06", b"new".to_vec());
+        match keyring.active() {
+            Ok((id, _)) => assert_eq!(id, "2024-01"),
+            Err(_) => assert!(false)
+        }
+
+        if let Err(_) = keyring.activate("2024-06") {
+            assert!(false);
+        }
+        match keyring.active() {
+            Ok((id, key)) => {
+                assert_eq!(id, "2024-06");
+                assert_eq!(key.as_slice(), b"new");
+            },
+            Err(_) => assert!(false)
+        }
+        if let Ok(_) = keyring.activate("2025-01") {
+            assert!(false);
+        }
+
+        assert_eq!(keyring.remove("2024-01"), Some(b"old".to_vec()));
+        assert_eq!(keyring.key_ids().collect::<Vec<&str>>(), ["2024-06"]);
+        assert_eq!(format!("{:?}", keyring), "Keyring { key_ids: [\"2024-06\"], active: Some(\"2024-06\") }");
+    }
+}
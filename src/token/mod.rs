@@ -0,0 +1,401 @@
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use crate::common::error::ErrorKind;
+use crate::scope::error::{ScopeError, ScopeErrorCase};
+use crate::scope::Scope;
+use crate::token::keyring::{unknown_key, Keyring};
+use crate::token::validation::Validation;
+
+pub mod api_key;
+pub mod delegation;
+pub mod encrypted;
+pub mod keyring;
+pub mod validation;
+
+/** Algorithm named in the header of signed tokens: HMAC with SHA-256. */
+pub const SIGNATURE_ALGORITHM: &str = "HS256";
+
+/** Separator between the base64url encoded parts of a token. */
+pub const PART_SEPARATOR: char = '.';
+
+type HmacSha256 = Hmac<Sha256>;
+
+#[derive(Serialize, Deserialize)]
+struct SignedHeader {
+    alg: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    kid: Option<String>
+}
+
+/** The fields shared by the headers of signed and encrypted tokens. */
+#[derive(Deserialize)]
+struct KeyIdHeader {
+    #[serde(default)]
+    kid: Option<String>
+}
+
+/**
+    GrantClaims is the payload of a signed token: the fingerprint of the schema the values were produced under,
+    and the value of every scope in the tree depth-first, with child scopes in name order. The optional time and
+    audience claims follow JWT and are checked by `Validation` whenever a token is decoded.
+ */
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GrantClaims {
+    pub schema_hash: u64,
+    pub values: Vec<u64>,
+    /** Time the token was issued, in seconds since the Unix epoch. */
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub iat: Option<u64>,
+    /** Time from which the token is no longer accepted, in seconds since the Unix epoch. */
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exp: Option<u64>,
+    /** The service the token is intended for. */
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub aud: Option<String>,
+    /** The principal the token was issued to, such as the ID of an API key. */
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sub: Option<String>,
+    /**
+        The values held by whoever delegated the grants when they were delegated, for delegated tokens. `Validation`
+        checks that every value is a subset of the recorded value for the same scope, but as both are written by the
+        issuer, `Scope::apply_delegated_token` checks the values against the delegator's current grants instead.
+     */
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub delegated_from: Option<Vec<u64>>
+}
+
+pub(crate) fn invalid_token(reason: &str) -> ErrorKind {
+    return ErrorKind::ScopeError(ScopeError::new(ScopeErrorCase::InvalidToken, reason));
+}
+
+pub(crate) fn encode_part(bytes: &[u8]) -> String {
+    return URL_SAFE_NO_PAD.encode(bytes);
+}
+
+pub(crate) fn decode_part(part: &str) -> Result<Vec<u8>, ErrorKind> {
+    return URL_SAFE_NO_PAD.decode(part).map_err(|_| invalid_token("malformed"));
+}
+
+pub(crate) fn split_token(token: &str) -> Result<[&str; 3], ErrorKind> {
+    let parts: Vec<&str> = token.split(PART_SEPARATOR).collect();
+
+    return <[&str; 3]>::try_from(parts).map_err(|_| invalid_token("malformed"));
+}
+
+fn signature(key: &[u8], signed: &str) -> HmacSha256 {
+    let mut mac = HmacSha256::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(signed.as_bytes());
+
+    return mac;
+}
+
+fn sign_with_header(claims: &GrantClaims, key: &[u8], kid: Option<&str>) -> String {
+    let header = SignedHeader { alg: SIGNATURE_ALGORITHM.to_string(), kid: kid.map(|kid| kid.to_string()) };
+    let mut token = encode_part(&serde_json::to_vec(&header).expect("token headers always serialize"));
+    token.push(PART_SEPARATOR);
+    token.push_str(&encode_part(&serde_json::to_vec(claims).expect("grant claims always serialize")));
+
+    let tag = signature(key, &token).finalize().into_bytes();
+    token.push(PART_SEPARATOR);
+    token.push_str(&encode_part(&tag));
+
+    return token;
+}
+
+/**
+    Sign a set of claims, producing `header.claims.signature` with every part base64url encoded without padding.
+ */
+pub fn sign(claims: &GrantClaims, key: &[u8]) -> String {
+    return sign_with_header(claims, key, None);
+}
+
+/** Sign a set of claims with the active key of a keyring, naming the key in the header. */
+pub fn sign_with<K: AsRef<[u8]>>(claims: &GrantClaims, keyring: &Keyring<K>) -> Result<String, ErrorKind> {
+    let (id, key) = keyring.active()?;
+
+    return Ok(sign_with_header(claims, key.as_ref(), Some(id)));
+}
+
+/**
+    Get the ID of the key that signed or encrypted a token, or None if its header does not name one. The header is
+    not authenticated until the token has been verified, so use the ID only to choose a key.
+ */
+pub fn key_id(token: &str) -> Result<Option<String>, ErrorKind> {
+    let [header, _, _] = split_token(token)?;
+    let header: KeyIdHeader = serde_json::from_slice(&decode_part(header)?).map_err(|_| invalid_token("malformed"))?;
+
+    return Ok(header.kid);
+}
+
+fn verify_with_keys<'a>(token: &str, keys: impl IntoIterator<Item = &'a [u8]>, validation: &Validation) -> Result<GrantClaims, ErrorKind> {
+    let [header, claims, tag] = split_token(token)?;
+    let tag = decode_part(tag)?;
+    let signed = &token[..header.len() + 1 + claims.len()];
+
+    if !keys.into_iter().any(|key| signature(key, signed).verify_slice(&tag).is_ok()) {
+        return Err(invalid_token("signature does not verify"));
+    }
+
+    let header: SignedHeader = serde_json::from_slice(&decode_part(header)?).map_err(|_| invalid_token("malformed"))?;
+    if header.alg != SIGNATURE_ALGORITHM {
+        return Err(invalid_token("unsupported algorithm"));
+    }
+
+    let claims: GrantClaims = serde_json::from_slice(&decode_part(claims)?).map_err(|_| invalid_token("malformed"))?;
+    validation.validate(&claims)?;
+
+    return Ok(claims);
+}
+
+/**
+    Verify the signature of a token produced by `sign`, check its claims against `validation` and return them.
+    The signature is compared in constant time, and nothing in the token is parsed before it has been verified.
+ */
+pub fn verify(token: &str, key: &[u8], validation: &Validation) -> Result<GrantClaims, ErrorKind> {
+    return verify_with_keys(token, [key], validation);
+}
+
+/**
+    Verify a token against a keyring and return its claims. A token naming a key is checked against that key
+    alone, failing with UnknownKey if the ring no longer holds it; a token naming no key is checked against every key.
+ */
+pub fn verify_with<K: AsRef<[u8]>>(token: &str, keyring: &Keyring<K>, validation: &Validation) -> Result<GrantClaims, ErrorKind> {
+    return match key_id(token)? {
+        Some(id) => {
+            let key = keyring.get(&id).ok_or_else(|| unknown_key(&id))?;
+            verify_with_keys(token, [key.as_ref()], validation)
+        },
+        None => verify_with_keys(token, keyring.keys().map(|key| key.as_ref()), validation)
+    }
+}
+
+impl Scope {
+    /** Get the claims describing the grant state of this scope and every child scope. */
+    pub fn grant_claims(&self) -> GrantClaims {
+        return GrantClaims {
+            schema_hash: self.fingerprint(),
+            values: self.scopes_in_encoding_order().into_iter().map(|scope| scope.as_u64()).collect(),
+            iat: None,
+            exp: None,
+            aud: None,
+            sub: None,
+            delegated_from: None
+        };
+    }
+
+    /** Export the grant state of this scope and every child scope as a token signed with `key`. */
+    pub fn to_signed_token(&self, key: &[u8]) -> String {
+        return sign(&self.grant_claims(), key);
+    }
+
+    /**
+        Set the grant state of this scope and every child scope from claims, failing with SchemaMismatch if
+        they were produced under a different schema. Nothing is changed unless every value is valid.
+     */
+    pub fn apply_claims(&mut self, claims: &GrantClaims) -> Result<&mut Scope, ErrorKind> {
+        self.verify_fingerprint(claims.schema_hash)?;
+        self.apply_values_in_encoding_order(&claims.values, "token")?;
+
+        return Ok(self);
+    }
+
+    /** Verify a token produced by `to_signed_token`, validate its claims and apply the grant state it carries. */
+    pub fn apply_signed_token(&mut self, token: &str, key: &[u8], validation: &Validation) -> Result<&mut Scope, ErrorKind> {
+        let claims = verify(token, key, validation)?;
+
+        return self.apply_claims(&claims);
+    }
+
+    /** Export the grant state of this scope and every child scope as a token signed with the active key. */
+    pub fn to_signed_token_with<K: AsRef<[u8]>>(&self, keyring: &Keyring<K>) -> Result<String, ErrorKind> {
+        return sign_with(&self.grant_claims(), keyring);
+    }
+
+    /** Verify a token against a keyring, validate its claims and apply the grant state it carries. */
+    pub fn apply_signed_token_with<K: AsRef<[u8]>>(&mut self, token: &str, keyring: &Keyring<K>, validation: &Validation) -> Result<&mut Scope, ErrorKind> {
+        let claims = verify_with(token, keyring, validation)?;
+
+        return self.apply_claims(&claims);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KEY: &[u8] = b"signing key used only by tests";
+
+    fn schema() -> Scope {
+        let mut scope = Scope::new("APP");
+        if let Err(_) = scope.add_permission("READ")
+            .and_then(|sc| sc.add_permission("WRITE"))
+            .and_then(|sc| sc.add_scope("billing")) {
+            assert!(false);
+        }
+        match scope.scope("billing") {
+            Some(billing) => {
+                if let Err(_) = billing.add_permission("REFUND") {
+                    assert!(false);
+                }
+            },
+            None => assert!(false)
+        }
+
+        return scope;
+    }
+
+    #[test]
+    fn test_signed_token_round_trip() {
+        let mut scope = schema();
+        if let Err(_) = scope.grant("WRITE") {
+            assert!(false);
+        }
+        match scope.scope("billing") {
+            Some(billing) => {
+                if let Err(_) = billing.grant("REFUND") {
+                    assert!(false);
+                }
+            },
+            None => assert!(false)
+        }
+
+        let token = scope.to_signed_token(KEY);
+        assert_eq!(token.split(PART_SEPARATOR).count(), 3);
+
+        let mut restored = schema();
+        match restored.apply_signed_token(&token, KEY, &Validation::at(0)) {
+            Ok(_) => {
+                assert_eq!(restored.check("WRITE"), true);
+                assert_eq!(restored.check("READ"), false);
+                assert_eq!(restored.check("billing.REFUND"), true);
+            },
+            Err(_) => assert!(false)
+        }
+    }
+
+    #[test]
+    fn test_signed_token_rejects_tampering() {
+        let mut scope = schema();
+        if let Err(_) = scope.grant("READ") {
+            assert!(false);
+        }
+        let token = scope.to_signed_token(KEY);
+
+        // swap the claims for ones granting everything, keeping the original signature
+        let parts: Vec<&str> = token.split(PART_SEPARATOR).collect();
+        let forged_claims = GrantClaims { values: alloc::vec![3, 1], ..scope.grant_claims() };
+        let forged = [parts[0], &encode_part(&serde_json::to_vec(&forged_claims).unwrap()), parts[2]].join(".");
+
+        let mut restored = schema();
+        for candidate in [forged.as_str(), "not a token", &token[..token.len() - 2]] {
+            match restored.apply_signed_token(candidate, KEY, &Validation::at(0)) {
+                Ok(_) => assert!(false),
+                Err(ErrorKind::ScopeError(err)) => assert_eq!(err.case(), ScopeErrorCase::InvalidToken),
+                Err(_) => assert!(false)
+            }
+        }
+        match restored.apply_signed_token(&token, b"some other key", &Validation::at(0)) {
+            Ok(_) => assert!(false),
+            Err(ErrorKind::ScopeError(err)) => assert_eq!(err.case(), ScopeErrorCase::InvalidToken),
+            Err(_) => assert!(false)
+        }
+        assert_eq!(restored.as_u64(), 0);
+    }
+
+    #[test]
+    fn test_signed_token_key_rotation() {
+        let mut keyring: Keyring<&[u8]> = Keyring::new();
+        keyring.insert("2024-01", b"first key");
+
+        let mut scope = schema();
+        if let Err(_) = scope.grant("READ") {
+            assert!(false);
+        }
+        let old_token = scope.to_signed_token_with(&keyring).unwrap_or_default();
+
+        keyring.insert("2024-06", b"second key");
+        if let Err(_) = keyring.activate("2024-06") {
+            assert!(false);
+        }
+        let new_token = scope.to_signed_token_with(&keyring).unwrap_or_default();
+
+        assert_eq!(key_id(&old_token).ok(), Some(Some("2024-01".to_string())));
+        assert_eq!(key_id(&new_token).ok(), Some(Some("2024-06".to_string())));
+        assert_eq!(key_id(&scope.to_signed_token(KEY)).ok(), Some(None));
+
+        // both tokens verify while the old key is kept for rotation
+        for token in [&old_token, &new_token] {
+            let mut restored = schema();
+            match restored.apply_signed_token_with(token, &keyring, &Validation::at(0)) {
+                Ok(_) => assert_eq!(restored.check("READ"), true),
+                Err(_) => assert!(false)
+            }
+        }
+
+        // a token cannot pick a different key from the ring than the one that signed it
+        let parts: Vec<&str> = old_token.split(PART_SEPARATOR).collect();
+        let relabelled = [&encode_part(br#"{"alg":"HS256","kid":"2024-06"}"#), parts[1], parts[2]].join(".");
+        match schema().apply_signed_token_with(&relabelled, &keyring, &Validation::at(0)) {
+            Ok(_) => assert!(false),
+            Err(ErrorKind::ScopeError(err)) => assert_eq!(err.case(), ScopeErrorCase::InvalidToken),
+            Err(_) => assert!(false)
+        }
+
+        keyring.remove("2024-01");
+        match schema().apply_signed_token_with(&old_token, &keyring, &Validation::at(0)) {
+            Ok(_) => assert!(false),
+            Err(ErrorKind::ScopeError(err)) => assert_eq!(err.case(), ScopeErrorCase::UnknownKey),
+            Err(_) => assert!(false)
+        }
+    }
+
+    #[test]
+    fn test_signed_token_expiry_and_audience() {
+        let mut claims = schema().grant_claims();
+        claims.iat = Some(1_700_000_000);
+        claims.exp = Some(1_700_003_600);
+        claims.aud = Some("billing".to_string());
+        let token = sign(&claims, KEY);
+
+        let mut validation = Validation::at(1_700_000_060);
+        validation.audience = Some("billing".to_string());
+        match verify(&token, KEY, &validation) {
+            Ok(verified) => assert_eq!(verified, claims),
+            Err(_) => assert!(false)
+        }
+
+        let mut replayed = validation.clone();
+        replayed.audience = Some("reporting".to_string());
+        let mut expired = validation.clone();
+        expired.now = 1_700_003_600;
+
+        for rejected in [replayed, expired, Validation::at(1_700_000_060)] {
+            match schema().apply_signed_token(&token, KEY, &rejected) {
+                Ok(_) => assert!(false),
+                Err(ErrorKind::ScopeError(err)) => assert_eq!(err.case(), ScopeErrorCase::InvalidToken),
+                Err(_) => assert!(false)
+            }
+        }
+    }
+
+    #[test]
+    fn test_signed_token_rejects_other_schema() {
+        let token = schema().to_signed_token(KEY);
+
+        let mut changed = schema();
+        if let Err(_) = changed.add_permission("DELETE") {
+            assert!(false);
+        }
+
+        match changed.apply_signed_token(&token, KEY, &Validation::at(0)) {
+            Ok(_) => assert!(false),
+            Err(ErrorKind::ScopeError(err)) => assert_eq!(err.case(), ScopeErrorCase::SchemaMismatch),
+            Err(_) => assert!(false)
+        }
+    }
+}
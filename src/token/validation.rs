@@ -0,0 +1,136 @@
+use alloc::string::String;
+use crate::common::error::ErrorKind;
+use crate::token::{invalid_token, GrantClaims};
+
+/**
+    Validation describes what a decoded token must satisfy beyond its signature. Times are seconds since the Unix
+    epoch, as in JWT; the caller supplies the current time so validation also works without a system clock.
+ */
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Validation {
+    /** The current time. */
+    pub now: u64,
+    /** Seconds of clock skew tolerated when checking `exp` and `iat`. */
+    pub leeway: u64,
+    /** The audience this service accepts. Tokens naming another audience, or none when one is set here, are rejected. */
+    pub audience: Option<String>
+}
+
+impl Validation {
+    /** Validate tokens at the given time, with no leeway and no audience. */
+    pub fn at(now: u64) -> Validation {
+        return Validation {
+            now,
+            leeway: 0,
+            audience: None
+        };
+    }
+
+    /**
+        Check the time and audience claims of a token: it must not have expired, must not have been issued in the
+        future, and must name the expected audience. A token naming an audience is rejected when none is expected,
+        so tokens issued for one service cannot be replayed against a service that does not check. Delegated tokens
+        must not grant more than the values they record for their delegator; whether the delegator still holds
+        those is checked by `Scope::apply_delegated_token`.
+     */
+    pub fn validate(&self, claims: &GrantClaims) -> Result<(), ErrorKind> {
+        if let Some(exp) = claims.exp {
+            if self.now >= exp.saturating_add(self.leeway) {
+                return Err(invalid_token("expired"));
+            }
+        }
+
+        if let Some(iat) = claims.iat {
+            if iat > self.now.saturating_add(self.leeway) {
+                return Err(invalid_token("issued in the future"));
+            }
+        }
+
+        if claims.aud != self.audience {
+            return Err(invalid_token("audience does not match"));
+        }
+
+        if let Some(delegator) = &claims.delegated_from {
+            let is_subset = delegator.len() == claims.values.len()
+                && claims.values.iter().zip(delegator).all(|(value, held)| value & !held == 0);
+
+            if !is_subset {
+                return Err(invalid_token("delegation exceeds the delegator's grants"));
+            }
+        }
+
+        return Ok(());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::string::ToString;
+    use alloc::vec;
+    use crate::scope::error::ScopeErrorCase;
+
+    fn get_claims() -> GrantClaims {
+        return GrantClaims {
+            schema_hash: 0,
+            values: vec![1],
+            iat: Some(1_000),
+            exp: Some(2_000),
+            aud: Some("billing".to_string()),
+            sub: None,
+            delegated_from: None
+        };
+    }
+
+    fn is_rejected(validation: &Validation, claims: &GrantClaims) -> bool {
+        return match validation.validate(claims) {
+            Ok(_) => false,
+            Err(ErrorKind::ScopeError(err)) => err.case() == ScopeErrorCase::InvalidToken,
+            Err(_) => false
+        }
+    }
+
+    #[test]
+    fn test_validate_times() {
+        let claims = get_claims();
+        let mut validation = Validation::at(1_500);
+        validation.audience = Some("billing".to_string());
+
+        assert_eq!(validation.validate(&claims).is_ok(), true);
+
+        validation.now = 2_000;
+        assert_eq!(is_rejected(&validation, &claims), true);
+        validation.leeway = 30;
+        assert_eq!(validation.validate(&claims).is_ok(), true);
+
+        validation.now = 900;
+        assert_eq!(is_rejected(&validation, &claims), true);
+        validation.leeway = 100;
+        assert_eq!(validation.validate(&claims).is_ok(), true);
+
+        let mut unbounded = get_claims();
+        unbounded.iat = None;
+        unbounded.exp = None;
+        validation.now = u64::MAX;
+        assert_eq!(validation.validate(&unbounded).is_ok(), true);
+    }
+
+    #[test]
+    fn test_validate_audience() {
+        let claims = get_claims();
+        let mut validation = Validation::at(1_500);
+
+        // a token for a specific audience is not accepted by a service that expects none
+        assert_eq!(is_rejected(&validation, &claims), true);
+
+        validation.audience = Some("reporting".to_string());
+        assert_eq!(is_rejected(&validation, &claims), true);
+
+        let mut anonymous = get_claims();
+        anonymous.aud = None;
+        assert_eq!(is_rejected(&validation, &anonymous), true);
+
+        validation.audience = None;
+        assert_eq!(validation.validate(&anonymous).is_ok(), true);
+    }
+}
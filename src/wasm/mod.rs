@@ -0,0 +1,204 @@
+/*!
+    Bindings exposing scopes, permissions and the JSON conversion API through `wasm-bindgen`, so the same schema
+    and checks can run in browsers and edge runtimes. Values cross the boundary as `BigInt`, and errors are thrown
+    as JS `Error`s carrying the message of the ErrorKind.
+ */
+
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use wasm_bindgen::prelude::*;
+use crate::common::error::ErrorKind;
+use crate::permission::Permission;
+use crate::scope::fingerprint::VersionedValue;
+use crate::scope::path::{PermissionPath, ScopePath};
+use crate::scope::Scope;
+
+fn to_js_error(err: ErrorKind) -> JsError {
+    return JsError::new(&err.to_string());
+}
+
+/** A scope tree, exported to JS as `Scope`. */
+#[wasm_bindgen(js_name = Scope)]
+pub struct JsScope {
+    inner: Scope
+}
+
+#[wasm_bindgen(js_class = Scope)]
+impl JsScope {
+    /** Create an empty root scope. */
+    #[wasm_bindgen(constructor)]
+    pub fn new(name: &str) -> JsScope {
+        return JsScope { inner: Scope::new(name) };
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn name(&self) -> String {
+        return self.inner.name().to_string();
+    }
+
+    /** Add a permission to the scope at `path`, e.g. `billing` or an empty string for this scope. */
+    #[wasm_bindgen(js_name = addPermission)]
+    pub fn add_permission(&mut self, path: &str, name: &str) -> Result<(), JsError> {
+        let scope = self.inner.resolve_scope_mut(&ScopePath::parse(path)).map_err(to_js_error)?;
+        scope.add_permission(name).map_err(to_js_error)?;
+
+        return Ok(());
+    }
+
+    /** Add a child scope to the scope at `path`, e.g. `billing` or an empty string for this scope. */
+    #[wasm_bindgen(js_name = addScope)]
+    pub fn add_scope(&mut self, path: &str, name: &str) -> Result<(), JsError> {
+        let scope = self.inner.resolve_scope_mut(&ScopePath::parse(path)).map_err(to_js_error)?;
+        scope.add_scope(name).map_err(to_js_error)?;
+
+        return Ok(());
+    }
+
+    /** Grant the permission at `path`, e.g. `READ` or `billing:REFUND`. */
+    pub fn grant(&mut self, path: &str) -> Result<(), JsError> {
+        let requested = PermissionPath::parse(path);
+        let scope = self.inner.resolve_scope_mut(&requested.scope).map_err(to_js_error)?;
+        scope.grant(&requested.permission).map_err(to_js_error)?;
+
+        return Ok(());
+    }
+
+    /** Revoke the permission at `path`, e.g. `READ` or `billing:REFUND`. */
+    pub fn revoke(&mut self, path: &str) -> Result<(), JsError> {
+        let requested = PermissionPath::parse(path);
+        let scope = self.inner.resolve_scope_mut(&requested.scope).map_err(to_js_error)?;
+        scope.revoke(&requested.permission).map_err(to_js_error)?;
+
+        return Ok(());
+    }
+
+    /** Check whether the permission at `path` is granted or implied, as `Scope::check`. */
+    pub fn check(&self, path: &str) -> bool {
+        return self.inner.check(path);
+    }
+
+    /** Get the permissions defined directly on this scope, in the order they were added. */
+    pub fn permissions(&self) -> Vec<JsPermission> {
+        return self.inner.permissions().map(|permission| JsPermission { inner: permission.clone() }).collect();
+    }
+
+    /** Get the value of this scope as a `BigInt`. */
+    #[wasm_bindgen(js_name = asU64)]
+    pub fn as_u64(&self) -> u64 {
+        return self.inner.as_u64();
+    }
+
+    /**
+        Set the grants of this scope from a value produced by `asU64` under the schema with fingerprint
+        `expectedFingerprint`, throwing if the schema has changed since.
+     */
+    #[wasm_bindgen(js_name = applyU64)]
+    pub fn apply_u64(&mut self, value: u64, expected_fingerprint: u64) -> Result<(), JsError> {
+        self.inner.apply_value(&VersionedValue { schema_hash: expected_fingerprint, value }).map_err(to_js_error)?;
+
+        return Ok(());
+    }
+
+    /** The fingerprint of the schema as a `BigInt`, to compare against the one the server publishes. */
+    pub fn fingerprint(&self) -> u64 {
+        return self.inner.fingerprint();
+    }
+
+    /**
+        Get the value of this scope followed by the values of its continuations as a `BigUint64Array`, for scopes
+        holding more permissions than fit in a single value. A scope that never filled up gives a single word.
+     */
+    #[wasm_bindgen(js_name = asWords)]
+    pub fn as_words(&self) -> Vec<u64> {
+        return self.inner.as_words();
+    }
+
+    /** Set the grants of this scope and its continuations from words produced by `asWords`. */
+    #[wasm_bindgen(js_name = applyWords)]
+    pub fn apply_words(&mut self, words: Vec<u64>) -> Result<(), JsError> {
+        self.inner.apply_words(&words).map_err(to_js_error)?;
+
+        return Ok(());
+    }
+
+    /** Export the tree as JSON text, as written by `Scope::to_json`. */
+    #[wasm_bindgen(js_name = toJson)]
+    pub fn to_json(&self) -> String {
+        return self.inner.to_json().to_string();
+    }
+
+    /**
+        Import a tree from JSON text written by `toJson`, throwing on malformed input. When `expectedFingerprint`
+        is given, the import also throws unless the schema has that fingerprint.
+     */
+    #[wasm_bindgen(js_name = fromJson)]
+    pub fn from_json(json: &str, expected_fingerprint: Option<u64>) -> Result<JsScope, JsError> {
+        let value = serde_json::from_str(json).map_err(|err| JsError::new(&err.to_string()))?;
+
+        return Ok(JsScope { inner: Scope::from_json_verified(value, expected_fingerprint).map_err(to_js_error)? });
+    }
+}
+
+/** A snapshot of a single permission, exported to JS as `Permission`. */
+#[wasm_bindgen(js_name = Permission)]
+pub struct JsPermission {
+    inner: Permission
+}
+
+#[wasm_bindgen(js_class = Permission)]
+impl JsPermission {
+    #[wasm_bindgen(getter)]
+    pub fn name(&self) -> String {
+        return self.inner.name.clone();
+    }
+
+    /** The bit of this permission as a `BigInt`. */
+    #[wasm_bindgen(getter)]
+    pub fn value(&self) -> u64 {
+        return self.inner.value;
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn granted(&self) -> bool {
+        return self.inner.has();
+    }
+}
+
+impl From<Scope> for JsScope {
+    fn from(scope: Scope) -> JsScope {
+        return JsScope { inner: scope };
+    }
+}
+
+impl From<JsScope> for Scope {
+    fn from(scope: JsScope) -> Scope {
+        return scope.inner;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scope_bindings() {
+        let mut scope = JsScope::new("APP");
+        assert!(scope.add_permission("", "READ").is_ok());
+        assert!(scope.add_scope("", "billing").is_ok());
+        assert!(scope.add_permission("billing", "REFUND").is_ok());
+        assert!(scope.grant("billing:REFUND").is_ok());
+
+        assert_eq!(scope.check("billing:REFUND"), true);
+        assert_eq!(scope.permissions().iter().map(|permission| permission.name()).collect::<Vec<String>>(), ["READ"]);
+
+        let mut restored = match JsScope::from_json(&scope.to_json(), Some(scope.fingerprint())) {
+            Ok(restored) => restored,
+            Err(_) => panic!("round trip failed")
+        };
+        assert_eq!(restored.check("billing:REFUND"), true);
+        assert_eq!(restored.check("READ"), false);
+        assert_eq!(restored.as_words(), [0]);
+        assert!(restored.apply_u64(1, scope.fingerprint()).is_ok());
+        assert_eq!(restored.check("READ"), true);
+    }
+}
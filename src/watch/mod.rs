@@ -0,0 +1,145 @@
+/*!
+    Change notifications for async tasks. A `WatchedScope` publishes a snapshot of its scope on a tokio watch
+    channel after every change, so caches and websocket broadcasters can await changes instead of polling.
+ */
+
+use alloc::vec::Vec;
+use tokio::sync::watch;
+use crate::common::error::ErrorKind;
+use crate::scope::path::ScopePath;
+use crate::scope::snapshot::ScopeSnapshot;
+use crate::scope::Scope;
+
+/** WatchSnapshot is the state of a watched scope after a change. */
+#[derive(Clone, Debug)]
+pub struct WatchSnapshot {
+    /** Number of changes published so far, starting at 0 for the scope the watch was created with. */
+    pub version: u64,
+    pub scope: ScopeSnapshot
+}
+
+/**
+    WatchedScope owns a scope and tells subscribers about every change made through `update`. Receivers only ever
+    see the latest snapshot, so a slow subscriber skips intermediate states rather than falling behind.
+ */
+pub struct WatchedScope {
+    scope: Scope,
+    sender: watch::Sender<WatchSnapshot>
+}
+
+/** List the revision of every scope in a tree, which changes whenever anything in the tree does. */
+fn revisions(scope: &Scope) -> Vec<(ScopePath, u64)> {
+    return scope.scopes_in_encoding_order().into_iter()
+        .map(|scope| (scope.path().clone(), scope.revision()))
+        .collect();
+}
+
+impl WatchedScope {
+    pub fn new(scope: Scope) -> WatchedScope {
+        let (sender, _) = watch::channel(WatchSnapshot { version: 0, scope: scope.snapshot() });
+
+        return WatchedScope { scope, sender };
+    }
+
+    pub fn scope(&self) -> &Scope {
+        return &self.scope;
+    }
+
+    /** Get a receiver of the snapshots published from now on, starting with the current one. */
+    pub fn subscribe(&self) -> watch::Receiver<WatchSnapshot> {
+        return self.sender.subscribe();
+    }
+
+    /**
+        Change the scope and publish a snapshot if anything in the tree changed, including changes to child scopes
+        and grants made through `permission` or `permissions_mut`.
+        A snapshot is published even when `change` fails after changing something, so subscribers always see the
+        scope as it is.
+     */
+    pub fn update<T, F>(&mut self, change: F) -> Result<T, ErrorKind> where F: FnOnce(&mut Scope) -> Result<T, ErrorKind> {
+        let before = revisions(&self.scope);
+        let result = change(&mut self.scope);
+
+        if revisions(&self.scope) != before {
+            let scope = self.scope.snapshot();
+            self.sender.send_modify(|snapshot| {
+                snapshot.version += 1;
+                snapshot.scope = scope;
+            });
+        }
+
+        return result;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::runtime::Builder;
+    use crate::{assert_granted, assert_not_granted, assert_ok};
+
+    fn get_watched_scope() -> WatchedScope {
+        let mut scope = Scope::new("APP");
+        assert_ok!(scope.add_permission("READ").and_then(|sc| sc.add_scope("billing")));
+        if let Some(billing) = scope.scope("billing") {
+            assert_ok!(billing.add_permission("PAY"));
+        }
+
+        return WatchedScope::new(scope);
+    }
+
+    #[test]
+    fn test_update_publishes_snapshot() {
+        let mut watched = get_watched_scope();
+        let mut receiver = watched.subscribe();
+        assert_eq!(receiver.borrow_and_update().version, 0);
+
+        // checks and failed changes leave nothing to publish
+        assert_eq!(assert_ok!(watched.update(|scope| Ok(scope.check("READ")))), false);
+        assert_eq!(watched.update(|scope| scope.grant("WRITE").map(|_| ())).is_err(), true);
+        assert_eq!(receiver.has_changed().ok(), Some(false));
+
+        // changes made directly on a child scope are published too
+        assert_ok!(watched.update(|scope| match scope.scope("billing") {
+            Some(billing) => billing.grant("PAY").map(|_| ()),
+            None => Ok(())
+        }));
+        assert_eq!(receiver.has_changed().ok(), Some(true));
+
+        let snapshot = receiver.borrow_and_update().clone();
+        assert_eq!(snapshot.version, 1);
+        assert_granted!(snapshot.scope, "billing:PAY");
+        assert_not_granted!(snapshot.scope, "READ");
+
+        // as are grants made through a permission reference
+        assert_ok!(watched.update(|scope| match scope.permission("READ") {
+            Some(read) => read.grant().map(|_| ()),
+            None => Ok(())
+        }));
+        let snapshot = receiver.borrow_and_update().clone();
+        assert_eq!(snapshot.version, 2);
+        assert_granted!(snapshot.scope, "READ");
+    }
+
+    #[test]
+    fn test_subscribers_await_changes() {
+        let runtime = Builder::new_current_thread().build().unwrap_or_else(|err| panic!("no runtime: {}", err));
+        let mut watched = get_watched_scope();
+        let mut receiver = watched.subscribe();
+
+        runtime.block_on(async {
+            let waiter = tokio::spawn(async move {
+                receiver.changed().await.ok();
+                let snapshot = receiver.borrow().clone();
+                snapshot
+            });
+
+            assert_ok!(watched.update(|scope| scope.grant("READ").map(|_| ())));
+
+            match waiter.await {
+                Ok(snapshot) => assert_granted!(snapshot.scope, "READ"),
+                Err(err) => panic!("the subscriber failed: {}", err)
+            }
+        });
+    }
+}